@@ -3,14 +3,31 @@
 
 use std::env;
 
-use ferroboy::{CartridgeBuilder, State};
+use ferroboy::{
+    Buttons, CartridgeBuilder, FrameBlending, Model, Palette, State, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
 use libretro_backend::{
-    AudioVideoInfo, Core, CoreInfo, GameData, LoadGameResult, PixelFormat, Region, RuntimeHandle,
+    AudioVideoInfo, Core, CoreInfo, GameData, JoypadButton, LoadGameResult, PixelFormat, Region,
+    RuntimeHandle,
 };
 
+/// How the RetroPad maps onto the DMG-01's buttons.
+const BUTTON_MAP: [(JoypadButton, Buttons); 8] = [
+    (JoypadButton::Right, Buttons::RIGHT),
+    (JoypadButton::Left, Buttons::LEFT),
+    (JoypadButton::Up, Buttons::UP),
+    (JoypadButton::Down, Buttons::DOWN),
+    (JoypadButton::A, Buttons::A),
+    (JoypadButton::B, Buttons::B),
+    (JoypadButton::Select, Buttons::SELECT),
+    (JoypadButton::Start, Buttons::START),
+];
+
 pub struct FerroboyCore {
     game_data: Option<GameData>,
     state: State,
+    video_buffer: Vec<u8>,
+    audio_buffer: Vec<i16>,
 }
 
 impl FerroboyCore {
@@ -18,6 +35,48 @@ impl FerroboyCore {
         Self {
             game_data: None,
             state: Default::default(),
+            video_buffer: Vec::new(),
+            audio_buffer: Vec::new(),
+        }
+    }
+}
+
+impl FerroboyCore {
+    /// Reads the palette from the `FERROBOY_PALETTE` environment variable,
+    /// either a preset name or four comma-separated colours.
+    fn palette() -> Palette {
+        env::var("FERROBOY_PALETTE")
+            .ok()
+            .and_then(|palette| palette.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Reads how much of each frame lingers into the next, as a percentage,
+    /// from the `FERROBOY_GHOSTING` environment variable.
+    fn frame_blending() -> FrameBlending {
+        env::var("FERROBOY_GHOSTING")
+            .ok()
+            .and_then(|persistence| persistence.parse().ok())
+            .map(|persistence| FrameBlending::Ghosting { persistence })
+            .unwrap_or_default()
+    }
+
+    /// Reads the audio sample rate from the `FERROBOY_SAMPLE_RATE`
+    /// environment variable.
+    fn sample_rate() -> u32 {
+        env::var("FERROBOY_SAMPLE_RATE")
+            .ok()
+            .and_then(|rate| rate.parse().ok())
+            .filter(|rate| *rate > 0)
+            .unwrap_or(ferroboy::DEFAULT_SAMPLE_RATE)
+    }
+
+    /// Emulates the SGB if the `FERROBOY_MODEL` environment variable is
+    /// `sgb`.
+    fn model() -> Model {
+        match env::var("FERROBOY_MODEL") {
+            Ok(model) if model.eq_ignore_ascii_case("sgb") => Model::Sgb,
+            _ => Model::Dmg,
         }
     }
 }
@@ -57,12 +116,26 @@ impl Core for FerroboyCore {
 
         match cartridge_builder.build() {
             Ok(cart) => {
+                self.state.config.palette = Self::palette();
+                self.state.config.frame_blending = Self::frame_blending();
+                self.state.config.sample_rate = Self::sample_rate();
+                self.state.config.model = Self::model();
                 self.state.load_cartridge(cart);
+
+                if ferroboy::start(&mut self.state).is_err() {
+                    return LoadGameResult::Failed(game_data);
+                }
+
                 self.game_data = Some(game_data);
 
                 let av_info = AudioVideoInfo::new()
-                    .video(160, 144, 60.0, PixelFormat::ARGB8888)
-                    .audio(44100.0)
+                    .video(
+                        SCREEN_WIDTH as u32,
+                        SCREEN_HEIGHT as u32,
+                        60.0,
+                        PixelFormat::ARGB8888,
+                    )
+                    .audio(f64::from(self.state.config.sample_rate))
                     .region(Region::NTSC);
 
                 LoadGameResult::Success(av_info)
@@ -76,20 +149,24 @@ impl Core for FerroboyCore {
     }
 
     fn on_run(&mut self, handle: &mut RuntimeHandle) {
-        let mut video_buffer: Vec<u8> = Vec::<u8>::with_capacity(160 * 144 * 4);
-
-        for _ in 0..160 * 144 {
-            video_buffer.push(0x00);
-            video_buffer.push(0x00);
-            video_buffer.push(0x00);
-            video_buffer.push(0xFF);
+        let buttons = BUTTON_MAP
+            .iter()
+            .filter(|(button, _)| handle.is_joypad_button_pressed(0, *button))
+            .fold(Buttons::empty(), |held, (_, mapped)| held | *mapped);
+        self.state.set_buttons(buttons);
+
+        if let Err(message) = ferroboy::run_frame(&mut self.state) {
+            println!("[ferroboy] {}", message);
         }
 
-        handle.upload_video_frame(&video_buffer);
+        self.state
+            .frame_pixels_into(ferroboy::PixelFormat::Argb8888, &mut self.video_buffer);
 
-        for _ in 0..1470 {
-            handle.upload_audio_frame(&[0, 0]);
-        }
+        handle.upload_video_frame(&self.video_buffer);
+
+        self.audio_buffer.clear();
+        self.state.audio_samples_into(&mut self.audio_buffer);
+        handle.upload_audio_frame(&self.audio_buffer);
     }
 
     fn on_reset(&mut self) {}
@@ -0,0 +1,101 @@
+use std::io::Write;
+
+use ferroboy::{validate_header, CartridgeType, HeaderWriter};
+
+/// Rewrites, or with `--validate` just checks, the header of a ROM.
+///
+/// ```sh
+/// ferroboy-dasm fix-header -r game.gb [-o fixed.gb] [--validate] [--logo] [--pad]
+///     [--title TITLE] [--type 0x01] [--ram-size 0x02] [--licensee 01] [--version 1]
+/// ```
+///
+/// Without `-o` the ROM is fixed in place. The checksums are always
+/// recomputed after every other field has been written.
+pub fn run(mut args: pico_args::Arguments) -> eyre::Result<()> {
+    let path: String = args.value_from_str(["-r", "--rom"])?;
+    let output_path: Option<String> = args.opt_value_from_str(["-o", "--output"])?;
+    let validate = args.contains("--validate");
+    let logo = args.contains("--logo");
+    let pad = args.contains("--pad");
+    let title: Option<String> = args.opt_value_from_str("--title")?;
+    let cartridge_type: Option<u8> = args.opt_value_from_fn("--type", parse_byte)?;
+    let ram_size: Option<u8> = args.opt_value_from_fn("--ram-size", parse_byte)?;
+    let licensee: Option<String> = args.opt_value_from_str("--licensee")?;
+    let version: Option<u8> = args.opt_value_from_fn("--version", parse_byte)?;
+
+    let unknown = args.finish();
+    if !unknown.is_empty() {
+        return Err(eyre::eyre!("Unknown arguments: {:?}", unknown));
+    }
+
+    let mut rom = std::fs::read(&path)?;
+
+    if validate {
+        return report(&rom);
+    }
+
+    let mut writer = HeaderWriter::new(&mut rom);
+
+    if logo {
+        writer = writer.write_logo();
+    }
+
+    if let Some(title) = title {
+        writer = writer.write_title(&title)?;
+    }
+
+    if let Some(code) = cartridge_type {
+        writer = writer.write_cartridge_type(CartridgeType::from_byte(code)?);
+    }
+
+    if let Some(code) = ram_size {
+        writer = writer.write_ram_size(code)?;
+    }
+
+    if let Some(licensee) = licensee {
+        writer = writer.write_licensee(&licensee)?;
+    }
+
+    if let Some(version) = version {
+        writer = writer.write_version(version);
+    }
+
+    if pad {
+        writer = writer.pad()?;
+    }
+
+    writer.fix_checksums();
+
+    let output_path = output_path.unwrap_or(path);
+    std::fs::File::create(&output_path)?.write_all(&rom)?;
+
+    println!("Header written to {}", output_path);
+
+    report(&rom)
+}
+
+fn report(rom: &[u8]) -> eyre::Result<()> {
+    let mismatches = validate_header(rom)?;
+
+    if mismatches.is_empty() {
+        println!("The header is valid");
+        return Ok(());
+    }
+
+    for mismatch in &mismatches {
+        println!("\t{}", mismatch);
+    }
+
+    Err(eyre::eyre!(
+        "{} header field(s) don't match",
+        mismatches.len()
+    ))
+}
+
+/// Parses a byte written in either decimal or `0x`-prefixed hex.
+fn parse_byte(value: &str) -> Result<u8, std::num::ParseIntError> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+}
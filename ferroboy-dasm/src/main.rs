@@ -3,11 +3,20 @@ use std::path::Path;
 
 use ferroboy::{Cartridge, CartridgeBuilder};
 
+mod fix_header;
+
 fn main() -> eyre::Result<()> {
     jane_eyre::install()?;
 
     let mut args = pico_args::Arguments::from_env();
 
+    if let Some(command) = args.subcommand()? {
+        return match command.as_str() {
+            "fix-header" => fix_header::run(args),
+            _ => Err(eyre::eyre!("'{}' isn't a valid command", command)),
+        };
+    }
+
     let path: String = args.value_from_str(["-r", "--rom"])?;
     let output_path: String = args.value_from_str(["-o", "--output"])?;
     let quiet: bool = args
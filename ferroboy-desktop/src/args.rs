@@ -1,5 +1,11 @@
 pub struct Args {
     pub rom_path: String,
+    pub patch_paths: Vec<String>,
+    pub dat_path: Option<String>,
+    pub palette: ferroboy::Palette,
+    pub ghosting: Option<u8>,
+    pub filter: Option<ferroboy::upscale::Filter>,
+    pub sgb: bool,
     pub should_step: bool,
 }
 
@@ -9,6 +15,15 @@ impl Args {
 
         Self {
             rom_path: args.value_from_str(["-r", "--rom"]).unwrap(),
+            patch_paths: args.values_from_str(["-p", "--patch"]).unwrap(),
+            dat_path: args.opt_value_from_str(["-d", "--dat"]).unwrap(),
+            palette: args
+                .opt_value_from_str(["-c", "--palette"])
+                .unwrap()
+                .unwrap_or_default(),
+            ghosting: args.opt_value_from_str(["-g", "--ghosting"]).unwrap(),
+            filter: args.opt_value_from_str(["-f", "--filter"]).unwrap(),
+            sgb: args.contains("--sgb"),
             should_step: args.contains(["-s", "--step"]),
         }
     }
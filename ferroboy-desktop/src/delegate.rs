@@ -1,8 +1,48 @@
-use druid::{AppDelegate, Target};
+use druid::{AppDelegate, Code, Event, Target};
+use ferroboy::Buttons;
 
 pub struct TopLevelDelegate;
 
+/// Maps a physical key onto the button it stands in for, so the layout
+/// stays put on non-QWERTY keyboards.
+fn button_for(code: Code) -> Option<Buttons> {
+    match code {
+        Code::ArrowRight => Some(Buttons::RIGHT),
+        Code::ArrowLeft => Some(Buttons::LEFT),
+        Code::ArrowUp => Some(Buttons::UP),
+        Code::ArrowDown => Some(Buttons::DOWN),
+        Code::KeyX => Some(Buttons::A),
+        Code::KeyZ => Some(Buttons::B),
+        Code::Backspace => Some(Buttons::SELECT),
+        Code::Enter => Some(Buttons::START),
+        _ => None,
+    }
+}
+
 impl AppDelegate<crate::state::State> for TopLevelDelegate {
+    fn event(
+        &mut self,
+        _ctx: &mut druid::DelegateCtx,
+        _window_id: druid::WindowId,
+        event: Event,
+        data: &mut crate::state::State,
+        _env: &druid::Env,
+    ) -> Option<Event> {
+        match &event {
+            Event::KeyDown(key) if !key.repeat => match button_for(key.code) {
+                Some(button) => data.0.press(button),
+                None => return Some(event),
+            },
+            Event::KeyUp(key) => match button_for(key.code) {
+                Some(button) => data.0.release(button),
+                None => return Some(event),
+            },
+            _ => return Some(event),
+        }
+
+        None
+    }
+
     fn command(
         &mut self,
         _ctx: &mut druid::DelegateCtx,
@@ -13,14 +13,33 @@ const DMG_CPU_CLOCK_DURATION: f32 = 1. / 4.194304;
 fn main() -> Result<(), PlatformError> {
     let args = args::Args::new();
 
+    let mut cartridge_builder =
+        ferroboy::CartridgeBuilder::new().with_file(std::fs::File::open(args.rom_path).unwrap());
+
+    for path in args.patch_paths {
+        cartridge_builder = cartridge_builder.with_patch_file(std::fs::File::open(path).unwrap());
+    }
+
+    let cartridge = cartridge_builder.build().unwrap();
+    let title = window_title(&cartridge, args.dat_path.as_deref());
+
     let state = ferroboy::StateBuilder::new()
-        .with_config(ferroboy::Config::default())
-        .with_cartridge(
-            ferroboy::CartridgeBuilder::new()
-                .with_file(std::fs::File::open(args.rom_path).unwrap())
-                .build()
-                .unwrap(),
+        .with_config(
+            ferroboy::ConfigBuilder::new()
+                .with_palette(args.palette)
+                .with_frame_blending(
+                    args.ghosting
+                        .map(|persistence| ferroboy::FrameBlending::Ghosting { persistence })
+                        .unwrap_or_default(),
+                )
+                .with_model(if args.sgb {
+                    ferroboy::Model::Sgb
+                } else {
+                    ferroboy::Model::Dmg
+                })
+                .build(),
         )
+        .with_cartridge(cartridge)
         .build();
 
     let mut state = crate::state::State(state);
@@ -33,7 +52,7 @@ fn main() -> Result<(), PlatformError> {
         run_emulation(&state);
     }
 
-    let main_window = WindowDesc::new(widgets::ui_builder).title("Ferroboy");
+    let main_window = WindowDesc::new(move || widgets::ui_builder(args.filter)).title(title);
 
     AppLauncher::with_window(main_window)
         .delegate(delegate::TopLevelDelegate)
@@ -41,6 +60,20 @@ fn main() -> Result<(), PlatformError> {
         .launch(state)
 }
 
+/// Names the window after the game, preferring its canonical name from the
+/// ROM database over the header title if a database was provided.
+fn window_title(cartridge: &ferroboy::Cartridge, dat_path: Option<&str>) -> String {
+    let entry = dat_path
+        .map(|path| ferroboy::RomDatabase::from_file(std::fs::File::open(path).unwrap()).unwrap())
+        .and_then(|database| cartridge.identify(&database).cloned());
+
+    match entry {
+        Some(entry) if entry.status.is_good() => format!("Ferroboy - {}", entry.name),
+        Some(entry) => format!("Ferroboy - {} ({})", entry.name, entry.status),
+        None => format!("Ferroboy - {}", cartridge.title),
+    }
+}
+
 fn prep_emulation(state: &mut state::State) {
     // TODO: This doesn't actually deal with boot ROM
     ferroboy::start(&mut state.0).expect("Couldn't start emulation!");
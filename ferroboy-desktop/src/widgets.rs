@@ -1,26 +1,127 @@
 use druid::{
-    widget::{Button, Flex, Image, Label},
-    Command, Env, ImageBuf, Target, Widget, WidgetExt,
+    piet::{ImageFormat, InterpolationMode},
+    widget::{Button, Flex, Label, Painter},
+    Command, Env, RenderContext, Target, Widget, WidgetExt,
+};
+use ferroboy::{
+    upscale::{Filter, Image},
+    AudioChannel, PixelFormat, SCREEN_HEIGHT, SCREEN_WIDTH,
 };
 
-pub fn ui_builder() -> impl Widget<crate::state::State> {
+pub fn ui_builder(filter: Option<Filter>) -> impl Widget<crate::state::State> {
     let data_column = Flex::column()
         .with_flex_child(step_button(), 1.0)
         .with_default_spacer()
         .with_flex_child(register_table(), 1.0)
+        .with_default_spacer()
+        .with_child(tile_viewer())
+        .with_default_spacer()
+        .with_child(channel_table())
         .expand_width();
 
     Flex::row()
-        .with_child(graphics_buffer())
+        .with_child(graphics_buffer(filter))
         .with_flex_child(data_column, 1.0)
 }
 
-pub fn graphics_buffer() -> impl Widget<crate::state::State> {
-    let pixelbuf: &[u8] = &[0u8; 160 * 144];
+pub fn graphics_buffer(filter: Option<Filter>) -> impl Widget<crate::state::State> {
+    let scale = filter.map_or(1, |filter| filter.factor());
 
-    let imagebuf = ImageBuf::from_raw(pixelbuf, druid::piet::ImageFormat::Grayscale, 160, 144);
+    Painter::new(move |ctx, data: &crate::state::State, _env| {
+        let mut pixels = data.0.frame_pixels(PixelFormat::Rgba8);
+
+        if let Some(filter) = filter {
+            let image = Image::from_rgba8(SCREEN_WIDTH, SCREEN_HEIGHT, &pixels);
+            pixels = filter.apply(&image).convert(PixelFormat::Rgba8);
+        }
+
+        if let Ok(image) = ctx.make_image(
+            SCREEN_WIDTH * scale,
+            SCREEN_HEIGHT * scale,
+            &pixels,
+            ImageFormat::RgbaSeparate,
+        ) {
+            let bounds = ctx.size().to_rect();
+            ctx.draw_image(&image, bounds, InterpolationMode::NearestNeighbor);
+        }
+    })
+    .fix_size(
+        (SCREEN_WIDTH * scale) as f64,
+        (SCREEN_HEIGHT * scale) as f64,
+    )
+}
+
+/// Every tile in VRAM, as the debugger's view of what the game has loaded.
+fn tile_viewer() -> impl Widget<crate::state::State> {
+    Painter::new(|ctx, data: &crate::state::State, _env| {
+        let sheet = data.0.inspect_video().tile_sheet();
+
+        if let Ok(image) = ctx.make_image(
+            sheet.width(),
+            sheet.height(),
+            sheet.data(),
+            ImageFormat::RgbaSeparate,
+        ) {
+            let bounds = ctx.size().to_rect();
+            ctx.draw_image(&image, bounds, InterpolationMode::NearestNeighbor);
+        }
+    })
+    .fix_size(128.0, 192.0)
+}
 
-    Image::new(imagebuf)
+/// What each sound channel is playing, with buttons to mute or solo it.
+fn channel_table() -> impl Widget<crate::state::State> {
+    let mut widget = Flex::column();
+
+    for channel in AudioChannel::ALL {
+        let summary = Label::new(move |data: &crate::state::State, _env: &Env| {
+            let info = data.0.mmu.apu().channel(channel);
+
+            if !info.enabled {
+                return "Off".to_string();
+            }
+
+            match info.duty {
+                Some(duty) => format!(
+                    "{:.1} Hz, duty {}, volume {}",
+                    info.frequency(),
+                    duty,
+                    info.volume
+                ),
+                None => format!("{:.1} Hz, volume {}", info.frequency(), info.volume),
+            }
+        });
+
+        let mute = Button::dynamic(move |data: &crate::state::State, _env| {
+            if data.0.mmu.apu().is_muted(channel) {
+                "Unmute".to_string()
+            } else {
+                "Mute".to_string()
+            }
+        })
+        .on_click(move |_context, data: &mut crate::state::State, _env| {
+            let apu = data.0.mmu.apu_mut();
+            let muted = apu.is_muted(channel);
+            apu.set_muted(channel, !muted);
+        });
+
+        let solo =
+            Button::new("Solo").on_click(move |_context, data: &mut crate::state::State, _env| {
+                data.0.mmu.apu_mut().solo(Some(channel));
+            });
+
+        widget.add_child(
+            Flex::row()
+                .with_child(Label::new(channel.to_string()))
+                .with_default_spacer()
+                .with_child(summary)
+                .with_default_spacer()
+                .with_child(mute)
+                .with_child(solo),
+        );
+    }
+
+    widget
 }
 
 fn step_button() -> impl Widget<crate::state::State> {
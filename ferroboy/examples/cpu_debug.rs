@@ -1,4 +1,6 @@
-use ferroboy::{start, tick, CartridgeBuilder, ConfigBuilder, StateBuilder};
+use ferroboy::{
+    start, test_rom::run_test_rom, CartridgeBuilder, ConfigBuilder, StateBuilder, CYCLES_PER_SECOND,
+};
 
 pub fn main() {
     println!("ferroboy v{}", env!("CARGO_PKG_VERSION"));
@@ -22,10 +24,12 @@ pub fn main() {
         println!("{:?}", state);
     }
 
-    loop {
-        match tick(&mut state) {
-            Ok(_) => {}
-            Err(msg) => std::panic::panic_any(msg),
-        }
+    // The full suite takes nearly a minute of emulated time
+    let verdict = run_test_rom(&mut state, 120 * CYCLES_PER_SECOND).unwrap();
+
+    println!("{}", verdict.output());
+
+    if !verdict.is_passed() {
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,78 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use ferroboy::{
+    audio::WavWriter,
+    gbs::{GbsFile, GbsPlayer},
+    ConfigBuilder, CYCLES_PER_FRAME, CYCLES_PER_SECOND, DEFAULT_SAMPLE_RATE,
+};
+
+/// Renders a track from a GBS file to WAV.
+///
+/// Tracks are numbered from 1, as they are in most players.
+fn main() {
+    println!("ferroboy v{}", env!("CARGO_PKG_VERSION"));
+    let mut args = pico_args::Arguments::from_env();
+    let path: String = args.value_from_str(["-f", "--file"]).unwrap();
+    let track: Option<u8> = args.opt_value_from_str(["-t", "--track"]).unwrap();
+    let seconds: u64 = args
+        .opt_value_from_str(["-s", "--seconds"])
+        .unwrap()
+        .unwrap_or(60);
+    let sample_rate: u32 = args
+        .opt_value_from_str(["-r", "--rate"])
+        .unwrap()
+        .unwrap_or(DEFAULT_SAMPLE_RATE);
+    let output: String = args
+        .opt_value_from_str(["-o", "--output"])
+        .unwrap()
+        .unwrap_or_else(|| {
+            Path::new(&path)
+                .with_extension("wav")
+                .to_string_lossy()
+                .into_owned()
+        });
+
+    let file = File::open(&path).unwrap_or_else(|_| panic!("Couldn't open file {}", path));
+    let gbs = GbsFile::from_file(file).unwrap();
+
+    println!("{} - {} ({})", gbs.title, gbs.author, gbs.copyright);
+    println!(
+        "{} tracks, played {}",
+        gbs.song_count,
+        if gbs.uses_timer() {
+            "off the timer"
+        } else {
+            "every VBlank"
+        }
+    );
+
+    let config = ConfigBuilder::new().with_sample_rate(sample_rate).build();
+    let mut player = GbsPlayer::new(gbs, config).unwrap();
+
+    if let Some(track) = track {
+        player.start_song(track.saturating_sub(1)).unwrap();
+    }
+
+    let output_file =
+        File::create(&output).unwrap_or_else(|_| panic!("Couldn't create file {}", output));
+    let mut wav = WavWriter::new(BufWriter::new(output_file), sample_rate).unwrap();
+    let mut samples = Vec::new();
+
+    // Run a frame at a time, so the APU's buffer never fills up
+    for _ in 0..seconds * CYCLES_PER_SECOND / CYCLES_PER_FRAME {
+        player.run_for(CYCLES_PER_FRAME).unwrap();
+
+        samples.clear();
+        player.audio_samples_into(&mut samples);
+        wav.write_samples(&samples).unwrap();
+    }
+
+    wav.finish().unwrap();
+
+    println!(
+        "Rendered track {} for {}s to {}",
+        player.song() + 1,
+        seconds,
+        output
+    );
+}
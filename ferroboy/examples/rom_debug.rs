@@ -1,17 +1,47 @@
-use ferroboy::CartridgeBuilder;
+use ferroboy::{CartridgeBuilder, RomDatabase};
 use std::env;
 
 fn main() {
     println!("ferroboy v{}", env!("CARGO_PKG_VERSION"));
     let mut args = pico_args::Arguments::from_env();
     let path: String = args.value_from_str(["-r", "--rom"]).unwrap();
+    let patch_paths: Vec<String> = args.values_from_str(["-p", "--patch"]).unwrap();
+    let dat_path: Option<String> = args.opt_value_from_str(["-d", "--dat"]).unwrap();
+
+    let database = dat_path.map(|dat_path| match std::fs::File::open(&dat_path) {
+        Ok(file) => RomDatabase::from_file(file).unwrap(),
+        Err(_) => panic!("Couldn't open file {}", dat_path),
+    });
 
     match std::fs::File::open(&path) {
         Ok(file) => {
-            let builder = CartridgeBuilder::new().with_file(file);
+            let mut builder = CartridgeBuilder::new().with_file(file);
+
+            for patch_path in &patch_paths {
+                match std::fs::File::open(patch_path) {
+                    Ok(patch) => builder = builder.with_patch_file(patch),
+                    Err(_) => panic!("Couldn't open file {}", patch_path),
+                }
+            }
 
             match builder.build() {
-                Ok(cart) => println!("{:?}", cart),
+                Ok(cart) => {
+                    println!("{:?}", cart);
+                    println!("CRC32: {:08X}", cart.crc32());
+                    println!("SHA-1: {}", cart.sha1());
+
+                    if let Some(database) = &database {
+                        match cart.identify(database) {
+                            Some(entry) => println!(
+                                "Identified: {} [{}] ({})",
+                                entry.name,
+                                entry.region.as_deref().unwrap_or("Unknown region"),
+                                entry.status
+                            ),
+                            None => println!("Not found in the database"),
+                        }
+                    }
+                }
                 Err(message) => println!("Invalid ROM: {}", message),
             }
         }
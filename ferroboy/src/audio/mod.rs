@@ -0,0 +1,12 @@
+//! Recording the APU's output, either as audio or as the register writes
+//! that produced it.
+//!
+//! WAV files hold the mixed samples from [`crate::State::audio_samples_into`],
+//! while VGM logs hold the writes from `Apu::drain_writes_into`, so
+//! they can be played back through another emulator's APU or diffed.
+
+mod vgm;
+mod wav;
+
+pub use vgm::{VgmLog, VGM_SAMPLE_RATE};
+pub use wav::WavWriter;
@@ -0,0 +1,217 @@
+use std::io::{self, Write};
+
+use crate::{system::RegisterWrite, CYCLES_PER_SECOND};
+
+/// The rate VGM files count time in, regardless of how they're played.
+pub const VGM_SAMPLE_RATE: u64 = 44_100;
+
+/// The VGM version the DMG was added in.
+const VERSION: u32 = 0x161;
+/// How many bytes the header takes up before the command stream.
+const HEADER_SIZE: usize = 0x100;
+/// Where the header's fields are, as offsets from the start of the file.
+const EOF_OFFSET: usize = 0x04;
+const VERSION_OFFSET: usize = 0x08;
+const TOTAL_SAMPLES_OFFSET: usize = 0x18;
+const DATA_OFFSET: usize = 0x34;
+const DMG_CLOCK_OFFSET: usize = 0x80;
+
+/// The first and last registers the DMG chip definition covers, NR10 to
+/// the end of wave RAM.
+const FIRST_REGISTER: u16 = 0xFF10;
+const LAST_REGISTER: u16 = 0xFF3F;
+
+const WRITE_DMG: u8 = 0xB3;
+const WAIT: u8 = 0x61;
+const WAIT_NTSC_FRAME: u8 = 0x62;
+const WAIT_PAL_FRAME: u8 = 0x63;
+const WAIT_SHORT: u8 = 0x70;
+const END_OF_DATA: u8 = 0x66;
+
+/// Builds a VGM log of sound register writes.
+///
+/// Time is counted from cycle 0 of the APU, so a log should start from
+/// power on, otherwise the registers won't be set up the way the game left
+/// them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VgmLog {
+    commands: Vec<u8>,
+    /// How many samples have been waited for so far.
+    samples: u64,
+}
+
+impl VgmLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a register write, waiting for its cycle first. Writes
+    /// outside of the sound registers are ignored.
+    pub fn push(&mut self, write: &RegisterWrite) {
+        if !(FIRST_REGISTER..=LAST_REGISTER).contains(&write.address) {
+            return;
+        }
+
+        self.wait_until(write.cycle);
+        self.commands.extend_from_slice(&[
+            WRITE_DMG,
+            (write.address - FIRST_REGISTER) as u8,
+            write.value,
+        ]);
+    }
+
+    /// Waits until a cycle, such as the end of the recording.
+    pub fn wait_until(&mut self, cycle: u64) {
+        let target = cycle * VGM_SAMPLE_RATE / CYCLES_PER_SECOND;
+
+        while self.samples < target {
+            let remaining = target - self.samples;
+
+            let (command, waited): (&[u8], u64) = match remaining {
+                735 => (&[WAIT_NTSC_FRAME], 735),
+                882 => (&[WAIT_PAL_FRAME], 882),
+                1..=16 => (&[WAIT_SHORT + remaining as u8 - 1], remaining),
+                _ => {
+                    let waited = remaining.min(u64::from(u16::MAX));
+                    let bytes = (waited as u16).to_le_bytes();
+                    self.commands.extend_from_slice(&[WAIT, bytes[0], bytes[1]]);
+                    self.samples += waited;
+                    continue;
+                }
+            };
+
+            self.commands.extend_from_slice(command);
+            self.samples += waited;
+        }
+    }
+
+    /// How long the log lasts, at `VGM_SAMPLE_RATE`.
+    pub fn total_samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Writes the log out as a VGM file.
+    ///
+    /// # Errors
+    /// If the log is too long for the header to hold its size or sample
+    /// count, or if writing fails.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let size = HEADER_SIZE + self.commands.len() + 1;
+        let eof = u32::try_from(size - EOF_OFFSET).map_err(|_| too_large("The log"))?;
+        let samples = u32::try_from(self.samples).map_err(|_| too_large("The sample count"))?;
+        let mut header = vec![0; HEADER_SIZE];
+
+        let mut set = |offset: usize, value: u32| {
+            header[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+
+        set(EOF_OFFSET, eof);
+        set(VERSION_OFFSET, VERSION);
+        set(TOTAL_SAMPLES_OFFSET, samples);
+        // The data offset is relative to itself
+        set(DATA_OFFSET, (HEADER_SIZE - DATA_OFFSET) as u32);
+        set(DMG_CLOCK_OFFSET, CYCLES_PER_SECOND as u32);
+        header[0..4].copy_from_slice(b"Vgm ");
+
+        writer.write_all(&header)?;
+        writer.write_all(&self.commands)?;
+        writer.write_all(&[END_OF_DATA])?;
+
+        Ok(())
+    }
+}
+
+fn too_large(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} is too large for a VGM file", what),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(cycle: u64, address: u16, value: u8) -> RegisterWrite {
+        RegisterWrite {
+            cycle,
+            address,
+            value,
+        }
+    }
+
+    fn commands(log: &VgmLog) -> Vec<u8> {
+        let mut file = Vec::new();
+        log.write_to(&mut file).unwrap();
+        file[HEADER_SIZE..].to_vec()
+    }
+
+    #[test]
+    fn it_writes_a_header() {
+        let mut log = VgmLog::new();
+        log.wait_until(CYCLES_PER_SECOND);
+
+        let mut file = Vec::new();
+        log.write_to(&mut file).unwrap();
+
+        assert_eq!(b"Vgm ", &file[0..4]);
+        assert_eq!(
+            u32::try_from(file.len() - 4).unwrap(),
+            u32::from_le_bytes([file[4], file[5], file[6], file[7]])
+        );
+        assert_eq!(&[0x61, 0x01, 0x00, 0x00], &file[0x08..0x0C]);
+        assert_eq!(&[0x44, 0xAC, 0x00, 0x00], &file[0x18..0x1C]);
+        assert_eq!(&[0xCC, 0x00, 0x00, 0x00], &file[0x34..0x38]);
+        assert_eq!(&[0x00, 0x00, 0x40, 0x00], &file[0x80..0x84]);
+        assert_eq!(Some(&END_OF_DATA), file.last());
+    }
+
+    #[test]
+    fn it_logs_writes_relative_to_nr10() {
+        let mut log = VgmLog::new();
+        log.push(&write(0, 0xFF26, 0x80));
+        log.push(&write(0, 0xFF30, 0x12));
+        log.push(&write(0, 0xFF40, 0x91));
+
+        assert_eq!(
+            vec![0xB3, 0x16, 0x80, 0xB3, 0x20, 0x12, END_OF_DATA],
+            commands(&log)
+        );
+    }
+
+    #[test]
+    fn it_waits_between_writes() {
+        // Just over 95 cycles to a sample
+        let mut log = VgmLog::new();
+        log.push(&write(952, 0xFF24, 0x77));
+        log.push(&write(CYCLES_PER_SECOND / 60 + 952, 0xFF25, 0xFF));
+        log.push(&write(CYCLES_PER_SECOND * 2, 0xFF26, 0x00));
+
+        let expected = [
+            &[0x79][..],         // 10 samples
+            &[0xB3, 0x14, 0x77], // NR50
+            &[0x62],             // A 60th of a second
+            &[0xB3, 0x15, 0xFF], // NR51
+            &[0x61, 0xFF, 0xFF], // Up to the limit of a long wait
+            &[0x61, 0xA0, 0x55], // And the rest of 2 seconds
+            &[0xB3, 0x16, 0x00], // NR52
+            &[END_OF_DATA],
+        ]
+        .concat();
+
+        assert_eq!(expected, commands(&log));
+        assert_eq!(88_200, log.total_samples());
+    }
+
+    #[test]
+    fn it_rejects_logs_too_long_for_the_header() {
+        let mut log = VgmLog::new();
+        log.samples = u64::from(u32::MAX) + 1;
+
+        let mut file = Vec::new();
+        let error = log.write_to(&mut file).unwrap_err();
+
+        assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+        assert!(file.is_empty());
+    }
+}
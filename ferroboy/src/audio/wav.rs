@@ -0,0 +1,166 @@
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// The size of the RIFF header and format chunk before the samples.
+const HEADER_SIZE: u32 = 44;
+const CHANNELS: u16 = 2;
+const BITS_PER_SAMPLE: u16 = 16;
+/// The most bytes of samples that fit, since the RIFF size covers them and
+/// everything in the header after it in 32 bits.
+const MAX_DATA_SIZE: u32 = u32::MAX - (HEADER_SIZE - 8);
+
+/// Writes interleaved 16-bit stereo samples to a WAV file.
+///
+/// The header's sizes aren't known until every sample has been written, so
+/// [`WavWriter::finish`] needs to be called to fill them in.
+#[derive(Debug)]
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    /// How many bytes of samples have been written.
+    data_size: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// # Errors
+    /// - The sample rate is too high for its byte rate to fit in the header
+    /// - Writing the header fails
+    pub fn new(mut writer: W, sample_rate: u32) -> io::Result<Self> {
+        let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+        let byte_rate = sample_rate
+            .checked_mul(u32::from(block_align))
+            .ok_or_else(|| too_large("The sample rate"))?;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(HEADER_SIZE - 8).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        // Uncompressed PCM
+        writer.write_all(&1u16.to_le_bytes())?;
+        writer.write_all(&CHANNELS.to_le_bytes())?;
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        writer.write_all(&byte_rate.to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+
+        Ok(Self {
+            writer,
+            data_size: 0,
+        })
+    }
+
+    /// Appends interleaved left and right samples.
+    ///
+    /// # Errors
+    /// - The samples would take the file past the 4GiB a WAV can hold, in
+    ///   which case none of them are written
+    /// - Writing the samples fails
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        let bytes: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+
+        let data_size = u32::try_from(bytes.len())
+            .ok()
+            .and_then(|length| self.data_size.checked_add(length))
+            .filter(|size| *size <= MAX_DATA_SIZE)
+            .ok_or_else(|| too_large("The recording"))?;
+
+        self.writer.write_all(&bytes)?;
+        self.data_size = data_size;
+
+        Ok(())
+    }
+
+    /// Fills in the header's sizes, handing back the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer
+            .write_all(&(HEADER_SIZE - 8 + self.data_size).to_le_bytes())?;
+
+        self.writer
+            .seek(SeekFrom::Start(u64::from(HEADER_SIZE) - 4))?;
+        self.writer.write_all(&self.data_size.to_le_bytes())?;
+
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+fn too_large(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} is too large for a WAV file", what),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ])
+    }
+
+    #[test]
+    fn it_writes_a_pcm_header() {
+        let wav = WavWriter::new(Cursor::new(Vec::new()), 48_000)
+            .unwrap()
+            .finish()
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(HEADER_SIZE as usize, wav.len());
+        assert_eq!(b"RIFF", &wav[0..4]);
+        assert_eq!(b"WAVEfmt ", &wav[8..16]);
+        assert_eq!(48_000, u32_at(&wav, 24));
+        assert_eq!(192_000, u32_at(&wav, 28));
+        assert_eq!(b"data", &wav[36..40]);
+    }
+
+    #[test]
+    fn it_fills_in_the_sizes() {
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), 44_100).unwrap();
+        writer.write_samples(&[1, -1, 0x1234, -2]).unwrap();
+        writer.write_samples(&[5, 6]).unwrap();
+        let wav = writer.finish().unwrap().into_inner();
+
+        assert_eq!(HEADER_SIZE as usize + 12, wav.len());
+        assert_eq!(36 + 12, u32_at(&wav, 4));
+        assert_eq!(12, u32_at(&wav, 40));
+        assert_eq!(&[0x01, 0x00, 0xFF, 0xFF, 0x34, 0x12], &wav[44..50]);
+    }
+
+    #[test]
+    fn it_rejects_sample_rates_too_high_for_the_header() {
+        let error = WavWriter::new(Cursor::new(Vec::new()), u32::MAX).unwrap_err();
+
+        assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+    }
+
+    #[test]
+    fn it_stops_at_the_size_limit() {
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), 44_100).unwrap();
+        writer.data_size = MAX_DATA_SIZE - 2;
+
+        writer.write_samples(&[1]).unwrap();
+
+        let error = writer.write_samples(&[2]).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, error.kind());
+        assert_eq!(MAX_DATA_SIZE, writer.data_size);
+        assert_eq!(HEADER_SIZE as usize + 2, writer.writer.get_ref().len());
+    }
+}
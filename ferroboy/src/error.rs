@@ -7,9 +7,15 @@ pub enum Error {
     #[error(transparent)]
     CartridgeLoad(#[from] CartridgeLoadError),
 
+    #[error(transparent)]
+    DatabaseLoad(#[from] DatabaseLoadError),
+
     #[error(transparent)]
     Disassembly(#[from] DisassemblyError),
 
+    #[error(transparent)]
+    GbsLoad(#[from] GbsLoadError),
+
     #[error(transparent)]
     Operation(#[from] OperationError),
 
@@ -21,6 +27,14 @@ pub enum Error {
     AddressOutOfRange(u32),
     #[error("'{0:0<2X}' isn't a valid opcode")]
     InvalidOperation(u8),
+    #[error("'{0}' isn't a valid palette")]
+    InvalidPalette(String),
+    #[error("'{0}' isn't a valid upscaling filter")]
+    InvalidFilter(String),
+    #[error("The program jumps to '{0}', which isn't placed")]
+    UndefinedLabel(String),
+    #[error("Writing up to {0:#06X} runs past the end of the ROM")]
+    RomOverflow(usize),
 }
 
 #[derive(Error, Debug)]
@@ -31,14 +45,60 @@ pub enum CartridgeLoadError {
     FileSystemError(#[from] std::io::Error),
     #[error("The header checksum isn't valid")]
     ChecksumFail,
+    #[error("The ROM is too small to hold a header")]
+    MissingHeader,
     #[error("The header title isn't valid")]
     InvalidTitle(#[from] std::string::FromUtf8Error),
+    #[error("The header title can't be longer than 16 bytes, but is {0}")]
+    InvalidTitleLength(usize),
+    #[error("'{0}' isn't a valid licensee code")]
+    InvalidLicensee(String),
     #[error("'{0}' isn't a valid number of banks")]
     InvalidBankCount(u8),
+    #[error("The ROM is {0} bytes, bigger than any cartridge")]
+    RomTooLarge(usize),
     #[error("'{0} isn't a valid number of RAM banks")]
     InvalidRamSize(u8),
     #[error("The cartridge mapper isn't supported")]
     InvalidMapper,
+    #[error("The patch format isn't recognised")]
+    UnknownPatchFormat,
+    #[error("The patch is truncated or malformed")]
+    MalformedPatch,
+    #[error("The patch file is corrupt")]
+    PatchChecksumFail,
+    #[error("The ROM doesn't match the one the patch was made for")]
+    PatchSourceMismatch,
+    #[error("The patched ROM doesn't match the patch's expected output")]
+    PatchTargetMismatch,
+}
+
+#[derive(Error, Debug)]
+pub enum DatabaseLoadError {
+    #[error(transparent)]
+    FileSystemError(#[from] std::io::Error),
+    #[error("The database isn't valid XML")]
+    InvalidXml(#[from] quick_xml::Error),
+    #[error("The database is malformed")]
+    Malformed,
+}
+
+#[derive(Error, Debug)]
+pub enum GbsLoadError {
+    #[error(transparent)]
+    FileSystemError(#[from] std::io::Error),
+    #[error("The file is too small to hold a GBS header")]
+    MissingHeader,
+    #[error("The file doesn't start with a GBS signature")]
+    InvalidSignature,
+    #[error("GBS version {0} isn't supported")]
+    UnsupportedVersion(u8),
+    #[error("The load address {0:04X} isn't in ROM")]
+    InvalidLoadAddress(u16),
+    #[error("Song {0} isn't in the file")]
+    InvalidSong(u8),
+    #[error("The routine at {0:04X} never returned")]
+    RoutineTimeout(u16),
 }
 
 #[derive(Error, Debug)]
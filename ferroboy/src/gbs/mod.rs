@@ -0,0 +1,239 @@
+//! Playback of GBS files, music ripped from Game Boy games along with just
+//! enough of their sound driver to play it.
+//!
+//! A GBS file is a header describing where to load the driver and which
+//! routines to call, followed by the driver itself. The init routine sets
+//! up a song, then the play routine is called at a fixed rate, either
+//! every VBlank or off the timer.
+
+mod player;
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+};
+
+pub use player::GbsPlayer;
+
+use crate::{error::GbsLoadError, system::timer, CYCLES_PER_FRAME};
+
+const HEADER_SIZE: usize = 0x70;
+const SIGNATURE: &[u8] = b"GBS";
+const VERSION: u8 = 1;
+/// The size of the title, author and copyright fields.
+const TEXT_SIZE: usize = 32;
+
+/// A parsed GBS file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GbsFile {
+    pub song_count: u8,
+    /// The song to start with, counting from 0.
+    pub first_song: u8,
+    /// Where the data is loaded in ROM.
+    pub load_address: u16,
+    /// The routine that sets up a song, given its number in A.
+    pub init_address: u16,
+    /// The routine that advances the song by one tick.
+    pub play_address: u16,
+    pub stack_pointer: u16,
+    /// TMA and TAC, which set how often the play routine is called if the
+    /// song is driven by the timer.
+    pub timer_modulo: u8,
+    pub timer_control: u8,
+    pub title: String,
+    pub author: String,
+    pub copyright: String,
+    data: Vec<u8>,
+}
+
+impl GbsFile {
+    pub fn from_file(file: File) -> crate::Result<Self> {
+        let mut buffer = Vec::new();
+
+        BufReader::new(file)
+            .read_to_end(&mut buffer)
+            .map_err(GbsLoadError::FileSystemError)?;
+
+        Self::from_buffer(&buffer)
+    }
+
+    pub fn from_buffer(buffer: &[u8]) -> crate::Result<Self> {
+        if buffer.len() < HEADER_SIZE {
+            return Err(GbsLoadError::MissingHeader.into());
+        }
+
+        if &buffer[0x00..0x03] != SIGNATURE {
+            return Err(GbsLoadError::InvalidSignature.into());
+        }
+
+        if buffer[0x03] != VERSION {
+            return Err(GbsLoadError::UnsupportedVersion(buffer[0x03]).into());
+        }
+
+        let word = |offset: usize| u16::from_le_bytes([buffer[offset], buffer[offset + 1]]);
+        let text = |offset: usize| {
+            let field = &buffer[offset..offset + TEXT_SIZE];
+            let end = field
+                .iter()
+                .position(|byte| *byte == 0)
+                .unwrap_or(TEXT_SIZE);
+
+            String::from_utf8_lossy(&field[..end]).into_owned()
+        };
+
+        let data = buffer[HEADER_SIZE..].to_vec();
+        let load_address = word(0x06);
+
+        if load_address >= 0x8000 {
+            return Err(GbsLoadError::InvalidLoadAddress(load_address).into());
+        }
+
+        Ok(Self {
+            song_count: buffer[0x04],
+            // The header counts songs from 1
+            first_song: buffer[0x05].saturating_sub(1),
+            load_address,
+            init_address: word(0x08),
+            play_address: word(0x0A),
+            stack_pointer: word(0x0C),
+            timer_modulo: buffer[0x0E],
+            timer_control: buffer[0x0F],
+            title: text(0x10),
+            author: text(0x30),
+            copyright: text(0x50),
+            data,
+        })
+    }
+
+    /// Whether the play routine is called off the timer rather than every
+    /// VBlank.
+    pub fn uses_timer(&self) -> bool {
+        self.timer_control & 0b100 != 0
+    }
+
+    /// How many T-cycles there are between calls to the play routine.
+    pub fn play_period(&self) -> u64 {
+        if !self.uses_timer() {
+            return CYCLES_PER_FRAME;
+        }
+
+        let ticks = 256 - u64::from(self.timer_modulo);
+
+        timer::tima_period(self.timer_control) * ticks
+    }
+
+    /// The data laid out as a ROM of whole 16 KiB banks, with anything past
+    /// 0x7FFF in the banks after.
+    ///
+    /// The RST vectors are moved up to the load address, so they're
+    /// redirected there if there's room below it.
+    fn rom(&self) -> Vec<u8> {
+        let start = usize::from(self.load_address);
+        let end = start + self.data.len();
+        // Rounded up to a whole number of banks
+        let size = (end.max(0x8000) + 0x3FFF) / 0x4000 * 0x4000;
+
+        let mut rom = vec![0; size];
+        rom[start..end].copy_from_slice(&self.data);
+
+        if start >= 0x40 {
+            for vector in (0x00..0x40).step_by(8) {
+                let target = (self.load_address + vector as u16).to_le_bytes();
+                rom[vector..vector + 3].copy_from_slice(&[0xC3, target[0], target[1]]);
+            }
+        }
+
+        rom
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::CYCLES_PER_SECOND;
+
+    /// Builds a GBS file around some code loaded at 0x400.
+    pub(crate) fn gbs(init: u16, play: u16, timer: (u8, u8), code: &[u8]) -> Vec<u8> {
+        let mut file = vec![0; HEADER_SIZE];
+        file[0x00..0x03].copy_from_slice(SIGNATURE);
+        file[0x03] = VERSION;
+        file[0x04] = 3;
+        file[0x05] = 2;
+        file[0x06..0x08].copy_from_slice(&0x400u16.to_le_bytes());
+        file[0x08..0x0A].copy_from_slice(&init.to_le_bytes());
+        file[0x0A..0x0C].copy_from_slice(&play.to_le_bytes());
+        file[0x0C..0x0E].copy_from_slice(&0xFFFEu16.to_le_bytes());
+        file[0x0E] = timer.0;
+        file[0x0F] = timer.1;
+        file[0x10..0x15].copy_from_slice(b"Title");
+        file[0x30..0x36].copy_from_slice(b"Author");
+        file[0x50..0x54].copy_from_slice(b"1998");
+        file.extend_from_slice(code);
+        file
+    }
+
+    #[test]
+    fn it_parses_the_header() {
+        let gbs = GbsFile::from_buffer(&gbs(0x400, 0x410, (0xC0, 0x04), &[0xC9])).unwrap();
+
+        assert_eq!(3, gbs.song_count);
+        assert_eq!(1, gbs.first_song);
+        assert_eq!(0x400, gbs.load_address);
+        assert_eq!(0x400, gbs.init_address);
+        assert_eq!(0x410, gbs.play_address);
+        assert_eq!(0xFFFE, gbs.stack_pointer);
+        assert_eq!("Title", gbs.title);
+        assert_eq!("Author", gbs.author);
+        assert_eq!("1998", gbs.copyright);
+    }
+
+    #[test]
+    fn it_rejects_invalid_files() {
+        let mut file = gbs(0x400, 0x400, (0, 0), &[0xC9]);
+
+        assert!(GbsFile::from_buffer(&file[..0x20]).is_err());
+
+        file[0x03] = 2;
+        assert!(GbsFile::from_buffer(&file).is_err());
+
+        file[0x00] = b'N';
+        assert!(GbsFile::from_buffer(&file).is_err());
+
+        let mut file = gbs(0x400, 0x400, (0, 0), &[0xC9]);
+        file[0x06..0x08].copy_from_slice(&0x8000u16.to_le_bytes());
+        assert!(GbsFile::from_buffer(&file).is_err());
+    }
+
+    #[test]
+    fn it_calls_play_at_the_vblank_or_timer_rate() {
+        let vblank = GbsFile::from_buffer(&gbs(0x400, 0x400, (0x00, 0x00), &[])).unwrap();
+        assert!(!vblank.uses_timer());
+        assert_eq!(CYCLES_PER_FRAME, vblank.play_period());
+
+        // 4096 Hz overflowing every 64 ticks is 64 Hz
+        let timer = GbsFile::from_buffer(&gbs(0x400, 0x400, (0xC0, 0x04), &[])).unwrap();
+        assert!(timer.uses_timer());
+        assert_eq!(CYCLES_PER_SECOND / 64, timer.play_period());
+    }
+
+    #[test]
+    fn it_lays_out_the_rom() {
+        let gbs = GbsFile::from_buffer(&gbs(0x400, 0x400, (0, 0), &[0xAA, 0xBB])).unwrap();
+        let rom = gbs.rom();
+
+        assert_eq!(0x8000, rom.len());
+        assert_eq!(&[0xAA, 0xBB], &rom[0x400..0x402]);
+        assert_eq!(&[0xC3, 0x38, 0x04], &rom[0x38..0x3B]);
+    }
+
+    #[test]
+    fn it_spreads_large_files_across_banks() {
+        let code = vec![0x42; 0x8000];
+        let gbs = GbsFile::from_buffer(&gbs(0x400, 0x400, (0, 0), &code)).unwrap();
+        let rom = gbs.rom();
+
+        assert_eq!(0xC000, rom.len());
+        assert_eq!(0x42, rom[0x83FF]);
+        assert_eq!(0x00, rom[0x8400]);
+    }
+}
@@ -0,0 +1,236 @@
+use super::GbsFile;
+use crate::{
+    error::GbsLoadError,
+    system::{
+        apu::{NR50, NR51, NR52},
+        timer::{TAC, TMA},
+        Config, Register, WideRegister,
+    },
+    State, StateBuilder, CYCLES_PER_SECOND,
+};
+
+/// Where routines return to when they're done. It's in the unusable area
+/// after OAM, so no driver will ever jump there itself.
+const RETURN_ADDRESS: u16 = 0xFEFF;
+/// How long a routine can run for before it's assumed to be stuck.
+const ROUTINE_TIMEOUT: u64 = CYCLES_PER_SECOND;
+
+/// Plays the songs in a GBS file.
+///
+/// Rather than relying on interrupts, the player calls the play routine
+/// itself at the rate the file asks for, and leaves the CPU idle in
+/// between while the APU keeps running.
+#[derive(Clone, Debug)]
+pub struct GbsPlayer {
+    gbs: GbsFile,
+    state: State,
+    song: u8,
+    /// The cycle the play routine is next due on.
+    next_play: u64,
+}
+
+impl GbsPlayer {
+    /// Sets up the player and starts the file's first song.
+    pub fn new(gbs: GbsFile, config: Config) -> crate::Result<Self> {
+        let song = gbs.first_song;
+        let mut player = Self {
+            gbs,
+            state: StateBuilder::new().with_config(config).build(),
+            song,
+            next_play: 0,
+        };
+
+        player.start_song(song)?;
+
+        Ok(player)
+    }
+
+    pub fn gbs(&self) -> &GbsFile {
+        &self.gbs
+    }
+
+    /// The song that's playing, counting from 0.
+    pub fn song(&self) -> u8 {
+        self.song
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Resets the hardware and runs the init routine for a song, counting
+    /// from 0.
+    pub fn start_song(&mut self, song: u8) -> crate::Result<()> {
+        if song >= self.gbs.song_count {
+            return Err(GbsLoadError::InvalidSong(song).into());
+        }
+
+        self.state = StateBuilder::new().with_config(self.state.config).build();
+        self.state.mmu.map_rom_banks(self.gbs.rom());
+
+        // Drivers expect the sound hardware to be on, as the boot ROM
+        // leaves it
+        self.state.mmu.write(NR52, 0x80);
+        self.state.mmu.write(NR50, 0x77);
+        self.state.mmu.write(NR51, 0xFF);
+        self.state.mmu.write(TMA, self.gbs.timer_modulo);
+        self.state.mmu.write(TAC, self.gbs.timer_control);
+
+        self.state
+            .cpu
+            .set16(WideRegister::Sp, self.gbs.stack_pointer);
+
+        self.song = song;
+        self.call(self.gbs.init_address, song)?;
+        self.next_play = self.state.cpu.clock() + self.gbs.play_period();
+
+        Ok(())
+    }
+
+    /// Runs for a number of T-cycles, calling the play routine whenever
+    /// it's due.
+    pub fn run_for(&mut self, cycles: u64) -> crate::Result<()> {
+        let end = self.state.cpu.clock() + cycles;
+
+        while self.next_play <= end {
+            self.idle_until(self.next_play);
+            self.call(self.gbs.play_address, self.song)?;
+            self.next_play += self.gbs.play_period();
+        }
+
+        self.idle_until(end);
+
+        Ok(())
+    }
+
+    /// Moves the audio produced since the last call into `buffer`, as
+    /// interleaved stereo samples at the configured sample rate.
+    pub fn audio_samples_into(&mut self, buffer: &mut Vec<i16>) {
+        self.state.audio_samples_into(buffer);
+    }
+
+    /// Calls a routine with a value in A, running it until it returns.
+    fn call(&mut self, address: u16, a: u8) -> crate::Result<()> {
+        let state = &mut self.state;
+        let start = state.cpu.clock();
+
+        // This mirrors how CALL stores the return address, so RET can pop it
+        let target = state.cpu.get16(WideRegister::Sp).wrapping_sub(2);
+        state.mmu.write(target, (RETURN_ADDRESS >> 8) as u8);
+        state
+            .mmu
+            .write(target.wrapping_add(1), RETURN_ADDRESS as u8);
+        state.cpu.set16(WideRegister::Sp, target);
+
+        state.cpu.set(Register::A, a);
+        state.jump(address);
+
+        while state.cpu.get16(WideRegister::Pc) != RETURN_ADDRESS {
+            if state.cpu.clock() - start > ROUTINE_TIMEOUT {
+                return Err(GbsLoadError::RoutineTimeout(address).into());
+            }
+
+            crate::tick(state)?;
+        }
+
+        Ok(())
+    }
+
+    /// Leaves the CPU idle until a cycle, while the rest of the hardware
+    /// keeps running.
+    fn idle_until(&mut self, cycle: u64) {
+        let clock = self.state.cpu.clock();
+
+        if cycle > clock {
+            self.state.cpu.increment_clock(cycle - clock);
+            self.state.step_hardware(clock);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gbs::tests::gbs, CYCLES_PER_FRAME};
+
+    /// Stores the song number in 0xC000, then counts calls to play in B
+    /// and 0xC001.
+    const COUNTER: [u8; 12] = [
+        0x21, 0x00, 0xC0, // LD HL, $C000
+        0x77, // LD (HL), A
+        0xC9, // RET
+        0x04, // INC B
+        0x78, // LD A, B
+        0x21, 0x01, 0xC0, // LD HL, $C001
+        0x77, // LD (HL), A
+        0xC9, // RET
+    ];
+
+    fn player(timer: (u8, u8), code: &[u8]) -> GbsPlayer {
+        let gbs = GbsFile::from_buffer(&gbs(0x400, 0x405, timer, code)).unwrap();
+        GbsPlayer::new(gbs, Config::default()).unwrap()
+    }
+
+    #[test]
+    fn it_inits_the_first_song() {
+        let player = player((0, 0), &COUNTER);
+
+        assert_eq!(1, player.song());
+        assert_eq!(1, player.state().mmu.read(0xC000));
+        assert_eq!(0x80, player.state().mmu.read(NR52) & 0x80);
+    }
+
+    #[test]
+    fn it_plays_every_vblank() {
+        let mut player = player((0, 0), &COUNTER);
+        player.run_for(CYCLES_PER_FRAME * 10).unwrap();
+
+        assert_eq!(10, player.state().mmu.read(0xC001));
+    }
+
+    #[test]
+    fn it_plays_off_the_timer() {
+        // 4096 Hz overflowing every 64 ticks is 64 Hz
+        let mut player = player((0xC0, 0x04), &COUNTER);
+        player.run_for(CYCLES_PER_SECOND / 4).unwrap();
+
+        assert_eq!(16, player.state().mmu.read(0xC001));
+    }
+
+    #[test]
+    fn it_switches_songs() {
+        let mut player = player((0, 0), &COUNTER);
+        player.run_for(CYCLES_PER_FRAME * 3).unwrap();
+        player.start_song(2).unwrap();
+
+        assert_eq!(2, player.state().mmu.read(0xC000));
+        assert_eq!(0, player.state().mmu.read(0xC001));
+        assert!(player.start_song(3).is_err());
+    }
+
+    #[test]
+    fn it_reads_from_switched_banks() {
+        // Selects bank 2 on init
+        let mut code = vec![
+            0x21, 0x00, 0x20, // LD HL, $2000
+            0x3E, 0x02, // LD A, 2
+            0x77, // LD (HL), A
+            0xC9, // RET
+        ];
+        code.resize(0x8000 - 0x400, 0x00);
+        code.push(0x42);
+
+        let player = player((0, 0), &code);
+
+        assert_eq!(0x42, player.state().mmu.read(0x4000));
+        assert_eq!(0x21, player.state().mmu.read(0x400));
+    }
+
+    #[test]
+    fn it_gives_up_on_routines_that_never_return() {
+        // JR -2
+        let gbs = GbsFile::from_buffer(&gbs(0x400, 0x400, (0, 0), &[0x18, 0xFE])).unwrap();
+
+        assert!(GbsPlayer::new(gbs, Config::default()).is_err());
+    }
+}
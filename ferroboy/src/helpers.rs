@@ -32,6 +32,44 @@ pub fn format_hex_address(address: u16, wide: bool) -> String {
     }
 }
 
+/// The lookup table for the reflected CRC-32 polynomial (`0xEDB88320`),
+/// built at compile time.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        let mut value = index as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            value = if value & 1 == 1 {
+                (value >> 1) ^ 0xEDB8_8320
+            } else {
+                value >> 1
+            };
+            bit += 1;
+        }
+
+        table[index] = value;
+        index += 1;
+    }
+
+    table
+};
+
+/// Computes the CRC-32 checksum of a buffer, as used by
+/// patch formats and ROM databases.
+///
+/// # Arguments
+///
+/// * `data` - The bytes to checksum
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        CRC32_TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,4 +91,18 @@ mod tests {
             assert_eq!((0xBE, 0xEF), u16_to_word(0xBEEF));
         }
     }
+
+    mod crc32 {
+        use super::*;
+
+        #[test]
+        fn it_checksums_an_empty_buffer() {
+            assert_eq!(0x0000_0000, crc32(&[]));
+        }
+
+        #[test]
+        fn it_checksums_a_buffer() {
+            assert_eq!(0xCBF4_3926, crc32(b"123456789"));
+        }
+    }
 }
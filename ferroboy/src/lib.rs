@@ -3,13 +3,23 @@
 
 pub use crate::{
     state::{State, StateBuilder},
-    system::{Cartridge, CartridgeBuilder, Config, ConfigBuilder},
+    system::{
+        global_checksum, header_checksum, validate_header, AudioChannel, Buttons, Cartridge,
+        CartridgeBuilder, CartridgeType, ChannelInfo, Colour, Config, ConfigBuilder, DumpStatus,
+        Frame, FrameBlending, HeaderMismatch, HeaderWriter, Interrupts, LcdMode, Lcdc, LinkCable,
+        Loopback, Model, OpposingDirections, Palette, Patch, PatchFormat, PixelFormat,
+        RecordedStream, RegisterWrite, Renderer, RomDatabase, RomEntry, SerialCapture, Sgb,
+        DEFAULT_SAMPLE_RATE, MAX_BLENDED_FRAMES, NINTENDO_LOGO, SCREEN_HEIGHT, SCREEN_WIDTH,
+    },
 };
 
 #[cfg(feature = "introspection")]
 pub use crate::{
     operations::Operation,
-    system::{Register, WideRegister, OPCODES},
+    system::{
+        Register, RgbaImage, Sprite, SpriteAttributes, Tile, TileMapArea, VideoInspector,
+        WideRegister, WindowInfo, OPCODES, TILE_COUNT,
+    },
 };
 
 #[cfg(feature = "disassembly")]
@@ -20,7 +30,20 @@ use crate::error::Error;
 #[cfg(not(feature = "introspection"))]
 use crate::system::{WideRegister, OPCODES};
 
+use crate::operations::HaltOperation;
+
+/// How many T-cycles it takes the PPU to draw a full frame.
+pub const CYCLES_PER_FRAME: u64 = 70224;
+/// The DMG-01's clock speed, in T-cycles per second.
+pub const CYCLES_PER_SECOND: u64 = 4_194_304;
+
+pub mod audio;
 pub mod error;
+pub mod gbs;
+pub mod link;
+pub mod printer;
+pub mod test_rom;
+pub mod upscale;
 
 mod assembly;
 mod helpers;
@@ -46,8 +69,20 @@ pub fn start(state: &mut State) -> Result<()> {
 /// In an ideal world, this should be done at the clock rate of the Gameboy, but technically
 /// can be done at any rate.
 pub fn tick(state: &mut State) -> Result<&'static dyn crate::operations::Operation> {
+    state.service_interrupts();
+
+    let clock = state.cpu.clock();
+
+    // A halted CPU does nothing, but the rest of the system keeps running
+    if state.is_halted() {
+        state.cpu.increment_clock(4);
+        state.step_hardware(clock);
+
+        return Ok(&HaltOperation);
+    }
+
     let address = state.cpu.get16(WideRegister::Pc);
-    let opcode = state.mmu[address];
+    let opcode = state.mmu.read(address);
 
     state.increment_program_counter()?;
 
@@ -61,8 +96,30 @@ pub fn tick(state: &mut State) -> Result<&'static dyn crate::operations::Operati
             println!("\t{:?}", operation);
         }
 
-        operation.act(state).map(|_| *operation)
+        operation.act(state)?;
+        state.step_hardware(clock);
+
+        Ok(*operation)
     } else {
         Err(Error::InvalidOperation(opcode))
     }
 }
+
+/// Steps the emulation until the PPU completes a frame, which is then
+/// available from `State::frame`.
+///
+/// While the LCD is off no frames are drawn, so this gives up after a
+/// frame's worth of cycles.
+pub fn run_frame(state: &mut State) -> Result<()> {
+    let start = state.cpu.clock();
+
+    while state.cpu.clock() - start < CYCLES_PER_FRAME {
+        tick(state)?;
+
+        if state.mmu.ppu.take_frame_ready() {
+            break;
+        }
+    }
+
+    Ok(())
+}
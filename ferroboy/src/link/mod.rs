@@ -0,0 +1,12 @@
+//! Ways of connecting the serial ports of separate emulator instances.
+//!
+//! Externally-clocked transfers only behave like they would on hardware if
+//! both sides are at roughly the same point in time when one of them starts
+//! a transfer, so these keep the two instances in step with each other,
+//! rather than letting either run freely.
+
+mod pair;
+mod tcp;
+
+pub use pair::{LinkedPair, Side};
+pub use tcp::TcpLinkCable;
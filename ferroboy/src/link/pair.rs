@@ -0,0 +1,227 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::{
+    system::{Buttons, Frame, LinkCable},
+    State, CYCLES_PER_FRAME,
+};
+
+/// One of the two instances in a `LinkedPair`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn index(self) -> usize {
+        match self {
+            Side::Left => 0,
+            Side::Right => 1,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+/// What each end of the cable last saw of its serial port.
+#[derive(Debug, Default)]
+struct Wire {
+    /// The byte in SB, for each side that's waiting on an external clock.
+    listening: [Option<u8>; 2],
+    /// Bytes sent to each side, that it hasn't picked up yet.
+    inbox: [Option<u8>; 2],
+}
+
+#[derive(Debug)]
+struct WireEnd {
+    wire: Arc<Mutex<Wire>>,
+    side: Side,
+}
+
+impl LinkCable for WireEnd {
+    fn exchange(&mut self, outgoing: u8) -> u8 {
+        let mut wire = self.wire.lock().unwrap_or_else(PoisonError::into_inner);
+        let other = self.side.other().index();
+
+        match wire.listening[other].take() {
+            Some(incoming) => {
+                wire.inbox[other] = Some(outgoing);
+                incoming
+            }
+            None => 0xFF,
+        }
+    }
+
+    fn poll(&mut self, _cycles: u64, listening: Option<u8>) -> Option<u8> {
+        let mut wire = self.wire.lock().unwrap_or_else(PoisonError::into_inner);
+        let side = self.side.index();
+
+        match wire.inbox[side].take() {
+            Some(incoming) => {
+                wire.listening[side] = None;
+                Some(incoming)
+            }
+            None => {
+                wire.listening[side] = listening;
+                None
+            }
+        }
+    }
+}
+
+/// Two instances in the same process, with their serial ports connected.
+///
+/// The pair is run one instruction at a time, always stepping whichever
+/// side is behind, so neither is ever more than an instruction ahead of
+/// the other. That means a transfer reaches the other side at the cycle it
+/// started on, just like it would on hardware, and every run plays out the
+/// same way.
+#[derive(Debug)]
+pub struct LinkedPair {
+    states: [State; 2],
+}
+
+impl LinkedPair {
+    /// Connects two instances that have already been started, replacing
+    /// anything that was plugged into their serial ports.
+    pub fn new(mut left: State, mut right: State) -> Self {
+        let wire = Arc::new(Mutex::new(Wire::default()));
+
+        for (state, side) in [(&mut left, Side::Left), (&mut right, Side::Right)] {
+            state.connect_link_cable(WireEnd {
+                wire: wire.clone(),
+                side,
+            });
+        }
+
+        Self {
+            states: [left, right],
+        }
+    }
+
+    pub fn state(&self, side: Side) -> &State {
+        &self.states[side.index()]
+    }
+
+    pub fn state_mut(&mut self, side: Side) -> &mut State {
+        &mut self.states[side.index()]
+    }
+
+    /// Holds down exactly the given buttons on one side.
+    pub fn set_buttons(&mut self, side: Side, buttons: Buttons) {
+        self.state_mut(side).set_buttons(buttons);
+    }
+
+    /// The most recently completed frame from each side.
+    pub fn frames(&self) -> (&Frame, &Frame) {
+        (self.states[0].frame(), self.states[1].frame())
+    }
+
+    /// Steps whichever side is behind.
+    pub fn tick(&mut self) -> crate::Result<Side> {
+        let side = self.behind();
+        crate::tick(self.state_mut(side))?;
+
+        Ok(side)
+    }
+
+    /// Runs both sides for a number of T-cycles.
+    pub fn run_for(&mut self, cycles: u64) -> crate::Result<()> {
+        let end = self.clock() + cycles;
+
+        while self.clock() < end {
+            self.tick()?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs until both sides have completed a frame, returning them.
+    ///
+    /// As with `run_frame`, a side with the LCD off gives up after a
+    /// frame's worth of cycles.
+    pub fn run_frame(&mut self) -> crate::Result<(&Frame, &Frame)> {
+        let end = self.clock() + CYCLES_PER_FRAME;
+        let mut finished = [false; 2];
+
+        while !finished.iter().all(|finished| *finished) && self.clock() < end {
+            let side = self.tick()?;
+
+            if self.states[side.index()].mmu.ppu.take_frame_ready() {
+                finished[side.index()] = true;
+            }
+        }
+
+        Ok(self.frames())
+    }
+
+    /// The cycle both sides have reached.
+    fn clock(&self) -> u64 {
+        self.states[0].cpu.clock().min(self.states[1].cpu.clock())
+    }
+
+    fn behind(&self) -> Side {
+        if self.states[1].cpu.clock() < self.states[0].cpu.clock() {
+            Side::Right
+        } else {
+            Side::Left
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wire() -> (WireEnd, WireEnd) {
+        let wire = Arc::new(Mutex::new(Wire::default()));
+
+        (
+            WireEnd {
+                wire: wire.clone(),
+                side: Side::Left,
+            },
+            WireEnd {
+                wire,
+                side: Side::Right,
+            },
+        )
+    }
+
+    #[test]
+    fn it_exchanges_with_a_listening_side() {
+        let (mut master, mut slave) = wire();
+
+        assert_eq!(None, slave.poll(4, Some(0xCD)));
+        assert_eq!(0xCD, master.exchange(0xAB));
+        assert_eq!(Some(0xAB), slave.poll(4, None));
+        assert_eq!(None, slave.poll(4, None));
+    }
+
+    #[test]
+    fn it_answers_0xff_when_the_other_side_isnt_listening() {
+        let (mut master, mut slave) = wire();
+
+        assert_eq!(None, slave.poll(4, None));
+        assert_eq!(0xFF, master.exchange(0xAB));
+        assert_eq!(None, slave.poll(4, Some(0xCD)));
+    }
+
+    #[test]
+    fn it_steps_whichever_side_is_behind() {
+        let mut pair = LinkedPair::new(State::default(), State::default());
+        pair.state_mut(Side::Left).cpu.increment_clock(8);
+
+        assert_eq!(Side::Right, pair.tick().unwrap());
+        assert_eq!(Side::Right, pair.tick().unwrap());
+        assert_eq!(Side::Left, pair.tick().unwrap());
+
+        pair.run_for(1000).unwrap();
+        assert!(pair.clock() >= 1008);
+    }
+}
@@ -0,0 +1,403 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError},
+    thread,
+    time::Duration,
+};
+
+use crate::{system::LinkCable, CYCLES_PER_FRAME};
+
+/// How often each side tells the other how far it's got, in T-cycles.
+const SYNC_INTERVAL: u64 = CYCLES_PER_FRAME / 4;
+/// How far either side can get ahead of the other before it has to wait
+/// for it to catch up.
+const WINDOW: u64 = CYCLES_PER_FRAME;
+/// How long to wait on the other side before giving up on it.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+const SYNC: u8 = 0x01;
+const TRANSFER: u8 = 0x02;
+const REPLY: u8 = 0x03;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Message {
+    /// The sender has run for this many T-cycles.
+    Sync(u64),
+    /// The sender started a transfer on its internal clock at a cycle.
+    Transfer(u64, u8),
+    /// The answer to a transfer.
+    Reply(u8),
+}
+
+impl Message {
+    fn write_to<W: Write>(self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Message::Sync(cycle) => {
+                writer.write_all(&[SYNC])?;
+                writer.write_all(&cycle.to_le_bytes())?;
+            }
+            Message::Transfer(cycle, byte) => {
+                writer.write_all(&[TRANSFER])?;
+                writer.write_all(&cycle.to_le_bytes())?;
+                writer.write_all(&[byte])?;
+            }
+            Message::Reply(byte) => writer.write_all(&[REPLY, byte])?,
+        }
+
+        writer.flush()
+    }
+
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut tag = [0; 1];
+        reader.read_exact(&mut tag)?;
+
+        let mut byte = [0; 1];
+        let mut cycle = [0; 8];
+
+        match tag[0] {
+            SYNC => {
+                reader.read_exact(&mut cycle)?;
+                Ok(Message::Sync(u64::from_le_bytes(cycle)))
+            }
+            TRANSFER => {
+                reader.read_exact(&mut cycle)?;
+                reader.read_exact(&mut byte)?;
+                Ok(Message::Transfer(u64::from_le_bytes(cycle), byte[0]))
+            }
+            REPLY => {
+                reader.read_exact(&mut byte)?;
+                Ok(Message::Reply(byte[0]))
+            }
+            tag => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown link message {:#04X}", tag),
+            )),
+        }
+    }
+}
+
+/// A link cable to another instance over TCP.
+///
+/// Both sides count the T-cycles they've run since connecting, and
+/// regularly share how far they've got, with neither allowed to get more
+/// than a frame ahead. A side that starts a transfer on its internal clock
+/// waits for the other side to answer, which it does once it's reached the
+/// same cycle and is listening, with the contents of SB. If it gets so far
+/// past the transfer that it would have to wait itself, it's missed it, and
+/// answers with 0xFF.
+///
+/// If the other side goes away, or stops responding, the cable acts as
+/// though it's been unplugged.
+pub struct TcpLinkCable {
+    writer: Option<BufWriter<TcpStream>>,
+    incoming: Receiver<Message>,
+    /// T-cycles run since connecting.
+    clock: u64,
+    /// The last cycle the other side said it had reached.
+    peer_clock: u64,
+    /// When to next tell the other side where this one's got to.
+    next_sync: u64,
+    /// Transfers the other side has started that haven't been answered.
+    pending: VecDeque<(u64, u8)>,
+}
+
+impl TcpLinkCable {
+    /// Connects to an instance that's listening for one.
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        Self::from_stream(TcpStream::connect(address)?)
+    }
+
+    /// Waits for another instance to connect.
+    pub fn accept(listener: &TcpListener) -> io::Result<Self> {
+        let (stream, _) = listener.accept()?;
+        Self::from_stream(stream)
+    }
+
+    pub fn from_stream(stream: TcpStream) -> io::Result<Self> {
+        // Every message is tiny and waited on, so don't let them queue up
+        stream.set_nodelay(true)?;
+
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let (sender, incoming) = mpsc::channel();
+
+        thread::spawn(move || {
+            while let Ok(message) = Message::read_from(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            writer: Some(BufWriter::new(stream)),
+            incoming,
+            clock: 0,
+            peer_clock: 0,
+            next_sync: SYNC_INTERVAL,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Whether the other side is still there.
+    pub fn is_connected(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    fn send(&mut self, message: Message) {
+        let sent = match self.writer.as_mut() {
+            Some(writer) => message.write_to(writer),
+            None => return,
+        };
+
+        if sent.is_err() {
+            self.disconnect();
+        }
+    }
+
+    fn disconnect(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.get_ref().shutdown(Shutdown::Both);
+        }
+
+        self.pending.clear();
+    }
+
+    fn handle(&mut self, message: Message) -> Option<u8> {
+        match message {
+            Message::Sync(cycle) => self.peer_clock = cycle,
+            Message::Transfer(cycle, byte) => {
+                self.peer_clock = cycle;
+                self.pending.push_back((cycle, byte));
+            }
+            Message::Reply(byte) => return Some(byte),
+        }
+
+        None
+    }
+
+    /// Handles whatever's arrived without waiting.
+    fn receive(&mut self) {
+        loop {
+            match self.incoming.try_recv() {
+                Ok(message) => {
+                    // Replies only come while waiting on one
+                    self.handle(message);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.disconnect();
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Waits for the next message, disconnecting if it doesn't come.
+    fn wait(&mut self) -> Option<Message> {
+        match self.incoming.recv_timeout(TIMEOUT) {
+            Ok(message) => Some(message),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {
+                self.disconnect();
+                None
+            }
+        }
+    }
+
+    /// Answers the oldest pending transfer if this side is listening and
+    /// has caught up to it.
+    fn answer(&mut self, listening: Option<u8>) -> Option<u8> {
+        let (cycle, byte) = *self.pending.front()?;
+        let outgoing = listening.filter(|_| self.clock >= cycle)?;
+
+        self.pending.pop_front();
+        self.send(Message::Reply(outgoing));
+
+        Some(byte)
+    }
+
+    /// Answers every pending transfer with 0xFF, as this side has gone past
+    /// them without listening.
+    fn miss(&mut self) {
+        while self.pending.pop_front().is_some() {
+            self.send(Message::Reply(0xFF));
+        }
+    }
+}
+
+impl LinkCable for TcpLinkCable {
+    fn exchange(&mut self, outgoing: u8) -> u8 {
+        // Both sides driving the clock at once get nothing from each other
+        self.miss();
+
+        self.send(Message::Transfer(self.clock, outgoing));
+
+        while self.is_connected() {
+            match self.wait() {
+                Some(Message::Transfer(cycle, _)) => {
+                    self.peer_clock = cycle;
+                    self.send(Message::Reply(0xFF));
+                }
+                Some(message) => {
+                    if let Some(incoming) = self.handle(message) {
+                        return incoming;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        0xFF
+    }
+
+    fn poll(&mut self, cycles: u64, listening: Option<u8>) -> Option<u8> {
+        if !self.is_connected() {
+            return None;
+        }
+
+        self.clock += cycles;
+        self.receive();
+
+        if let Some(incoming) = self.answer(listening) {
+            return Some(incoming);
+        }
+
+        if self.clock < self.next_sync {
+            return None;
+        }
+
+        self.send(Message::Sync(self.clock));
+        self.next_sync = self.clock + SYNC_INTERVAL;
+
+        // Too far ahead, so wait for the other side to catch up. If it's
+        // stuck waiting on an answer, this side won't be listening any time
+        // soon, so it's missed the transfer.
+        while self.is_connected() && self.clock > self.peer_clock + WINDOW {
+            if let Some(incoming) = self.answer(listening) {
+                return Some(incoming);
+            }
+
+            self.miss();
+
+            if let Some(message) = self.wait() {
+                self.handle(message);
+            }
+        }
+
+        self.answer(listening)
+    }
+}
+
+impl Drop for TcpLinkCable {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+impl std::fmt::Debug for TcpLinkCable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TcpLinkCable")
+            .field("connected", &self.is_connected())
+            .field("clock", &self.clock)
+            .field("peer_clock", &self.peer_clock)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> (TcpLinkCable, TcpLinkCable) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = thread::spawn(move || TcpLinkCable::connect(address).unwrap());
+        let server = TcpLinkCable::accept(&listener).unwrap();
+
+        (server, client.join().unwrap())
+    }
+
+    #[test]
+    fn it_round_trips_messages() {
+        for message in [
+            Message::Sync(0x0123_4567_89AB_CDEF),
+            Message::Transfer(42, 0x99),
+            Message::Reply(0x12),
+        ] {
+            let mut buffer = Vec::new();
+            message.write_to(&mut buffer).unwrap();
+
+            assert_eq!(message, Message::read_from(&mut &buffer[..]).unwrap());
+        }
+    }
+
+    #[test]
+    fn it_exchanges_with_a_listening_side() {
+        let (mut master, mut slave) = pair();
+
+        let slave = thread::spawn(move || loop {
+            if let Some(incoming) = slave.poll(4, Some(0xCD)) {
+                return incoming;
+            }
+        });
+
+        assert_eq!(0xCD, master.exchange(0xAB));
+        assert_eq!(0xAB, slave.join().unwrap());
+    }
+
+    #[test]
+    fn it_answers_0xff_when_a_transfer_is_missed() {
+        let (mut master, mut slave) = pair();
+
+        let slave = thread::spawn(move || {
+            for _ in 0..8 {
+                assert_eq!(None, slave.poll(SYNC_INTERVAL, None));
+            }
+        });
+
+        assert_eq!(0xFF, master.exchange(0xAB));
+
+        // Catch up, so the other side can finish
+        master.poll(8 * SYNC_INTERVAL, None);
+        slave.join().unwrap();
+    }
+
+    #[test]
+    fn it_answers_0xff_when_both_sides_drive_the_clock() {
+        let (mut left, mut right) = pair();
+
+        let right = thread::spawn(move || right.exchange(0x12));
+
+        assert_eq!(0xFF, left.exchange(0x34));
+        assert_eq!(0xFF, right.join().unwrap());
+    }
+
+    #[test]
+    fn it_keeps_the_sides_in_step() {
+        let (mut ahead, mut behind) = pair();
+        let (done, finished) = mpsc::channel();
+
+        thread::spawn(move || {
+            ahead.poll(WINDOW + SYNC_INTERVAL, None);
+            done.send(ahead.clock).unwrap();
+        });
+
+        // The side that's ahead can't carry on until this one catches up
+        assert!(finished.recv_timeout(Duration::from_millis(50)).is_err());
+
+        behind.poll(SYNC_INTERVAL, None);
+        assert_eq!(Ok(WINDOW + SYNC_INTERVAL), finished.recv());
+    }
+
+    #[test]
+    fn it_unplugs_when_the_other_side_goes_away() {
+        let (mut cable, other) = pair();
+        drop(other);
+
+        assert_eq!(0xFF, cable.exchange(0x12));
+        assert!(!cable.is_connected());
+        assert_eq!(None, cable.poll(SYNC_INTERVAL, Some(0x34)));
+    }
+}
@@ -65,7 +65,7 @@ impl Operation for AndOperation {
             AndTarget::Register(reg) => state.cpu.get(*reg),
             AndTarget::Address => {
                 let address = state.cpu.get16(WideRegister::Hl);
-                state.mmu[address]
+                state.mmu.read(address)
             }
             AndTarget::Immediate => state.read_byte()?,
         };
@@ -104,8 +104,8 @@ impl Operation for CallOperation {
             .unwrap_or(true);
 
         if conditional_passed {
-            state.mmu[target] = (program_counter >> 8) as u8;
-            state.mmu[target + 1] = program_counter as u8;
+            state.mmu.write(target, (program_counter >> 8) as u8);
+            state.mmu.write(target + 1, program_counter as u8);
 
             let address = word_to_u16(state.read_word()?);
 
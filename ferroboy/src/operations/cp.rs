@@ -81,7 +81,7 @@ impl Operation for CpOperation {
             CpTarget::Register(r) => state.cpu.get(*r),
             CpTarget::Address => {
                 let address = state.cpu.get16(WideRegister::Hl);
-                state.mmu[address]
+                state.mmu.read(address)
             }
             CpTarget::Immediate => state.read_byte()?,
         };
@@ -132,8 +132,6 @@ impl Disassemble for Load8RegisterCopyOperation {
     }
 }
 
-// FIXME: This doesn't work for LD A,(C), LD A,(a8) or LD A,(a16)
-// ? Maybe a flag enum? Load8MemorySource with values Register, WideRegister, Immediate, WideImmediate?
 // FIXME: Metrics here are based as-implemented, but should be updated when impl is correct
 // e.g. LD A,(a16) is 3 bytes, 16 cycles vs LD A,(HL) at 1 byte 8 cycles
 /// Load an 8-bit value from the address stored in a 16-bit register.
@@ -175,7 +173,7 @@ impl Operation for Load8FromMemoryOperation {
         let address_high = state.cpu.get(high);
         let address_low = state.cpu.get(low);
         let address = word_to_u16((address_high, address_low));
-        let value = state.mmu[address];
+        let value = state.mmu.read(address);
 
         state.cpu.set(self.0, value);
         state.cpu.increment_clock(8);
@@ -215,7 +213,7 @@ impl Operation for Load8AbsoluteOperation {
             Load8AbsoluteTarget::HLPositive => target + 1,
         };
 
-        state.mmu[target] = value;
+        state.mmu.write(target, value);
         state.cpu.set16(WideRegister::Hl, new_address);
         state.cpu.increment_clock(2);
 
@@ -249,13 +247,10 @@ impl Disassemble for Load8AbsoluteOperation {
 
 #[derive(Clone, Copy, Debug)]
 pub enum Load8RegisterToMemoryTarget {
-    // ? Should this lose it's argument? Can only be C
-    Register(Register),
     WideRegister(WideRegister),
 }
 
 // FIXME: Metrics here are based on implementation and should be fixed
-// FIXME: Doesn't support LD (a16),A
 /// Copies a value from a register to the address held in a register.
 ///
 /// # Opcode Reference
@@ -267,10 +262,8 @@ pub enum Load8RegisterToMemoryTarget {
 /// ## Runtime
 /// | Metric | Size |
 /// |:-------|:-----|
-/// | Length | 1*   |
-/// | Cycles | 8*   |
-///
-/// `*`: `LD (C),A` (0xE2) is 2 bytes and 8 cycles.
+/// | Length | 1    |
+/// | Cycles | 8    |
 ///
 /// ## Flags
 /// | Flag          | Value        |
@@ -294,11 +287,10 @@ impl Operation for Load8RegisterToMemoryOperation {
     fn act(&self, state: &mut State) -> crate::Result<()> {
         let address = match self.0 {
             Load8RegisterToMemoryTarget::WideRegister(r) => state.cpu.get16(r),
-            Load8RegisterToMemoryTarget::Register(r) => u16::from(state.cpu.get(r)),
         };
 
         let value = state.cpu.get(self.1);
-        state.mmu[address] = value;
+        state.mmu.write(address, value);
 
         state.cpu.increment_clock(8);
 
@@ -318,14 +310,216 @@ impl Disassemble for Load8RegisterToMemoryOperation {
                 "({})",
                 match self.0 {
                     Load8RegisterToMemoryTarget::WideRegister(r) => r.to_string(),
-                    Load8RegisterToMemoryTarget::Register(r) => r.to_string(),
                 }
             ))
             .with_arg(self.1)
-            .with_size(match self.0 {
-                Load8RegisterToMemoryTarget::Register(Register::C) => 2,
-                _ => 1,
-            })
+            .build()
+    }
+}
+
+/// Where the accumulator loads find the address they read or write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Load8AccumulatorAddress {
+    /// `(C)`: 0xFF00 plus the value of C.
+    HighC,
+    /// `(a8)`: 0xFF00 plus an immediate byte, written `LDH`.
+    HighImmediate,
+    /// `(a16)`: an immediate address.
+    Immediate,
+}
+
+impl Load8AccumulatorAddress {
+    const HIGH_PAGE: u16 = 0xFF00;
+
+    fn resolve(self, state: &mut State) -> crate::Result<u16> {
+        Ok(match self {
+            Self::HighC => Self::HIGH_PAGE | u16::from(state.cpu.get(Register::C)),
+            Self::HighImmediate => Self::HIGH_PAGE | u16::from(state.read_byte()?),
+            Self::Immediate => word_to_u16(state.read_word()?),
+        })
+    }
+
+    fn command(self) -> &'static str {
+        match self {
+            Self::HighImmediate => "LDH",
+            _ => "LD",
+        }
+    }
+
+    fn size(self) -> u8 {
+        match self {
+            Self::HighC => 1,
+            Self::HighImmediate => 2,
+            Self::Immediate => 3,
+        }
+    }
+
+    fn cycles(self) -> u64 {
+        match self {
+            Self::HighC => 8,
+            Self::HighImmediate => 12,
+            Self::Immediate => 16,
+        }
+    }
+
+    fn disassemble(self, cartridge: &Cartridge, offset: usize) -> String {
+        match self {
+            Self::HighC => "(C)".into(),
+            Self::HighImmediate => format!("($FF{:02X})", cartridge.data[offset + 1]),
+            Self::Immediate => format!(
+                "(${:X})",
+                word_to_u16((cartridge.data[offset + 2], cartridge.data[offset + 1]))
+            ),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::HighC => "(C)",
+            Self::HighImmediate => "(a8)",
+            Self::Immediate => "(a16)",
+        }
+    }
+}
+
+/// Copies A into memory, at an address in the high page or an immediate one.
+///
+/// # Opcode Reference
+/// ## Assembly definition
+/// ```a
+/// LD (C),A
+/// LDH (a8),A
+/// LD (a16),A
+/// ```
+///
+/// ## Runtime
+/// | Address | Length | Cycles |
+/// |:--------|:-------|:-------|
+/// | `(C)`   | 1      | 8      |
+/// | `(a8)`  | 2      | 12     |
+/// | `(a16)` | 3      | 16     |
+///
+/// ## Flags
+/// | Flag          | Value        |
+/// |:--------------|:-------------|
+/// | Zero          | Not Affected |
+/// | Subtraction   | Not Affected |
+/// | Half-Carry    | Not Affected |
+/// | Carry         | Not Affected |
+///
+/// # Examples
+/// ```rs
+/// Load8AccumulatorToMemoryOperation(Load8AccumulatorAddress::HighImmediate)
+/// ```
+///
+/// # Errors
+/// - The operation will fail if the immediate runs past the end of memory
+#[derive(Clone, Copy, Debug)]
+pub struct Load8AccumulatorToMemoryOperation(pub Load8AccumulatorAddress);
+
+impl Operation for Load8AccumulatorToMemoryOperation {
+    fn act(&self, state: &mut State) -> crate::Result<()> {
+        let address = self.0.resolve(state)?;
+        let value = state.cpu.get(Register::A);
+
+        state.mmu.write(address, value);
+        state.cpu.increment_clock(self.0.cycles());
+
+        Ok(())
+    }
+}
+
+impl Disassemble for Load8AccumulatorToMemoryOperation {
+    fn disassemble(
+        &self,
+        cartridge: &Cartridge,
+        offset: usize,
+    ) -> crate::Result<AssemblyInstruction> {
+        AssemblyInstructionBuilder::new()
+            .with_command(self.0.command())
+            .with_arg(self.0.disassemble(cartridge, offset))
+            .with_arg(Register::A)
+            .with_size(self.0.size())
+            .build()
+    }
+
+    fn describe(&self) -> crate::Result<AssemblyInstruction> {
+        AssemblyInstructionBuilder::new()
+            .with_command(self.0.command())
+            .with_arg(self.0.describe())
+            .with_arg(Register::A)
+            .with_size(self.0.size())
+            .build()
+    }
+}
+
+/// Loads A from memory, at an address in the high page or an immediate one.
+///
+/// # Opcode Reference
+/// ## Assembly definition
+/// ```a
+/// LD A,(C)
+/// LDH A,(a8)
+/// LD A,(a16)
+/// ```
+///
+/// ## Runtime
+/// | Address | Length | Cycles |
+/// |:--------|:-------|:-------|
+/// | `(C)`   | 1      | 8      |
+/// | `(a8)`  | 2      | 12     |
+/// | `(a16)` | 3      | 16     |
+///
+/// ## Flags
+/// | Flag          | Value        |
+/// |:--------------|:-------------|
+/// | Zero          | Not Affected |
+/// | Subtraction   | Not Affected |
+/// | Half-Carry    | Not Affected |
+/// | Carry         | Not Affected |
+///
+/// # Examples
+/// ```rs
+/// Load8AccumulatorFromMemoryOperation(Load8AccumulatorAddress::Immediate)
+/// ```
+///
+/// # Errors
+/// - The operation will fail if the immediate runs past the end of memory
+#[derive(Clone, Copy, Debug)]
+pub struct Load8AccumulatorFromMemoryOperation(pub Load8AccumulatorAddress);
+
+impl Operation for Load8AccumulatorFromMemoryOperation {
+    fn act(&self, state: &mut State) -> crate::Result<()> {
+        let address = self.0.resolve(state)?;
+        let value = state.mmu.read(address);
+
+        state.cpu.set(Register::A, value);
+        state.cpu.increment_clock(self.0.cycles());
+
+        Ok(())
+    }
+}
+
+impl Disassemble for Load8AccumulatorFromMemoryOperation {
+    fn disassemble(
+        &self,
+        cartridge: &Cartridge,
+        offset: usize,
+    ) -> crate::Result<AssemblyInstruction> {
+        AssemblyInstructionBuilder::new()
+            .with_command(self.0.command())
+            .with_arg(Register::A)
+            .with_arg(self.0.disassemble(cartridge, offset))
+            .with_size(self.0.size())
+            .build()
+    }
+
+    fn describe(&self) -> crate::Result<AssemblyInstruction> {
+        AssemblyInstructionBuilder::new()
+            .with_command(self.0.command())
+            .with_arg(Register::A)
+            .with_arg(self.0.describe())
+            .with_size(self.0.size())
             .build()
     }
 }
@@ -398,6 +592,96 @@ mod tests {
         assert_eq!(0xBE, state.mmu[0x5E50]);
     }
 
+    #[test]
+    fn it_writes_a_into_the_high_page() {
+        let mut state = State::default();
+        let op = Load8AccumulatorToMemoryOperation(Load8AccumulatorAddress::HighImmediate);
+
+        state.mmu.mutate(|mmu| mmu[0x0000] = 0x80);
+        state.cpu.set(Register::A, 0xBE);
+
+        op.act(&mut state).unwrap();
+
+        assert_eq!(0xBE, state.mmu[0xFF80]);
+        assert_eq!(0x0001, state.cpu.get16(WideRegister::Pc));
+    }
+
+    #[test]
+    fn it_writes_a_to_the_high_page_offset_by_c() {
+        let mut state = State::default();
+        let op = Load8AccumulatorToMemoryOperation(Load8AccumulatorAddress::HighC);
+
+        state.cpu.set(Register::C, 0x81);
+        state.cpu.set(Register::A, 0xBE);
+
+        op.act(&mut state).unwrap();
+
+        assert_eq!(0xBE, state.mmu[0xFF81]);
+        assert_eq!(0x0000, state.cpu.get16(WideRegister::Pc));
+    }
+
+    #[test]
+    fn it_writes_a_to_an_immediate_address() {
+        let mut state = State::default();
+        let op = Load8AccumulatorToMemoryOperation(Load8AccumulatorAddress::Immediate);
+
+        state.mmu.mutate(|mmu| {
+            mmu[0x0000] = 0x50;
+            mmu[0x0001] = 0xC0;
+        });
+        state.cpu.set(Register::A, 0xBE);
+
+        op.act(&mut state).unwrap();
+
+        assert_eq!(0xBE, state.mmu[0xC050]);
+        assert_eq!(0x0002, state.cpu.get16(WideRegister::Pc));
+    }
+
+    #[test]
+    fn it_loads_a_from_the_high_page() {
+        let mut state = State::default();
+        let op = Load8AccumulatorFromMemoryOperation(Load8AccumulatorAddress::HighImmediate);
+
+        state.mmu.mutate(|mmu| {
+            mmu[0x0000] = 0x80;
+            mmu[0xFF80] = 0xFE;
+        });
+
+        op.act(&mut state).unwrap();
+
+        assert_eq!(0xFE, state.cpu.get(Register::A));
+    }
+
+    #[test]
+    fn it_loads_a_from_the_high_page_offset_by_c() {
+        let mut state = State::default();
+        let op = Load8AccumulatorFromMemoryOperation(Load8AccumulatorAddress::HighC);
+
+        state.mmu.mutate(|mmu| mmu[0xFF81] = 0xFE);
+        state.cpu.set(Register::C, 0x81);
+
+        op.act(&mut state).unwrap();
+
+        assert_eq!(0xFE, state.cpu.get(Register::A));
+    }
+
+    #[test]
+    fn it_loads_a_from_an_immediate_address() {
+        let mut state = State::default();
+        let op = Load8AccumulatorFromMemoryOperation(Load8AccumulatorAddress::Immediate);
+
+        state.mmu.mutate(|mmu| {
+            mmu[0x0000] = 0x50;
+            mmu[0x0001] = 0xC0;
+            mmu[0xC050] = 0xFE;
+        });
+
+        op.act(&mut state).unwrap();
+
+        assert_eq!(0xFE, state.cpu.get(Register::A));
+        assert_eq!(0x0002, state.cpu.get16(WideRegister::Pc));
+    }
+
     #[test]
     fn it_disassembles_immediate_to_register() {
         let cartridge = Cartridge {
@@ -439,4 +723,22 @@ mod tests {
 
         assert_eq!("LD (HL),A", instruction.to_string());
     }
+
+    #[test]
+    fn it_disassembles_accumulator_loads() {
+        let cartridge = Cartridge {
+            data: vec![0xE0, 0x46, 0xFA, 0x00, 0xC0],
+            ..Default::default()
+        };
+
+        let ldh = Load8AccumulatorToMemoryOperation(Load8AccumulatorAddress::HighImmediate)
+            .disassemble(&cartridge, 0)
+            .unwrap();
+        let ld = Load8AccumulatorFromMemoryOperation(Load8AccumulatorAddress::Immediate)
+            .disassemble(&cartridge, 2)
+            .unwrap();
+
+        assert_eq!("LDH ($FF46),A", ldh.to_string());
+        assert_eq!("LD A,($C000)", ld.to_string());
+    }
 }
@@ -81,7 +81,7 @@ impl Operation for OrOperation {
             OrTarget::Register(reg) => state.cpu.get(*reg),
             OrTarget::Address => {
                 let address = state.cpu.get16(WideRegister::Hl);
-                state.mmu[address]
+                state.mmu.read(address)
             }
             OrTarget::Immediate => state.read_byte()?,
         };
@@ -52,8 +52,8 @@ impl Operation for PopOperation {
                 let (high, low) = self.0.try_into().unwrap();
                 let address = state.cpu.get16(WideRegister::Sp);
 
-                state.cpu.set(low, state.mmu[address]);
-                state.cpu.set(high, state.mmu[address + 1]);
+                state.cpu.set(low, state.mmu.read(address));
+                state.cpu.set(high, state.mmu.read(address + 1));
                 state.cpu.set16(WideRegister::Sp, address + 2);
                 state.cpu.increment_clock(12);
 
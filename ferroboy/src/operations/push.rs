@@ -51,8 +51,8 @@ impl Operation for PushOperation {
                 let (high, low) = (state.cpu.get(high), state.cpu.get(low));
 
                 let address = state.cpu.get16(WideRegister::Sp);
-                state.mmu[address - 1] = high;
-                state.mmu[address - 2] = low;
+                state.mmu.write(address - 1, high);
+                state.mmu.write(address - 2, low);
 
                 state.cpu.set16(WideRegister::Sp, address - 2);
                 state.cpu.increment_clock(16);
@@ -66,7 +66,7 @@ pub struct RetOperation(pub Option<RetCondition>);
 impl Operation for RetOperation {
     fn act(&self, state: &mut State) -> crate::Result<()> {
         let target = state.cpu.get16(WideRegister::Sp);
-        let (high, low) = (state.mmu[target], state.mmu[target + 1]);
+        let (high, low) = (state.mmu.read(target), state.mmu.read(target + 1));
         let address = word_to_u16((high, low));
 
         let is_zero = state.cpu.has_flag(Flags::ZERO);
@@ -113,14 +113,64 @@ impl Disassemble for RetOperation {
     fn describe(&self) -> crate::Result<AssemblyInstruction> {
         let mut builder = AssemblyInstructionBuilder::new().with_command("RET");
 
-        if self.0.is_some() {
-            builder = builder.with_arg(self.0.unwrap());
+        if let Some(flag) = self.0 {
+            builder = builder.with_arg(flag);
         }
 
         builder.build()
     }
 }
 
+/// Returns from an interrupt handler, re-enabling interrupts.
+///
+/// # Opcode reference
+/// ## Assembly definition
+/// ```a
+/// RETI
+/// ```
+///
+/// ## Runtime
+/// | Metric | Size |
+/// |:-------|:-----|
+/// | Length | 1    |
+/// | Cycles | 16   |
+///
+/// ## Flags
+/// | Flag        | Value     |
+/// |:------------|:----------|
+/// | Zero        | Unchanged |
+/// | Subtraction | Unchanged |
+/// | Half-Carry  | Unchanged |
+/// | Carry       | Unchanged |
+///
+/// # Examples
+/// ```rs
+/// RetiOperation.act(&mut state).unwrap();
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct RetiOperation;
+
+impl Operation for RetiOperation {
+    fn act(&self, state: &mut State) -> crate::Result<()> {
+        RetOperation(None).act(state)?;
+        state.cpu.enable_interrupts();
+
+        Ok(())
+    }
+}
+
+impl Disassemble for RetiOperation {
+    fn disassemble(&self, _: &crate::Cartridge, _: usize) -> crate::Result<AssemblyInstruction> {
+        self.describe()
+    }
+
+    fn describe(&self) -> crate::Result<AssemblyInstruction> {
+        AssemblyInstructionBuilder::new()
+            .with_command("RETI")
+            .build()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -374,4 +424,29 @@ mod tests {
             );
         }
     }
+
+    mod reti {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn it_returns_and_enables_interrupts() {
+            let mut state = State::default();
+            state.cpu.disable_interrupts();
+            state.cpu.set16(WideRegister::Sp, 0xBEEF);
+            state.mmu[0xBEEF] = 0xFA;
+            state.mmu[0xBEF0] = 0xCE;
+
+            RetiOperation.act(&mut state).unwrap();
+
+            assert_eq!(0xFACE, state.cpu.get16(WideRegister::Pc));
+            assert_eq!(0xBEF1, state.cpu.get16(WideRegister::Sp));
+            assert!(state.cpu.interrupts_enabled());
+        }
+
+        #[test]
+        fn it_describes_properly() {
+            assert_eq!("RETI", RetiOperation.describe().unwrap().to_string());
+        }
+    }
 }
@@ -41,8 +41,8 @@ impl Operation for RstOperation {
         let address = state.cpu.get16(WideRegister::Pc);
         let target = state.cpu.get16(WideRegister::Sp) - 2;
 
-        state.mmu[target] = (address >> 8) as u8;
-        state.mmu[target + 1] = address as u8;
+        state.mmu.write(target, (address >> 8) as u8);
+        state.mmu.write(target + 1, address as u8);
 
         state.cpu.set16(WideRegister::Pc, self.0);
 
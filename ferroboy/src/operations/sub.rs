@@ -62,7 +62,7 @@ impl Operation for SubOperation {
             SubTarget::Register(reg) => state.cpu.get(*reg),
             SubTarget::Address => {
                 let address = state.cpu.get16(WideRegister::Hl);
-                state.mmu[address]
+                state.mmu.read(address)
             }
             SubTarget::Immediate => state.read_byte()?,
         };
@@ -81,7 +81,7 @@ impl Operation for XorOperation {
             XorTarget::Register(reg) => state.cpu.get(*reg),
             XorTarget::Address => {
                 let address = state.cpu.get16(WideRegister::Hl);
-                state.mmu[address]
+                state.mmu.read(address)
             }
             XorTarget::Immediate => state.read_byte()?,
         };
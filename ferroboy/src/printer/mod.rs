@@ -0,0 +1,523 @@
+//! Emulation of the Game Boy Printer, a thermal printer that plugs into
+//! the link port.
+//!
+//! Games talk to the printer in packets, each starting with the magic
+//! bytes 0x88 0x33, followed by a command, whether the data is compressed,
+//! the data's length and the data itself, a checksum, and then two bytes
+//! during which the printer answers with its ID and status. Images are sent
+//! as tiles, two rows at a time, before being printed with a palette,
+//! margins and exposure.
+
+mod printout;
+
+use bitflags::bitflags;
+
+pub use printout::Printout;
+
+use crate::{system::LinkCable, CYCLES_PER_FRAME};
+
+/// How wide the paper is, in pixels.
+pub const PAPER_WIDTH: usize = 160;
+
+const MAGIC: [u8; 2] = [0x88, 0x33];
+/// What the printer answers with during the first byte after a packet.
+const DEVICE_ID: u8 = 0x81;
+
+const COMMAND_INIT: u8 = 0x01;
+const COMMAND_PRINT: u8 = 0x02;
+const COMMAND_DATA: u8 = 0x04;
+const COMMAND_STATUS: u8 = 0x0F;
+
+/// How much image data the printer can hold, nine packets' worth.
+const BUFFER_SIZE: usize = 0x1680;
+const TILES_PER_ROW: usize = PAPER_WIDTH / 8;
+const BYTES_PER_TILE: usize = 16;
+const BYTES_PER_TILE_ROW: usize = TILES_PER_ROW * BYTES_PER_TILE;
+/// How many rows of pixels each margin step feeds the paper by.
+const ROWS_PER_FEED: usize = 16;
+/// How long it takes to print each row of tiles.
+const CYCLES_PER_TILE_ROW: u64 = CYCLES_PER_FRAME;
+/// The exposure that leaves shades as they are.
+const DEFAULT_EXPOSURE: u8 = 0x40;
+
+bitflags! {
+    /// The printer's status byte.
+    pub struct PrinterStatus: u8 {
+        const CHECKSUM_ERROR = 0b0000_0001;
+        const PRINTING = 0b0000_0010;
+        /// Data has ended, and it's ready to print.
+        const IMAGE_DATA_FULL = 0b0000_0100;
+        /// Data has been received that hasn't been printed.
+        const UNPROCESSED_DATA = 0b0000_1000;
+        const PACKET_ERROR = 0b0001_0000;
+        const PAPER_JAM = 0b0010_0000;
+        const OTHER_ERROR = 0b0100_0000;
+        const LOW_BATTERY = 0b1000_0000;
+    }
+}
+
+impl Default for PrinterStatus {
+    fn default() -> Self {
+        PrinterStatus::empty()
+    }
+}
+
+/// Where the printer is in receiving a packet.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Stage {
+    Magic(usize),
+    Command,
+    Compression,
+    Length(usize),
+    Data,
+    Checksum(usize),
+    DeviceId,
+    Status,
+}
+
+impl Default for Stage {
+    fn default() -> Self {
+        Stage::Magic(0)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Packet {
+    command: u8,
+    compressed: bool,
+    length: u16,
+    data: Vec<u8>,
+    checksum: u16,
+}
+
+impl Packet {
+    fn is_valid(&self) -> bool {
+        let length = self.length.to_le_bytes();
+        let header = [
+            self.command,
+            u8::from(self.compressed),
+            length[0],
+            length[1],
+        ];
+
+        let sum = header
+            .iter()
+            .chain(&self.data)
+            .fold(0u16, |sum, byte| sum.wrapping_add(u16::from(*byte)));
+
+        sum == self.checksum
+    }
+}
+
+/// A Game Boy Printer, to plug into the serial port.
+///
+/// The paper is one continuous strip, which is torn off into a
+/// [`Printout`] whenever a print feeds it on afterwards. Printing isn't
+/// instant, and the printer reports itself busy for a while after each
+/// print, as games wait on it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Printer {
+    stage: Stage,
+    packet: Packet,
+    status: PrinterStatus,
+    /// Decompressed tile data waiting to be printed.
+    buffer: Vec<u8>,
+    /// How many more T-cycles the current print takes.
+    printing: u64,
+    /// The paper that's been printed on but not torn off.
+    sheet: Option<Printout>,
+    printouts: Vec<Printout>,
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(&self) -> PrinterStatus {
+        self.status
+    }
+
+    /// The printouts that have been torn off so far.
+    pub fn printouts(&self) -> &[Printout] {
+        &self.printouts
+    }
+
+    /// Takes the printouts that have been torn off so far.
+    pub fn take_printouts(&mut self) -> Vec<Printout> {
+        std::mem::take(&mut self.printouts)
+    }
+
+    /// Tears off the paper that's still in the printer, if it's been
+    /// printed on.
+    pub fn tear_off(&mut self) -> Option<Printout> {
+        self.sheet.take()
+    }
+
+    /// Receives the next byte of a packet, returning the printer's answer.
+    fn receive(&mut self, byte: u8) -> u8 {
+        let (next, answer) = match self.stage {
+            Stage::Magic(index) if byte != MAGIC[index] => (Stage::Magic(0), 0x00),
+            Stage::Magic(0) => (Stage::Magic(1), 0x00),
+            Stage::Magic(_) => {
+                self.packet = Packet::default();
+                (Stage::Command, 0x00)
+            }
+            Stage::Command => {
+                self.packet.command = byte;
+                (Stage::Compression, 0x00)
+            }
+            Stage::Compression => {
+                self.packet.compressed = byte & 0x01 != 0;
+                (Stage::Length(0), 0x00)
+            }
+            Stage::Length(0) => {
+                self.packet.length = u16::from(byte);
+                (Stage::Length(1), 0x00)
+            }
+            Stage::Length(_) => {
+                self.packet.length |= u16::from(byte) << 8;
+
+                if self.packet.length == 0 {
+                    (Stage::Checksum(0), 0x00)
+                } else {
+                    (Stage::Data, 0x00)
+                }
+            }
+            Stage::Data => {
+                self.packet.data.push(byte);
+
+                if self.packet.data.len() == usize::from(self.packet.length) {
+                    (Stage::Checksum(0), 0x00)
+                } else {
+                    (Stage::Data, 0x00)
+                }
+            }
+            Stage::Checksum(0) => {
+                self.packet.checksum = u16::from(byte);
+                (Stage::Checksum(1), 0x00)
+            }
+            Stage::Checksum(_) => {
+                self.packet.checksum |= u16::from(byte) << 8;
+                self.process();
+                (Stage::DeviceId, 0x00)
+            }
+            Stage::DeviceId => (Stage::Status, DEVICE_ID),
+            Stage::Status => (Stage::Magic(0), self.status.bits()),
+        };
+
+        self.stage = next;
+        answer
+    }
+
+    fn process(&mut self) {
+        let packet = std::mem::take(&mut self.packet);
+
+        if !packet.is_valid() {
+            self.status |= PrinterStatus::CHECKSUM_ERROR;
+            return;
+        }
+
+        self.status -= PrinterStatus::CHECKSUM_ERROR;
+
+        match packet.command {
+            COMMAND_INIT => {
+                self.buffer.clear();
+                self.status = PrinterStatus::empty();
+            }
+            COMMAND_PRINT if packet.data.len() == 4 => self.print(&packet.data),
+            // An empty data packet marks the end of the image
+            COMMAND_DATA if packet.data.is_empty() => {
+                self.status |= PrinterStatus::IMAGE_DATA_FULL;
+            }
+            COMMAND_DATA => {
+                let data = if packet.compressed {
+                    decompress(&packet.data)
+                } else {
+                    packet.data
+                };
+
+                let space = BUFFER_SIZE - self.buffer.len();
+                self.buffer
+                    .extend_from_slice(&data[..data.len().min(space)]);
+                self.status |= PrinterStatus::UNPROCESSED_DATA;
+            }
+            COMMAND_STATUS => {}
+            _ => self.status |= PrinterStatus::PACKET_ERROR,
+        }
+    }
+
+    /// Prints the buffered image, from a print command's sheet count,
+    /// margins, palette and exposure.
+    fn print(&mut self, arguments: &[u8]) {
+        let (margins, palette, exposure) = (arguments[1], arguments[2], arguments[3] & 0x7F);
+        let tile_rows = self.buffer.len() / BYTES_PER_TILE_ROW;
+
+        let sheet = self.sheet.get_or_insert_with(|| Printout::new(PAPER_WIDTH));
+        sheet.feed(usize::from(margins >> 4) * ROWS_PER_FEED);
+
+        for tiles in self.buffer.chunks_exact(BYTES_PER_TILE_ROW) {
+            sheet.print(&decode_tile_row(tiles, palette, exposure));
+        }
+
+        let feed_after = usize::from(margins & 0x0F);
+        if feed_after > 0 {
+            sheet.feed(feed_after * ROWS_PER_FEED);
+            self.printouts.extend(self.sheet.take());
+        }
+
+        self.buffer.clear();
+        self.printing = CYCLES_PER_TILE_ROW * tile_rows.max(1) as u64;
+        self.status -= PrinterStatus::UNPROCESSED_DATA;
+        self.status |= PrinterStatus::PRINTING;
+    }
+}
+
+impl LinkCable for Printer {
+    fn exchange(&mut self, outgoing: u8) -> u8 {
+        self.receive(outgoing)
+    }
+
+    fn poll(&mut self, cycles: u64, _listening: Option<u8>) -> Option<u8> {
+        if self.printing > 0 {
+            self.printing = self.printing.saturating_sub(cycles);
+
+            if self.printing == 0 {
+                self.status -= PrinterStatus::PRINTING | PrinterStatus::IMAGE_DATA_FULL;
+            }
+        }
+
+        None
+    }
+}
+
+/// Expands the printer's run-length encoding, where a control byte with
+/// the top bit set repeats the next byte `(n & 0x7F) + 2` times, and
+/// otherwise is followed by `n + 1` bytes to copy as they are.
+fn decompress(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut bytes = data.iter();
+
+    while let Some(control) = bytes.next() {
+        if control & 0x80 != 0 {
+            if let Some(byte) = bytes.next() {
+                let count = usize::from(control & 0x7F) + 2;
+                output.resize(output.len() + count, *byte);
+            }
+        } else {
+            output.extend(bytes.by_ref().take(usize::from(*control) + 1));
+        }
+    }
+
+    output
+}
+
+/// Decodes a row of 2bpp tiles into eight rows of pixels, mapping colours
+/// through the palette and darkening them by the exposure.
+fn decode_tile_row(tiles: &[u8], palette: u8, exposure: u8) -> Vec<u8> {
+    // Out of 256, so 0x00 lightens shades by a quarter and 0x7F darkens
+    // them by about as much
+    let scale = 256 + u32::from(exposure) - u32::from(DEFAULT_EXPOSURE);
+    let mut pixels = vec![0xFF; PAPER_WIDTH * 8];
+
+    for (tile_index, tile) in tiles.chunks_exact(BYTES_PER_TILE).enumerate() {
+        for (y, line) in tile.chunks_exact(2).enumerate() {
+            for x in 0..8 {
+                let bit = 7 - x;
+                let colour = ((line[1] >> bit) & 1) << 1 | ((line[0] >> bit) & 1);
+                let shade = u32::from((palette >> (colour * 2)) & 0b11);
+                let darkness = (shade * 85 * scale / 256).min(0xFF);
+
+                pixels[y * PAPER_WIDTH + tile_index * 8 + x] = 0xFF - darkness as u8;
+            }
+        }
+    }
+
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The palette most games print with, mapping each colour to itself.
+    const IDENTITY_PALETTE: u8 = 0b11_10_01_00;
+
+    fn packet(command: u8, compressed: bool, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![command, u8::from(compressed)];
+        bytes.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(data);
+
+        let checksum = bytes
+            .iter()
+            .fold(0u16, |sum, byte| sum.wrapping_add(u16::from(*byte)));
+
+        let mut packet = MAGIC.to_vec();
+        packet.extend(bytes);
+        packet.extend_from_slice(&checksum.to_le_bytes());
+        packet.extend_from_slice(&[0x00, 0x00]);
+        packet
+    }
+
+    /// Sends a packet, returning the device ID and status.
+    fn send(printer: &mut Printer, packet: &[u8]) -> (u8, PrinterStatus) {
+        let answers: Vec<u8> = packet.iter().map(|byte| printer.exchange(*byte)).collect();
+
+        assert!(answers[..answers.len() - 2]
+            .iter()
+            .all(|answer| *answer == 0));
+
+        (
+            answers[answers.len() - 2],
+            PrinterStatus::from_bits_truncate(answers[answers.len() - 1]),
+        )
+    }
+
+    /// A row of tiles with every pixel in `colour`.
+    fn solid_tile_row(colour: u8) -> Vec<u8> {
+        let low = if colour & 1 != 0 { 0xFF } else { 0x00 };
+        let high = if colour & 2 != 0 { 0xFF } else { 0x00 };
+
+        [low, high].repeat(BYTES_PER_TILE_ROW / 2)
+    }
+
+    fn finish_printing(printer: &mut Printer) {
+        printer.poll(crate::CYCLES_PER_SECOND, None);
+    }
+
+    #[test]
+    fn it_answers_with_its_id_and_status() {
+        let mut printer = Printer::new();
+
+        assert_eq!(
+            (DEVICE_ID, PrinterStatus::empty()),
+            send(&mut printer, &packet(COMMAND_STATUS, false, &[]))
+        );
+    }
+
+    #[test]
+    fn it_waits_for_the_magic_bytes() {
+        let mut printer = Printer::new();
+
+        let mut bytes = vec![0x00, 0x88, 0x88, 0x12];
+        bytes.extend(packet(COMMAND_STATUS, false, &[]));
+
+        assert_eq!(DEVICE_ID, send(&mut printer, &bytes).0);
+    }
+
+    #[test]
+    fn it_flags_bad_checksums() {
+        let mut printer = Printer::new();
+        let mut bytes = packet(COMMAND_DATA, false, &[0x12; 4]);
+        bytes[10] ^= 0xFF;
+
+        let (_, status) = send(&mut printer, &bytes);
+        assert_eq!(PrinterStatus::CHECKSUM_ERROR, status);
+        assert!(printer.buffer.is_empty());
+    }
+
+    #[test]
+    fn it_buffers_data() {
+        let mut printer = Printer::new();
+
+        let (_, status) = send(&mut printer, &packet(COMMAND_DATA, false, &[0x12; 0x280]));
+        assert_eq!(PrinterStatus::UNPROCESSED_DATA, status);
+
+        let (_, status) = send(&mut printer, &packet(COMMAND_DATA, false, &[]));
+        assert_eq!(
+            PrinterStatus::UNPROCESSED_DATA | PrinterStatus::IMAGE_DATA_FULL,
+            status
+        );
+
+        let (_, status) = send(&mut printer, &packet(COMMAND_INIT, false, &[]));
+        assert_eq!(PrinterStatus::empty(), status);
+        assert!(printer.buffer.is_empty());
+    }
+
+    #[test]
+    fn it_decompresses_data() {
+        assert_eq!(
+            vec![0xAA, 0xAA, 0xAA, 0x01, 0x02, 0x55, 0x55],
+            decompress(&[0x81, 0xAA, 0x01, 0x01, 0x02, 0x80, 0x55])
+        );
+
+        let mut printer = Printer::new();
+        send(&mut printer, &packet(COMMAND_DATA, true, &[0xFF, 0x00]));
+
+        assert_eq!(vec![0x00; 129], printer.buffer);
+    }
+
+    #[test]
+    fn it_prints_with_the_palette() {
+        let mut printer = Printer::new();
+        let mut data = solid_tile_row(0b01);
+        data.extend(solid_tile_row(0b11));
+
+        send(&mut printer, &packet(COMMAND_DATA, false, &data));
+        let (_, status) = send(
+            &mut printer,
+            &packet(
+                COMMAND_PRINT,
+                false,
+                &[1, 0x00, 0b00_00_11_00, DEFAULT_EXPOSURE],
+            ),
+        );
+        assert_eq!(PrinterStatus::PRINTING, status);
+
+        let sheet = printer.tear_off().unwrap();
+        assert_eq!((PAPER_WIDTH, 16), (sheet.width(), sheet.height()));
+        assert!(sheet.pixels()[..PAPER_WIDTH * 8].iter().all(|p| *p == 0x00));
+        assert!(sheet.pixels()[PAPER_WIDTH * 8..].iter().all(|p| *p == 0xFF));
+    }
+
+    #[test]
+    fn it_darkens_with_the_exposure() {
+        let shade = |exposure| decode_tile_row(&solid_tile_row(1), IDENTITY_PALETTE, exposure)[0];
+
+        assert_eq!(0xAA, shade(DEFAULT_EXPOSURE));
+        assert!(shade(0x00) > 0xAA);
+        assert!(shade(0x7F) < 0xAA);
+    }
+
+    #[test]
+    fn it_stays_busy_while_printing() {
+        let mut printer = Printer::new();
+        send(
+            &mut printer,
+            &packet(COMMAND_DATA, false, &solid_tile_row(3)),
+        );
+        send(
+            &mut printer,
+            &packet(COMMAND_PRINT, false, &[1, 0x00, IDENTITY_PALETTE, 0x40]),
+        );
+
+        printer.poll(CYCLES_PER_TILE_ROW - 1, None);
+        assert_eq!(PrinterStatus::PRINTING, printer.status());
+
+        printer.poll(1, None);
+        assert_eq!(PrinterStatus::empty(), printer.status());
+    }
+
+    #[test]
+    fn it_tears_off_strips_after_feeding_the_paper() {
+        let mut printer = Printer::new();
+
+        // Two strips printed back to back, then fed out
+        for margins in [0x10, 0x03] {
+            send(
+                &mut printer,
+                &packet(COMMAND_DATA, false, &solid_tile_row(3)),
+            );
+            send(
+                &mut printer,
+                &packet(COMMAND_PRINT, false, &[1, margins, IDENTITY_PALETTE, 0x40]),
+            );
+            finish_printing(&mut printer);
+        }
+
+        let printouts = printer.take_printouts();
+        assert_eq!(1, printouts.len());
+        assert_eq!(16 + 8 + 8 + 3 * 16, printouts[0].height());
+        assert!(printer.tear_off().is_none());
+    }
+}
@@ -0,0 +1,172 @@
+use std::io::{self, Write};
+
+use crate::helpers::crc32;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// The most a stored deflate block can hold.
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+/// A printed sheet of paper, as shades of grey from black (0x00) to white
+/// (0xFF).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Printout {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Printout {
+    pub(crate) fn new(width: usize) -> Self {
+        Self {
+            width,
+            height: 0,
+            pixels: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pixels, a row at a time.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Feeds the paper on by a number of blank rows.
+    pub(crate) fn feed(&mut self, rows: usize) {
+        self.pixels
+            .resize(self.pixels.len() + rows * self.width, 0xFF);
+        self.height += rows;
+    }
+
+    /// Prints rows onto the end of the sheet.
+    ///
+    /// # Panics
+    /// If `pixels` isn't a whole number of rows.
+    pub(crate) fn print(&mut self, pixels: &[u8]) {
+        assert_eq!(0, pixels.len() % self.width, "pixels must be whole rows");
+
+        self.pixels.extend_from_slice(pixels);
+        self.height += pixels.len() / self.width;
+    }
+
+    /// Writes the printout as an 8-bit greyscale PNG.
+    ///
+    /// The image data is stored rather than compressed, which keeps this
+    /// simple at the cost of some size, but printouts are small anyway.
+    pub fn write_png<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // 8-bit greyscale, with the default compression, filtering and no
+        // interlacing
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+        // Every row starts with its filter type, which is always none
+        let mut scanlines = Vec::with_capacity((self.width + 1) * self.height);
+        for row in self.pixels.chunks(self.width) {
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+
+        writer.write_all(PNG_SIGNATURE)?;
+        write_chunk(&mut writer, b"IHDR", &header)?;
+        write_chunk(&mut writer, b"IDAT", &zlib_stored(&scanlines))?;
+        write_chunk(&mut writer, b"IEND", &[])
+    }
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let checksum = crc32(&[&kind[..], data].concat());
+
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&checksum.to_be_bytes())
+}
+
+/// Wraps data in a zlib stream of stored deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Deflate with a 32 KiB window, and no preset dictionary
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+
+    // Even nothing at all needs a final block
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+
+        stream.push(u8::from(last));
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + u32::from(*byte)) % MODULUS;
+        (a, (b + a) % MODULUS)
+    });
+
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_checksums_with_adler32() {
+        assert_eq!(0x0000_0001, adler32(&[]));
+        assert_eq!(0x11E6_0398, adler32(b"Wikipedia"));
+    }
+
+    #[test]
+    fn it_writes_a_png() {
+        let mut printout = Printout::new(3);
+        printout.print(&[0x00, 0x55, 0xAA, 0xFF, 0x00, 0x55]);
+        printout.feed(1);
+
+        let mut file = Vec::new();
+        printout.write_png(&mut file).unwrap();
+
+        let decoder = png::Decoder::new(&file[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).unwrap();
+
+        assert_eq!((3, 3), (info.width, info.height));
+        assert_eq!(png::ColorType::Grayscale, info.color_type);
+        assert_eq!(printout.pixels(), &buffer[..info.buffer_size()]);
+    }
+
+    #[test]
+    fn it_splits_large_images_into_blocks() {
+        let mut printout = Printout::new(160);
+        printout.feed(500);
+
+        let mut file = Vec::new();
+        printout.write_png(&mut file).unwrap();
+
+        let mut reader = png::Decoder::new(&file[..]).read_info().unwrap();
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buffer).unwrap();
+
+        assert!(buffer.iter().all(|pixel| *pixel == 0xFF));
+    }
+}
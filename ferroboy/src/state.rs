@@ -1,6 +1,8 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use crate::system::{Cartridge, Config, Cpu, Mmu, WideRegister};
+use crate::system::{
+    Buttons, Cartridge, Config, Cpu, Frame, LinkCable, Mmu, PixelFormat, WideRegister,
+};
 
 /// The current state of the emulation.
 ///
@@ -19,15 +21,100 @@ impl State {
         self.cpu.is_halted()
     }
 
+    /// The most recently completed frame of LCD output.
+    pub fn frame(&self) -> &Frame {
+        self.mmu.ppu.frame()
+    }
+
+    /// The most recently completed frame, coloured with the configured
+    /// palette and blended with earlier frames if frame blending is on.
+    ///
+    /// When the SGB is colouring the screen its palettes are used instead.
+    pub fn frame_pixels(&self, format: PixelFormat) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.frame_pixels_into(format, &mut buffer);
+        buffer
+    }
+
+    /// As [`State::frame_pixels`], but reuses an existing buffer.
+    pub fn frame_pixels_into(&self, format: PixelFormat, buffer: &mut Vec<u8>) {
+        match self.mmu.sgb() {
+            Some(sgb) => {
+                self.mmu
+                    .ppu
+                    .colour_frame_into(|x, y| sgb.palette_at(x, y), format, buffer)
+            }
+            None => self
+                .mmu
+                .ppu
+                .convert_frame_into(&self.config.palette, format, buffer),
+        }
+    }
+
+    /// Decodes the tiles, tile maps and sprites in video memory, coloured
+    /// with the configured palette.
+    #[cfg(feature = "introspection")]
+    pub fn inspect_video(&self) -> crate::VideoInspector<'_> {
+        self.mmu.inspect_video(self.config.palette)
+    }
+
+    /// Moves the audio produced since the last call into `buffer`, as
+    /// interleaved stereo samples at the configured sample rate.
+    pub fn audio_samples_into(&mut self, buffer: &mut Vec<i16>) {
+        self.mmu.apu.drain_samples_into(buffer);
+    }
+
+    /// Holds down buttons, on top of any that are already held.
+    pub fn press(&mut self, buttons: Buttons) {
+        let interrupts = self.mmu.joypad.press(buttons);
+        self.mmu.request_interrupt(interrupts);
+    }
+
+    /// Lets go of buttons, leaving any others held.
+    pub fn release(&mut self, buttons: Buttons) {
+        let interrupts = self.mmu.joypad.release(buttons);
+        self.mmu.request_interrupt(interrupts);
+    }
+
+    /// Holds down exactly the given buttons, for hosts that poll the whole
+    /// input state each frame.
+    pub fn set_buttons(&mut self, buttons: Buttons) {
+        let interrupts = self.mmu.joypad.set_buttons(buttons);
+        self.mmu.request_interrupt(interrupts);
+    }
+
+    /// Plugs something into the serial port, returning a handle to it that
+    /// stays shared with the emulation, e.g. to read back what it received.
+    pub fn connect_link_cable<C>(&mut self, cable: C) -> Arc<Mutex<C>>
+    where
+        C: LinkCable + 'static,
+    {
+        let cable = Arc::new(Mutex::new(cable));
+        self.mmu.serial.connect(cable.clone());
+        cable
+    }
+
+    /// Unplugs whatever's in the serial port, returning it.
+    pub fn disconnect_link_cable(&mut self) -> Option<Arc<Mutex<dyn LinkCable>>> {
+        self.mmu.serial.disconnect()
+    }
+
     pub fn load_cartridge(&mut self, cartridge: Cartridge) {
         let cart = Arc::new(Some(cartridge));
+        let cable = self.mmu.serial.disconnect();
         self.mmu = Mmu::new(cart.clone());
+        self.mmu.configure(&self.config);
+
+        // The cable stays plugged in when the cartridge is swapped
+        if let Some(cable) = cable {
+            self.mmu.serial.connect(cable);
+        }
         self.cartridge = cart;
     }
 
     pub(crate) fn read_byte(&mut self) -> crate::Result<u8> {
         let pc = self.cpu.get16(WideRegister::Pc);
-        let word = self.mmu[pc];
+        let word = self.mmu.read(pc);
 
         self.increment_program_counter()?;
 
@@ -57,6 +144,51 @@ impl State {
         self.cpu.set16(WideRegister::Pc, destination);
     }
 
+    /// Advances the memory-mapped hardware by however many T-cycles the
+    /// CPU has run since `since`.
+    pub(crate) fn step_hardware(&mut self, since: u64) {
+        self.mmu.step(self.cpu.clock() - since);
+    }
+
+    /// Jumps to the handler for the highest priority pending interrupt, if
+    /// interrupts are enabled.
+    ///
+    /// Returns whether an interrupt was serviced.
+    pub(crate) fn service_interrupts(&mut self) -> bool {
+        let pending = self.mmu.pending_interrupts();
+
+        let interrupt = match pending.highest_priority() {
+            Some(interrupt) => interrupt,
+            None => return false,
+        };
+
+        self.cpu.resume();
+
+        if !self.cpu.interrupts_enabled() {
+            return false;
+        }
+
+        let clock = self.cpu.clock();
+
+        self.cpu.disable_interrupts();
+        self.mmu.clear_interrupt(interrupt);
+
+        // This mirrors how CALL stores the return address, so RETI can pop it
+        let address = self.cpu.get16(WideRegister::Pc);
+        let target = self.cpu.get16(WideRegister::Sp).wrapping_sub(2);
+
+        self.mmu.write(target, (address >> 8) as u8);
+        self.mmu.write(target.wrapping_add(1), address as u8);
+
+        self.cpu.set16(WideRegister::Sp, target);
+        self.jump(interrupt.vector());
+        self.cpu.increment_clock(20);
+
+        self.step_hardware(clock);
+
+        true
+    }
+
     pub(crate) fn map_cartridge(&mut self) -> crate::Result<()> {
         match self.cartridge.as_ref() {
             Some(cart) => {
@@ -104,11 +236,13 @@ impl StateBuilder {
 
     pub fn build(self) -> State {
         let cart = Arc::new(self.cartridge);
+        let mut mmu = Mmu::new(cart.clone());
+        mmu.configure(&self.config);
 
         State {
             config: self.config,
             cpu: Default::default(),
-            mmu: Mmu::new(cart.clone()),
+            mmu,
             cartridge: cart,
         }
     }
@@ -117,6 +251,7 @@ impl StateBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::system::Interrupts;
 
     #[test]
     fn it_reads_a_byte() {
@@ -131,6 +266,177 @@ mod tests {
         assert_eq!(0x01, state.cpu.get16(WideRegister::Pc));
     }
 
+    #[test]
+    fn it_configures_the_hardware() {
+        let config = crate::ConfigBuilder::new()
+            .with_renderer(crate::Renderer::Fifo)
+            .with_sample_rate(48_000)
+            .build();
+        let mut state = StateBuilder::new().with_config(config).build();
+
+        assert_eq!(crate::Renderer::Fifo, state.mmu.ppu().renderer());
+        assert_eq!(48_000, state.mmu.apu().sample_rate());
+
+        state.load_cartridge(crate::Cartridge::default());
+        assert_eq!(crate::Renderer::Fifo, state.mmu.ppu().renderer());
+        assert_eq!(48_000, state.mmu.apu().sample_rate());
+    }
+
+    #[test]
+    fn it_requests_the_joypad_interrupt_on_press() {
+        let config = crate::ConfigBuilder::new()
+            .with_opposing_directions(crate::OpposingDirections::Neutral)
+            .build();
+        let mut state = StateBuilder::new().with_config(config).build();
+        state.mmu.mutate(|mmu| mmu[0xFFFF] = 0xFF);
+        state.mmu.write(0xFF00, 0x20);
+
+        state.press(Buttons::LEFT | Buttons::RIGHT);
+        assert!(state.mmu.pending_interrupts().is_empty());
+
+        state.set_buttons(Buttons::LEFT);
+        assert_eq!(Interrupts::JOYPAD, state.mmu.pending_interrupts());
+        assert_eq!(0xED, state.mmu.read(0xFF00));
+
+        state.release(Buttons::LEFT);
+        assert_eq!(0xEF, state.mmu.read(0xFF00));
+    }
+
+    #[test]
+    fn it_keeps_the_link_cable_across_cartridges() {
+        let mut state = State::default();
+        let cable = state.connect_link_cable(crate::RecordedStream::new([0x12]));
+
+        state.load_cartridge(crate::Cartridge::default());
+        state.mmu.write(0xFF01, 0x34);
+        state.mmu.write(0xFF02, 0x81);
+        state.mmu.step(4096);
+
+        assert_eq!(0x12, state.mmu.read(0xFF01));
+        assert_eq!(&[0x34], cable.lock().unwrap().sent());
+        assert!(state.disconnect_link_cable().is_some());
+        assert!(!state.mmu.serial().is_connected());
+    }
+
+    #[test]
+    fn it_colours_frames_with_the_sgb() {
+        let cartridge = crate::test_rom::RomBuilder::new("SGB")
+            .with_sgb_support()
+            .cartridge()
+            .unwrap();
+
+        let config = crate::ConfigBuilder::new()
+            .with_model(crate::Model::Sgb)
+            .with_palette(crate::Palette::HIGH_CONTRAST)
+            .build();
+        let mut state = StateBuilder::new()
+            .with_config(config)
+            .with_cartridge(cartridge.clone())
+            .build();
+
+        // PAL01, with colour 0 red
+        let mut packet = [0; 16];
+        packet[0..2].copy_from_slice(&[0x01, 0x1F]);
+        for select in crate::system::pulses(&packet) {
+            state.mmu.write(0xFF00, select);
+        }
+
+        assert_eq!(
+            [0xFF, 0x00, 0x00, 0xFF],
+            state.frame_pixels(PixelFormat::Rgba8)[0..4]
+        );
+
+        // Cartridges that don't support it are left alone
+        state.load_cartridge(Cartridge::default());
+        assert!(state.mmu.sgb().is_none());
+        assert_eq!([0xFF; 4], state.frame_pixels(PixelFormat::Rgba8)[0..4]);
+
+        let state = StateBuilder::new().with_cartridge(cartridge).build();
+        assert!(state.mmu.sgb().is_none());
+    }
+
+    #[test]
+    fn it_services_interrupts() {
+        let mut state = State::default();
+        state.cpu.set16(WideRegister::Pc, 0xBEEF);
+        state.cpu.set16(WideRegister::Sp, 0xFFFE);
+        state.mmu.mutate(|mmu| mmu[0xFFFF] = 0xFF);
+        state
+            .mmu
+            .request_interrupt(Interrupts::TIMER | Interrupts::JOYPAD);
+
+        assert!(state.service_interrupts());
+
+        assert_eq!(0x50, state.cpu.get16(WideRegister::Pc));
+        assert_eq!(0xFFFC, state.cpu.get16(WideRegister::Sp));
+        assert_eq!(0xBE, state.mmu[0xFFFC]);
+        assert_eq!(0xEF, state.mmu[0xFFFD]);
+        assert!(!state.cpu.interrupts_enabled());
+        assert_eq!(Interrupts::JOYPAD, state.mmu.pending_interrupts());
+    }
+
+    #[test]
+    fn it_only_wakes_from_halt_when_interrupts_are_disabled() {
+        let mut state = State::default();
+        state.cpu.set16(WideRegister::Pc, 0xBEEF);
+        state.cpu.disable_interrupts();
+        state.cpu.halt();
+        state.mmu.mutate(|mmu| mmu[0xFFFF] = 0xFF);
+        state.mmu.request_interrupt(Interrupts::VBLANK);
+
+        assert!(!state.service_interrupts());
+
+        assert!(!state.is_halted());
+        assert_eq!(0xBEEF, state.cpu.get16(WideRegister::Pc));
+        assert_eq!(Interrupts::VBLANK, state.mmu.pending_interrupts());
+    }
+
+    #[test]
+    fn it_ignores_interrupts_that_arent_enabled() {
+        let mut state = State::default();
+        state
+            .mmu
+            .mutate(|mmu| mmu[0xFFFF] = Interrupts::TIMER.bits());
+        state.mmu.request_interrupt(Interrupts::VBLANK);
+
+        assert!(!state.service_interrupts());
+    }
+
+    #[test]
+    fn it_idles_while_halted() {
+        let mut state = State::default();
+        state.cpu.set16(WideRegister::Pc, 0xBEEF);
+        state.cpu.halt();
+
+        crate::tick(&mut state).unwrap();
+        crate::tick(&mut state).unwrap();
+
+        assert!(state.is_halted());
+        assert_eq!(0xBEEF, state.cpu.get16(WideRegister::Pc));
+        assert_eq!(8, state.cpu.clock());
+    }
+
+    #[test]
+    fn it_wakes_from_halt_into_the_interrupt_handler() {
+        let mut state = State::default();
+        state.cpu.set16(WideRegister::Pc, 0xBEEF);
+        state.cpu.set16(WideRegister::Sp, 0xFFFE);
+        state.cpu.halt();
+        state.mmu.mutate(|mmu| mmu[0xFFFF] = 0xFF);
+
+        crate::tick(&mut state).unwrap();
+        assert!(state.is_halted());
+
+        state.mmu.request_interrupt(Interrupts::SERIAL);
+        crate::tick(&mut state).unwrap();
+
+        // The handler's first instruction, a NOP, runs straight away
+        assert!(!state.is_halted());
+        assert_eq!(0x59, state.cpu.get16(WideRegister::Pc));
+        assert_eq!(0xFFFC, state.cpu.get16(WideRegister::Sp));
+        assert!(state.mmu.pending_interrupts().is_empty());
+    }
+
     #[test]
     fn it_reads_a_word() {
         let mut state = State::default();
@@ -0,0 +1,125 @@
+/// Fades a channel's volume up or down over time, as set by NRx2.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct Envelope {
+    register: u8,
+    volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    pub(super) fn read(&self) -> u8 {
+        self.register
+    }
+
+    /// Changes take effect the next time the channel is triggered.
+    pub(super) fn write(&mut self, value: u8) {
+        self.register = value;
+    }
+
+    /// The channel's DAC is only powered while the top five bits of NRx2
+    /// are set, i.e. while it isn't silent and fading down.
+    pub(super) fn dac_enabled(&self) -> bool {
+        self.register & 0xF8 != 0
+    }
+
+    pub(super) fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    pub(super) fn trigger(&mut self) {
+        self.volume = self.register >> 4;
+        self.timer = self.period();
+    }
+
+    /// Clocked by the frame sequencer at 64 Hz.
+    pub(super) fn clock(&mut self) {
+        if self.register & 0b111 == 0 {
+            return;
+        }
+
+        self.timer = self.timer.saturating_sub(1);
+
+        if self.timer > 0 {
+            return;
+        }
+
+        self.timer = self.period();
+
+        if self.register & 0b1000 != 0 {
+            self.volume = (self.volume + 1).min(15);
+        } else {
+            self.volume = self.volume.saturating_sub(1);
+        }
+    }
+
+    /// A period of 0 is treated as 8, though the envelope doesn't change.
+    fn period(&self) -> u8 {
+        match self.register & 0b111 {
+            0 => 8,
+            period => period,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn envelope(register: u8) -> Envelope {
+        let mut envelope = Envelope::default();
+        envelope.write(register);
+        envelope.trigger();
+        envelope
+    }
+
+    #[test]
+    fn it_fades_out() {
+        let mut envelope = envelope(0xF1);
+        assert_eq!(15, envelope.volume());
+
+        envelope.clock();
+        assert_eq!(14, envelope.volume());
+
+        for _ in 0..20 {
+            envelope.clock();
+        }
+
+        assert_eq!(0, envelope.volume());
+    }
+
+    #[test]
+    fn it_fades_in_by_its_period() {
+        let mut envelope = envelope(0x0A);
+
+        envelope.clock();
+        assert_eq!(0, envelope.volume());
+
+        envelope.clock();
+        assert_eq!(1, envelope.volume());
+
+        for _ in 0..40 {
+            envelope.clock();
+        }
+
+        assert_eq!(15, envelope.volume());
+    }
+
+    #[test]
+    fn it_holds_with_a_period_of_zero() {
+        let mut envelope = envelope(0x80);
+
+        for _ in 0..16 {
+            envelope.clock();
+        }
+
+        assert_eq!(8, envelope.volume());
+    }
+
+    #[test]
+    fn it_powers_the_dac_from_the_top_bits() {
+        assert!(!envelope(0x00).dac_enabled());
+        assert!(!envelope(0x07).dac_enabled());
+        assert!(envelope(0x08).dac_enabled());
+        assert!(envelope(0x10).dac_enabled());
+    }
+}
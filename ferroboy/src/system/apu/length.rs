@@ -0,0 +1,127 @@
+/// Turns a channel off once it's been playing for long enough, if NRx4
+/// asks it to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) struct LengthCounter {
+    /// 64 for most channels, 256 for the wave channel.
+    max: u16,
+    counter: u16,
+    enabled: bool,
+}
+
+impl LengthCounter {
+    pub(super) fn new(max: u16) -> Self {
+        Self {
+            max,
+            counter: 0,
+            enabled: false,
+        }
+    }
+
+    pub(super) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Loads the length from NRx1, which counts up to the maximum.
+    pub(super) fn load(&mut self, length: u8) {
+        self.counter = self.max - u16::from(length);
+    }
+
+    /// Applies NRx4's length enable bit, returning whether the channel
+    /// should be turned off.
+    ///
+    /// Enabling the counter while the frame sequencer's next step won't
+    /// clock it clocks it an extra time, which can run it out.
+    pub(super) fn set_enabled(&mut self, enabled: bool, next_step_clocks_length: bool) -> bool {
+        let was_enabled = std::mem::replace(&mut self.enabled, enabled);
+
+        if !was_enabled && enabled && !next_step_clocks_length {
+            return self.clock();
+        }
+
+        false
+    }
+
+    /// Powering the APU off clears NRx4's enable bit, but the DMG keeps
+    /// the count.
+    pub(super) fn power_off(&mut self) {
+        self.enabled = false;
+    }
+
+    /// Reloads an expired counter when the channel is triggered.
+    pub(super) fn trigger(&mut self, next_step_clocks_length: bool) {
+        if self.counter == 0 {
+            self.counter = self.max;
+
+            // Which gets the same extra clock as enabling the counter
+            if self.enabled && !next_step_clocks_length {
+                self.counter -= 1;
+            }
+        }
+    }
+
+    /// Clocked by the frame sequencer at 256 Hz, returning whether the
+    /// channel should be turned off.
+    pub(super) fn clock(&mut self) -> bool {
+        if !self.enabled || self.counter == 0 {
+            return false;
+        }
+
+        self.counter -= 1;
+        self.counter == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_runs_out() {
+        let mut length = LengthCounter::new(64);
+        length.load(62);
+        length.set_enabled(true, true);
+
+        assert!(!length.clock());
+        assert!(length.clock());
+        assert!(!length.clock());
+    }
+
+    #[test]
+    fn it_only_counts_while_enabled() {
+        let mut length = LengthCounter::new(64);
+        length.load(63);
+
+        assert!(!length.clock());
+        assert!(!length.is_enabled());
+    }
+
+    #[test]
+    fn it_reloads_on_trigger() {
+        let mut length = LengthCounter::new(256);
+        length.trigger(true);
+        length.set_enabled(true, true);
+
+        for _ in 0..255 {
+            assert!(!length.clock());
+        }
+
+        assert!(length.clock());
+    }
+
+    #[test]
+    fn it_clocks_an_extra_time_when_enabled_early() {
+        let mut length = LengthCounter::new(64);
+        length.load(63);
+
+        assert!(length.set_enabled(true, false));
+    }
+
+    #[test]
+    fn it_reloads_one_short_when_triggered_early() {
+        let mut length = LengthCounter::new(64);
+        length.set_enabled(true, true);
+        length.trigger(false);
+
+        assert_eq!(63, length.counter);
+    }
+}
@@ -0,0 +1,839 @@
+mod envelope;
+mod length;
+mod noise;
+mod resampler;
+mod ring;
+mod square;
+mod sweep;
+mod tap;
+mod wave;
+
+pub use tap::{AudioChannel, ChannelInfo};
+
+use noise::Noise;
+use resampler::Resampler;
+use ring::RingBuffer;
+use square::Square;
+use wave::Wave;
+
+use crate::{CYCLES_PER_FRAME, CYCLES_PER_SECOND};
+
+pub(crate) const NR10: u16 = 0xFF10;
+const NR11: u16 = 0xFF11;
+pub(crate) const NR14: u16 = 0xFF14;
+pub(crate) const NR21: u16 = 0xFF16;
+pub(crate) const NR24: u16 = 0xFF19;
+pub(crate) const NR30: u16 = 0xFF1A;
+const NR31: u16 = 0xFF1B;
+pub(crate) const NR34: u16 = 0xFF1E;
+pub(crate) const NR41: u16 = 0xFF20;
+pub(crate) const NR44: u16 = 0xFF23;
+pub(crate) const NR50: u16 = 0xFF24;
+pub(crate) const NR51: u16 = 0xFF25;
+pub(crate) const NR52: u16 = 0xFF26;
+pub(crate) const WAVE_RAM_START: u16 = 0xFF30;
+pub(crate) const WAVE_RAM_END: u16 = 0xFF3F;
+
+/// The bits of each register from NR10 to NR52 that always read high,
+/// either because they're unused or write-only.
+const READ_MASKS: [u8; 0x17] = [
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10-NR14
+    0xFF, 0x3F, 0x00, 0xFF, 0xBF, // NR20-NR24
+    0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30-NR34
+    0xFF, 0xFF, 0x00, 0x00, 0xBF, // NR40-NR44
+    0x00, 0x00, 0x70, // NR50-NR52
+];
+
+/// The frame sequencer is clocked at 512 Hz.
+const CYCLES_PER_SEQUENCER_STEP: u32 = 8192;
+/// The rate samples are produced at unless configured otherwise.
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+/// How many frames of samples are kept for the frontend to collect.
+const BUFFERED_FRAMES: usize = 3;
+/// Scales the loudest possible mix, four channels at 15 and full volume,
+/// to just under `i16::MAX`.
+const MIX_SCALE: i32 = 68;
+
+/// A write to one of the sound registers or wave RAM.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RegisterWrite {
+    /// How many T-cycles the APU had run for when the write happened.
+    pub cycle: u64,
+    pub address: u16,
+    pub value: u8,
+}
+
+/// The interface the APU uses to drive its four channels.
+///
+/// Registers are numbered 0-4 within each channel, so NR21 is register 1
+/// of channel 2.
+trait Channel {
+    /// Reads a register, before the read mask is applied.
+    fn read(&self, register: u8) -> u8;
+
+    fn write(&mut self, register: u8, value: u8, next_step_clocks_length: bool);
+
+    /// Advances the channel by one T-cycle.
+    fn tick(&mut self);
+
+    /// The channel's current output, from 0 to 15.
+    fn output(&self) -> u8;
+
+    fn is_enabled(&self) -> bool;
+
+    fn dac_enabled(&self) -> bool;
+
+    /// How many T-cycles one cycle of the waveform takes.
+    fn waveform_period(&self) -> u32;
+
+    fn duty(&self) -> Option<u8> {
+        None
+    }
+
+    fn volume(&self) -> u8;
+
+    /// Clocked by the frame sequencer at 256 Hz.
+    fn clock_length(&mut self);
+
+    /// Clears every register, as turning the APU off does. On the DMG the
+    /// length counters are left alone.
+    fn power_off(&mut self);
+}
+
+/// An implementation of the DMG-01's audio processing unit.
+///
+/// The APU has four channels, two square waves, a programmable wave and
+/// noise, which are mixed into stereo according to NR50 and NR51. Their
+/// lengths, envelopes and sweep are clocked by a frame sequencer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Apu {
+    powered: bool,
+    square1: Square,
+    square2: Square,
+    wave: Wave,
+    noise: Noise,
+    /// Master volume for each side, and the unused VIN bits.
+    nr50: u8,
+    /// Which channels are sent to which side.
+    nr51: u8,
+
+    sequencer_timer: u32,
+    /// The next step the frame sequencer will take, from 0 to 7.
+    sequencer_step: u8,
+
+    resampler: Resampler,
+    /// Interleaved stereo samples that haven't been collected yet.
+    samples: RingBuffer<i16>,
+
+    /// Which channels are left out of the mix, by their bit in NR52. This
+    /// is purely for debugging, the channels otherwise play on as normal.
+    muted: u8,
+    /// Each channel's output as of every sample, if they're being
+    /// recorded.
+    taps: Option<Box<[RingBuffer<u8>; 4]>>,
+
+    /// How many T-cycles the APU has run for.
+    cycles: u64,
+    /// Every register write since logging started, if it has.
+    write_log: Option<Vec<RegisterWrite>>,
+}
+
+impl Apu {
+    pub fn is_powered(&self) -> bool {
+        self.powered
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.resampler.sample_rate()
+    }
+
+    /// Moves any samples produced so far into `buffer`, as interleaved
+    /// left and right pairs at the configured sample rate.
+    ///
+    /// Only the last few frames' worth are kept, so this should be called
+    /// once a frame.
+    pub fn drain_samples_into(&mut self, buffer: &mut Vec<i16>) {
+        self.samples.drain_into(buffer);
+    }
+
+    /// What a channel is currently playing.
+    pub fn channel(&self, channel: AudioChannel) -> ChannelInfo {
+        let channels: [&dyn Channel; 4] = [&self.square1, &self.square2, &self.wave, &self.noise];
+        let channel = channels[channel.index()];
+
+        ChannelInfo {
+            enabled: channel.is_enabled(),
+            dac_enabled: channel.dac_enabled(),
+            period: channel.waveform_period(),
+            duty: channel.duty(),
+            volume: channel.volume(),
+            output: channel.output(),
+        }
+    }
+
+    pub fn is_muted(&self, channel: AudioChannel) -> bool {
+        self.muted & (1 << channel.index()) != 0
+    }
+
+    /// Leaves a channel out of the mix, without otherwise affecting it.
+    pub fn set_muted(&mut self, channel: AudioChannel, muted: bool) {
+        if muted {
+            self.muted |= 1 << channel.index();
+        } else {
+            self.muted &= !(1 << channel.index());
+        }
+    }
+
+    /// Mutes every channel but one, or unmutes them all given `None`.
+    pub fn solo(&mut self, channel: Option<AudioChannel>) {
+        self.muted = match channel {
+            Some(channel) => 0b1111 & !(1 << channel.index()),
+            None => 0,
+        };
+    }
+
+    /// Starts or stops recording each channel's output alongside the
+    /// mixed samples, for [`Apu::drain_tap_into`].
+    pub fn set_tapping(&mut self, enabled: bool) {
+        if enabled == self.taps.is_some() {
+            return;
+        }
+
+        self.taps = enabled.then(|| Box::new(Self::tap_buffers(self.sample_rate())));
+    }
+
+    /// Moves a channel's recorded output into `buffer`, one value from 0 to
+    /// 15 for each stereo sample produced since the last call.
+    ///
+    /// Muting doesn't affect the recording, but nothing is recorded unless
+    /// tapping has been turned on with [`Apu::set_tapping`].
+    pub fn drain_tap_into(&mut self, channel: AudioChannel, buffer: &mut Vec<u8>) {
+        if let Some(taps) = self.taps.as_mut() {
+            taps[channel.index()].drain_into(buffer);
+        }
+    }
+
+    /// How many T-cycles the APU has run for, the timeline register writes
+    /// are logged against.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Starts or stops logging register writes, for
+    /// [`Apu::drain_writes_into`].
+    pub fn set_logging(&mut self, enabled: bool) {
+        if enabled == self.write_log.is_some() {
+            return;
+        }
+
+        self.write_log = enabled.then(Vec::new);
+    }
+
+    /// Moves every register write logged since the last call into
+    /// `buffer`, oldest first.
+    ///
+    /// Writes are logged as the CPU makes them, including ones the APU
+    /// ignores while it's powered off.
+    pub fn drain_writes_into(&mut self, buffer: &mut Vec<RegisterWrite>) {
+        if let Some(log) = self.write_log.as_mut() {
+            buffer.append(log);
+        }
+    }
+
+    /// Changes the rate samples are produced at, dropping any that haven't
+    /// been collected yet.
+    pub(crate) fn set_sample_rate(&mut self, sample_rate: u32) {
+        if sample_rate == self.sample_rate() {
+            return;
+        }
+
+        self.resampler = Resampler::new(sample_rate);
+        self.samples = Self::sample_buffer(sample_rate);
+
+        if let Some(taps) = self.taps.as_mut() {
+            **taps = Self::tap_buffers(sample_rate);
+        }
+    }
+
+    /// The most samples a single frame can produce on each side.
+    fn samples_per_frame(sample_rate: u32) -> usize {
+        let samples =
+            (u64::from(sample_rate) * CYCLES_PER_FRAME + CYCLES_PER_SECOND - 1) / CYCLES_PER_SECOND;
+
+        samples as usize
+    }
+
+    /// A buffer for `BUFFERED_FRAMES` frames of stereo samples.
+    fn sample_buffer(sample_rate: u32) -> RingBuffer<i16> {
+        RingBuffer::new(Self::samples_per_frame(sample_rate) * 2 * BUFFERED_FRAMES)
+    }
+
+    /// A buffer for each channel's output over `BUFFERED_FRAMES` frames.
+    fn tap_buffers(sample_rate: u32) -> [RingBuffer<u8>; 4] {
+        let capacity = Self::samples_per_frame(sample_rate) * BUFFERED_FRAMES;
+
+        [
+            RingBuffer::new(capacity),
+            RingBuffer::new(capacity),
+            RingBuffer::new(capacity),
+            RingBuffer::new(capacity),
+        ]
+    }
+
+    pub(crate) fn read(&self, address: u16) -> u8 {
+        let value = match address {
+            NR10..=NR14 => self.square1.read((address - NR10) as u8),
+            NR21..=NR24 => self.square2.read((address - NR21 + 1) as u8),
+            NR30..=NR34 => self.wave.read((address - NR30) as u8),
+            NR41..=NR44 => self.noise.read((address - NR41 + 1) as u8),
+            NR50 => self.nr50,
+            NR51 => self.nr51,
+            NR52 => self.status(),
+            WAVE_RAM_START..=WAVE_RAM_END => {
+                return self.wave.read_ram(usize::from(address - WAVE_RAM_START))
+            }
+            _ => return 0xFF,
+        };
+
+        value | READ_MASKS[usize::from(address - NR10)]
+    }
+
+    pub(crate) fn write(&mut self, address: u16, value: u8) {
+        if let Some(log) = self.write_log.as_mut() {
+            log.push(RegisterWrite {
+                cycle: self.cycles,
+                address,
+                value,
+            });
+        }
+
+        match address {
+            NR52 => self.set_powered(value & 0x80 != 0),
+            WAVE_RAM_START..=WAVE_RAM_END => {
+                self.wave
+                    .write_ram(usize::from(address - WAVE_RAM_START), value);
+            }
+            // While powered off the registers ignore writes, except that
+            // the DMG still lets the lengths be set
+            NR11 | NR21 if !self.powered => self.write_channel(address, value & 0x3F),
+            NR31 | NR41 if !self.powered => self.write_channel(address, value),
+            _ if !self.powered => {}
+            NR50 => self.nr50 = value,
+            NR51 => self.nr51 = value,
+            _ => self.write_channel(address, value),
+        }
+    }
+
+    fn write_channel(&mut self, address: u16, value: u8) {
+        // Length counters are clocked on even steps
+        let next_step_clocks_length = self.sequencer_step % 2 == 0;
+
+        match address {
+            NR10..=NR14 => {
+                self.square1
+                    .write((address - NR10) as u8, value, next_step_clocks_length)
+            }
+            NR21..=NR24 => {
+                self.square2
+                    .write((address - NR21 + 1) as u8, value, next_step_clocks_length)
+            }
+            NR30..=NR34 => self
+                .wave
+                .write((address - NR30) as u8, value, next_step_clocks_length),
+            NR41..=NR44 => {
+                self.noise
+                    .write((address - NR41 + 1) as u8, value, next_step_clocks_length)
+            }
+            _ => {}
+        }
+    }
+
+    /// NR52, the power bit and which channels are playing.
+    fn status(&self) -> u8 {
+        let channels: [&dyn Channel; 4] = [&self.square1, &self.square2, &self.wave, &self.noise];
+
+        channels
+            .iter()
+            .enumerate()
+            .filter(|(_, channel)| channel.is_enabled())
+            .fold(u8::from(self.powered) << 7, |status, (index, _)| {
+                status | 1 << index
+            })
+    }
+
+    fn set_powered(&mut self, powered: bool) {
+        if powered && !self.powered {
+            // The frame sequencer starts again from the top
+            self.sequencer_step = 0;
+            self.sequencer_timer = 0;
+        } else if !powered && self.powered {
+            for channel in self.channels_mut() {
+                channel.power_off();
+            }
+
+            self.nr50 = 0;
+            self.nr51 = 0;
+        }
+
+        self.powered = powered;
+    }
+
+    fn channels_mut(&mut self) -> [&mut dyn Channel; 4] {
+        [
+            &mut self.square1,
+            &mut self.square2,
+            &mut self.wave,
+            &mut self.noise,
+        ]
+    }
+
+    /// Advances the APU by a number of T-cycles.
+    pub(crate) fn step(&mut self, cycles: u64) {
+        self.cycles += cycles;
+
+        for _ in 0..cycles {
+            self.tick();
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.powered {
+            for channel in self.channels_mut() {
+                channel.tick();
+            }
+
+            self.sequencer_timer += 1;
+
+            if self.sequencer_timer == CYCLES_PER_SEQUENCER_STEP {
+                self.sequencer_timer = 0;
+                self.step_sequencer();
+            }
+        }
+
+        let mix = self.mix();
+
+        if !self.resampler.tick(mix, &mut self.samples) {
+            return;
+        }
+
+        if let Some(taps) = self.taps.as_mut() {
+            let channels: [&dyn Channel; 4] =
+                [&self.square1, &self.square2, &self.wave, &self.noise];
+
+            for (tap, channel) in taps.iter_mut().zip(channels) {
+                tap.push(channel.output());
+            }
+        }
+    }
+
+    /// Lengths are clocked on even steps, the sweep on steps 2 and 6, and
+    /// envelopes on step 7.
+    fn step_sequencer(&mut self) {
+        let step = self.sequencer_step;
+        self.sequencer_step = (step + 1) % 8;
+
+        if step % 2 == 0 {
+            for channel in self.channels_mut() {
+                channel.clock_length();
+            }
+        }
+
+        if step == 2 || step == 6 {
+            self.square1.clock_sweep();
+        }
+
+        if step == 7 {
+            self.square1.clock_envelope();
+            self.square2.clock_envelope();
+            self.noise.clock_envelope();
+        }
+    }
+
+    /// Mixes the channels into a left and right level, scaled to the range
+    /// of an `i16`.
+    fn mix(&self) -> [i32; 2] {
+        if !self.powered {
+            return [0; 2];
+        }
+
+        let channels: [&dyn Channel; 4] = [&self.square1, &self.square2, &self.wave, &self.noise];
+        let mut output = [0; 2];
+
+        for (index, channel) in channels.iter().enumerate() {
+            if !channel.dac_enabled() || self.muted & (1 << index) != 0 {
+                continue;
+            }
+
+            // Each DAC maps 0-15 onto an analog level, from 15 down to -15
+            let level = 15 - 2 * i32::from(channel.output());
+
+            if self.nr51 & (0x10 << index) != 0 {
+                output[0] += level;
+            }
+
+            if self.nr51 & (0x01 << index) != 0 {
+                output[1] += level;
+            }
+        }
+
+        let volumes = [(self.nr50 >> 4) & 0b111, self.nr50 & 0b111];
+
+        for (side, volume) in output.iter_mut().zip(volumes) {
+            *side *= (i32::from(volume) + 1) * MIX_SCALE;
+        }
+
+        output
+    }
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self {
+            powered: false,
+            square1: Square::new(true),
+            square2: Square::new(false),
+            wave: Wave::new(),
+            noise: Noise::new(),
+            nr50: 0,
+            nr51: 0,
+            sequencer_timer: 0,
+            sequencer_step: 0,
+            resampler: Resampler::new(DEFAULT_SAMPLE_RATE),
+            samples: Self::sample_buffer(DEFAULT_SAMPLE_RATE),
+            muted: 0,
+            taps: None,
+            cycles: 0,
+            write_log: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NR12: u16 = 0xFF12;
+    const NR42: u16 = 0xFF21;
+
+    fn powered() -> Apu {
+        let mut apu = Apu::default();
+        apu.write(NR52, 0x80);
+        apu
+    }
+
+    /// Plays channel 1 as a 50% square at full volume, on both sides.
+    fn playing() -> Apu {
+        let mut apu = powered();
+        apu.write(NR50, 0x77);
+        apu.write(NR51, 0x11);
+        apu.write(NR11, 0x80);
+        apu.write(NR12, 0xF0);
+        apu.write(NR14, 0x87);
+        apu
+    }
+
+    #[test]
+    fn it_masks_register_reads() {
+        let mut apu = powered();
+
+        for address in NR10..NR52 {
+            apu.write(address, 0x00);
+        }
+
+        for (offset, mask) in READ_MASKS.iter().enumerate().take(0x16) {
+            assert_eq!(
+                *mask,
+                apu.read(NR10 + offset as u16),
+                "{:04X}",
+                NR10 + offset as u16
+            );
+        }
+
+        assert_eq!(0xF0, apu.read(NR52));
+
+        for address in 0xFF27..WAVE_RAM_START {
+            assert_eq!(0xFF, apu.read(address));
+        }
+    }
+
+    #[test]
+    fn it_reads_back_written_registers() {
+        let mut apu = powered();
+        apu.write(NR10, 0x7F);
+        apu.write(NR12, 0xA5);
+        apu.write(NR50, 0x35);
+        apu.write(0xFF1C, 0x60);
+        apu.write(0xFF22, 0x5A);
+
+        assert_eq!(0xFF, apu.read(NR10));
+        assert_eq!(0xA5, apu.read(NR12));
+        assert_eq!(0x35, apu.read(NR50));
+        assert_eq!(0xFF, apu.read(0xFF1C));
+        assert_eq!(0x5A, apu.read(0xFF22));
+    }
+
+    #[test]
+    fn it_reports_playing_channels() {
+        let mut apu = playing();
+        assert_eq!(0xF1, apu.read(NR52));
+
+        apu.write(NR30, 0x80);
+        apu.write(NR34, 0x80);
+        assert_eq!(0xF5, apu.read(NR52));
+
+        // Without a DAC, the noise channel doesn't start
+        apu.write(NR44, 0x80);
+        assert_eq!(0xF5, apu.read(NR52));
+    }
+
+    #[test]
+    fn it_clears_registers_when_powered_off() {
+        let mut apu = playing();
+        apu.write(NR52, 0x00);
+
+        assert_eq!(0x70, apu.read(NR52));
+        assert_eq!(0x00, apu.read(NR12));
+        assert_eq!(0x00, apu.read(NR50));
+
+        apu.write(NR12, 0xF0);
+        apu.write(NR51, 0xFF);
+        assert_eq!(0x00, apu.read(NR12));
+        assert_eq!(0x00, apu.read(NR51));
+
+        apu.write(NR52, 0x80);
+        assert_eq!(0xF0, apu.read(NR52));
+    }
+
+    #[test]
+    fn it_keeps_wave_ram_and_lengths_when_powered_off() {
+        let mut apu = powered();
+        apu.write(WAVE_RAM_START, 0x12);
+        apu.write(NR52, 0x00);
+
+        // Writes to lengths still go through, but not to duty
+        apu.write(NR11, 0xFF);
+        apu.write(NR31, 0xFF);
+        apu.write(WAVE_RAM_START + 1, 0x34);
+        apu.write(NR52, 0x80);
+
+        assert_eq!(0x12, apu.read(WAVE_RAM_START));
+        assert_eq!(0x34, apu.read(WAVE_RAM_START + 1));
+        assert_eq!(0x3F, apu.read(NR11));
+
+        // One tick left on channel 1's length
+        apu.write(NR12, 0xF0);
+        apu.write(NR14, 0xC0);
+        assert_eq!(0xF1, apu.read(NR52));
+
+        apu.step(u64::from(CYCLES_PER_SEQUENCER_STEP));
+        assert_eq!(0xF0, apu.read(NR52));
+    }
+
+    #[test]
+    fn it_clocks_lengths_at_256_hz() {
+        let mut apu = powered();
+        apu.write(NR42, 0xF0);
+        apu.write(NR41, 0x3E);
+        apu.write(NR44, 0xC0);
+
+        apu.step(u64::from(CYCLES_PER_SEQUENCER_STEP));
+        assert_eq!(0xF8, apu.read(NR52));
+
+        // Step 1 doesn't clock lengths, but step 2 does
+        apu.step(u64::from(CYCLES_PER_SEQUENCER_STEP) * 2);
+        assert_eq!(0xF0, apu.read(NR52));
+    }
+
+    #[test]
+    fn it_produces_samples_at_the_sample_rate() {
+        for rate in [DEFAULT_SAMPLE_RATE, 48_000] {
+            let mut apu = Apu::default();
+            apu.set_sample_rate(rate);
+
+            let mut samples = Vec::new();
+            let mut total = 0;
+
+            // 60 frames is just over a second
+            for _ in 0..60 {
+                apu.step(CYCLES_PER_FRAME);
+                apu.drain_samples_into(&mut samples);
+                total += samples.len();
+                samples.clear();
+            }
+
+            let expected = u64::from(rate) * 60 * CYCLES_PER_FRAME / CYCLES_PER_SECOND * 2;
+            assert!(
+                (total as i64 - expected as i64).abs() <= 2,
+                "{} samples at {} Hz",
+                total,
+                rate
+            );
+        }
+    }
+
+    #[test]
+    fn it_drops_samples_that_arent_collected() {
+        let mut apu = Apu::default();
+        let mut samples = Vec::new();
+
+        apu.step(CYCLES_PER_SECOND);
+        apu.drain_samples_into(&mut samples);
+
+        assert_eq!(apu.samples.capacity(), samples.len());
+        assert!(samples.iter().all(|sample| *sample == 0));
+    }
+
+    #[test]
+    fn it_pans_channels() {
+        let mut apu = playing();
+        apu.write(NR51, 0x10);
+
+        let mut samples = Vec::new();
+        apu.step(CYCLES_PER_SECOND / 100);
+        apu.drain_samples_into(&mut samples);
+
+        assert!(samples.chunks(2).any(|pair| pair[0] != 0));
+        assert!(samples.chunks(2).all(|pair| pair[1] == 0));
+    }
+
+    #[test]
+    fn it_plays_a_square_wave() {
+        let mut apu = playing();
+
+        let mut samples = Vec::new();
+        apu.step(CYCLES_PER_SECOND / 100);
+        apu.drain_samples_into(&mut samples);
+
+        // Band-limiting rings around each edge, so only count it once the
+        // wave has swung well past 0
+        let mut high = false;
+        let mut highs = 0;
+
+        for sample in samples.iter().step_by(2) {
+            if !high && *sample > 2000 {
+                high = true;
+                highs += 1;
+            } else if high && *sample < -2000 {
+                high = false;
+            }
+        }
+
+        // 131072 / (2048 - 0x700) Hz is 512 Hz, so about 5 cycles in 10 ms
+        assert!((4..=6).contains(&highs), "{} rising edges", highs);
+    }
+
+    #[test]
+    fn it_leaves_muted_channels_out_of_the_mix() {
+        let mut apu = playing();
+        apu.set_muted(AudioChannel::Square1, true);
+
+        let mut samples = Vec::new();
+        apu.step(CYCLES_PER_SECOND / 100);
+        apu.drain_samples_into(&mut samples);
+
+        assert!(apu.is_muted(AudioChannel::Square1));
+        assert!(samples.iter().all(|sample| *sample == 0));
+
+        // The channel itself keeps playing
+        assert_eq!(0xF1, apu.read(NR52));
+    }
+
+    #[test]
+    fn it_solos_a_channel() {
+        let mut apu = playing();
+
+        apu.solo(Some(AudioChannel::Wave));
+        assert!(AudioChannel::ALL
+            .iter()
+            .all(|channel| apu.is_muted(*channel) == (*channel != AudioChannel::Wave)));
+
+        apu.solo(None);
+        assert!(AudioChannel::ALL
+            .iter()
+            .all(|channel| !apu.is_muted(*channel)));
+    }
+
+    #[test]
+    fn it_describes_channels() {
+        let apu = playing();
+        let info = apu.channel(AudioChannel::Square1);
+
+        assert!(info.enabled);
+        assert!(info.dac_enabled);
+        assert_eq!(Some(2), info.duty);
+        assert_eq!(15, info.volume);
+        assert_eq!(8192, info.period);
+        assert!((info.frequency() - 512.0).abs() < f64::EPSILON);
+
+        let info = apu.channel(AudioChannel::Noise);
+        assert!(!info.enabled);
+        assert_eq!(None, info.duty);
+    }
+
+    #[test]
+    fn it_taps_each_channels_output() {
+        let mut apu = playing();
+        apu.set_muted(AudioChannel::Square1, true);
+        apu.set_tapping(true);
+        apu.step(CYCLES_PER_SECOND / 100);
+
+        let mut square = Vec::new();
+        let mut noise = Vec::new();
+        apu.drain_tap_into(AudioChannel::Square1, &mut square);
+        apu.drain_tap_into(AudioChannel::Noise, &mut noise);
+
+        // A 50% duty cycle spends half its time high
+        let highs = square.iter().filter(|output| **output == 15).count();
+        assert!((440..=441).contains(&square.len()));
+        assert!((200..=240).contains(&highs), "{} high samples", highs);
+        assert!(square.iter().all(|output| *output == 0 || *output == 15));
+        assert!(noise.iter().all(|output| *output == 0));
+    }
+
+    #[test]
+    fn it_only_taps_when_asked() {
+        let mut apu = playing();
+        apu.step(CYCLES_PER_SECOND / 100);
+
+        let mut square = Vec::new();
+        apu.drain_tap_into(AudioChannel::Square1, &mut square);
+
+        assert!(square.is_empty());
+    }
+
+    #[test]
+    fn it_logs_register_writes() {
+        let mut apu = Apu::default();
+        apu.write(NR52, 0x80);
+        apu.set_logging(true);
+
+        apu.step(100);
+        apu.write(NR50, 0x77);
+        apu.step(50);
+        apu.write(WAVE_RAM_START, 0x12);
+
+        let mut writes = Vec::new();
+        apu.drain_writes_into(&mut writes);
+
+        assert_eq!(
+            vec![
+                RegisterWrite {
+                    cycle: 100,
+                    address: NR50,
+                    value: 0x77
+                },
+                RegisterWrite {
+                    cycle: 150,
+                    address: WAVE_RAM_START,
+                    value: 0x12
+                },
+            ],
+            writes
+        );
+
+        apu.set_logging(false);
+        apu.write(NR51, 0xFF);
+        apu.drain_writes_into(&mut writes);
+        assert_eq!(2, writes.len());
+    }
+}
@@ -0,0 +1,234 @@
+use super::{envelope::Envelope, length::LengthCounter, Channel};
+
+/// The base periods NR43's divisor code selects, in T-cycles.
+const DIVISORS: [u16; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+/// Channel 4, which plays pseudo-random noise from a linear feedback
+/// shift register.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) struct Noise {
+    length: LengthCounter,
+    envelope: Envelope,
+    /// NR43, the clock shift, LFSR width and divisor code.
+    register: u8,
+    timer: u32,
+    lfsr: u16,
+    enabled: bool,
+}
+
+impl Noise {
+    pub(super) fn new() -> Self {
+        Self {
+            length: LengthCounter::new(64),
+            envelope: Envelope::default(),
+            register: 0,
+            timer: 0,
+            lfsr: 0,
+            enabled: false,
+        }
+    }
+
+    pub(super) fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    fn period(&self) -> u32 {
+        let divisor = DIVISORS[usize::from(self.register & 0b111)];
+        u32::from(divisor) << (self.register >> 4)
+    }
+
+    /// Whether the LFSR is only 7 bits wide, which makes for a more tonal
+    /// noise.
+    fn is_short(&self) -> bool {
+        self.register & 0b1000 != 0
+    }
+
+    fn trigger(&mut self, next_step_clocks_length: bool) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger(next_step_clocks_length);
+        self.envelope.trigger();
+        self.timer = self.period();
+        self.lfsr = 0x7FFF;
+    }
+
+    fn shift(&mut self) {
+        let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+        self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+
+        if self.is_short() {
+            self.lfsr = (self.lfsr & !(1 << 6)) | (feedback << 6);
+        }
+    }
+}
+
+impl Channel for Noise {
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            2 => self.envelope.read(),
+            3 => self.register,
+            4 => u8::from(self.length.is_enabled()) << 6,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, register: u8, value: u8, next_step_clocks_length: bool) {
+        match register {
+            1 => self.length.load(value & 0x3F),
+            2 => {
+                self.envelope.write(value);
+
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.register = value,
+            4 => {
+                if self
+                    .length
+                    .set_enabled(value & 0x40 != 0, next_step_clocks_length)
+                {
+                    self.enabled = false;
+                }
+
+                if value & 0x80 != 0 {
+                    self.trigger(next_step_clocks_length);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period();
+
+        // Shifts of 14 and 15 stop the LFSR from being clocked at all
+        if self.register >> 4 < 14 {
+            self.shift();
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if !self.enabled || self.lfsr & 1 != 0 {
+            return 0;
+        }
+
+        self.envelope.volume()
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn dac_enabled(&self) -> bool {
+        self.envelope.dac_enabled()
+    }
+
+    fn waveform_period(&self) -> u32 {
+        self.period()
+    }
+
+    fn volume(&self) -> u8 {
+        self.envelope.volume()
+    }
+
+    fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    fn power_off(&mut self) {
+        let mut length = self.length;
+        length.power_off();
+
+        *self = Self {
+            length,
+            ..Self::new()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noise(register: u8) -> Noise {
+        let mut noise = Noise::new();
+        noise.write(2, 0xF0, true);
+        noise.write(3, register, true);
+        noise.write(4, 0x80, true);
+        noise
+    }
+
+    /// How many shifts it takes the LFSR to get back to where it started.
+    fn sequence_length(noise: &mut Noise) -> usize {
+        let start = noise.lfsr;
+
+        (1..=0x8000)
+            .find(|_| {
+                noise.shift();
+                noise.lfsr == start
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn it_repeats_every_32767_shifts() {
+        assert_eq!(0x7FFF, sequence_length(&mut noise(0x00)));
+    }
+
+    #[test]
+    fn it_repeats_every_127_shifts_in_short_mode() {
+        let mut noise = noise(0x08);
+
+        // Let the top bits settle into the 7-bit sequence first
+        for _ in 0..16 {
+            noise.shift();
+        }
+
+        assert_eq!(127, sequence_length(&mut noise));
+    }
+
+    #[test]
+    fn it_outputs_the_inverted_low_bit() {
+        let mut noise = noise(0x00);
+        assert_eq!(0, noise.output());
+
+        // 0x7FFF shifts in 0 at the top, so the low bit clears after 15
+        for _ in 0..15 {
+            noise.shift();
+        }
+
+        assert_eq!(15, noise.output());
+    }
+
+    #[test]
+    fn it_clocks_at_the_divided_rate() {
+        let mut noise = noise(0x21);
+
+        for _ in 0..63 {
+            noise.tick();
+        }
+
+        assert_eq!(0x7FFF, noise.lfsr);
+
+        noise.tick();
+        assert_eq!(0x3FFF, noise.lfsr);
+    }
+
+    #[test]
+    fn it_stops_clocking_at_the_top_shifts() {
+        let mut noise = noise(0xE0);
+
+        for _ in 0..0x100000 {
+            noise.tick();
+        }
+
+        assert_eq!(0x7FFF, noise.lfsr);
+    }
+}
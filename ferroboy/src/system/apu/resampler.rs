@@ -0,0 +1,275 @@
+//! Band-limited resampling from the APU's T-cycle rate down to the host's
+//! sample rate.
+//!
+//! Rather than sampling the mix, which would alias every edge of the
+//! square waves into audible noise, each change in the mix is drawn into
+//! the output as a band-limited step, the same approach as Blargg's
+//! `blip_buf`. The output buffer holds the differences between samples,
+//! so each step is only a handful of writes, and it's summed up as
+//! samples are read out.
+
+use std::collections::VecDeque;
+
+use once_cell::sync::Lazy;
+
+use super::ring::RingBuffer;
+use crate::CYCLES_PER_SECOND;
+
+/// How many fractional positions between two samples steps can land on.
+const PHASE_BITS: u32 = 6;
+const PHASES: usize = 1 << PHASE_BITS;
+/// How many samples each step is spread across.
+const KERNEL_WIDTH: usize = 16;
+/// The fixed-point scale of the kernel, which each phase sums to.
+const KERNEL_UNIT_BITS: u32 = 15;
+/// The cutoff frequency of the kernel, as a fraction of the Nyquist
+/// frequency, leaving some room for the window's roll-off.
+const CUTOFF: f64 = 0.9;
+/// How much of its charge the high-pass filter's capacitor keeps every
+/// T-cycle, which removes the DC offset of the DACs.
+const HIGH_PASS_CHARGE: f64 = 0.999958;
+
+/// The impulse response of a step landing on each phase, a windowed sinc.
+///
+/// Each phase sums to exactly `1 << KERNEL_UNIT_BITS`, so a step always
+/// settles at the right level.
+static KERNEL: Lazy<[[i64; KERNEL_WIDTH]; PHASES]> = Lazy::new(|| {
+    let mut kernel = [[0; KERNEL_WIDTH]; PHASES];
+    let half_width = KERNEL_WIDTH as f64 / 2.0;
+
+    for (phase, taps) in kernel.iter_mut().enumerate() {
+        let centre = half_width - 1.0 + phase as f64 / PHASES as f64;
+
+        let response: Vec<f64> = (0..KERNEL_WIDTH)
+            .map(|tap| {
+                let x = tap as f64 - centre;
+                let sinc = if x == 0.0 {
+                    CUTOFF
+                } else {
+                    (std::f64::consts::PI * CUTOFF * x).sin() / (std::f64::consts::PI * x)
+                };
+                let angle = std::f64::consts::PI * x / half_width;
+                let blackman = 0.42 + 0.5 * angle.cos() + 0.08 * (2.0 * angle).cos();
+
+                sinc * blackman
+            })
+            .collect();
+
+        let total: f64 = response.iter().sum();
+        let unit = (1i64 << KERNEL_UNIT_BITS) as f64;
+
+        for (tap, value) in taps.iter_mut().zip(&response) {
+            *tap = (value / total * unit).round() as i64;
+        }
+
+        // Rounding leaves the sum slightly off, so make it up in the middle
+        let error = (1 << KERNEL_UNIT_BITS) - taps.iter().sum::<i64>();
+        taps[KERNEL_WIDTH / 2] += error;
+    }
+
+    kernel
+});
+
+/// One side of the stereo output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Side {
+    /// The level the mix was last drawn at.
+    amplitude: i32,
+    /// The differences between upcoming samples, scaled by the kernel.
+    deltas: VecDeque<i64>,
+    /// The sum of every delta read so far, i.e. the current sample.
+    integrator: i64,
+    /// The charge of the high-pass filter's capacitor, scaled like
+    /// `integrator`.
+    capacitor: i64,
+}
+
+impl Side {
+    fn new() -> Self {
+        Self {
+            amplitude: 0,
+            deltas: VecDeque::from(vec![0; KERNEL_WIDTH]),
+            integrator: 0,
+            capacitor: 0,
+        }
+    }
+
+    fn update(&mut self, phase: usize, amplitude: i32) {
+        let delta = i64::from(amplitude - self.amplitude);
+
+        if delta == 0 {
+            return;
+        }
+
+        self.amplitude = amplitude;
+
+        for (slot, tap) in self.deltas.iter_mut().zip(&KERNEL[phase]) {
+            *slot += delta * tap;
+        }
+    }
+
+    fn next_sample(&mut self, charge: i64) -> i16 {
+        self.integrator += self.deltas.pop_front().unwrap_or_default();
+        self.deltas.push_back(0);
+
+        let output = self.integrator - self.capacitor;
+        self.capacitor = self.integrator - ((output * charge) >> KERNEL_UNIT_BITS);
+
+        (output >> KERNEL_UNIT_BITS).clamp(i64::from(i16::MIN), i64::from(i16::MAX)) as i16
+    }
+}
+
+/// Converts the APU's mix into stereo samples at a host sample rate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Resampler {
+    sample_rate: u32,
+    /// How far through a sample each T-cycle moves, as 32.32 fixed point.
+    step: u64,
+    /// How far through the current sample the APU is, as 32.32 fixed point.
+    position: u64,
+    /// The high-pass filter's charge factor per sample, scaled by the
+    /// kernel's unit.
+    charge: i64,
+    sides: [Side; 2],
+}
+
+impl Resampler {
+    pub(super) fn new(sample_rate: u32) -> Self {
+        let cycles_per_sample = CYCLES_PER_SECOND as f64 / f64::from(sample_rate);
+        let charge = HIGH_PASS_CHARGE.powf(cycles_per_sample) * (1i64 << KERNEL_UNIT_BITS) as f64;
+
+        Self {
+            sample_rate,
+            step: (u64::from(sample_rate) << 32) / CYCLES_PER_SECOND,
+            position: 0,
+            charge: charge as i64,
+            sides: [Side::new(), Side::new()],
+        }
+    }
+
+    pub(super) fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Advances by one T-cycle with the current mix, pushing a sample
+    /// pair to `output` whenever one is complete.
+    ///
+    /// Returns whether a pair was pushed.
+    pub(super) fn tick(&mut self, mix: [i32; 2], output: &mut RingBuffer<i16>) -> bool {
+        let phase = ((self.position >> (32 - PHASE_BITS)) as usize) & (PHASES - 1);
+
+        for (side, amplitude) in self.sides.iter_mut().zip(mix) {
+            side.update(phase, amplitude);
+        }
+
+        self.position += self.step;
+
+        if self.position >> 32 == 0 {
+            return false;
+        }
+
+        self.position &= 0xFFFF_FFFF;
+
+        for side in &mut self.sides {
+            output.push(side.next_sample(self.charge));
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs a square wave of a given frequency and amplitude through the
+    /// resampler for a tenth of a second, returning the left side.
+    fn resample(frequency: u64, amplitude: i32) -> Vec<i16> {
+        let mut resampler = Resampler::new(44_100);
+        let mut output = RingBuffer::new(44_100);
+        let half_period = CYCLES_PER_SECOND / frequency / 2;
+
+        for cycle in 0..CYCLES_PER_SECOND / 10 {
+            let level = if (cycle / half_period) % 2 == 0 {
+                amplitude
+            } else {
+                -amplitude
+            };
+
+            resampler.tick([level, level], &mut output);
+        }
+
+        let mut samples = Vec::new();
+        output.drain_into(&mut samples);
+        samples.into_iter().step_by(2).collect()
+    }
+
+    fn rms(samples: &[i16]) -> f64 {
+        let total: f64 = samples
+            .iter()
+            .map(|sample| f64::from(*sample).powi(2))
+            .sum();
+        (total / samples.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn it_normalises_every_phase() {
+        for taps in KERNEL.iter() {
+            assert_eq!(1 << KERNEL_UNIT_BITS, taps.iter().sum::<i64>());
+        }
+    }
+
+    #[test]
+    fn it_produces_samples_at_the_host_rate() {
+        for rate in [44_100, 48_000] {
+            let mut resampler = Resampler::new(rate);
+            let mut output = RingBuffer::new(rate as usize * 2);
+
+            for _ in 0..CYCLES_PER_SECOND {
+                resampler.tick([0, 0], &mut output);
+            }
+
+            let pairs = output.len() as i64 / 2;
+            assert!(
+                (pairs - i64::from(rate)).abs() <= 1,
+                "{} at {}",
+                pairs,
+                rate
+            );
+        }
+    }
+
+    #[test]
+    fn it_settles_on_the_level_of_a_step() {
+        let mut resampler = Resampler::new(44_100);
+        let mut output = RingBuffer::new(64);
+
+        for _ in 0..2000 {
+            resampler.tick([10_000, -10_000], &mut output);
+        }
+
+        let mut samples = Vec::new();
+        output.drain_into(&mut samples);
+
+        // The high-pass filter pulls it back towards 0 only slowly
+        let (left, right) = (samples[samples.len() - 2], samples[samples.len() - 1]);
+        assert!((9_000..=10_000).contains(&left), "{}", left);
+        assert!((-10_000..=-9_000).contains(&right), "{}", right);
+    }
+
+    #[test]
+    fn it_passes_audible_frequencies() {
+        let samples = resample(1_000, 8_000);
+
+        // A square wave's RMS is its amplitude
+        assert!(rms(&samples[1000..]) > 7_000.0, "{}", rms(&samples));
+    }
+
+    #[test]
+    fn it_filters_frequencies_past_nyquist() {
+        // Sampling this directly would alias down to a loud 7.5 kHz tone
+        let samples = resample(51_600, 8_000);
+
+        assert!(rms(&samples[1000..]) < 800.0, "{}", rms(&samples));
+    }
+}
@@ -0,0 +1,114 @@
+/// A fixed-size queue of samples.
+///
+/// Once it's full the oldest samples are dropped to make room, so a
+/// frontend that falls behind loses a little audio rather than the
+/// emulation building up an ever-growing backlog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct RingBuffer<T> {
+    samples: Vec<T>,
+    /// Where the oldest sample is.
+    start: usize,
+    len: usize,
+}
+
+impl<T: Copy + Default> RingBuffer<T> {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            samples: vec![T::default(); capacity],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub(super) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(super) fn capacity(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub(super) fn push(&mut self, sample: T) {
+        let capacity = self.capacity();
+        self.samples[(self.start + self.len) % capacity] = sample;
+
+        if self.len == capacity {
+            self.start = (self.start + 1) % capacity;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    /// Moves every queued sample into `buffer`, oldest first.
+    ///
+    /// Stereo samples are pushed in pairs into a buffer of even capacity,
+    /// so they're only ever dropped in pairs and this always starts on a
+    /// left sample.
+    pub(super) fn drain_into(&mut self, buffer: &mut Vec<T>) {
+        let capacity = self.capacity();
+        let (end, wrapped) = (self.start + self.len, self.start + self.len > capacity);
+
+        if wrapped {
+            buffer.extend_from_slice(&self.samples[self.start..]);
+            buffer.extend_from_slice(&self.samples[..end - capacity]);
+        } else {
+            buffer.extend_from_slice(&self.samples[self.start..end]);
+        }
+
+        self.start = 0;
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(ring: &mut RingBuffer<i16>) -> Vec<i16> {
+        let mut buffer = Vec::new();
+        ring.drain_into(&mut buffer);
+        buffer
+    }
+
+    #[test]
+    fn it_queues_samples_in_order() {
+        let mut ring = RingBuffer::new(8);
+
+        for sample in 1..=4 {
+            ring.push(sample);
+        }
+
+        assert_eq!(4, ring.len());
+        assert_eq!(vec![1, 2, 3, 4], drain(&mut ring));
+        assert_eq!(0, ring.len());
+    }
+
+    #[test]
+    fn it_drops_the_oldest_samples_when_full() {
+        let mut ring = RingBuffer::new(4);
+
+        for sample in 1..=6 {
+            ring.push(sample);
+        }
+
+        assert_eq!(vec![3, 4, 5, 6], drain(&mut ring));
+    }
+
+    #[test]
+    fn it_wraps_around() {
+        let mut ring = RingBuffer::new(4);
+
+        for sample in 1..=3 {
+            ring.push(sample);
+        }
+
+        drain(&mut ring);
+
+        for sample in 4..=7 {
+            ring.push(sample);
+        }
+
+        assert_eq!(vec![4, 5, 6, 7], drain(&mut ring));
+    }
+}
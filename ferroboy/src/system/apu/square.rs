@@ -0,0 +1,252 @@
+use super::{
+    envelope::Envelope,
+    length::LengthCounter,
+    sweep::{Sweep, SweepResult},
+    Channel,
+};
+
+/// The waveforms of the four duty cycles, played from the top bit down.
+const DUTY_PATTERNS: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+
+/// Channels 1 and 2, which play square waves. Only channel 1 has a sweep.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) struct Square {
+    sweep: Option<Sweep>,
+    length: LengthCounter,
+    envelope: Envelope,
+    duty: u8,
+    frequency: u16,
+    timer: u16,
+    position: u8,
+    enabled: bool,
+}
+
+impl Square {
+    pub(super) fn new(has_sweep: bool) -> Self {
+        Self {
+            sweep: has_sweep.then(Sweep::default),
+            length: LengthCounter::new(64),
+            envelope: Envelope::default(),
+            duty: 0,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            enabled: false,
+        }
+    }
+
+    /// Clocked by the frame sequencer at 128 Hz.
+    pub(super) fn clock_sweep(&mut self) {
+        let result = match self.sweep.as_mut() {
+            Some(sweep) => sweep.clock(),
+            None => return,
+        };
+
+        match result {
+            SweepResult::Unchanged => {}
+            SweepResult::Frequency(frequency) => self.frequency = frequency,
+            SweepResult::Overflow => self.enabled = false,
+        }
+    }
+
+    pub(super) fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    fn period(&self) -> u16 {
+        (2048 - self.frequency) * 4
+    }
+
+    fn trigger(&mut self, next_step_clocks_length: bool) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger(next_step_clocks_length);
+        self.envelope.trigger();
+        self.timer = self.period();
+
+        if let Some(sweep) = self.sweep.as_mut() {
+            if sweep.trigger(self.frequency) {
+                self.enabled = false;
+            }
+        }
+    }
+}
+
+impl Channel for Square {
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            0 => self.sweep.map_or(0, |sweep| sweep.read()),
+            1 => self.duty << 6,
+            2 => self.envelope.read(),
+            4 => u8::from(self.length.is_enabled()) << 6,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, register: u8, value: u8, next_step_clocks_length: bool) {
+        match register {
+            0 => {
+                if let Some(sweep) = self.sweep.as_mut() {
+                    if sweep.write(value) {
+                        self.enabled = false;
+                    }
+                }
+            }
+            1 => {
+                self.duty = value >> 6;
+                self.length.load(value & 0x3F);
+            }
+            2 => {
+                self.envelope.write(value);
+
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            }
+            3 => self.frequency = (self.frequency & 0x700) | u16::from(value),
+            4 => {
+                self.frequency = (self.frequency & 0xFF) | (u16::from(value & 0b111) << 8);
+
+                if self
+                    .length
+                    .set_enabled(value & 0x40 != 0, next_step_clocks_length)
+                {
+                    self.enabled = false;
+                }
+
+                if value & 0x80 != 0 {
+                    self.trigger(next_step_clocks_length);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period();
+        self.position = (self.position + 1) % 8;
+    }
+
+    fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+
+        let high = DUTY_PATTERNS[usize::from(self.duty)] & (0x80 >> self.position) != 0;
+
+        if high {
+            self.envelope.volume()
+        } else {
+            0
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn dac_enabled(&self) -> bool {
+        self.envelope.dac_enabled()
+    }
+
+    fn waveform_period(&self) -> u32 {
+        u32::from(self.period()) * 8
+    }
+
+    fn duty(&self) -> Option<u8> {
+        Some(self.duty)
+    }
+
+    fn volume(&self) -> u8 {
+        self.envelope.volume()
+    }
+
+    fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    fn power_off(&mut self) {
+        let mut length = self.length;
+        length.power_off();
+
+        *self = Self {
+            length,
+            ..Self::new(self.sweep.is_some())
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A triggered channel at full volume, with a duty step every 4 ticks.
+    fn square(duty: u8) -> Square {
+        let mut square = Square::new(false);
+        square.write(1, duty << 6, true);
+        square.write(2, 0xF0, true);
+        square.write(3, 0xFF, true);
+        square.write(4, 0x87, true);
+        square
+    }
+
+    fn waveform(square: &mut Square) -> Vec<u8> {
+        (0..8)
+            .map(|_| {
+                for _ in 0..4 {
+                    square.tick();
+                }
+
+                square.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_plays_the_duty_cycle() {
+        assert_eq!(vec![0, 0, 0, 0, 0, 0, 15, 0], waveform(&mut square(0)));
+        assert_eq!(vec![0, 0, 0, 0, 15, 15, 15, 15], waveform(&mut square(2)));
+        assert_eq!(vec![15, 15, 15, 15, 15, 15, 0, 0], waveform(&mut square(3)));
+    }
+
+    #[test]
+    fn it_needs_its_dac_to_play() {
+        let mut square = square(2);
+        assert!(square.is_enabled());
+
+        square.write(2, 0x07, true);
+        assert!(!square.is_enabled());
+
+        square.write(4, 0x80, true);
+        assert!(!square.is_enabled());
+    }
+
+    #[test]
+    fn it_stops_when_its_length_runs_out() {
+        let mut square = square(2);
+        square.write(1, 0x3F, true);
+        square.write(4, 0xC7, true);
+
+        square.clock_length();
+        assert!(!square.is_enabled());
+    }
+
+    #[test]
+    fn it_stops_when_its_sweep_overflows() {
+        let mut square = Square::new(true);
+        square.write(0, 0x11, true);
+        square.write(2, 0xF0, true);
+        square.write(3, 0xFF, true);
+        square.write(4, 0x83, true);
+        assert!(square.is_enabled());
+
+        square.clock_sweep();
+        assert!(!square.is_enabled());
+    }
+}
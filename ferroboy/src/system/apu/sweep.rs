@@ -0,0 +1,173 @@
+/// The highest frequency a channel can play, in NRx3 and NRx4's 11 bits.
+const MAX_FREQUENCY: u16 = 2047;
+
+/// Channel 1's frequency sweep, set by NR10.
+///
+/// The sweep works on a shadow copy of the frequency, and only writes the
+/// result back to NR13/NR14 if it hasn't overflowed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct Sweep {
+    register: u8,
+    enabled: bool,
+    shadow: u16,
+    timer: u8,
+    /// Whether a calculation has subtracted since the last trigger, which
+    /// makes switching to addition turn the channel off.
+    negated: bool,
+}
+
+/// What a sweep clock did to the channel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) enum SweepResult {
+    Unchanged,
+    Frequency(u16),
+    Overflow,
+}
+
+impl Sweep {
+    pub(super) fn read(&self) -> u8 {
+        self.register
+    }
+
+    /// Returns whether the channel should be turned off.
+    pub(super) fn write(&mut self, value: u8) -> bool {
+        self.register = value;
+
+        self.negated && !self.is_negate()
+    }
+
+    /// Returns whether the channel should be turned off straight away
+    /// because the first calculation overflowed.
+    pub(super) fn trigger(&mut self, frequency: u16) -> bool {
+        self.shadow = frequency;
+        self.timer = self.period();
+        self.enabled = self.register & 0b111_0000 != 0 || self.shift() != 0;
+        self.negated = false;
+
+        self.shift() != 0 && self.calculate() > MAX_FREQUENCY
+    }
+
+    /// Clocked by the frame sequencer at 128 Hz.
+    pub(super) fn clock(&mut self) -> SweepResult {
+        self.timer = self.timer.saturating_sub(1);
+
+        if self.timer > 0 {
+            return SweepResult::Unchanged;
+        }
+
+        self.timer = self.period();
+
+        if !self.enabled || self.register & 0b111_0000 == 0 {
+            return SweepResult::Unchanged;
+        }
+
+        let frequency = self.calculate();
+
+        if frequency > MAX_FREQUENCY {
+            return SweepResult::Overflow;
+        }
+
+        if self.shift() == 0 {
+            return SweepResult::Unchanged;
+        }
+
+        self.shadow = frequency;
+
+        // The new frequency is checked for overflow again, but not used
+        if self.calculate() > MAX_FREQUENCY {
+            return SweepResult::Overflow;
+        }
+
+        SweepResult::Frequency(frequency)
+    }
+
+    fn calculate(&mut self) -> u16 {
+        let offset = self.shadow >> self.shift();
+
+        if self.is_negate() {
+            self.negated = true;
+            self.shadow - offset
+        } else {
+            self.shadow + offset
+        }
+    }
+
+    /// A period of 0 is treated as 8.
+    fn period(&self) -> u8 {
+        match (self.register >> 4) & 0b111 {
+            0 => 8,
+            period => period,
+        }
+    }
+
+    fn is_negate(&self) -> bool {
+        self.register & 0b1000 != 0
+    }
+
+    fn shift(&self) -> u8 {
+        self.register & 0b111
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sweep(register: u8) -> Sweep {
+        let mut sweep = Sweep::default();
+        sweep.write(register);
+        sweep
+    }
+
+    #[test]
+    fn it_sweeps_up() {
+        let mut sweep = sweep(0x11);
+        assert!(!sweep.trigger(0x100));
+
+        assert_eq!(SweepResult::Frequency(0x180), sweep.clock());
+        assert_eq!(SweepResult::Frequency(0x240), sweep.clock());
+    }
+
+    #[test]
+    fn it_sweeps_down() {
+        let mut sweep = sweep(0x29);
+        sweep.trigger(0x100);
+
+        assert_eq!(SweepResult::Unchanged, sweep.clock());
+        assert_eq!(SweepResult::Frequency(0x80), sweep.clock());
+    }
+
+    #[test]
+    fn it_overflows_on_trigger() {
+        let mut sweep = sweep(0x11);
+
+        assert!(sweep.trigger(0x600));
+    }
+
+    #[test]
+    fn it_overflows_when_checking_the_next_frequency() {
+        let mut sweep = sweep(0x11);
+        assert!(!sweep.trigger(0x500));
+
+        assert_eq!(SweepResult::Overflow, sweep.clock());
+    }
+
+    #[test]
+    fn it_does_nothing_with_a_period_of_zero() {
+        let mut sweep = sweep(0x01);
+        sweep.trigger(0x100);
+
+        for _ in 0..16 {
+            assert_eq!(SweepResult::Unchanged, sweep.clock());
+        }
+    }
+
+    #[test]
+    fn it_disables_the_channel_when_leaving_negate_mode() {
+        let mut sweep = sweep(0x19);
+        sweep.trigger(0x100);
+        sweep.clock();
+
+        assert!(sweep.write(0x11));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::CYCLES_PER_SECOND;
+
+/// One of the APU's four sound channels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AudioChannel {
+    Square1,
+    Square2,
+    Wave,
+    Noise,
+}
+
+impl AudioChannel {
+    pub const ALL: [AudioChannel; 4] = [
+        AudioChannel::Square1,
+        AudioChannel::Square2,
+        AudioChannel::Wave,
+        AudioChannel::Noise,
+    ];
+
+    /// The channel's position in NR51 and NR52, from 0 to 3.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl std::fmt::Display for AudioChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            AudioChannel::Square1 => "Square 1",
+            AudioChannel::Square2 => "Square 2",
+            AudioChannel::Wave => "Wave",
+            AudioChannel::Noise => "Noise",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// A snapshot of what a channel is playing, for debugging.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChannelInfo {
+    /// Whether the channel is playing, as reported in NR52.
+    pub enabled: bool,
+    pub dac_enabled: bool,
+    /// How many T-cycles one cycle of the waveform takes. For the noise
+    /// channel, this is how often the LFSR is clocked instead.
+    pub period: u32,
+    /// The square channels' duty cycle, from 0 for 12.5% to 3 for 75%.
+    pub duty: Option<u8>,
+    /// The envelope's volume from 0 to 15, or for the wave channel, the
+    /// loudest sample NR32's output level lets through.
+    pub volume: u8,
+    /// The channel's current output, from 0 to 15.
+    pub output: u8,
+}
+
+impl ChannelInfo {
+    /// The frequency of the waveform in Hz, or how often the noise
+    /// channel's LFSR is clocked.
+    pub fn frequency(&self) -> f64 {
+        CYCLES_PER_SECOND as f64 / f64::from(self.period.max(1))
+    }
+}
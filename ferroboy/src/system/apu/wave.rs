@@ -0,0 +1,237 @@
+use super::{length::LengthCounter, Channel};
+
+/// The size of wave RAM, which holds 32 four-bit samples.
+pub(super) const WAVE_RAM_SIZE: usize = 16;
+
+/// Channel 3, which plays back a waveform from wave RAM.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(super) struct Wave {
+    dac_enabled: bool,
+    length: LengthCounter,
+    /// NR32's output level, 0 for mute then 100%, 50% and 25%.
+    level: u8,
+    frequency: u16,
+    timer: u16,
+    /// Which of the 32 samples is playing.
+    position: u8,
+    /// The most recently read sample, which keeps playing until the next
+    /// one is read.
+    sample: u8,
+    enabled: bool,
+    ram: [u8; WAVE_RAM_SIZE],
+}
+
+impl Wave {
+    pub(super) fn new() -> Self {
+        Self {
+            dac_enabled: false,
+            length: LengthCounter::new(256),
+            level: 0,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            sample: 0,
+            enabled: false,
+            ram: [0; WAVE_RAM_SIZE],
+        }
+    }
+
+    /// Reads wave RAM as the CPU sees it.
+    ///
+    /// While the channel is playing it has control of wave RAM, so the CPU
+    /// gets whichever byte it's currently playing from instead. This is
+    /// how the CGB behaves, the DMG only allows it on the exact cycle the
+    /// channel reads the byte and returns 0xFF otherwise.
+    pub(super) fn read_ram(&self, offset: usize) -> u8 {
+        self.ram[self.ram_offset(offset)]
+    }
+
+    pub(super) fn write_ram(&mut self, offset: usize, value: u8) {
+        self.ram[self.ram_offset(offset)] = value;
+    }
+
+    fn ram_offset(&self, offset: usize) -> usize {
+        if self.enabled {
+            usize::from(self.position / 2)
+        } else {
+            offset
+        }
+    }
+
+    fn period(&self) -> u16 {
+        (2048 - self.frequency) * 2
+    }
+
+    fn trigger(&mut self, next_step_clocks_length: bool) {
+        self.enabled = self.dac_enabled;
+        self.length.trigger(next_step_clocks_length);
+        self.timer = self.period();
+        self.position = 0;
+    }
+}
+
+impl Channel for Wave {
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            0 => u8::from(self.dac_enabled) << 7,
+            2 => self.level << 5,
+            4 => u8::from(self.length.is_enabled()) << 6,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, register: u8, value: u8, next_step_clocks_length: bool) {
+        match register {
+            0 => {
+                self.dac_enabled = value & 0x80 != 0;
+
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
+            }
+            1 => self.length.load(value),
+            2 => self.level = (value >> 5) & 0b11,
+            3 => self.frequency = (self.frequency & 0x700) | u16::from(value),
+            4 => {
+                self.frequency = (self.frequency & 0xFF) | (u16::from(value & 0b111) << 8);
+
+                if self
+                    .length
+                    .set_enabled(value & 0x40 != 0, next_step_clocks_length)
+                {
+                    self.enabled = false;
+                }
+
+                if value & 0x80 != 0 {
+                    self.trigger(next_step_clocks_length);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.timer > 1 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period();
+        self.position = (self.position + 1) % 32;
+
+        let byte = self.ram[usize::from(self.position / 2)];
+        self.sample = if self.position % 2 == 0 {
+            byte >> 4
+        } else {
+            byte & 0xF
+        };
+    }
+
+    fn output(&self) -> u8 {
+        if !self.enabled || self.level == 0 {
+            return 0;
+        }
+
+        self.sample >> (self.level - 1)
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    fn waveform_period(&self) -> u32 {
+        u32::from(self.period()) * 32
+    }
+
+    fn volume(&self) -> u8 {
+        match self.level {
+            0 => 0,
+            level => 15 >> (level - 1),
+        }
+    }
+
+    fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    /// Wave RAM isn't a register, so it survives too.
+    fn power_off(&mut self) {
+        let mut length = self.length;
+        length.power_off();
+
+        *self = Self {
+            length,
+            ram: self.ram,
+            ..Self::new()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A triggered channel playing a ramp, with a new sample every 2 ticks.
+    fn wave(level: u8) -> Wave {
+        let mut wave = Wave::new();
+
+        for offset in 0..WAVE_RAM_SIZE {
+            let sample = (offset as u8 * 2) % 16;
+            wave.write_ram(offset, sample << 4 | (sample + 1));
+        }
+
+        wave.write(0, 0x80, true);
+        wave.write(2, level << 5, true);
+        wave.write(3, 0xFF, true);
+        wave.write(4, 0x87, true);
+        wave
+    }
+
+    fn samples(wave: &mut Wave, count: usize) -> Vec<u8> {
+        (0..count)
+            .map(|_| {
+                wave.tick();
+                wave.tick();
+                wave.output()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_plays_wave_ram_from_the_second_sample() {
+        assert_eq!(vec![1, 2, 3, 4, 5], samples(&mut wave(1), 5));
+    }
+
+    #[test]
+    fn it_shifts_samples_by_the_output_level() {
+        assert_eq!(vec![0, 1, 1, 2, 2, 3, 3, 4], samples(&mut wave(2), 8));
+        assert_eq!(vec![0, 0, 0, 1, 1, 1, 1, 2], samples(&mut wave(3), 8));
+        assert_eq!(vec![0, 0, 0], samples(&mut wave(0), 3));
+    }
+
+    #[test]
+    fn it_gives_the_cpu_the_playing_byte() {
+        let mut wave = wave(1);
+        samples(&mut wave, 4);
+
+        assert_eq!(0x45, wave.read_ram(0));
+
+        wave.write(0, 0x00, true);
+        assert_eq!(0x01, wave.read_ram(0));
+    }
+
+    #[test]
+    fn it_keeps_wave_ram_when_powered_off() {
+        let mut wave = wave(1);
+        wave.power_off();
+
+        assert!(!wave.is_enabled());
+        assert_eq!(0x23, wave.read_ram(1));
+    }
+}
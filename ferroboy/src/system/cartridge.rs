@@ -5,10 +5,12 @@ use std::io::BufReader;
 use crate::{
     assembly::{AssemblyInstruction, AssemblyInstructionStream},
     error::CartridgeLoadError,
-    system::{Config, Mmu},
+    helpers::crc32,
+    system::{header, Config, Mmu, Patch, RomDatabase, RomEntry},
 };
 
-const CARTRIDGE_HEADER: [u8; 48] = [
+/// The Nintendo logo the boot ROM expects to find at 0x0104.
+pub const NINTENDO_LOGO: [u8; 48] = [
     0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
     0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
     0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
@@ -62,7 +64,8 @@ pub enum CartridgeType {
 }
 
 impl CartridgeType {
-    fn from_byte(byte: u8) -> crate::Result<Self> {
+    /// Converts the cartridge type byte from the header at 0x0147.
+    pub fn from_byte(byte: u8) -> crate::Result<Self> {
         match byte {
             0x00 => Ok(Self::RomOnly),
 
@@ -110,7 +113,7 @@ impl CartridgeType {
 // TODO: Re-examine the API of this struct.
 // ? Do all these fields need to be exposed?
 // ? Should this use a builder instead of `from_buffer`/`from_file`?
-// ? Should there be flags for colour/SGB compatibility?
+// ? Should there be flags for colour compatibility?
 /// A Gameboy cartridge.
 #[derive(Clone, PartialEq, Eq)]
 pub struct Cartridge {
@@ -131,6 +134,28 @@ impl Cartridge {
         }
     }
 
+    /// Whether the header says the cartridge supports SGB functions, which
+    /// the SGB model needs before it'll colour the screen.
+    pub fn supports_sgb(&self) -> bool {
+        header::supports_sgb(&self.data)
+    }
+
+    /// The CRC-32 of the whole ROM, as used by ROM databases.
+    pub fn crc32(&self) -> u32 {
+        crc32(&self.data)
+    }
+
+    /// The lowercase hex SHA-1 of the whole ROM, as used by ROM databases.
+    pub fn sha1(&self) -> String {
+        sha1_smol::Sha1::from(&self.data).digest().to_string()
+    }
+
+    /// Looks this ROM up in a database of known dumps, to find its
+    /// canonical name, region, and whether it's a good dump.
+    pub fn identify<'db>(&self, database: &'db RomDatabase) -> Option<&'db RomEntry> {
+        database.find(self.crc32(), &self.sha1())
+    }
+
     pub(crate) fn load_banks(&self, mmu: &mut Mmu) {
         mmu.bank0_mut().copy_from_slice(&self.data[0x0000..=0x3FFF]);
         mmu.bank1_mut().copy_from_slice(&self.data[0x4000..=0x7FFF])
@@ -183,9 +208,20 @@ enum CartridgeSource<'a> {
     Buffer(&'a [u8]),
 }
 
+/// An enum indicating where a patch should be loaded from.
+enum PatchSource<'a> {
+    /// Load the patch from a file.
+    File(File),
+    /// Load the patch from a buffer.
+    Buffer(&'a [u8]),
+    /// The patch has already been loaded.
+    Loaded(Patch),
+}
+
 pub struct CartridgeBuilder<'a> {
     config: Config,
     source: CartridgeSource<'a>,
+    patches: Vec<PatchSource<'a>>,
 }
 
 impl<'a> CartridgeBuilder<'a> {
@@ -208,8 +244,54 @@ impl<'a> CartridgeBuilder<'a> {
         self
     }
 
+    /// Adds an IPS, UPS or BPS patch to apply to the ROM before it's parsed.
+    ///
+    /// Patches are applied in the order they're added, and only ever to
+    /// the in-memory copy of the ROM.
+    pub fn with_patch(mut self, patch: Patch) -> Self {
+        self.patches.push(PatchSource::Loaded(patch));
+        self
+    }
+
+    /// Adds a patch to be read from a file when the cartridge is built,
+    /// then applied like [`with_patch`](Self::with_patch).
+    ///
+    /// IPS, UPS and BPS patches are accepted, with the format detected from
+    /// the magic bytes at the start of the file.
+    ///
+    /// # Errors
+    /// `build` fails with:
+    /// - `FileSystemError` if the file can't be read
+    /// - `UnknownPatchFormat` if it doesn't start with a known magic
+    /// - `MalformedPatch` if it's truncated or its records are invalid
+    /// - `PatchSourceMismatch` if a UPS or BPS patch was made for a
+    ///   different ROM
+    /// - `PatchTargetMismatch` if the patched ROM isn't the one a UPS or BPS
+    ///   patch expects to produce
+    /// - `PatchChecksumFail` if the patch's own checksum doesn't match
+    /// - `MissingHeader` if the patched ROM is too short to have a header
+    pub fn with_patch_file(mut self, file: File) -> Self {
+        self.patches.push(PatchSource::File(file));
+        self
+    }
+
+    /// Adds a patch held in memory, applied when the cartridge is built
+    /// like [`with_patch`](Self::with_patch).
+    ///
+    /// IPS, UPS and BPS patches are accepted, with the format detected from
+    /// the magic bytes at the start of the buffer.
+    ///
+    /// # Errors
+    /// `build` fails with the same errors as
+    /// [`with_patch_file`](Self::with_patch_file), apart from
+    /// `FileSystemError`.
+    pub fn with_patch_buffer(mut self, buffer: &'a [u8]) -> Self {
+        self.patches.push(PatchSource::Buffer(buffer));
+        self
+    }
+
     pub fn build(self) -> crate::Result<Cartridge> {
-        let buffer: Vec<u8> = match self.source {
+        let mut buffer: Vec<u8> = match self.source {
             CartridgeSource::Empty => {
                 return Err(CartridgeLoadError::NoSourceSet.into());
             }
@@ -226,6 +308,21 @@ impl<'a> CartridgeBuilder<'a> {
             }
         };
 
+        for source in self.patches {
+            let patch = match source {
+                PatchSource::File(file) => Patch::from_file(file)?,
+                PatchSource::Buffer(buf) => Patch::from_buffer(buf)?,
+                PatchSource::Loaded(patch) => patch,
+            };
+
+            buffer = patch.apply(&buffer)?;
+        }
+
+        // A patch can shrink the ROM, so this is only checked once they're applied
+        if buffer.len() < header::HEADER_END {
+            return Err(CartridgeLoadError::MissingHeader.into());
+        }
+
         if self.config.enable_boot_check {
             Self::validate_cartridge_header(&buffer)?;
         }
@@ -246,13 +343,13 @@ impl<'a> CartridgeBuilder<'a> {
         })
     }
 
-    fn validate_cartridge_header(buffer: &[u8]) -> crate::Result<()> {
+    pub(crate) fn validate_cartridge_header(buffer: &[u8]) -> crate::Result<()> {
         if buffer.len() < 0x134 {
             return Err(CartridgeLoadError::ChecksumFail.into());
         }
 
         for (index, byte) in buffer[0x0104..=0x0133].iter().enumerate() {
-            if *byte != CARTRIDGE_HEADER[index] {
+            if *byte != NINTENDO_LOGO[index] {
                 return Err(CartridgeLoadError::ChecksumFail.into());
             }
         }
@@ -260,13 +357,13 @@ impl<'a> CartridgeBuilder<'a> {
         Ok(())
     }
 
-    fn parse_cartridge_title(buffer: &[u8]) -> crate::Result<String> {
+    pub(crate) fn parse_cartridge_title(buffer: &[u8]) -> crate::Result<String> {
         String::from_utf8(buffer[0x134..=0x143].into())
             .map(|s| s.trim_end_matches('\u{0}').to_string())
             .map_err(|e| CartridgeLoadError::InvalidTitle(e).into())
     }
 
-    fn parse_bank_count(buffer: &[u8]) -> crate::Result<u8> {
+    pub(crate) fn parse_bank_count(buffer: &[u8]) -> crate::Result<u8> {
         let value = match buffer[0x148] {
             0 => 0,
             v @ 1..=7 => 2u8.pow((v + 1).into()),
@@ -279,8 +376,12 @@ impl<'a> CartridgeBuilder<'a> {
         Ok(value)
     }
 
-    fn parse_ram_size(buffer: &[u8]) -> crate::Result<u8> {
-        let value = match buffer[0x149] {
+    pub(crate) fn parse_ram_size(buffer: &[u8]) -> crate::Result<u8> {
+        Self::ram_size_from_code(buffer[0x149])
+    }
+
+    pub(crate) fn ram_size_from_code(code: u8) -> crate::Result<u8> {
+        let value = match code {
             0 => 0,
             1 => 2,
             2 => 8,
@@ -295,7 +396,7 @@ impl<'a> CartridgeBuilder<'a> {
         buffer[0x14A] == 0
     }
 
-    fn parse_cartridge_type(buffer: &[u8]) -> crate::Result<CartridgeType> {
+    pub(crate) fn parse_cartridge_type(buffer: &[u8]) -> crate::Result<CartridgeType> {
         CartridgeType::from_byte(buffer[0x147])
     }
 }
@@ -305,13 +406,51 @@ impl Default for CartridgeBuilder<'_> {
         Self {
             config: Config::default(),
             source: CartridgeSource::Empty,
+            patches: Vec::new(),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    mod cartridge {
+        use crate::{Cartridge, RomDatabase};
+
+        #[test]
+        fn it_checksums_the_rom() {
+            let cartridge = Cartridge {
+                data: b"123456789".to_vec(),
+                ..Default::default()
+            };
+
+            assert_eq!(0xCBF4_3926, cartridge.crc32());
+            assert_eq!("f7c3bc1d808e04732adf679965ccc34ca7ae3441", cartridge.sha1());
+        }
+
+        #[test]
+        fn it_identifies_the_rom() {
+            let cartridge = Cartridge {
+                data: b"123456789".to_vec(),
+                ..Default::default()
+            };
+            let database = r#"<datafile><game name="Digits (World)">
+                <rom name="Digits (World).gb" size="9" crc="CBF43926" sha1="F7C3BC1D808E04732ADF679965CCC34CA7AE3441"/>
+            </game></datafile>"#
+                .parse::<RomDatabase>()
+                .unwrap();
+
+            let entry = cartridge.identify(&database).unwrap();
+
+            assert_eq!("Digits (World)", entry.name);
+        }
+    }
+
     mod cartridge_builder {
+        use crate::{
+            error::{CartridgeLoadError, Error},
+            CartridgeBuilder, ConfigBuilder,
+        };
+
         #[test]
         #[should_panic]
         fn it_validates_the_cartridge_header() {
@@ -323,5 +462,68 @@ mod tests {
         fn it_parses_the_cartridge_title() {
             todo!("This was part of the cartridge before, and needs to now cover the builder");
         }
+
+        #[test]
+        fn it_applies_patches_before_parsing() {
+            let mut rom = vec![0u8; 0x8000];
+            rom[0x134..0x138].copy_from_slice(b"TEST");
+
+            let cartridge = CartridgeBuilder::new()
+                .with_config(&ConfigBuilder::new().without_boot_check().build())
+                .with_buffer(&rom)
+                .with_patch_buffer(b"PATCH\x00\x01\x34\x00\x04PTCHEOF")
+                .build()
+                .unwrap();
+
+            assert_eq!("PTCH", cartridge.title);
+            assert_eq!(b"TEST", &rom[0x134..0x138]);
+        }
+
+        #[test]
+        fn it_applies_patches_in_order() {
+            let rom = vec![0u8; 0x8000];
+
+            let cartridge = CartridgeBuilder::new()
+                .with_config(&ConfigBuilder::new().without_boot_check().build())
+                .with_buffer(&rom)
+                .with_patch_buffer(b"PATCH\x00\x01\x34\x00\x02ABEOF")
+                .with_patch_buffer(b"PATCH\x00\x01\x35\x00\x01CEOF")
+                .build()
+                .unwrap();
+
+            assert_eq!("AC", cartridge.title);
+        }
+
+        #[test]
+        fn it_rejects_patches_that_truncate_the_header() {
+            let rom = vec![0u8; 0x8000];
+
+            let result = CartridgeBuilder::new()
+                .with_config(&ConfigBuilder::new().without_boot_check().build())
+                .with_buffer(&rom)
+                .with_patch_buffer(b"PATCHEOF\x00\x01\x00")
+                .build();
+
+            assert!(matches!(
+                result,
+                Err(Error::CartridgeLoad(CartridgeLoadError::MissingHeader))
+            ));
+        }
+
+        #[test]
+        fn it_reports_patch_errors() {
+            let rom = vec![0u8; 0x8000];
+
+            let result = CartridgeBuilder::new()
+                .with_config(&ConfigBuilder::new().without_boot_check().build())
+                .with_buffer(&rom)
+                .with_patch_buffer(b"NOT A PATCH")
+                .build();
+
+            assert!(matches!(
+                result,
+                Err(Error::CartridgeLoad(CartridgeLoadError::UnknownPatchFormat))
+            ));
+        }
     }
 }
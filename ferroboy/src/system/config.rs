@@ -1,3 +1,21 @@
+use crate::system::{FrameBlending, OpposingDirections, Palette, Renderer, DEFAULT_SAMPLE_RATE};
+
+/// The hardware being emulated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Model {
+    /// The original DMG-01.
+    Dmg,
+    /// The Super Game Boy, which colours the screen of cartridges that
+    /// support it with palettes they send over the joypad register.
+    Sgb,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self::Dmg
+    }
+}
+
 // ? Do these fields need to actually be exposed on the external interface?
 // Might be better off having pub get and pub(crate) set
 /// Configuration options for the emulation.
@@ -11,18 +29,43 @@ pub struct Config {
     /// real cartridge would, e.g. with the appropriate logo bitmap
     /// and initial JMP.
     pub enable_boot_check: bool,
+    /// How the PPU draws frames. The scanline renderer is cheaper, but
+    /// the FIFO renderer is needed for mid-line effects.
+    pub renderer: Renderer,
+    /// The colours frames are displayed in.
+    pub palette: Palette,
+    /// Whether frames are blended together to mimic the LCD's ghosting.
+    pub frame_blending: FrameBlending,
+    /// The rate audio samples are produced at, in Hz.
+    pub sample_rate: u32,
+    /// What the joypad does when opposite directions are held at once.
+    pub opposing_directions: OpposingDirections,
+    /// The hardware being emulated.
+    pub model: Model,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             enable_boot_check: true,
+            renderer: Renderer::default(),
+            palette: Palette::default(),
+            frame_blending: FrameBlending::default(),
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            opposing_directions: OpposingDirections::default(),
+            model: Model::default(),
         }
     }
 }
 
 pub struct ConfigBuilder {
     enable_boot_check: bool,
+    renderer: Renderer,
+    palette: Palette,
+    frame_blending: FrameBlending,
+    sample_rate: u32,
+    opposing_directions: OpposingDirections,
+    model: Model,
 }
 
 impl ConfigBuilder {
@@ -30,6 +73,12 @@ impl ConfigBuilder {
     pub fn new() -> Self {
         Self {
             enable_boot_check: true,
+            renderer: Renderer::default(),
+            palette: Palette::default(),
+            frame_blending: FrameBlending::default(),
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            opposing_directions: OpposingDirections::default(),
+            model: Model::default(),
         }
     }
 
@@ -38,9 +87,71 @@ impl ConfigBuilder {
         self
     }
 
+    pub fn with_renderer(mut self, renderer: Renderer) -> Self {
+        self.renderer = renderer;
+        self
+    }
+
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    pub fn with_frame_blending(mut self, frame_blending: FrameBlending) -> Self {
+        self.frame_blending = frame_blending;
+        self
+    }
+
+    /// Sets the rate audio samples are produced at, in Hz. A rate of 0
+    /// can't produce any samples, so it falls back to
+    /// `DEFAULT_SAMPLE_RATE`.
+    pub fn with_sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = if sample_rate == 0 {
+            DEFAULT_SAMPLE_RATE
+        } else {
+            sample_rate
+        };
+        self
+    }
+
+    pub fn with_opposing_directions(mut self, opposing_directions: OpposingDirections) -> Self {
+        self.opposing_directions = opposing_directions;
+        self
+    }
+
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
     pub fn build(&self) -> Config {
         Config {
             enable_boot_check: self.enable_boot_check,
+            renderer: self.renderer,
+            palette: self.palette,
+            frame_blending: self.frame_blending,
+            sample_rate: self.sample_rate,
+            opposing_directions: self.opposing_directions,
+            model: self.model,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_sets_the_sample_rate() {
+        let config = ConfigBuilder::new().with_sample_rate(22_050).build();
+
+        assert_eq!(22_050, config.sample_rate);
+    }
+
+    #[test]
+    fn it_falls_back_to_the_default_sample_rate_for_0() {
+        let config = ConfigBuilder::new().with_sample_rate(0).build();
+
+        assert_eq!(DEFAULT_SAMPLE_RATE, config.sample_rate);
+    }
+}
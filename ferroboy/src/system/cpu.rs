@@ -131,6 +131,11 @@ impl Cpu {
         }
     }
 
+    /// How many T-cycles the CPU has run for.
+    pub(crate) fn clock(&self) -> u64 {
+        self.clock
+    }
+
     pub(crate) fn increment_clock(&mut self, amount: u64) {
         self.clock += amount;
     }
@@ -165,6 +170,12 @@ impl Cpu {
         self.halted = true;
         self.halted
     }
+
+    /// Wakes the CPU from HALT, which happens whenever an interrupt is
+    /// pending, whether or not interrupts are enabled.
+    pub(crate) fn resume(&mut self) {
+        self.halted = false;
+    }
 }
 
 impl Default for Cpu {
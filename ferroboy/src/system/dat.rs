@@ -0,0 +1,477 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufReader;
+
+use quick_xml::events::{BytesStart, Event};
+
+use crate::error::DatabaseLoadError;
+
+/// How trustworthy a dump is, as recorded by a ROM database.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DumpStatus {
+    /// A known dump that hasn't been flagged either way.
+    Good,
+    /// A dump that's been verified against multiple cartridges.
+    Verified,
+    /// A dump known to contain errors.
+    Bad,
+    /// A dump with more data than the cartridge actually holds.
+    Overdump,
+    /// A modified copy of a known dump.
+    Hacked,
+}
+
+impl DumpStatus {
+    /// Whether this dump can be trusted to match the real cartridge.
+    pub fn is_good(&self) -> bool {
+        matches!(self, Self::Good | Self::Verified)
+    }
+
+    /// Parses the GoodTools-style `[b]`, `[o]`, `[h]` and `[!]` tags.
+    fn from_name(name: &str) -> Option<Self> {
+        if name.contains("[b") {
+            Some(Self::Bad)
+        } else if name.contains("[o") {
+            Some(Self::Overdump)
+        } else if name.contains("[h") {
+            Some(Self::Hacked)
+        } else if name.contains("[!]") {
+            Some(Self::Verified)
+        } else {
+            None
+        }
+    }
+
+    /// Parses the `status` attribute or `flags` field of a ROM entry.
+    fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "baddump" => Some(Self::Bad),
+            "verified" => Some(Self::Verified),
+            "good" => Some(Self::Good),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DumpStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DumpStatus::Good => "Good",
+                DumpStatus::Verified => "Verified",
+                DumpStatus::Bad => "Bad dump",
+                DumpStatus::Overdump => "Overdump",
+                DumpStatus::Hacked => "Hacked",
+            }
+        )
+    }
+}
+
+/// A single known dump from a ROM database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RomEntry {
+    /// The canonical name of the game, e.g. `Tetris (World) (Rev 1)`.
+    pub name: String,
+    pub region: Option<String>,
+    pub status: DumpStatus,
+    pub size: Option<usize>,
+    pub crc32: Option<u32>,
+    /// The lowercase hex SHA-1 of the ROM.
+    pub sha1: Option<String>,
+}
+
+impl RomEntry {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            region: Self::region_from_name(name),
+            status: DumpStatus::from_name(name).unwrap_or(DumpStatus::Good),
+            size: None,
+            crc32: None,
+            sha1: None,
+        }
+    }
+
+    /// No-Intro names always put the region in the first parenthesised
+    /// group, e.g. `Tetris (World) (Rev 1)`.
+    fn region_from_name(name: &str) -> Option<String> {
+        let start = name.find('(')? + 1;
+        let end = start + name[start..].find(')')?;
+
+        Some(name[start..end].to_string())
+    }
+
+    fn set_field(&mut self, key: &str, value: &str) {
+        match key {
+            "size" => self.size = value.parse().ok(),
+            "crc" => self.crc32 = u32::from_str_radix(value, 16).ok(),
+            "sha1" => self.sha1 = Some(value.to_ascii_lowercase()),
+            "status" | "flags" => {
+                if let Some(status) = DumpStatus::from_flag(value) {
+                    // Tags in the name are more specific than the flag
+                    if self.status == DumpStatus::Good {
+                        self.status = status;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A database of known-good dumps.
+///
+/// Both the No-Intro XML format and the older ClrMamePro text format are
+/// supported, and the format is detected from the contents.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RomDatabase {
+    entries: Vec<RomEntry>,
+}
+
+impl RomDatabase {
+    pub fn from_file(file: File) -> crate::Result<Self> {
+        let mut buf_reader = BufReader::new(file);
+        let mut buffer = String::new();
+
+        buf_reader
+            .read_to_string(&mut buffer)
+            .map_err(DatabaseLoadError::FileSystemError)?;
+
+        buffer.parse()
+    }
+
+    pub fn entries(&self) -> &[RomEntry] {
+        &self.entries
+    }
+
+    /// Finds the entry matching the given checksums.
+    ///
+    /// SHA-1 is preferred where the database has it, since CRC-32
+    /// collisions between dumps aren't unheard of.
+    pub fn find(&self, crc32: u32, sha1: &str) -> Option<&RomEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.sha1.as_deref() == Some(sha1))
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .find(|entry| entry.sha1.is_none() && entry.crc32 == Some(crc32))
+            })
+    }
+
+    fn parse_xml(source: &str) -> crate::Result<Vec<RomEntry>> {
+        let mut reader = quick_xml::Reader::from_str(source);
+        reader.trim_text(true);
+
+        let mut entries = Vec::new();
+        let mut game: Option<(String, Option<String>)> = None;
+
+        loop {
+            match reader.read_event().map_err(DatabaseLoadError::InvalidXml)? {
+                Event::Start(tag) | Event::Empty(tag) => match tag.name().as_ref() {
+                    b"game" | b"machine" => {
+                        let name = Self::xml_attribute(&tag, "name")?.unwrap_or_default();
+                        game = Some((name, None));
+                    }
+                    b"release" => {
+                        if let Some((_, region)) = game.as_mut() {
+                            *region = Self::xml_attribute(&tag, "region")?;
+                        }
+                    }
+                    b"rom" => {
+                        let (name, region) = game.as_ref().ok_or(DatabaseLoadError::Malformed)?;
+                        let mut entry = RomEntry::new(name);
+
+                        if region.is_some() {
+                            entry.region = region.clone();
+                        }
+
+                        for attribute in tag.attributes() {
+                            let attribute =
+                                attribute.map_err(|e| DatabaseLoadError::InvalidXml(e.into()))?;
+                            let key = String::from_utf8_lossy(attribute.key.as_ref());
+                            let value = attribute
+                                .unescape_value()
+                                .map_err(DatabaseLoadError::InvalidXml)?;
+
+                            entry.set_field(&key, &value);
+                        }
+
+                        entries.push(entry);
+                    }
+                    _ => {}
+                },
+                Event::End(tag) if matches!(tag.name().as_ref(), b"game" | b"machine") => {
+                    game = None;
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn xml_attribute(tag: &BytesStart<'_>, key: &str) -> crate::Result<Option<String>> {
+        let attribute = tag
+            .try_get_attribute(key)
+            .map_err(DatabaseLoadError::InvalidXml)?;
+
+        match attribute {
+            Some(attribute) => Ok(Some(
+                attribute
+                    .unescape_value()
+                    .map_err(DatabaseLoadError::InvalidXml)?
+                    .into_owned(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn parse_clrmamepro(source: &str) -> crate::Result<Vec<RomEntry>> {
+        let tokens = ClrMameProTokens::new(source).collect::<Vec<_>>();
+        let mut entries = Vec::new();
+        let mut tokens = tokens.iter().peekable();
+
+        while let Some(token) = tokens.next() {
+            let is_game = matches!(token, Token::Word(word) if word == "game");
+
+            if tokens.next() != Some(&Token::Open) {
+                return Err(DatabaseLoadError::Malformed.into());
+            }
+
+            let mut depth = 1;
+            let mut name = String::new();
+            let mut roms: Vec<Vec<(String, String)>> = Vec::new();
+
+            while depth > 0 {
+                match tokens.next().ok_or(DatabaseLoadError::Malformed)? {
+                    Token::Open => depth += 1,
+                    Token::Close => depth -= 1,
+                    Token::Word(key) if depth == 1 && key == "name" => {
+                        name = Self::clrmamepro_value(tokens.next())?;
+                    }
+                    Token::Word(key) if depth == 1 && key == "rom" => {
+                        if tokens.next() != Some(&Token::Open) {
+                            return Err(DatabaseLoadError::Malformed.into());
+                        }
+
+                        let mut fields = Vec::new();
+
+                        loop {
+                            match tokens.next().ok_or(DatabaseLoadError::Malformed)? {
+                                Token::Close => break,
+                                Token::Word(key) => {
+                                    let value = Self::clrmamepro_value(tokens.next())?;
+                                    fields.push((key.clone(), value));
+                                }
+                                Token::Open => return Err(DatabaseLoadError::Malformed.into()),
+                            }
+                        }
+
+                        roms.push(fields);
+                    }
+                    Token::Word(_) => {
+                        // Skip over the value of any field we don't care about
+                        if tokens.peek() != Some(&&Token::Open) {
+                            tokens.next();
+                        }
+                    }
+                }
+            }
+
+            if is_game {
+                for fields in roms {
+                    let mut entry = RomEntry::new(&name);
+
+                    for (key, value) in fields {
+                        entry.set_field(&key, &value);
+                    }
+
+                    entries.push(entry);
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn clrmamepro_value(token: Option<&Token>) -> crate::Result<String> {
+        match token {
+            Some(Token::Word(value)) => Ok(value.clone()),
+            _ => Err(DatabaseLoadError::Malformed.into()),
+        }
+    }
+}
+
+impl std::str::FromStr for RomDatabase {
+    type Err = crate::error::Error;
+
+    fn from_str(source: &str) -> crate::Result<Self> {
+        let source = source.trim_start_matches('\u{FEFF}').trim_start();
+
+        let entries = if source.starts_with('<') {
+            Self::parse_xml(source)?
+        } else {
+            Self::parse_clrmamepro(source)?
+        };
+
+        Ok(Self { entries })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Word(String),
+}
+
+/// Splits a ClrMamePro DAT into parentheses and (possibly quoted) words.
+struct ClrMameProTokens<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> ClrMameProTokens<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            chars: source.chars().peekable(),
+        }
+    }
+}
+
+impl Iterator for ClrMameProTokens<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        match self.chars.next()? {
+            '(' => Some(Token::Open),
+            ')' => Some(Token::Close),
+            '"' => {
+                let mut word = String::new();
+
+                for c in self.chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+
+                    word.push(c);
+                }
+
+                Some(Token::Word(word))
+            }
+            c => {
+                let mut word = c.to_string();
+
+                while let Some(c) = self
+                    .chars
+                    .next_if(|c| !c.is_whitespace() && *c != '(' && *c != ')')
+                {
+                    word.push(c);
+                }
+
+                Some(Token::Word(word))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE datafile PUBLIC "-//Logiqx//DTD ROM Management Datafile//EN" "http://www.logiqx.com/dtds/datafile.dtd">
+<datafile>
+    <header>
+        <name>Nintendo - Game Boy</name>
+    </header>
+    <game name="Tetris (World) (Rev 1)">
+        <description>Tetris (World) (Rev 1)</description>
+        <rom name="Tetris (World) (Rev 1).gb" size="32768" crc="46df91ad" sha1="74591cc9501af93873f9a5d3eb12da12c0723bbc" status="verified"/>
+    </game>
+    <game name="Example (Japan)">
+        <rom name="Example (Japan).gb" size="32768" crc="DEADBEEF" status="baddump"/>
+    </game>
+</datafile>
+"#;
+
+    const CLRMAMEPRO: &str = r#"clrmamepro (
+	name "Nintendo - Game Boy"
+	version 20230101
+)
+
+game (
+	name "Tetris (World) (Rev 1)"
+	description "Tetris (World) (Rev 1)"
+	rom ( name "Tetris (World) (Rev 1).gb" size 32768 crc 46DF91AD sha1 74591CC9501AF93873F9A5D3EB12DA12C0723BBC flags verified )
+)
+
+game (
+	name "Example (USA) [h1]"
+	rom ( name "Example (USA) [h1].gb" size 32768 crc DEADBEEF )
+)
+"#;
+
+    #[test]
+    fn it_parses_xml_databases() {
+        let database = XML.parse::<RomDatabase>().unwrap();
+
+        assert_eq!(2, database.entries().len());
+
+        let entry = &database.entries()[0];
+        assert_eq!("Tetris (World) (Rev 1)", entry.name);
+        assert_eq!(Some("World".to_string()), entry.region);
+        assert_eq!(DumpStatus::Verified, entry.status);
+        assert_eq!(Some(32768), entry.size);
+        assert_eq!(Some(0x46DF_91AD), entry.crc32);
+
+        assert_eq!(DumpStatus::Bad, database.entries()[1].status);
+    }
+
+    #[test]
+    fn it_parses_clrmamepro_databases() {
+        let database = CLRMAMEPRO.parse::<RomDatabase>().unwrap();
+
+        assert_eq!(2, database.entries().len());
+
+        let entry = &database.entries()[0];
+        assert_eq!("Tetris (World) (Rev 1)", entry.name);
+        assert_eq!(DumpStatus::Verified, entry.status);
+        assert_eq!(
+            Some("74591cc9501af93873f9a5d3eb12da12c0723bbc".to_string()),
+            entry.sha1
+        );
+
+        let entry = &database.entries()[1];
+        assert_eq!(Some("USA".to_string()), entry.region);
+        assert_eq!(DumpStatus::Hacked, entry.status);
+    }
+
+    #[test]
+    fn it_finds_entries_by_checksum() {
+        let database = XML.parse::<RomDatabase>().unwrap();
+
+        let entry = database
+            .find(0, "74591cc9501af93873f9a5d3eb12da12c0723bbc")
+            .unwrap();
+        assert_eq!("Tetris (World) (Rev 1)", entry.name);
+
+        let entry = database.find(0xDEAD_BEEF, "").unwrap();
+        assert_eq!("Example (Japan)", entry.name);
+
+        assert!(database.find(0x1234_5678, "").is_none());
+    }
+
+    #[test]
+    fn it_rejects_malformed_databases() {
+        assert!("game ( name".parse::<RomDatabase>().is_err());
+        assert!("<datafile><game></datafile>"
+            .parse::<RomDatabase>()
+            .is_err());
+    }
+}
@@ -0,0 +1,436 @@
+use crate::{
+    error::CartridgeLoadError,
+    system::{
+        cartridge::{CartridgeBuilder, NINTENDO_LOGO},
+        CartridgeType,
+    },
+};
+
+const LOGO_ADDRESS: usize = 0x0104;
+const TITLE_ADDRESS: usize = 0x0134;
+const TITLE_LENGTH: usize = 16;
+const NEW_LICENSEE_ADDRESS: usize = 0x0144;
+const SGB_FLAG_ADDRESS: usize = 0x0146;
+const CARTRIDGE_TYPE_ADDRESS: usize = 0x0147;
+const ROM_SIZE_ADDRESS: usize = 0x0148;
+const RAM_SIZE_ADDRESS: usize = 0x0149;
+const OLD_LICENSEE_ADDRESS: usize = 0x014B;
+const VERSION_ADDRESS: usize = 0x014C;
+const HEADER_CHECKSUM_ADDRESS: usize = 0x014D;
+const GLOBAL_CHECKSUM_ADDRESS: usize = 0x014E;
+pub(crate) const HEADER_END: usize = 0x0150;
+
+/// The old licensee code that means "look at the new licensee code instead".
+const USE_NEW_LICENSEE: u8 = 0x33;
+
+/// The SGB flag that marks a cartridge as supporting SGB functions.
+const SGB_SUPPORTED: u8 = 0x03;
+
+/// The smallest ROM a cartridge can hold, two 16KiB banks.
+const MIN_ROM_SIZE: usize = 0x8000;
+/// The code for the largest ROM size, 8MiB.
+const MAX_ROM_SIZE_CODE: u8 = 0x08;
+
+/// Computes the header checksum the boot ROM checks, over 0x0134–0x014C.
+///
+/// A cartridge with the wrong header checksum won't boot on real hardware.
+pub fn header_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE_ADDRESS..HEADER_CHECKSUM_ADDRESS]
+        .iter()
+        .fold(0u8, |checksum, byte| {
+            checksum.wrapping_sub(*byte).wrapping_sub(1)
+        })
+}
+
+/// Computes the global checksum, the sum of every byte in the ROM other
+/// than the checksum itself.
+///
+/// Nothing on the DMG checks this, but emulators and tools often do.
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|(index, _)| !(GLOBAL_CHECKSUM_ADDRESS..HEADER_END).contains(index))
+        .fold(0u16, |checksum, (_, byte)| {
+            checksum.wrapping_add(u16::from(*byte))
+        })
+}
+
+/// Whether the header says the cartridge supports SGB functions. The SGB
+/// also insists on the old licensee code pointing at the new one.
+pub(crate) fn supports_sgb(rom: &[u8]) -> bool {
+    rom.len() >= HEADER_END
+        && rom[SGB_FLAG_ADDRESS] == SGB_SUPPORTED
+        && rom[OLD_LICENSEE_ADDRESS] == USE_NEW_LICENSEE
+}
+
+/// The ROM size the header declares, from the code at 0x0148.
+fn declared_rom_size(code: u8) -> Option<usize> {
+    match code {
+        0x00..=MAX_ROM_SIZE_CODE => Some(MIN_ROM_SIZE << code),
+        0x52 => Some(72 * 0x4000),
+        0x53 => Some(80 * 0x4000),
+        0x54 => Some(96 * 0x4000),
+        _ => None,
+    }
+}
+
+/// A difference between what a ROM's header says and what it should say.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HeaderMismatch {
+    /// The Nintendo logo is missing or corrupt, so the boot ROM will lock up.
+    Logo,
+    /// The cartridge type byte isn't a known mapper.
+    CartridgeType(u8),
+    /// The ROM size code is invalid or doesn't match the file's size.
+    RomSize {
+        declared: Option<usize>,
+        actual: usize,
+    },
+    /// The RAM size code is invalid.
+    RamSize(u8),
+    HeaderChecksum {
+        expected: u8,
+        found: u8,
+    },
+    GlobalChecksum {
+        expected: u16,
+        found: u16,
+    },
+}
+
+impl std::fmt::Display for HeaderMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderMismatch::Logo => write!(f, "The Nintendo logo doesn't match"),
+            HeaderMismatch::CartridgeType(code) => {
+                write!(f, "'{:02X}' isn't a known cartridge type", code)
+            }
+            HeaderMismatch::RomSize {
+                declared: Some(declared),
+                actual,
+            } => write!(
+                f,
+                "The header declares {}B of ROM, but the ROM is {}B",
+                declared, actual
+            ),
+            HeaderMismatch::RomSize {
+                declared: None,
+                actual,
+            } => write!(f, "The ROM size code isn't valid for a {}B ROM", actual),
+            HeaderMismatch::RamSize(code) => write!(f, "'{:02X}' isn't a valid RAM size", code),
+            HeaderMismatch::HeaderChecksum { expected, found } => write!(
+                f,
+                "The header checksum is ${:02X} but should be ${:02X}",
+                found, expected
+            ),
+            HeaderMismatch::GlobalChecksum { expected, found } => write!(
+                f,
+                "The global checksum is ${:04X} but should be ${:04X}",
+                found, expected
+            ),
+        }
+    }
+}
+
+/// Checks a ROM's header, reporting everything that doesn't match.
+///
+/// # Errors
+/// - The ROM is too small to contain a header
+pub fn validate_header(rom: &[u8]) -> crate::Result<Vec<HeaderMismatch>> {
+    if rom.len() < HEADER_END {
+        return Err(CartridgeLoadError::MissingHeader.into());
+    }
+
+    let mut mismatches = Vec::new();
+
+    if CartridgeBuilder::validate_cartridge_header(rom).is_err() {
+        mismatches.push(HeaderMismatch::Logo);
+    }
+
+    if CartridgeBuilder::parse_cartridge_type(rom).is_err() {
+        mismatches.push(HeaderMismatch::CartridgeType(rom[CARTRIDGE_TYPE_ADDRESS]));
+    }
+
+    let declared = declared_rom_size(rom[ROM_SIZE_ADDRESS]);
+    if declared != Some(rom.len()) {
+        mismatches.push(HeaderMismatch::RomSize {
+            declared,
+            actual: rom.len(),
+        });
+    }
+
+    if CartridgeBuilder::parse_ram_size(rom).is_err() {
+        mismatches.push(HeaderMismatch::RamSize(rom[RAM_SIZE_ADDRESS]));
+    }
+
+    let expected = header_checksum(rom);
+    let found = rom[HEADER_CHECKSUM_ADDRESS];
+    if expected != found {
+        mismatches.push(HeaderMismatch::HeaderChecksum { expected, found });
+    }
+
+    let expected = global_checksum(rom);
+    let found = u16::from_be_bytes([
+        rom[GLOBAL_CHECKSUM_ADDRESS],
+        rom[GLOBAL_CHECKSUM_ADDRESS + 1],
+    ]);
+    if expected != found {
+        mismatches.push(HeaderMismatch::GlobalChecksum { expected, found });
+    }
+
+    Ok(mismatches)
+}
+
+/// Rewrites the fields of a ROM's header.
+///
+/// This is meant for homebrew, where the assembler doesn't know the
+/// checksums. Checksums should be fixed last, after every other change.
+///
+/// # Examples
+/// ```rs
+/// HeaderWriter::new(&mut rom)
+///     .write_logo()
+///     .write_title("HOMEBREW")?
+///     .pad()?
+///     .fix_checksums();
+/// ```
+pub struct HeaderWriter<'a> {
+    rom: &'a mut Vec<u8>,
+}
+
+impl<'a> HeaderWriter<'a> {
+    /// Prepares a ROM for rewriting, growing it to fit a header if needed.
+    pub fn new(rom: &'a mut Vec<u8>) -> Self {
+        if rom.len() < HEADER_END {
+            rom.resize(HEADER_END, 0);
+        }
+
+        Self { rom }
+    }
+
+    pub fn write_logo(self) -> Self {
+        self.rom[LOGO_ADDRESS..LOGO_ADDRESS + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+        self
+    }
+
+    /// Writes the title, padded with zeroes.
+    ///
+    /// # Errors
+    /// - The title is longer than 16 bytes
+    pub fn write_title(self, title: &str) -> crate::Result<Self> {
+        if title.len() > TITLE_LENGTH {
+            return Err(CartridgeLoadError::InvalidTitleLength(title.len()).into());
+        }
+
+        let field = &mut self.rom[TITLE_ADDRESS..TITLE_ADDRESS + TITLE_LENGTH];
+        field.fill(0);
+        field[..title.len()].copy_from_slice(title.as_bytes());
+
+        Ok(self)
+    }
+
+    pub fn write_cartridge_type(self, cartridge_type: CartridgeType) -> Self {
+        self.rom[CARTRIDGE_TYPE_ADDRESS] = cartridge_type as u8;
+        self
+    }
+
+    /// Writes the RAM size code, e.g. `0x02` for a single 8KiB bank.
+    ///
+    /// # Errors
+    /// - The code isn't a valid RAM size
+    pub fn write_ram_size(self, code: u8) -> crate::Result<Self> {
+        CartridgeBuilder::ram_size_from_code(code)?;
+        self.rom[RAM_SIZE_ADDRESS] = code;
+
+        Ok(self)
+    }
+
+    /// Writes the licensee code.
+    ///
+    /// Two-character codes use the new licensee field, and set the old one
+    /// to `0x33` to point at it. Codes prefixed with `0x` are written as
+    /// the old single-byte code.
+    ///
+    /// # Errors
+    /// - The code isn't either two ASCII characters or a hex byte
+    pub fn write_licensee(self, code: &str) -> crate::Result<Self> {
+        if let Some(hex) = code.strip_prefix("0x") {
+            let old = u8::from_str_radix(hex, 16)
+                .map_err(|_| CartridgeLoadError::InvalidLicensee(code.to_string()))?;
+
+            self.rom[OLD_LICENSEE_ADDRESS] = old;
+            return Ok(self);
+        }
+
+        if code.len() != 2 || !code.is_ascii() {
+            return Err(CartridgeLoadError::InvalidLicensee(code.to_string()).into());
+        }
+
+        self.rom[NEW_LICENSEE_ADDRESS..NEW_LICENSEE_ADDRESS + 2].copy_from_slice(code.as_bytes());
+        self.rom[OLD_LICENSEE_ADDRESS] = USE_NEW_LICENSEE;
+
+        Ok(self)
+    }
+
+    /// Marks the cartridge as supporting SGB functions.
+    ///
+    /// The SGB ignores the flag unless the old licensee code is `0x33`, so
+    /// that's written too. Any old licensee code should be replaced with a
+    /// new one.
+    pub fn write_sgb_support(self) -> Self {
+        self.rom[SGB_FLAG_ADDRESS] = SGB_SUPPORTED;
+        self.rom[OLD_LICENSEE_ADDRESS] = USE_NEW_LICENSEE;
+        self
+    }
+
+    pub fn write_version(self, version: u8) -> Self {
+        self.rom[VERSION_ADDRESS] = version;
+        self
+    }
+
+    /// Pads the ROM with `0xFF` up to the next valid size, and writes the
+    /// matching ROM size code.
+    ///
+    /// # Errors
+    /// - The ROM is bigger than the largest cartridge, 8MiB
+    pub fn pad(self) -> crate::Result<Self> {
+        let code = (0..=MAX_ROM_SIZE_CODE)
+            .find(|code| MIN_ROM_SIZE << code >= self.rom.len())
+            .ok_or(CartridgeLoadError::RomTooLarge(self.rom.len()))?;
+
+        let size = MIN_ROM_SIZE << code;
+        if self.rom.len() < size {
+            self.rom.resize(size, 0xFF);
+        }
+
+        self.rom[ROM_SIZE_ADDRESS] = code;
+        Ok(self)
+    }
+
+    /// Recomputes the header and global checksums.
+    pub fn fix_checksums(self) -> Self {
+        self.rom[HEADER_CHECKSUM_ADDRESS] = header_checksum(self.rom);
+
+        let global = global_checksum(self.rom).to_be_bytes();
+        self.rom[GLOBAL_CHECKSUM_ADDRESS..HEADER_END].copy_from_slice(&global);
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CartridgeBuilder, ConfigBuilder};
+
+    fn homebrew() -> Vec<u8> {
+        let mut rom = vec![0u8; 0x6000];
+
+        HeaderWriter::new(&mut rom)
+            .write_logo()
+            .write_title("HOMEBREW")
+            .unwrap()
+            .write_cartridge_type(CartridgeType::Mbc1)
+            .write_ram_size(0x02)
+            .unwrap()
+            .write_licensee("01")
+            .unwrap()
+            .write_version(1)
+            .pad()
+            .unwrap()
+            .fix_checksums();
+
+        rom
+    }
+
+    #[test]
+    fn it_computes_the_header_checksum() {
+        let mut rom = vec![0u8; 0x150];
+        rom[0x134..0x13A].copy_from_slice(b"TETRIS");
+
+        assert_eq!(0x0C, header_checksum(&rom));
+    }
+
+    #[test]
+    fn it_writes_a_valid_header() {
+        let rom = homebrew();
+
+        assert_eq!(0x8000, rom.len());
+        assert_eq!(0xFF, rom[0x7FFF]);
+        assert_eq!(Vec::<HeaderMismatch>::new(), validate_header(&rom).unwrap());
+
+        let cartridge = CartridgeBuilder::new()
+            .with_config(&ConfigBuilder::new().build())
+            .with_buffer(&rom)
+            .build()
+            .unwrap();
+
+        assert_eq!("HOMEBREW", cartridge.title);
+        assert_eq!(CartridgeType::Mbc1, cartridge.cartridge_type);
+        assert_eq!(8, cartridge.ram_size);
+    }
+
+    #[test]
+    fn it_reports_mismatches() {
+        let mut rom = homebrew();
+        rom[0x104] = 0x00;
+        rom[0x14D] = rom[0x14D].wrapping_add(1);
+        rom.truncate(0x4000);
+
+        let mismatches = validate_header(&rom).unwrap();
+
+        assert!(mismatches.contains(&HeaderMismatch::Logo));
+        assert!(mismatches.contains(&HeaderMismatch::RomSize {
+            declared: Some(0x8000),
+            actual: 0x4000
+        }));
+        assert!(mismatches
+            .iter()
+            .any(|m| matches!(m, HeaderMismatch::HeaderChecksum { .. })));
+        assert!(mismatches
+            .iter()
+            .any(|m| matches!(m, HeaderMismatch::GlobalChecksum { .. })));
+    }
+
+    #[test]
+    fn it_rejects_long_titles() {
+        let mut rom = Vec::new();
+
+        assert!(HeaderWriter::new(&mut rom)
+            .write_title("THIS TITLE IS TOO LONG")
+            .is_err());
+    }
+
+    #[test]
+    fn it_refuses_to_pad_past_the_largest_rom_size() {
+        let mut rom = vec![0u8; (MIN_ROM_SIZE << MAX_ROM_SIZE_CODE) + 1];
+
+        assert!(matches!(
+            HeaderWriter::new(&mut rom).pad(),
+            Err(crate::Error::CartridgeLoad(
+                CartridgeLoadError::RomTooLarge(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn it_writes_old_licensee_codes() {
+        let mut rom = Vec::new();
+        HeaderWriter::new(&mut rom).write_licensee("0x01").unwrap();
+
+        assert_eq!(0x01, rom[OLD_LICENSEE_ADDRESS]);
+    }
+
+    #[test]
+    fn it_writes_sgb_support() {
+        let mut rom = homebrew();
+        assert!(!supports_sgb(&rom));
+
+        HeaderWriter::new(&mut rom).write_sgb_support();
+        assert!(supports_sgb(&rom));
+
+        // The SGB ignores cartridges with an old licensee code
+        HeaderWriter::new(&mut rom).write_licensee("0x01").unwrap();
+        assert!(!supports_sgb(&rom));
+    }
+}
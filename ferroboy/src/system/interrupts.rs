@@ -0,0 +1,59 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// The interrupt sources, as laid out in the IF (0xFF0F) and IE (0xFFFF)
+    /// registers. Lower bits have higher priority.
+    pub struct Interrupts: u8 {
+        const VBLANK = 0b0000_0001;
+        const LCD_STAT = 0b0000_0010;
+        const TIMER = 0b0000_0100;
+        const SERIAL = 0b0000_1000;
+        const JOYPAD = 0b0001_0000;
+    }
+}
+
+impl Interrupts {
+    /// The interrupt that should be serviced first, if any are set.
+    pub fn highest_priority(self) -> Option<Self> {
+        if self.is_empty() {
+            None
+        } else {
+            Self::from_bits(1 << self.bits.trailing_zeros())
+        }
+    }
+
+    /// The address the CPU jumps to when servicing this interrupt.
+    ///
+    /// Only meaningful for a single interrupt.
+    pub fn vector(self) -> u16 {
+        0x0040 + 0x08 * self.bits.trailing_zeros() as u16
+    }
+}
+
+impl Default for Interrupts {
+    fn default() -> Self {
+        Interrupts::empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_prioritises_lower_bits() {
+        let pending = Interrupts::TIMER | Interrupts::LCD_STAT | Interrupts::JOYPAD;
+
+        assert_eq!(Some(Interrupts::LCD_STAT), pending.highest_priority());
+        assert_eq!(None, Interrupts::empty().highest_priority());
+    }
+
+    #[test]
+    fn it_maps_interrupts_to_vectors() {
+        assert_eq!(0x40, Interrupts::VBLANK.vector());
+        assert_eq!(0x48, Interrupts::LCD_STAT.vector());
+        assert_eq!(0x50, Interrupts::TIMER.vector());
+        assert_eq!(0x58, Interrupts::SERIAL.vector());
+        assert_eq!(0x60, Interrupts::JOYPAD.vector());
+    }
+}
@@ -0,0 +1,254 @@
+use bitflags::bitflags;
+
+use crate::system::Interrupts;
+
+pub(crate) const P1: u16 = 0xFF00;
+
+/// Selects the d-pad when cleared.
+const SELECT_DPAD: u8 = 0b0001_0000;
+/// Selects the buttons when cleared.
+const SELECT_BUTTONS: u8 = 0b0010_0000;
+
+bitflags! {
+    /// The DMG-01's eight inputs. The d-pad is the low nibble and the
+    /// buttons the high, in the order P1 reports them.
+    pub struct Buttons: u8 {
+        const RIGHT = 0b0000_0001;
+        const LEFT = 0b0000_0010;
+        const UP = 0b0000_0100;
+        const DOWN = 0b0000_1000;
+        const A = 0b0001_0000;
+        const B = 0b0010_0000;
+        const SELECT = 0b0100_0000;
+        const START = 0b1000_0000;
+    }
+}
+
+impl Default for Buttons {
+    fn default() -> Self {
+        Buttons::empty()
+    }
+}
+
+/// What to do when the host holds opposite directions at once, which the
+/// d-pad's rocker makes impossible on hardware. Some games glitch out or
+/// even crash when it happens.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OpposingDirections {
+    /// Passes both directions through.
+    Allow,
+    /// Only the most recently pressed direction is held.
+    LastPressed,
+    /// Neither direction is held.
+    Neutral,
+}
+
+impl Default for OpposingDirections {
+    fn default() -> Self {
+        Self::LastPressed
+    }
+}
+
+/// An implementation of the DMG-01's joypad register, P1.
+///
+/// The inputs are wired as a matrix, with bits 4 and 5 of P1 selecting the
+/// d-pad or the buttons, and the selected inputs pulling the low nibble
+/// down while they're held. The joypad interrupt fires whenever one of
+/// those lines falls.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Joypad {
+    /// What the host is holding, before the opposing direction policy.
+    held: Buttons,
+    /// The directions pressed most recently on each axis, which win under
+    /// `OpposingDirections::LastPressed`.
+    latest: Buttons,
+    /// The select bits last written to P1.
+    select: u8,
+    opposing_directions: OpposingDirections,
+}
+
+impl Joypad {
+    /// What the game sees as held, after the opposing direction policy.
+    pub fn buttons(&self) -> Buttons {
+        let mut buttons = self.held;
+
+        for axis in [Buttons::LEFT | Buttons::RIGHT, Buttons::UP | Buttons::DOWN] {
+            if !buttons.contains(axis) {
+                continue;
+            }
+
+            match self.opposing_directions {
+                OpposingDirections::Allow => {}
+                OpposingDirections::LastPressed => buttons &= !(axis & !self.latest),
+                OpposingDirections::Neutral => buttons &= !axis,
+            }
+        }
+
+        buttons
+    }
+
+    pub(crate) fn set_opposing_directions(&mut self, opposing_directions: OpposingDirections) {
+        self.opposing_directions = opposing_directions;
+    }
+
+    pub(crate) fn read(&self) -> u8 {
+        0xC0 | self.select | self.lines()
+    }
+
+    pub(crate) fn write(&mut self, value: u8) -> Interrupts {
+        self.update(|joypad| joypad.select = value & (SELECT_DPAD | SELECT_BUTTONS))
+    }
+
+    pub(crate) fn press(&mut self, buttons: Buttons) -> Interrupts {
+        self.set_buttons(self.held | buttons)
+    }
+
+    pub(crate) fn release(&mut self, buttons: Buttons) -> Interrupts {
+        self.set_buttons(self.held - buttons)
+    }
+
+    pub(crate) fn set_buttons(&mut self, buttons: Buttons) -> Interrupts {
+        let pressed = buttons - self.held;
+
+        self.update(|joypad| {
+            for axis in [Buttons::LEFT | Buttons::RIGHT, Buttons::UP | Buttons::DOWN] {
+                // If both were pressed at once there's no telling which was
+                // first, so the existing one keeps priority
+                if (pressed & axis).bits().count_ones() == 1 {
+                    joypad.latest = (joypad.latest - axis) | (pressed & axis);
+                }
+            }
+
+            joypad.held = buttons;
+        })
+    }
+
+    /// The low nibble of P1, where a selected input that's held reads 0.
+    fn lines(&self) -> u8 {
+        let buttons = self.buttons().bits();
+        let mut lines = 0x0F;
+
+        if self.select & SELECT_DPAD == 0 {
+            lines &= !(buttons & 0x0F);
+        }
+
+        if self.select & SELECT_BUTTONS == 0 {
+            lines &= !(buttons >> 4);
+        }
+
+        lines
+    }
+
+    /// Makes a change, requesting the joypad interrupt if any of the lines
+    /// fall.
+    fn update<F>(&mut self, change: F) -> Interrupts
+    where
+        F: FnOnce(&mut Self),
+    {
+        let before = self.lines();
+        change(self);
+
+        if before & !self.lines() != 0 {
+            Interrupts::JOYPAD
+        } else {
+            Interrupts::empty()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joypad(opposing_directions: OpposingDirections) -> Joypad {
+        let mut joypad = Joypad::default();
+        joypad.set_opposing_directions(opposing_directions);
+        joypad
+    }
+
+    #[test]
+    fn it_reads_nothing_held() {
+        let mut joypad = Joypad::default();
+        assert_eq!(0xCF, joypad.read());
+
+        joypad.write(0x30);
+        assert_eq!(0xFF, joypad.read());
+    }
+
+    #[test]
+    fn it_reads_the_selected_inputs() {
+        let mut joypad = Joypad::default();
+        joypad.press(Buttons::DOWN | Buttons::A | Buttons::START);
+
+        joypad.write(0x20);
+        assert_eq!(0xE7, joypad.read());
+
+        joypad.write(0x10);
+        assert_eq!(0xD6, joypad.read());
+
+        joypad.write(0x30);
+        assert_eq!(0xFF, joypad.read());
+
+        // Selecting both combines them
+        joypad.write(0x00);
+        assert_eq!(0xC6, joypad.read());
+    }
+
+    #[test]
+    fn it_releases_inputs() {
+        let mut joypad = Joypad::default();
+        joypad.write(0x10);
+        joypad.press(Buttons::A | Buttons::B);
+        joypad.release(Buttons::A);
+
+        assert_eq!(Buttons::B, joypad.buttons());
+        assert_eq!(0xDD, joypad.read());
+    }
+
+    #[test]
+    fn it_interrupts_when_a_selected_line_falls() {
+        let mut joypad = Joypad::default();
+        joypad.write(0x20);
+
+        assert_eq!(Interrupts::JOYPAD, joypad.press(Buttons::UP));
+        assert_eq!(Interrupts::empty(), joypad.press(Buttons::UP));
+        assert_eq!(Interrupts::empty(), joypad.release(Buttons::UP));
+
+        // Unselected inputs can't pull the lines down
+        assert_eq!(Interrupts::empty(), joypad.press(Buttons::START));
+
+        // Selecting the buttons with one held does though
+        assert_eq!(Interrupts::JOYPAD, joypad.write(0x10));
+    }
+
+    #[test]
+    fn it_allows_opposing_directions() {
+        let mut joypad = joypad(OpposingDirections::Allow);
+        joypad.press(Buttons::LEFT | Buttons::RIGHT);
+
+        assert_eq!(Buttons::LEFT | Buttons::RIGHT, joypad.buttons());
+    }
+
+    #[test]
+    fn it_prefers_the_last_pressed_direction() {
+        let mut joypad = joypad(OpposingDirections::LastPressed);
+
+        joypad.press(Buttons::LEFT | Buttons::UP);
+        joypad.press(Buttons::RIGHT);
+        assert_eq!(Buttons::RIGHT | Buttons::UP, joypad.buttons());
+
+        joypad.release(Buttons::RIGHT);
+        assert_eq!(Buttons::LEFT | Buttons::UP, joypad.buttons());
+
+        joypad.set_buttons(Buttons::LEFT | Buttons::UP | Buttons::DOWN);
+        assert_eq!(Buttons::LEFT | Buttons::DOWN, joypad.buttons());
+    }
+
+    #[test]
+    fn it_neutralises_opposing_directions() {
+        let mut joypad = joypad(OpposingDirections::Neutral);
+        joypad.press(Buttons::UP | Buttons::DOWN | Buttons::LEFT);
+
+        assert_eq!(Buttons::LEFT, joypad.buttons());
+    }
+}
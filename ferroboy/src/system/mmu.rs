@@ -3,7 +3,32 @@ use std::{
     sync::Arc,
 };
 
-use crate::Cartridge;
+use crate::{
+    system::{
+        apu, joypad, ppu, serial, sgb, timer, Apu, Config, Interrupts, Joypad, Model, Ppu, Serial,
+        Sgb, Timer,
+    },
+    Cartridge,
+};
+
+const ROM: std::ops::Range<usize> = 0x0000..0x8000;
+const ROM_BANK_SELECT: std::ops::Range<usize> = 0x2000..0x4000;
+const ROM_BANK_SIZE: usize = 0x4000;
+const VRAM: std::ops::Range<usize> = 0x8000..0xA000;
+const OAM: std::ops::Range<usize> = 0xFE00..0xFEA0;
+const OAM_DMA: u16 = 0xFF46;
+const INTERRUPT_FLAGS: u16 = 0xFF0F;
+const INTERRUPT_ENABLE: u16 = 0xFFFF;
+
+/// An OAM DMA transfer in progress, which copies one byte a M-cycle from
+/// `source` into OAM.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct OamDma {
+    source: u16,
+    copied: u16,
+    /// T-cycles towards copying the next byte.
+    cycles: u64,
+}
 
 /// The Gameboy's memory mapper.
 ///
@@ -12,10 +37,26 @@ use crate::Cartridge;
 /// mapped into the RAM at different offsets. This struct does much
 /// the same as the hardware version did, mapping the various memory
 /// addresses to the actual implementors.
+///
+/// Indexing into the MMU accesses the backing memory directly, bypassing
+/// the memory-mapped hardware. The CPU should always go through `read`
+/// and `write` instead.
 #[derive(Clone, PartialEq, Eq)]
 pub struct Mmu {
     cartridge: Arc<Option<Cartridge>>,
     memory: [u8; 0x10000],
+    /// The full ROM, if the GBS player has mapped it in with switchable
+    /// banks. Otherwise the ROM area is plain memory.
+    rom_banks: Option<Arc<Vec<u8>>>,
+    oam_dma: Option<OamDma>,
+    pub(crate) ppu: Ppu,
+    pub(crate) apu: Apu,
+    pub(crate) timer: Timer,
+    pub(crate) joypad: Joypad,
+    pub(crate) serial: Serial,
+    /// The SGB's colouring, when it's being emulated and the cartridge
+    /// supports it.
+    pub(crate) sgb: Option<Sgb>,
 }
 
 // TODO: instead of having a monolithic block of bytes, break this into structs
@@ -28,7 +69,236 @@ impl Mmu {
         Self {
             cartridge,
             memory: [0; 0x10000],
+            rom_banks: None,
+            oam_dma: None,
+            ppu: Ppu::default(),
+            apu: Apu::default(),
+            timer: Timer::default(),
+            joypad: Joypad::default(),
+            serial: Serial::default(),
+            sgb: None,
+        }
+    }
+
+    /// Applies the parts of the configuration that the hardware cares about.
+    pub(crate) fn configure(&mut self, config: &Config) {
+        self.ppu.set_renderer(config.renderer);
+        self.ppu.set_frame_blending(config.frame_blending);
+        self.apu.set_sample_rate(config.sample_rate);
+        self.joypad
+            .set_opposing_directions(config.opposing_directions);
+
+        // Reconfiguring shouldn't lose the palettes the game has sent
+        let sgb = config.model == Model::Sgb
+            && self
+                .cartridge
+                .as_ref()
+                .as_ref()
+                .map_or(false, Cartridge::supports_sgb);
+
+        if sgb != self.sgb.is_some() {
+            self.sgb = if sgb {
+                Some(Sgb::new(config.palette))
+            } else {
+                None
+            };
+        }
+    }
+
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
+    pub fn apu(&self) -> &Apu {
+        &self.apu
+    }
+
+    pub fn apu_mut(&mut self) -> &mut Apu {
+        &mut self.apu
+    }
+
+    pub fn timer(&self) -> &Timer {
+        &self.timer
+    }
+
+    pub fn joypad(&self) -> &Joypad {
+        &self.joypad
+    }
+
+    pub fn serial(&self) -> &Serial {
+        &self.serial
+    }
+
+    pub fn sgb(&self) -> Option<&Sgb> {
+        self.sgb.as_ref()
+    }
+
+    /// Reads a byte as the CPU would see it.
+    ///
+    /// VRAM and OAM read as 0xFF while the PPU is using them, and OAM does
+    /// for the length of an OAM DMA transfer.
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            _ if VRAM.contains(&usize::from(address)) && !self.ppu.vram_accessible() => 0xFF,
+            _ if OAM.contains(&usize::from(address)) && !self.oam_accessible() => 0xFF,
+            ppu::LCDC..=ppu::LYC | ppu::BGP..=ppu::WX => self.ppu.read(address),
+            apu::NR10..=apu::WAVE_RAM_END => self.apu.read(address),
+            timer::DIV..=timer::TAC => self.timer.read(address),
+            joypad::P1 => match &self.sgb {
+                Some(sgb) => sgb.read(self.joypad.read()),
+                None => self.joypad.read(),
+            },
+            serial::SB..=serial::SC => self.serial.read(address),
+            // The top three bits of IF are unused, and always read high
+            INTERRUPT_FLAGS => 0xE0 | self.memory[address as usize],
+            _ => self.memory[address as usize],
+        }
+    }
+
+    /// Writes a byte as the CPU would, triggering any side-effects the
+    /// memory-mapped hardware has.
+    ///
+    /// Writes to VRAM and OAM are dropped while the PPU is using them, and
+    /// to OAM during an OAM DMA transfer.
+    pub(crate) fn write(&mut self, address: u16, value: u8) {
+        match address {
+            _ if ROM.contains(&usize::from(address)) && self.rom_banks.is_some() => {
+                if ROM_BANK_SELECT.contains(&usize::from(address)) {
+                    self.select_rom_bank(value);
+                }
+            }
+            _ if VRAM.contains(&usize::from(address)) && !self.ppu.vram_accessible() => {}
+            _ if OAM.contains(&usize::from(address)) && !self.oam_accessible() => {}
+            OAM_DMA => {
+                self.memory[address as usize] = value;
+                self.oam_dma = Some(OamDma {
+                    source: u16::from(value) << 8,
+                    copied: 0,
+                    cycles: 0,
+                });
+            }
+            ppu::LCDC..=ppu::LYC | ppu::BGP..=ppu::WX => {
+                let interrupts = self.ppu.write(address, value);
+                self.request_interrupt(interrupts);
+            }
+            apu::NR10..=apu::WAVE_RAM_END => self.apu.write(address, value),
+            timer::DIV..=timer::TAC => self.timer.write(address, value),
+            joypad::P1 => {
+                let interrupts = self.joypad.write(value);
+                self.request_interrupt(interrupts);
+
+                if let Some(sgb) = &mut self.sgb {
+                    sgb.write(value, || {
+                        self.ppu
+                            .screen_tiles(&self.memory[VRAM], sgb::TRANSFER_TILES)
+                    });
+                }
+            }
+            serial::SB..=serial::SC => self.serial.write(address, value),
+            INTERRUPT_FLAGS => self.memory[address as usize] = value & Interrupts::all().bits(),
+            _ => self.memory[address as usize] = value,
+        }
+    }
+
+    /// Maps in a ROM of 16 KiB banks, with bank 0 fixed and the rest
+    /// switched into 0x4000-0x7FFF by writing to 0x2000-0x3FFF, like an
+    /// MBC1. Other writes to the ROM area are then ignored.
+    ///
+    /// Only the GBS player uses this, since GBS files assume that banking
+    /// whatever cartridge they were ripped from. It bypasses the cartridge
+    /// and its type entirely, so it isn't how cartridge mappers should be
+    /// emulated.
+    ///
+    /// # Panics
+    /// If `rom` is smaller than two banks, or isn't a whole number of them.
+    pub(crate) fn map_rom_banks(&mut self, rom: Vec<u8>) {
+        assert!(
+            rom.len() >= ROM.len() && rom.len() % ROM_BANK_SIZE == 0,
+            "ROM must be made up of at least two whole banks"
+        );
+
+        self.memory[ROM].copy_from_slice(&rom[ROM]);
+        self.rom_banks = Some(Arc::new(rom));
+    }
+
+    /// Bank 0 can't be switched in, and selects bank 1 instead.
+    fn select_rom_bank(&mut self, value: u8) {
+        let rom = match self.rom_banks.as_ref() {
+            Some(rom) => rom,
+            None => return,
+        };
+
+        let bank_count = rom.len() / ROM_BANK_SIZE;
+        let bank = usize::from(value.max(1)) % bank_count;
+        let start = bank * ROM_BANK_SIZE;
+
+        self.memory[0x4000..0x8000].copy_from_slice(&rom[start..start + ROM_BANK_SIZE]);
+    }
+
+    fn oam_accessible(&self) -> bool {
+        self.oam_dma.is_none() && self.ppu.oam_accessible()
+    }
+
+    /// Copies a byte into OAM for every M-cycle of an OAM DMA transfer,
+    /// regardless of what the PPU is doing.
+    fn step_oam_dma(&mut self, cycles: u64) {
+        let mut dma = match self.oam_dma {
+            Some(dma) => dma,
+            None => return,
+        };
+
+        dma.cycles += cycles;
+
+        while dma.cycles >= 4 && usize::from(dma.copied) < OAM.len() {
+            // Sources past work RAM read from its echo
+            let source = match dma.source + dma.copied {
+                address @ 0xE000..=0xFFFF => address - 0x2000,
+                address => address,
+            };
+
+            self.memory[OAM.start + usize::from(dma.copied)] = self.memory[usize::from(source)];
+            dma.copied += 1;
+            dma.cycles -= 4;
         }
+
+        self.oam_dma = if usize::from(dma.copied) < OAM.len() {
+            Some(dma)
+        } else {
+            None
+        };
+    }
+
+    /// A decoded view of VRAM and OAM, coloured with `palette`.
+    #[cfg(feature = "introspection")]
+    pub fn inspect_video(&self, palette: crate::Palette) -> crate::VideoInspector<'_> {
+        crate::VideoInspector::new(&self.ppu, &self.memory[VRAM], &self.memory[OAM], palette)
+    }
+
+    /// Advances the memory-mapped hardware by a number of T-cycles.
+    pub(crate) fn step(&mut self, cycles: u64) {
+        self.step_oam_dma(cycles);
+
+        let interrupts = self.ppu.step(cycles, &self.memory[VRAM], &self.memory[OAM])
+            | self.timer.step(cycles)
+            | self.serial.step(cycles);
+        self.apu.step(cycles);
+
+        self.request_interrupt(interrupts);
+    }
+
+    pub(crate) fn request_interrupt(&mut self, interrupts: Interrupts) {
+        self.memory[INTERRUPT_FLAGS as usize] |= interrupts.bits();
+    }
+
+    pub(crate) fn clear_interrupt(&mut self, interrupts: Interrupts) {
+        self.memory[INTERRUPT_FLAGS as usize] &= !interrupts.bits();
+    }
+
+    /// The interrupts that have been both requested and enabled.
+    pub(crate) fn pending_interrupts(&self) -> Interrupts {
+        Interrupts::from_bits_truncate(
+            self.memory[INTERRUPT_FLAGS as usize] & self.memory[INTERRUPT_ENABLE as usize],
+        )
     }
 
     pub fn bank0(&self) -> &[u8] {
@@ -46,14 +316,6 @@ impl Mmu {
     pub fn bank1_mut(&mut self) -> &mut [u8] {
         &mut self.memory[0x4000..=0x7FFF]
     }
-
-    pub fn game_link(&self) -> &[u8] {
-        &self.memory[0xFF01..=0xFF02]
-    }
-
-    pub fn game_link_mut(&mut self) -> &mut [u8] {
-        &mut self.memory[0xFF01..=0xFF02]
-    }
 }
 
 impl std::fmt::Debug for Mmu {
@@ -86,3 +348,242 @@ impl Mmu {
         mutator(self);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::LcdMode;
+
+    fn mmu_in_mode(mode: LcdMode) -> Mmu {
+        let mut mmu = Mmu::new(Arc::new(None));
+
+        while mmu.ppu.mode() != mode {
+            mmu.step(1);
+        }
+
+        mmu
+    }
+
+    #[test]
+    fn it_locks_vram_while_drawing() {
+        let mut mmu = mmu_in_mode(LcdMode::Drawing);
+        mmu.memory[0x8000] = 0x42;
+
+        assert_eq!(0xFF, mmu.read(0x8000));
+
+        mmu.write(0x8000, 0x24);
+        assert_eq!(0x42, mmu[0x8000]);
+    }
+
+    #[test]
+    fn it_locks_oam_while_scanning_and_drawing() {
+        for mode in [LcdMode::OamScan, LcdMode::Drawing] {
+            let mut mmu = mmu_in_mode(mode);
+            mmu.memory[0xFE00] = 0x42;
+
+            assert_eq!(0xFF, mmu.read(0xFE00));
+
+            mmu.write(0xFE00, 0x24);
+            assert_eq!(0x42, mmu[0xFE00]);
+        }
+    }
+
+    #[test]
+    fn it_unlocks_vram_during_oam_scan() {
+        let mut mmu = mmu_in_mode(LcdMode::OamScan);
+
+        mmu.write(0x9FFF, 0x24);
+        assert_eq!(0x24, mmu.read(0x9FFF));
+    }
+
+    #[test]
+    fn it_unlocks_everything_during_blanking() {
+        for mode in [LcdMode::HBlank, LcdMode::VBlank] {
+            let mut mmu = mmu_in_mode(mode);
+
+            mmu.write(0x8000, 0x24);
+            mmu.write(0xFE9F, 0x42);
+
+            assert_eq!(0x24, mmu.read(0x8000));
+            assert_eq!(0x42, mmu.read(0xFE9F));
+        }
+    }
+
+    #[test]
+    fn it_unlocks_everything_when_the_lcd_is_off() {
+        let mut mmu = mmu_in_mode(LcdMode::Drawing);
+        mmu.write(ppu::LCDC, 0x00);
+
+        mmu.write(0x8000, 0x24);
+        mmu.write(0xFE00, 0x42);
+
+        assert_eq!(0x24, mmu.read(0x8000));
+        assert_eq!(0x42, mmu.read(0xFE00));
+    }
+
+    #[test]
+    fn it_copies_into_oam_with_dma() {
+        let mut mmu = Mmu::new(Arc::new(None));
+        mmu.write(ppu::LCDC, 0x00);
+
+        for (index, address) in (0xC100..0xC1A0).enumerate() {
+            mmu[address] = index as u8;
+        }
+
+        mmu.write(OAM_DMA, 0xC1);
+        assert_eq!(0xC1, mmu.read(OAM_DMA));
+
+        mmu.step(4 * 80);
+        assert_eq!(79, mmu[0xFE4F]);
+        assert_eq!(0x00, mmu[0xFE50]);
+
+        mmu.step(4 * 80);
+        assert_eq!((0..160).collect::<Vec<u8>>(), mmu.memory[OAM].to_vec());
+    }
+
+    #[test]
+    fn it_locks_oam_during_dma() {
+        let mut mmu = Mmu::new(Arc::new(None));
+        mmu.write(ppu::LCDC, 0x00);
+        mmu[0xC000] = 0x42;
+
+        mmu.write(OAM_DMA, 0xC0);
+        mmu.step(4);
+        mmu.write(0xFE01, 0x24);
+
+        assert_eq!(0xFF, mmu.read(0xFE00));
+        assert_eq!(0x00, mmu[0xFE01]);
+
+        mmu.step(4 * 159);
+
+        assert_eq!(0x42, mmu.read(0xFE00));
+    }
+
+    #[test]
+    fn it_routes_sound_registers_to_the_apu() {
+        let mut mmu = Mmu::new(Arc::new(None));
+        assert_eq!(0x70, mmu.read(apu::NR52));
+
+        mmu.write(apu::NR52, 0x80);
+        mmu.write(apu::NR50, 0x77);
+
+        assert_eq!(0xF0, mmu.read(apu::NR52));
+        assert_eq!(0x77, mmu.read(apu::NR50));
+        assert!(mmu.apu().is_powered());
+    }
+
+    #[test]
+    fn it_switches_rom_banks() {
+        let mut mmu = Mmu::new(Arc::new(None));
+        let rom: Vec<u8> = (0..4).flat_map(|bank| vec![bank; ROM_BANK_SIZE]).collect();
+        mmu.map_rom_banks(rom);
+
+        assert_eq!(0, mmu.read(0x0000));
+        assert_eq!(1, mmu.read(0x4000));
+
+        mmu.write(0x2000, 3);
+        assert_eq!(3, mmu.read(0x7FFF));
+
+        mmu.write(0x3FFF, 0);
+        assert_eq!(1, mmu.read(0x4000));
+
+        // Banks past the end wrap around, and the ROM itself is read-only
+        mmu.write(0x2000, 6);
+        mmu.write(0x0000, 0xFF);
+        assert_eq!(2, mmu.read(0x4000));
+        assert_eq!(0, mmu.read(0x0000));
+    }
+
+    #[test]
+    fn it_requests_timer_interrupts() {
+        let mut mmu = Mmu::new(Arc::new(None));
+        mmu.write(timer::TAC, 0b101);
+        mmu.write(timer::TIMA, 0xFF);
+
+        mmu.step(20);
+
+        assert_eq!(0xE4, mmu.read(INTERRUPT_FLAGS) & 0xE4);
+        assert_eq!(0x00, mmu.read(timer::TIMA));
+    }
+
+    #[test]
+    fn it_requests_serial_interrupts() {
+        let mut mmu = Mmu::new(Arc::new(None));
+        mmu.write(serial::SB, 0x42);
+        mmu.write(serial::SC, 0x81);
+
+        mmu.step(4096);
+
+        assert_eq!(0xE8, mmu.read(INTERRUPT_FLAGS) & 0xE8);
+        assert_eq!(0xFF, mmu.read(serial::SB));
+    }
+
+    fn sgb_mmu() -> Mmu {
+        let cartridge = crate::test_rom::RomBuilder::new("SGB")
+            .with_sgb_support()
+            .cartridge()
+            .unwrap();
+        let mut mmu = Mmu::new(Arc::new(Some(cartridge)));
+        mmu.configure(&crate::ConfigBuilder::new().with_model(Model::Sgb).build());
+        mmu
+    }
+
+    fn send_sgb_packet(mmu: &mut Mmu, data: &[u8]) {
+        let mut packet = [0; 16];
+        packet[..data.len()].copy_from_slice(data);
+
+        for select in sgb::pulses(&packet) {
+            mmu.write(joypad::P1, select);
+        }
+    }
+
+    #[test]
+    fn it_only_emulates_the_sgb_when_supported() {
+        let mut mmu = Mmu::new(Arc::new(Some(Cartridge::default())));
+        mmu.configure(&crate::ConfigBuilder::new().with_model(Model::Sgb).build());
+        assert!(mmu.sgb().is_none());
+
+        let mut mmu = sgb_mmu();
+        assert!(mmu.sgb().is_some());
+
+        mmu.configure(&crate::ConfigBuilder::new().build());
+        assert!(mmu.sgb().is_none());
+    }
+
+    #[test]
+    fn it_reports_the_sgb_joypad_id() {
+        let mut mmu = sgb_mmu();
+        // MLT_REQ, for two joypads
+        send_sgb_packet(&mut mmu, &[0x89, 0x01]);
+
+        mmu.write(joypad::P1, 0x10);
+        mmu.write(joypad::P1, 0x30);
+        assert_eq!(0xFE, mmu.read(joypad::P1));
+    }
+
+    #[test]
+    fn it_transfers_sgb_palettes_from_the_screen() {
+        let mut mmu = sgb_mmu();
+        mmu.write(ppu::LCDC, ppu::Lcdc::TILE_DATA.bits());
+
+        // Tiles 0-255 on screen in order, as games set up for transfers
+        for index in 0..256u16 {
+            mmu.write(0x9800 + index / 20 * 32 + index % 20, index as u8);
+        }
+
+        // The second palette is the second half of tile 0
+        let colours = [0x1F, 0x00, 0xE0, 0x03, 0x00, 0x7C, 0x00, 0x00];
+        for (offset, byte) in colours.iter().enumerate() {
+            mmu.write(0x8008 + offset as u16, *byte);
+        }
+
+        // PAL_TRN, then PAL_SET picking it for every palette
+        send_sgb_packet(&mut mmu, &[0x59]);
+        send_sgb_packet(&mut mmu, &[0x51, 1, 0, 1, 0, 1, 0, 1, 0, 0]);
+
+        assert_eq!(
+            crate::Palette::from_hex([0xFF0000, 0x00FF00, 0x0000FF, 0x000000]),
+            mmu.sgb().unwrap().palette(2)
+        );
+    }
+}
@@ -1,19 +1,50 @@
 mod alu;
+pub(crate) mod apu;
 mod cartridge;
 mod config;
 mod cpu;
+mod dat;
+mod header;
+mod interrupts;
+mod joypad;
 mod mmu;
 mod opcodes;
+mod patch;
+pub(crate) mod ppu;
 mod register;
+mod serial;
+mod sgb;
+pub(crate) mod timer;
 
 pub use alu::Alu;
+pub use apu::{Apu, AudioChannel, ChannelInfo, RegisterWrite, DEFAULT_SAMPLE_RATE};
 pub use cartridge::Cartridge;
 pub use cartridge::CartridgeBuilder;
 pub use cartridge::CartridgeType;
+pub use cartridge::NINTENDO_LOGO;
 pub use config::Config;
 pub use config::ConfigBuilder;
+pub use config::Model;
 pub use cpu::Cpu;
 pub use cpu::Flags;
+pub use dat::{DumpStatus, RomDatabase, RomEntry};
+pub use header::{global_checksum, header_checksum, validate_header, HeaderMismatch, HeaderWriter};
+pub use interrupts::Interrupts;
+pub use joypad::{Buttons, Joypad, OpposingDirections};
 pub use mmu::Mmu;
 pub use opcodes::OPCODES;
+pub use patch::{Patch, PatchFormat};
+pub use ppu::{
+    Colour, Frame, FrameBlending, LcdMode, Lcdc, Palette, PixelFormat, Ppu, Renderer,
+    MAX_BLENDED_FRAMES, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+#[cfg(feature = "introspection")]
+pub use ppu::{
+    RgbaImage, Sprite, SpriteAttributes, Tile, TileMapArea, VideoInspector, WindowInfo, TILE_COUNT,
+};
 pub use register::{Register, WideRegister};
+pub use serial::{LinkCable, Loopback, RecordedStream, Serial, SerialCapture};
+#[cfg(test)]
+pub(crate) use sgb::pulses;
+pub use sgb::Sgb;
+pub use timer::Timer;
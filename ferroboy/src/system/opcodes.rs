@@ -318,32 +318,50 @@ static INTERNAL_OPCODES: &[(u8, &'static dyn Operation)] = &[
     (0xD6, &SubOperation(SubTarget::Immediate)),
     (0xD7, &RstOperation(0x10)),
     (0xD8, &RetOperation(Some(RetCondition::Carry))),
+    (0xD9, &RetiOperation),
     (0xDA, &JumpPositionOperation(JumpPositionFlags::Carry)),
     (0xDC, &CallOperation(Some(CallCondition::Carry))),
     (0xDF, &RstOperation(0x18)),
     ////////////////////
     // Rank E opcodes
+    (
+        0xE0,
+        &Load8AccumulatorToMemoryOperation(Load8AccumulatorAddress::HighImmediate),
+    ),
     (0xE1, &PopOperation(WideRegister::De)),
     (
         0xE2,
-        &Load8RegisterToMemoryOperation(
-            Load8RegisterToMemoryTarget::Register(Register::C),
-            Register::A,
-        ),
+        &Load8AccumulatorToMemoryOperation(Load8AccumulatorAddress::HighC),
     ),
     (0xE5, &PushOperation(WideRegister::Hl)),
     (0xE6, &AndOperation(AndTarget::Immediate)),
     (0xE7, &RstOperation(0x20)),
     (0xE9, &JumpPositionOperation(JumpPositionFlags::Register)),
+    (
+        0xEA,
+        &Load8AccumulatorToMemoryOperation(Load8AccumulatorAddress::Immediate),
+    ),
     (0xEE, &XorOperation(XorTarget::Immediate)),
     (0xEF, &RstOperation(0x28)),
     ////////////////////
     // Rank F opcodes
+    (
+        0xF0,
+        &Load8AccumulatorFromMemoryOperation(Load8AccumulatorAddress::HighImmediate),
+    ),
     (0xF1, &PopOperation(WideRegister::Hl)),
+    (
+        0xF2,
+        &Load8AccumulatorFromMemoryOperation(Load8AccumulatorAddress::HighC),
+    ),
     (0xF3, &DisableInterruptsOperation),
     (0xF5, &PushOperation(WideRegister::Af)),
     (0xF6, &OrOperation(OrTarget::Immediate)),
     (0xF7, &RstOperation(0x30)),
+    (
+        0xFA,
+        &Load8AccumulatorFromMemoryOperation(Load8AccumulatorAddress::Immediate),
+    ),
     (0xFB, &EnableInterruptsOperation),
     (0xFE, &CpOperation(CpTarget::Immediate)),
     (0xFF, &RstOperation(0x38)),
@@ -0,0 +1,185 @@
+use super::{PatchFooter, PatchReader};
+use crate::error::CartridgeLoadError;
+
+/// Applies a BPS patch.
+///
+/// BPS builds the target from four kinds of action: copying from the
+/// source at the current output position, copying literal bytes out of
+/// the patch, or copying from a relative position in either the source
+/// or the target written so far.
+pub(super) fn apply(patch: &[u8], rom: &[u8]) -> crate::Result<Vec<u8>> {
+    let footer = PatchFooter::validate(patch)?;
+    footer.check_source(rom)?;
+
+    let body_end = patch.len() - PatchFooter::LENGTH;
+    let mut reader = PatchReader::new(&patch[..body_end], 4);
+
+    let source_size = reader.read_varint()?;
+    let target_size = reader.read_target_size()?;
+    let metadata_size = reader.read_varint()?;
+    reader.read_bytes(metadata_size)?;
+
+    if source_size != rom.len() {
+        return Err(CartridgeLoadError::PatchSourceMismatch.into());
+    }
+
+    let mut output = Vec::<u8>::with_capacity(target_size);
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+
+    while !reader.is_at(body_end) {
+        let action = reader.read_varint()?;
+        let length = (action >> 2) + 1;
+
+        if output.len() + length > target_size {
+            return Err(CartridgeLoadError::MalformedPatch.into());
+        }
+
+        match action & 0b11 {
+            // SourceRead
+            0 => {
+                let start = output.len();
+                let bytes = rom
+                    .get(start..start + length)
+                    .ok_or(CartridgeLoadError::MalformedPatch)?;
+
+                output.extend_from_slice(bytes);
+            }
+            // TargetRead
+            1 => output.extend_from_slice(reader.read_bytes(length)?),
+            // SourceCopy
+            2 => {
+                source_offset = relative(source_offset, reader.read_varint()?)?;
+                let bytes = rom
+                    .get(source_offset..source_offset + length)
+                    .ok_or(CartridgeLoadError::MalformedPatch)?;
+
+                output.extend_from_slice(bytes);
+                source_offset += length;
+            }
+            // TargetCopy
+            _ => {
+                target_offset = relative(target_offset, reader.read_varint()?)?;
+
+                // The copy may overlap what it's writing, so it has to go
+                // byte-by-byte to repeat patterns the way the format intends.
+                for _ in 0..length {
+                    let byte = *output
+                        .get(target_offset)
+                        .ok_or(CartridgeLoadError::MalformedPatch)?;
+
+                    output.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if output.len() != target_size {
+        return Err(CartridgeLoadError::MalformedPatch.into());
+    }
+
+    footer.check_target(&output)?;
+
+    Ok(output)
+}
+
+/// Applies a signed relative offset, where the lowest bit is the sign.
+fn relative(offset: usize, encoded: usize) -> crate::Result<usize> {
+    let distance = encoded >> 1;
+
+    let result = if encoded & 1 == 1 {
+        offset.checked_sub(distance)
+    } else {
+        offset.checked_add(distance)
+    };
+
+    result.ok_or_else(|| CartridgeLoadError::MalformedPatch.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_varint, with_footer};
+    use super::*;
+
+    fn build_patch(source: &[u8], target: &[u8], actions: &[usize], literals: &[u8]) -> Vec<u8> {
+        let mut patch = b"BPS1".to_vec();
+        patch.extend(encode_varint(source.len()));
+        patch.extend(encode_varint(target.len()));
+        patch.extend(encode_varint(0));
+
+        let mut literals = literals.iter();
+
+        for action in actions {
+            patch.extend(encode_varint(*action));
+
+            if action & 0b11 == 1 {
+                for _ in 0..(action >> 2) + 1 {
+                    patch.push(*literals.next().unwrap());
+                }
+            }
+        }
+
+        with_footer(patch, source, target)
+    }
+
+    #[test]
+    fn it_reads_from_the_source_and_patch() {
+        let source = [0x00, 0x01, 0x02, 0x03];
+        let target = [0x00, 0x01, 0xBE, 0xEF];
+        // SourceRead 2, TargetRead 2
+        let patch = build_patch(&source, &target, &[(1 << 2), (1 << 2) | 1], &[0xBE, 0xEF]);
+
+        assert_eq!(target.to_vec(), apply(&patch, &source).unwrap());
+    }
+
+    #[test]
+    fn it_copies_from_the_source_and_target() {
+        let source = [0x00, 0x01, 0x02, 0x03];
+        let target = [0x02, 0x03, 0x02, 0x03, 0x02, 0x03];
+
+        // SourceCopy 2 from +2, TargetCopy 4 from +0 (overlapping)
+        let mut patch = b"BPS1".to_vec();
+        patch.extend(encode_varint(source.len()));
+        patch.extend(encode_varint(target.len()));
+        patch.extend(encode_varint(0));
+        patch.extend(encode_varint((1 << 2) | 2));
+        patch.extend(encode_varint(2 << 1));
+        patch.extend(encode_varint((3 << 2) | 3));
+        patch.extend(encode_varint(0));
+        let patch = with_footer(patch, &source, &target);
+
+        assert_eq!(target.to_vec(), apply(&patch, &source).unwrap());
+    }
+
+    #[test]
+    fn it_rejects_the_wrong_source() {
+        let source = [0x00, 0x01, 0x02, 0x03];
+        let target = [0x00, 0x01, 0x02, 0x03];
+        let patch = build_patch(&source, &target, &[(3 << 2)], &[]);
+
+        assert!(matches!(
+            apply(&patch, &[0xFF; 4]),
+            Err(crate::Error::CartridgeLoad(
+                CartridgeLoadError::PatchSourceMismatch
+            ))
+        ));
+    }
+
+    #[test]
+    fn it_rejects_targets_bigger_than_a_cartridge() {
+        let source = [0x00, 0x01, 0x02, 0x03];
+        let mut patch = b"BPS1".to_vec();
+        patch.extend(encode_varint(source.len()));
+        patch.extend(encode_varint(usize::MAX >> 8));
+        patch.extend(encode_varint(0));
+        let patch = with_footer(patch, &source, &[]);
+
+        assert!(matches!(
+            apply(&patch, &source),
+            Err(crate::Error::CartridgeLoad(
+                CartridgeLoadError::MalformedPatch
+            ))
+        ));
+    }
+}
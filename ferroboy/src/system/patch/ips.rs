@@ -0,0 +1,103 @@
+use super::PatchReader;
+use crate::error::CartridgeLoadError;
+
+/// The largest ROM IPS can describe, since offsets are 24-bit.
+const MAX_SIZE: usize = 0x100_0000;
+
+/// Applies an IPS patch.
+///
+/// IPS is a list of records, each an offset and a run of bytes to write
+/// there, or a run-length encoded fill when the size is zero. The list
+/// ends with `EOF`, optionally followed by a length to truncate to.
+pub(super) fn apply(patch: &[u8], rom: &[u8]) -> crate::Result<Vec<u8>> {
+    let mut reader = PatchReader::new(patch, 5);
+    let mut output = rom.to_vec();
+
+    loop {
+        let offset = reader.read_be(3)?;
+
+        // "EOF" is also a valid offset, but real patches never use it.
+        if offset == 0x45_4F46 {
+            break;
+        }
+
+        let length = reader.read_be(2)?;
+
+        let (length, fill) = if length == 0 {
+            (reader.read_be(2)?, Some(reader.read_u8()?))
+        } else {
+            (length, None)
+        };
+
+        let end = offset + length;
+
+        if end > MAX_SIZE {
+            return Err(CartridgeLoadError::MalformedPatch.into());
+        }
+
+        if output.len() < end {
+            output.resize(end, 0);
+        }
+
+        match fill {
+            Some(value) => output[offset..end].fill(value),
+            None => output[offset..end].copy_from_slice(reader.read_bytes(length)?),
+        }
+    }
+
+    if !reader.is_at(patch.len()) {
+        let truncate = reader.read_be(3)?;
+        output.truncate(truncate);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_writes_records() {
+        let patch = b"PATCH\x00\x00\x01\x00\x02\xBE\xEFEOF";
+
+        assert_eq!(
+            vec![0x00, 0xBE, 0xEF, 0x03],
+            apply(patch, &[0, 1, 2, 3]).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_fills_run_length_records() {
+        let patch = b"PATCH\x00\x00\x01\x00\x00\x00\x02\xAAEOF";
+
+        assert_eq!(
+            vec![0x00, 0xAA, 0xAA, 0x03],
+            apply(patch, &[0, 1, 2, 3]).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_extends_the_rom() {
+        let patch = b"PATCH\x00\x00\x04\x00\x01\xFFEOF";
+
+        assert_eq!(
+            vec![0x00, 0x00, 0x00, 0x00, 0xFF],
+            apply(patch, &[0; 3]).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_truncates_the_rom() {
+        let patch = b"PATCHEOF\x00\x00\x02";
+
+        assert_eq!(vec![0x00, 0x01], apply(patch, &[0, 1, 2, 3]).unwrap());
+    }
+
+    #[test]
+    fn it_rejects_truncated_patches() {
+        let patch = b"PATCH\x00\x00\x01\x00\x04\xBE";
+
+        assert!(apply(patch, &[0; 4]).is_err());
+    }
+}
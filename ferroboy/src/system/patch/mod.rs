@@ -0,0 +1,316 @@
+mod bps;
+mod ips;
+mod ups;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufReader;
+
+use crate::{error::CartridgeLoadError, helpers::crc32};
+
+/// The largest target a UPS or BPS patch can build, the size of the
+/// biggest cartridge. Anything bigger is rejected before it's allocated.
+const MAX_PATCH_TARGET: usize = 8 * 1024 * 1024;
+
+/// The soft-patch formats that can be applied to a ROM.
+///
+/// ROM hacks and translations are distributed as patches against the
+/// original dump rather than as ROMs, so that the original is never
+/// modified and only the differences need to be shared.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PatchFormat {
+    /// International Patching System, the oldest and simplest format.
+    /// It has no checksums and can't address past 16MiB.
+    Ips,
+    /// Universal Patching System, which XORs the target with the source
+    /// and validates both with CRC-32.
+    Ups,
+    /// Beat Patching System, which describes the target as a series of
+    /// copies from the source, the target, or the patch itself.
+    Bps,
+}
+
+impl PatchFormat {
+    fn detect(buffer: &[u8]) -> crate::Result<Self> {
+        if buffer.starts_with(b"PATCH") {
+            Ok(Self::Ips)
+        } else if buffer.starts_with(b"UPS1") {
+            Ok(Self::Ups)
+        } else if buffer.starts_with(b"BPS1") {
+            Ok(Self::Bps)
+        } else {
+            Err(CartridgeLoadError::UnknownPatchFormat.into())
+        }
+    }
+}
+
+/// A soft-patch that can be applied to a ROM before it's parsed.
+///
+/// The format is detected from the patch's magic bytes, so the file
+/// extension doesn't matter.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub format: PatchFormat,
+    data: Vec<u8>,
+}
+
+impl Patch {
+    pub fn from_buffer(buffer: &[u8]) -> crate::Result<Self> {
+        let format = PatchFormat::detect(buffer)?;
+
+        Ok(Self {
+            format,
+            data: buffer.into(),
+        })
+    }
+
+    pub fn from_file(file: File) -> crate::Result<Self> {
+        let mut buf_reader = BufReader::new(file);
+        let mut buffer = Vec::<u8>::new();
+
+        buf_reader
+            .read_to_end(&mut buffer)
+            .map_err(CartridgeLoadError::FileSystemError)?;
+
+        Self::from_buffer(&buffer)
+    }
+
+    /// Applies the patch to `rom`, returning the patched ROM.
+    ///
+    /// # Errors
+    /// - The patch is truncated or otherwise malformed
+    /// - The patch's own checksum doesn't match (UPS/BPS)
+    /// - `rom` isn't the ROM the patch was made for (UPS/BPS)
+    /// - The output doesn't match the expected checksum (UPS/BPS)
+    pub fn apply(&self, rom: &[u8]) -> crate::Result<Vec<u8>> {
+        match self.format {
+            PatchFormat::Ips => ips::apply(&self.data, rom),
+            PatchFormat::Ups => ups::apply(&self.data, rom),
+            PatchFormat::Bps => bps::apply(&self.data, rom),
+        }
+    }
+}
+
+impl std::fmt::Debug for Patch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(stringify!(Patch))
+            .field("format", &self.format)
+            .field("data", &format!("Vec<u8> {}B", self.data.len()))
+            .finish()
+    }
+}
+
+/// A cursor over the body of a patch.
+///
+/// Every read is bounds-checked, since a truncated patch should be an
+/// error rather than a panic.
+struct PatchReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8], position: usize) -> Self {
+        Self { data, position }
+    }
+
+    fn is_at(&self, position: usize) -> bool {
+        self.position >= position
+    }
+
+    fn read_u8(&mut self) -> crate::Result<u8> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or(CartridgeLoadError::MalformedPatch)?;
+
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn read_bytes(&mut self, length: usize) -> crate::Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.data.len())
+            .ok_or(CartridgeLoadError::MalformedPatch)?;
+
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    /// Reads a big-endian integer of `width` bytes, as used by IPS.
+    fn read_be(&mut self, width: usize) -> crate::Result<usize> {
+        Ok(self
+            .read_bytes(width)?
+            .iter()
+            .fold(0usize, |value, byte| value << 8 | usize::from(*byte)))
+    }
+
+    /// Reads the size of the target a UPS or BPS patch builds.
+    fn read_target_size(&mut self) -> crate::Result<usize> {
+        let size = self.read_varint()?;
+
+        if size > MAX_PATCH_TARGET {
+            return Err(CartridgeLoadError::MalformedPatch.into());
+        }
+
+        Ok(size)
+    }
+
+    /// Reads the variable-length integer encoding shared by UPS and BPS.
+    ///
+    /// Each byte carries seven bits, with the high bit marking the final
+    /// byte. Every continuation also adds one to the next place value,
+    /// so that each number has exactly one encoding.
+    fn read_varint(&mut self) -> crate::Result<usize> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+
+        loop {
+            let byte = self.read_u8()?;
+
+            value = usize::from(byte & 0x7F)
+                .checked_mul(shift)
+                .and_then(|v| v.checked_add(value))
+                .ok_or(CartridgeLoadError::MalformedPatch)?;
+
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+
+            shift = shift
+                .checked_mul(0x80)
+                .ok_or(CartridgeLoadError::MalformedPatch)?;
+            value = value
+                .checked_add(shift)
+                .ok_or(CartridgeLoadError::MalformedPatch)?;
+        }
+    }
+}
+
+/// The trailing checksums shared by UPS and BPS patches.
+struct PatchFooter {
+    source: u32,
+    target: u32,
+}
+
+impl PatchFooter {
+    const LENGTH: usize = 12;
+
+    /// Reads the footer and checks the patch against its own checksum.
+    fn validate(patch: &[u8]) -> crate::Result<Self> {
+        if patch.len() < Self::LENGTH {
+            return Err(CartridgeLoadError::MalformedPatch.into());
+        }
+
+        let footer = &patch[patch.len() - Self::LENGTH..];
+        let read = |index: usize| {
+            u32::from_le_bytes([
+                footer[index],
+                footer[index + 1],
+                footer[index + 2],
+                footer[index + 3],
+            ])
+        };
+
+        if crc32(&patch[..patch.len() - 4]) != read(8) {
+            return Err(CartridgeLoadError::PatchChecksumFail.into());
+        }
+
+        Ok(Self {
+            source: read(0),
+            target: read(4),
+        })
+    }
+
+    fn check_source(&self, rom: &[u8]) -> crate::Result<()> {
+        if crc32(rom) == self.source {
+            Ok(())
+        } else {
+            Err(CartridgeLoadError::PatchSourceMismatch.into())
+        }
+    }
+
+    fn check_target(&self, output: &[u8]) -> crate::Result<()> {
+        if crc32(output) == self.target {
+            Ok(())
+        } else {
+            Err(CartridgeLoadError::PatchTargetMismatch.into())
+        }
+    }
+}
+
+/// Encodes a number the way [`PatchReader::read_varint`] decodes it.
+#[cfg(test)]
+fn encode_varint(mut value: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    loop {
+        let low = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            bytes.push(0x80 | low);
+            return bytes;
+        }
+
+        bytes.push(low);
+        value -= 1;
+    }
+}
+
+/// Appends the source, target and patch checksums to a UPS/BPS body.
+#[cfg(test)]
+fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(target).to_le_bytes());
+    patch.extend_from_slice(&crc32(&patch).to_le_bytes());
+    patch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_detects_the_patch_format() {
+        assert_eq!(
+            PatchFormat::Ips,
+            Patch::from_buffer(b"PATCHEOF").unwrap().format
+        );
+        assert_eq!(
+            PatchFormat::Ups,
+            Patch::from_buffer(b"UPS1").unwrap().format
+        );
+        assert_eq!(
+            PatchFormat::Bps,
+            Patch::from_buffer(b"BPS1").unwrap().format
+        );
+    }
+
+    #[test]
+    fn it_rejects_unknown_formats() {
+        assert!(Patch::from_buffer(b"NOPE").is_err());
+    }
+
+    #[test]
+    fn it_reads_varints() {
+        let mut reader = PatchReader::new(&[0x81, 0x00, 0x80, 0x01, 0x81], 0);
+
+        assert_eq!(1, reader.read_varint().unwrap());
+        assert_eq!(128, reader.read_varint().unwrap());
+        assert_eq!(257, reader.read_varint().unwrap());
+    }
+
+    #[test]
+    fn it_round_trips_varints() {
+        for value in [0, 1, 127, 128, 255, 16511, 16512, 0x12_3456] {
+            let encoded = encode_varint(value);
+            let mut reader = PatchReader::new(&encoded, 0);
+
+            assert_eq!(value, reader.read_varint().unwrap());
+        }
+    }
+}
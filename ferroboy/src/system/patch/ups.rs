@@ -0,0 +1,133 @@
+use super::{PatchFooter, PatchReader};
+use crate::error::CartridgeLoadError;
+
+/// Applies a UPS patch.
+///
+/// UPS stores the target as runs of bytes XORed with the source, each
+/// preceded by how far to skip ahead and terminated by a zero byte. The
+/// patch, the source and the target are all validated with CRC-32.
+pub(super) fn apply(patch: &[u8], rom: &[u8]) -> crate::Result<Vec<u8>> {
+    let footer = PatchFooter::validate(patch)?;
+    footer.check_source(rom)?;
+
+    let body_end = patch.len() - PatchFooter::LENGTH;
+    let mut reader = PatchReader::new(&patch[..body_end], 4);
+
+    let source_size = reader.read_varint()?;
+    let target_size = reader.read_target_size()?;
+
+    if source_size != rom.len() {
+        return Err(CartridgeLoadError::PatchSourceMismatch.into());
+    }
+
+    let mut output = rom.to_vec();
+    output.resize(target_size, 0);
+
+    let mut pointer = 0usize;
+
+    while !reader.is_at(body_end) {
+        pointer = pointer
+            .checked_add(reader.read_varint()?)
+            .ok_or(CartridgeLoadError::MalformedPatch)?;
+
+        loop {
+            let xor = reader.read_u8()?;
+
+            if xor == 0 {
+                break;
+            }
+
+            let byte = output
+                .get_mut(pointer)
+                .ok_or(CartridgeLoadError::MalformedPatch)?;
+
+            *byte ^= xor;
+            pointer += 1;
+        }
+
+        // The terminating zero also stands for one unchanged byte.
+        pointer += 1;
+    }
+
+    footer.check_target(&output)?;
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{encode_varint, with_footer};
+    use super::*;
+
+    fn build_patch(source: &[u8], target: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut patch = b"UPS1".to_vec();
+        patch.extend(encode_varint(source.len()));
+        patch.extend(encode_varint(target.len()));
+        patch.extend_from_slice(body);
+
+        with_footer(patch, source, target)
+    }
+
+    #[test]
+    fn it_xors_the_source() {
+        let source = [0x00, 0x01, 0x02, 0x03];
+        let target = [0x00, 0xFF, 0x02, 0x03];
+        let patch = build_patch(&source, &target, &[0x81, 0xFE, 0x00]);
+
+        assert_eq!(target.to_vec(), apply(&patch, &source).unwrap());
+    }
+
+    #[test]
+    fn it_resizes_the_output() {
+        let source = [0x00, 0x01];
+        let target = [0x00, 0x01, 0x00, 0xAA];
+        let patch = build_patch(&source, &target, &[0x83, 0xAA, 0x00]);
+
+        assert_eq!(target.to_vec(), apply(&patch, &source).unwrap());
+    }
+
+    #[test]
+    fn it_rejects_the_wrong_source() {
+        let source = [0x00, 0x01, 0x02, 0x03];
+        let target = [0x00, 0xFF, 0x02, 0x03];
+        let patch = build_patch(&source, &target, &[0x81, 0xFE, 0x00]);
+
+        assert!(matches!(
+            apply(&patch, &[0x01, 0x01, 0x02, 0x03]),
+            Err(crate::Error::CartridgeLoad(
+                CartridgeLoadError::PatchSourceMismatch
+            ))
+        ));
+    }
+
+    #[test]
+    fn it_rejects_a_corrupt_patch() {
+        let source = [0x00, 0x01, 0x02, 0x03];
+        let target = [0x00, 0xFF, 0x02, 0x03];
+        let mut patch = build_patch(&source, &target, &[0x81, 0xFE, 0x00]);
+        patch[7] ^= 0xFF;
+
+        assert!(matches!(
+            apply(&patch, &source),
+            Err(crate::Error::CartridgeLoad(
+                CartridgeLoadError::PatchChecksumFail
+            ))
+        ));
+    }
+
+    #[test]
+    fn it_rejects_targets_bigger_than_a_cartridge() {
+        let source = [0x00, 0x01, 0x02, 0x03];
+        let mut patch = b"UPS1".to_vec();
+        patch.extend(encode_varint(source.len()));
+        patch.extend(encode_varint(usize::MAX >> 8));
+        let patch = with_footer(patch, &source, &[]);
+
+        assert!(matches!(
+            apply(&patch, &source),
+            Err(crate::Error::CartridgeLoad(
+                CartridgeLoadError::MalformedPatch
+            ))
+        ));
+    }
+}
@@ -0,0 +1,398 @@
+use super::{
+    tiles::{bg_tile_address, decode_row, tile_map, TILE_MAP_WIDTH},
+    Lcdc, Ppu, SCREEN_WIDTH,
+};
+
+/// The window is drawn from `WX - 7`, so anything past this is off-screen.
+pub(super) const WINDOW_X_OFFSET: u8 = 7;
+const WINDOW_X_MAX: u8 = SCREEN_WIDTH as u8 + WINDOW_X_OFFSET - 1;
+
+impl Ppu {
+    /// Draws the background and window for the current line into colour
+    /// indices, before the palette is applied. Sprites need the indices
+    /// rather than shades to work out their priority.
+    pub(super) fn render_background(&mut self, vram: &[u8]) -> [u8; SCREEN_WIDTH] {
+        let mut line = [0; SCREEN_WIDTH];
+        let window = self.window_visible();
+
+        // On the DMG this blanks the window too, though it's still fetched
+        if self.lcdc.contains(Lcdc::BG_ENABLE) {
+            let bg_map = tile_map(self.lcdc.contains(Lcdc::BG_TILE_MAP));
+            let y = self.ly.wrapping_add(self.scy);
+
+            for (x, pixel) in line.iter_mut().enumerate() {
+                let x = (x as u8).wrapping_add(self.scx);
+                *pixel = self.fetch_pixel(vram, bg_map, x, y);
+            }
+
+            if window {
+                let window_map = tile_map(self.lcdc.contains(Lcdc::WINDOW_TILE_MAP));
+                // WX values below 7 push the window's left edge off-screen
+                let start = usize::from(self.wx.saturating_sub(WINDOW_X_OFFSET));
+                let skipped = WINDOW_X_OFFSET.saturating_sub(self.wx);
+
+                for (x, pixel) in line.iter_mut().enumerate().skip(start) {
+                    let x = (x - start) as u8 + skipped;
+                    *pixel = self.fetch_pixel(vram, window_map, x, self.window_line);
+                }
+            }
+        }
+
+        if window {
+            self.window_line += 1;
+        }
+
+        line
+    }
+
+    /// Whether the window covers any of the current line.
+    ///
+    /// Once LY has matched WY the window stays triggered for the rest of
+    /// the frame, even if WY changes afterwards.
+    fn window_visible(&self) -> bool {
+        self.lcdc.contains(Lcdc::WINDOW_ENABLE) && self.window_triggered && self.wx <= WINDOW_X_MAX
+    }
+
+    /// Looks up the colour index at a position in a 256x256 tile map.
+    pub(super) fn fetch_pixel(&self, vram: &[u8], map: usize, x: u8, y: u8) -> u8 {
+        let (x, y) = (usize::from(x), usize::from(y));
+
+        let tile = vram[map + (y / 8) * TILE_MAP_WIDTH + x / 8];
+        let address = bg_tile_address(self.lcdc, tile);
+
+        decode_row(vram, address, y % 8)[x % 8]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        tiles::{apply_palette, encode_tile, TILE_SIZE},
+        Frame, BGP, LCDC, SCREEN_HEIGHT, SCX, SCY, WX, WY,
+    };
+    use super::*;
+
+    /// The colour of every pixel in the gradient tile.
+    fn gradient(x: usize, y: usize) -> u8 {
+        ((x + y) % 4) as u8
+    }
+
+    /// VRAM with a gradient tile and a solid tile, laid out as a
+    /// checkerboard in the first map and stripes in the second.
+    ///
+    /// The gradient is tile 1 and the solid tile is 2, both in the unsigned
+    /// block and again in the signed one.
+    fn vram() -> Vec<u8> {
+        let mut vram = vec![0; 0x2000];
+        let gradient = encode_tile(gradient);
+        let solid = encode_tile(|_, _| 3);
+
+        for base in [0x0000, 0x1000] {
+            vram[base + TILE_SIZE..base + TILE_SIZE * 2].copy_from_slice(&gradient);
+            vram[base + TILE_SIZE * 2..base + TILE_SIZE * 3].copy_from_slice(&solid);
+        }
+
+        for y in 0..TILE_MAP_WIDTH {
+            for x in 0..TILE_MAP_WIDTH {
+                vram[0x1800 + y * TILE_MAP_WIDTH + x] = if (x + y) % 2 == 0 { 1 } else { 2 };
+                vram[0x1C00 + y * TILE_MAP_WIDTH + x] = if y % 2 == 0 { 1 } else { 0 };
+            }
+        }
+
+        vram
+    }
+
+    /// The colour index of a pixel in the first map, computed without VRAM.
+    fn checkerboard(x: usize, y: usize) -> u8 {
+        if (x / 8 + y / 8) % 2 == 0 {
+            gradient(x % 8, y % 8)
+        } else {
+            3
+        }
+    }
+
+    /// The colour index of a pixel in the second map, computed without VRAM.
+    fn stripes(x: usize, y: usize) -> u8 {
+        if (y / 8) % 2 == 0 {
+            gradient(x % 8, y % 8)
+        } else {
+            0
+        }
+    }
+
+    fn render_frame(ppu: &mut Ppu, vram: &[u8]) -> Frame {
+        while !ppu.take_frame_ready() {
+            ppu.step(1, vram, &[0; 0xA0]);
+        }
+
+        ppu.frame().clone()
+    }
+
+    fn assert_frame(frame: &Frame, expected: impl Fn(usize, usize) -> u8) {
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                assert_eq!(
+                    expected(x, y),
+                    frame.pixel(x, y),
+                    "pixel ({}, {}) differs",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    /// The start of a line of a frame, as text.
+    fn line_start(frame: &Frame, y: usize, length: usize) -> String {
+        frame.row(y)[..length]
+            .iter()
+            .map(|shade| shade.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn it_draws_the_background_with_unsigned_tiles() {
+        let mut ppu = Ppu::default();
+        ppu.write(BGP, 0b11_10_01_00);
+
+        let frame = render_frame(&mut ppu, &vram());
+
+        assert_frame(&frame, checkerboard);
+    }
+
+    #[test]
+    fn it_draws_the_background_with_signed_tiles() {
+        let mut vram = vram();
+        // Wipe the unsigned block so only the signed copies can be used
+        vram[..0x0800].fill(0);
+
+        let mut ppu = Ppu::default();
+        ppu.write(LCDC, (Lcdc::LCD_ENABLE | Lcdc::BG_ENABLE).bits);
+        ppu.write(BGP, 0b11_10_01_00);
+
+        let frame = render_frame(&mut ppu, &vram);
+
+        assert_frame(&frame, checkerboard);
+    }
+
+    #[test]
+    fn it_draws_the_background_from_the_second_map() {
+        let mut ppu = Ppu::default();
+        ppu.write(
+            LCDC,
+            (Lcdc::LCD_ENABLE | Lcdc::TILE_DATA | Lcdc::BG_ENABLE | Lcdc::BG_TILE_MAP).bits,
+        );
+        ppu.write(BGP, 0b11_10_01_00);
+
+        let frame = render_frame(&mut ppu, &vram());
+
+        assert_frame(&frame, stripes);
+    }
+
+    #[test]
+    fn it_scrolls_and_wraps_the_background() {
+        let mut ppu = Ppu::default();
+        ppu.write(BGP, 0b11_10_01_00);
+        ppu.write(SCX, 250);
+        ppu.write(SCY, 131);
+
+        let frame = render_frame(&mut ppu, &vram());
+
+        assert_frame(&frame, |x, y| {
+            checkerboard((x + 250) % 256, (y + 131) % 256)
+        });
+
+        // Each line starts with the last six pixels of a solid tile on the
+        // right edge of the map, then wraps around to a gradient. Line 125
+        // wraps around to the top of the map too.
+        assert_eq!("333333301230123333333", line_start(&frame, 0, 21));
+        assert_eq!("333333012301233333333", line_start(&frame, 1, 21));
+        assert_eq!("333333012301233333333", line_start(&frame, 125, 21));
+    }
+
+    #[test]
+    fn it_applies_the_background_palette() {
+        let palette = 0b00_01_11_10;
+
+        let mut ppu = Ppu::default();
+        ppu.write(BGP, palette);
+
+        let frame = render_frame(&mut ppu, &vram());
+
+        assert_frame(&frame, |x, y| apply_palette(palette, checkerboard(x, y)));
+    }
+
+    #[test]
+    fn it_blanks_the_background_when_disabled() {
+        let mut ppu = Ppu::default();
+        ppu.write(
+            LCDC,
+            (Lcdc::LCD_ENABLE | Lcdc::TILE_DATA | Lcdc::WINDOW_ENABLE).bits,
+        );
+        ppu.write(BGP, 0b00_01_10_11);
+
+        let frame = render_frame(&mut ppu, &vram());
+
+        assert_frame(&frame, |_, _| 0);
+    }
+
+    #[test]
+    fn it_draws_the_window() {
+        let mut ppu = Ppu::default();
+        ppu.write(
+            LCDC,
+            (Lcdc::LCD_ENABLE
+                | Lcdc::TILE_DATA
+                | Lcdc::BG_ENABLE
+                | Lcdc::WINDOW_ENABLE
+                | Lcdc::WINDOW_TILE_MAP)
+                .bits,
+        );
+        ppu.write(BGP, 0b11_10_01_00);
+        ppu.write(SCX, 3);
+        ppu.write(WX, 47);
+        ppu.write(WY, 20);
+
+        let frame = render_frame(&mut ppu, &vram());
+
+        assert_frame(&frame, |x, y| {
+            if x >= 40 && y >= 20 {
+                stripes(x - 40, y - 20)
+            } else {
+                checkerboard(x + 3, y)
+            }
+        });
+    }
+
+    #[test]
+    fn it_clips_the_window_when_wx_is_below_seven() {
+        let mut ppu = Ppu::default();
+        ppu.write(
+            LCDC,
+            (Lcdc::LCD_ENABLE
+                | Lcdc::TILE_DATA
+                | Lcdc::BG_ENABLE
+                | Lcdc::WINDOW_ENABLE
+                | Lcdc::WINDOW_TILE_MAP)
+                .bits,
+        );
+        ppu.write(BGP, 0b11_10_01_00);
+        ppu.write(WX, 2);
+
+        let frame = render_frame(&mut ppu, &vram());
+
+        assert_frame(&frame, |x, y| stripes(x + 5, y));
+
+        // The window's first five columns are cut off
+        assert_eq!("12301230123", line_start(&frame, 0, 11));
+        assert_eq!("23012301230", line_start(&frame, 1, 11));
+        assert_eq!("00000000000", line_start(&frame, 8, 11));
+    }
+
+    #[test]
+    fn it_hides_the_window_past_the_right_edge() {
+        let mut ppu = Ppu::default();
+        ppu.write(
+            LCDC,
+            (Lcdc::LCD_ENABLE
+                | Lcdc::TILE_DATA
+                | Lcdc::BG_ENABLE
+                | Lcdc::WINDOW_ENABLE
+                | Lcdc::WINDOW_TILE_MAP)
+                .bits,
+        );
+        ppu.write(BGP, 0b11_10_01_00);
+        ppu.write(WX, 167);
+
+        let frame = render_frame(&mut ppu, &vram());
+
+        assert_frame(&frame, checkerboard);
+    }
+
+    #[test]
+    fn it_resumes_the_window_from_its_own_line_counter() {
+        let vram = vram();
+        let enabled = Lcdc::LCD_ENABLE | Lcdc::TILE_DATA | Lcdc::BG_ENABLE | Lcdc::WINDOW_TILE_MAP;
+
+        let mut ppu = Ppu::default();
+        ppu.write(LCDC, (enabled | Lcdc::WINDOW_ENABLE).bits);
+        ppu.write(BGP, 0b11_10_01_00);
+        ppu.write(WX, 7);
+
+        // Draw ten lines with the window, then ten without
+        ppu.step(456 * 10, &vram, &[0; 0xA0]);
+        ppu.write(LCDC, enabled.bits);
+        ppu.step(456 * 10, &vram, &[0; 0xA0]);
+        ppu.write(LCDC, (enabled | Lcdc::WINDOW_ENABLE).bits);
+
+        let frame = render_frame(&mut ppu, &vram);
+
+        assert_frame(&frame, |x, y| match y {
+            0..=9 => stripes(x, y),
+            10..=19 => checkerboard(x, y),
+            // The window picks up from line 10 rather than line 20
+            _ => stripes(x, y - 10),
+        });
+    }
+
+    #[test]
+    fn it_triggers_the_window_when_ly_matches_wy() {
+        let vram = vram();
+
+        let mut ppu = Ppu::default();
+        ppu.write(
+            LCDC,
+            (Lcdc::LCD_ENABLE
+                | Lcdc::TILE_DATA
+                | Lcdc::BG_ENABLE
+                | Lcdc::WINDOW_ENABLE
+                | Lcdc::WINDOW_TILE_MAP)
+                .bits,
+        );
+        ppu.write(BGP, 0b11_10_01_00);
+        ppu.write(WX, 7);
+        ppu.write(WY, 30);
+
+        // Moving WY above LY after it's triggered doesn't hide the window
+        ppu.step(456 * 40, &vram, &[0; 0xA0]);
+        ppu.write(WY, 100);
+
+        let frame = render_frame(&mut ppu, &vram);
+
+        assert_frame(&frame, |x, y| {
+            if y >= 30 {
+                stripes(x, y - 30)
+            } else {
+                checkerboard(x, y)
+            }
+        });
+    }
+
+    #[test]
+    fn it_triggers_the_window_while_the_background_is_off() {
+        let vram = vram();
+        let enabled =
+            Lcdc::LCD_ENABLE | Lcdc::TILE_DATA | Lcdc::WINDOW_ENABLE | Lcdc::WINDOW_TILE_MAP;
+
+        let mut ppu = Ppu::default();
+        ppu.write(LCDC, enabled.bits);
+        ppu.write(BGP, 0b11_10_01_00);
+        ppu.write(WX, 7);
+        ppu.write(WY, 30);
+
+        // LY passes WY with the background off, and it's turned back on later
+        ppu.step(456 * 40, &vram, &[0; 0xA0]);
+        ppu.write(LCDC, (enabled | Lcdc::BG_ENABLE).bits);
+
+        let frame = render_frame(&mut ppu, &vram);
+
+        assert_frame(&frame, |x, y| {
+            if y >= 40 {
+                // The window was still fetched while it was blanked
+                stripes(x, y - 30)
+            } else {
+                0
+            }
+        });
+    }
+}
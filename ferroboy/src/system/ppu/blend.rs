@@ -0,0 +1,212 @@
+use std::collections::VecDeque;
+
+use super::{Frame, Palette, PixelFormat, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// How finely blended shades are tracked, in steps per shade.
+const LEVEL_SCALE: u16 = 256;
+/// The most frames `FrameBlending::Weighted` can average over.
+pub const MAX_BLENDED_FRAMES: usize = 4;
+
+/// How completed frames are blended with the ones before them.
+///
+/// The DMG-01's LCD was slow to respond, so pixels that flickered on and
+/// off every frame looked half-transparent rather than flickering. Some
+/// games rely on this for transparency effects.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrameBlending {
+    /// Frames are shown as they were drawn.
+    Off,
+    /// Each pixel only moves part of the way to its new shade every frame,
+    /// like a slow LCD. `persistence` is the percentage of the previous
+    /// frame that's kept, from 0 to 100.
+    Ghosting { persistence: u8 },
+    /// A weighted average of the most recent frames, newest first, e.g.
+    /// `[1, 1, 0, 0]` averages the last two frames.
+    Weighted([u8; MAX_BLENDED_FRAMES]),
+}
+
+impl Default for FrameBlending {
+    fn default() -> Self {
+        Self::Off
+    }
+}
+
+/// Blends each completed frame into a running picture, according to a
+/// `FrameBlending` response curve.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FrameBlender {
+    blending: FrameBlending,
+    /// The blended shade of every pixel, scaled by `LEVEL_SCALE`.
+    levels: Vec<u16>,
+    /// The most recent frames, newest first, for `FrameBlending::Weighted`.
+    history: VecDeque<Frame>,
+}
+
+impl FrameBlender {
+    pub(crate) fn new(blending: FrameBlending) -> Self {
+        Self {
+            blending,
+            levels: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            history: VecDeque::with_capacity(MAX_BLENDED_FRAMES),
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.blending != FrameBlending::Off
+    }
+
+    /// Blends a newly completed frame into the picture.
+    pub(crate) fn push(&mut self, frame: &Frame) {
+        match self.blending {
+            FrameBlending::Off => {}
+            FrameBlending::Ghosting { persistence } => {
+                let kept = u32::from(persistence.min(100));
+
+                for (level, shade) in self.levels.iter_mut().zip(frame.pixels()) {
+                    let target = u32::from(*shade) * u32::from(LEVEL_SCALE);
+                    *level = ((target * (100 - kept) + u32::from(*level) * kept) / 100) as u16;
+                }
+            }
+            FrameBlending::Weighted(weights) => {
+                if self.history.len() == MAX_BLENDED_FRAMES {
+                    self.history.pop_back();
+                }
+
+                self.history.push_front(frame.clone());
+
+                // Frames that haven't been drawn yet don't count
+                let weights = &weights[..self.history.len()];
+                let total: u32 = weights.iter().map(|weight| u32::from(*weight)).sum();
+
+                if total == 0 {
+                    return;
+                }
+
+                for (index, level) in self.levels.iter_mut().enumerate() {
+                    let sum: u32 = self
+                        .history
+                        .iter()
+                        .zip(weights)
+                        .map(|(frame, weight)| {
+                            u32::from(frame.pixels()[index]) * u32::from(*weight)
+                        })
+                        .sum();
+
+                    *level = (sum * u32::from(LEVEL_SCALE) / total) as u16;
+                }
+            }
+        }
+    }
+
+    /// Forgets every frame blended so far, leaving a blank picture.
+    pub(crate) fn reset(&mut self) {
+        self.levels.fill(0);
+        self.history.clear();
+    }
+
+    /// Colours the blended picture, as `Frame::colour_into` does for a
+    /// single frame.
+    pub(crate) fn colour_into<F>(&self, palette_at: F, format: PixelFormat, buffer: &mut Vec<u8>)
+    where
+        F: Fn(usize, usize) -> Palette,
+    {
+        buffer.clear();
+        buffer.reserve(self.levels.len() * format.bytes_per_pixel());
+
+        for (index, level) in self.levels.iter().enumerate() {
+            let palette = palette_at(index % SCREEN_WIDTH, index / SCREEN_WIDTH);
+            format.encode(palette.blend(*level, LEVEL_SCALE), buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(shade: u8) -> Frame {
+        let mut frame = Frame::new();
+
+        for y in 0..SCREEN_HEIGHT {
+            frame.row_mut(y).fill(shade);
+        }
+
+        frame
+    }
+
+    fn first_pixel(blender: &FrameBlender) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        blender.colour_into(
+            |_, _| Palette::HIGH_CONTRAST,
+            PixelFormat::Rgba8,
+            &mut buffer,
+        );
+        buffer[0..4].to_vec()
+    }
+
+    #[test]
+    fn it_ghosts_frames() {
+        let mut blender = FrameBlender::new(FrameBlending::Ghosting { persistence: 50 });
+
+        blender.push(&frame(3));
+        assert_eq!(LEVEL_SCALE * 3 / 2, blender.levels[0]);
+
+        blender.push(&frame(3));
+        assert_eq!(LEVEL_SCALE * 3 * 3 / 4, blender.levels[0]);
+
+        blender.push(&frame(0));
+        assert_eq!(LEVEL_SCALE * 3 * 3 / 8, blender.levels[0]);
+    }
+
+    #[test]
+    fn it_shows_frames_as_is_without_persistence() {
+        let mut blender = FrameBlender::new(FrameBlending::Ghosting { persistence: 0 });
+
+        blender.push(&frame(2));
+        assert_eq!(vec![0x55, 0x55, 0x55, 0xFF], first_pixel(&blender));
+    }
+
+    #[test]
+    fn it_averages_flickering_frames() {
+        let mut blender = FrameBlender::new(FrameBlending::Weighted([1, 1, 0, 0]));
+
+        blender.push(&frame(0));
+        blender.push(&frame(2));
+        assert_eq!(vec![0xAA, 0xAA, 0xAA, 0xFF], first_pixel(&blender));
+
+        blender.push(&frame(0));
+        assert_eq!(vec![0xAA, 0xAA, 0xAA, 0xFF], first_pixel(&blender));
+    }
+
+    #[test]
+    fn it_only_weighs_frames_that_have_been_drawn() {
+        let mut blender = FrameBlender::new(FrameBlending::Weighted([1, 1, 1, 1]));
+
+        blender.push(&frame(3));
+        assert_eq!(vec![0x00, 0x00, 0x00, 0xFF], first_pixel(&blender));
+    }
+
+    #[test]
+    fn it_resets_to_a_blank_picture() {
+        let mut blender = FrameBlender::new(FrameBlending::Weighted([1, 1, 0, 0]));
+        blender.push(&frame(3));
+        blender.reset();
+
+        assert_eq!(vec![0xFF, 0xFF, 0xFF, 0xFF], first_pixel(&blender));
+
+        // Frames from before the reset don't count
+        blender.push(&frame(2));
+        assert_eq!(vec![0x55, 0x55, 0x55, 0xFF], first_pixel(&blender));
+    }
+
+    #[test]
+    fn it_forgets_frames_past_the_weights() {
+        let mut blender = FrameBlender::new(FrameBlending::Weighted([1, 1, 1, 1]));
+
+        for shade in [3, 0, 0, 0, 0] {
+            blender.push(&frame(shade));
+        }
+
+        assert_eq!(0, blender.levels[0]);
+    }
+}
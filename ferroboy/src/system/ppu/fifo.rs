@@ -0,0 +1,614 @@
+use std::collections::VecDeque;
+
+use super::{
+    sprites::{Sprite, SpriteAttributes, SPRITE_X_OFFSET},
+    tiles::{apply_palette, bg_tile_address, tile_map, TILE_MAP_WIDTH},
+    Lcdc, Ppu, SCREEN_WIDTH,
+};
+
+/// How many dots each of the fetcher's read steps takes.
+const FETCH_STEP_DOTS: u8 = 2;
+/// How many dots it takes to fetch a sprite's tile once the background
+/// fetcher is out of the way.
+const SPRITE_FETCH_DOTS: u8 = 6;
+/// On hardware the first tile of every line is fetched twice, with the
+/// first copy thrown away. Rather than model the throwaway fetch, the
+/// start of the line is held up by the six dots it takes, which keeps an
+/// undisturbed line at 172 dots.
+const STARTUP_DOTS: u8 = 6;
+/// The window is drawn from `WX - 7`.
+const WINDOW_X_OFFSET: u8 = 7;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum FetcherStep {
+    Tile,
+    DataLow,
+    DataHigh,
+    /// Waiting for the FIFO to empty so the row can be pushed.
+    Push,
+}
+
+/// A pixel waiting in the background FIFO.
+///
+/// Only the colour index is stored, the palette is applied when the pixel
+/// is shifted out so that mid-line palette changes land where they should.
+type BgPixel = u8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct SpritePixel {
+    colour: u8,
+    palette: bool,
+    bg_priority: bool,
+}
+
+impl SpritePixel {
+    const TRANSPARENT: Self = Self {
+        colour: 0,
+        palette: false,
+        bg_priority: false,
+    };
+}
+
+/// The state of the pixel FIFO renderer through mode 3.
+///
+/// Rather than draw the line all at once, the background fetcher reads a
+/// tile row every few dots into a FIFO, which shifts one pixel out to the
+/// LCD per dot. Sprites and the window interrupt the fetcher, which is
+/// what makes mode 3 vary in length, and registers are read at the point
+/// the hardware would read them so mid-line changes take effect mid-line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct PixelFifo {
+    background: VecDeque<BgPixel>,
+    sprites: VecDeque<SpritePixel>,
+
+    step: FetcherStep,
+    step_dots: u8,
+    /// Which tile across the fetcher is on, relative to where it started.
+    fetcher_x: u8,
+    tile: u8,
+    low: u8,
+    high: u8,
+
+    /// The next pixel on the LCD to be drawn.
+    lcd_x: u8,
+    /// How many pixels to throw away before drawing, for fine scrolling.
+    discard: u8,
+    /// Dots left before the fetcher starts.
+    stall: u8,
+    in_window: bool,
+
+    /// The sprites on this line, in OAM order, and whether each has been
+    /// fetched yet.
+    line_sprites: Vec<(Sprite, bool)>,
+    /// The sprite being fetched, and how many dots of its fetch are left.
+    sprite_fetch: Option<(usize, u8)>,
+}
+
+impl Default for FetcherStep {
+    fn default() -> Self {
+        Self::Tile
+    }
+}
+
+impl Ppu {
+    /// Prepares the FIFO renderer at the start of mode 3.
+    pub(super) fn start_fifo(&mut self, oam: &[u8]) {
+        let line_sprites = self
+            .scan_oam(oam)
+            .into_iter()
+            .map(|sprite| (sprite, false))
+            .collect();
+
+        self.fifo = PixelFifo {
+            discard: self.scx % 8,
+            stall: STARTUP_DOTS,
+            line_sprites,
+            ..PixelFifo::default()
+        };
+    }
+
+    /// Advances the FIFO renderer by a dot, returning whether the line is
+    /// finished.
+    pub(super) fn tick_fifo(&mut self, vram: &[u8]) -> bool {
+        if self.fifo.stall > 0 {
+            self.fifo.stall -= 1;
+            return false;
+        }
+
+        if self.fifo.sprite_fetch.is_some() {
+            self.tick_sprite_fetch(vram);
+            return false;
+        }
+
+        if self.should_start_window() {
+            self.fifo.in_window = true;
+            self.fifo.background.clear();
+            self.fifo.step = FetcherStep::Tile;
+            self.fifo.step_dots = 0;
+            self.fifo.fetcher_x = 0;
+            // WX values below 7 push the window's left edge off-screen
+            self.fifo.discard = WINDOW_X_OFFSET.saturating_sub(self.wx);
+        }
+
+        if let Some(index) = self.next_sprite() {
+            self.fifo.sprite_fetch = Some((index, SPRITE_FETCH_DOTS));
+            self.tick_sprite_fetch(vram);
+            return false;
+        }
+
+        self.shift_pixel();
+        self.tick_fetcher(vram);
+
+        if usize::from(self.fifo.lcd_x) == SCREEN_WIDTH {
+            if self.fifo.in_window {
+                self.window_line += 1;
+            }
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn should_start_window(&self) -> bool {
+        !self.fifo.in_window
+            && self.window_triggered
+            && self.lcdc.contains(Lcdc::WINDOW_ENABLE)
+            && self.fifo.lcd_x + WINDOW_X_OFFSET >= self.wx
+            // The window can't start until the fine scroll is done with
+            && (self.fifo.discard == 0 || self.wx < WINDOW_X_OFFSET)
+    }
+
+    /// The next sprite on the line that's reached the LCD position, if
+    /// sprites are enabled.
+    fn next_sprite(&self) -> Option<usize> {
+        if !self.lcdc.contains(Lcdc::OBJ_ENABLE) || self.fifo.discard > 0 {
+            return None;
+        }
+
+        let x = i16::from(self.fifo.lcd_x);
+
+        self.fifo
+            .line_sprites
+            .iter()
+            .position(|(sprite, fetched)| !fetched && i16::from(sprite.x) - SPRITE_X_OFFSET <= x)
+    }
+
+    /// Fetches a sprite into the sprite FIFO.
+    ///
+    /// The fetch has to wait for the background fetcher to finish the
+    /// tile it's working on, then takes a few more dots of its own.
+    fn tick_sprite_fetch(&mut self, vram: &[u8]) {
+        if self.fifo.step != FetcherStep::Push {
+            self.tick_fetcher(vram);
+
+            // The sprite's fetch starts on the dot the background's finishes
+            if self.fifo.step != FetcherStep::Push {
+                return;
+            }
+        }
+
+        let (index, dots) = match self.fifo.sprite_fetch {
+            Some(fetch) => fetch,
+            None => return,
+        };
+
+        if dots > 1 {
+            self.fifo.sprite_fetch = Some((index, dots - 1));
+            return;
+        }
+
+        self.fifo.sprite_fetch = None;
+        self.fifo.line_sprites[index].1 = true;
+
+        let sprite = self.fifo.line_sprites[index].0;
+        let pixels = sprite.row(vram, self.ly, self.sprite_height());
+
+        // Sprites hanging off the left edge lose their leftmost pixels
+        let clipped = (SPRITE_X_OFFSET - i16::from(sprite.x).min(SPRITE_X_OFFSET)) as usize;
+        let offset =
+            (i16::from(sprite.x) - SPRITE_X_OFFSET - i16::from(self.fifo.lcd_x)).max(0) as usize;
+
+        while self.fifo.sprites.len() < offset + 8 - clipped {
+            self.fifo.sprites.push_back(SpritePixel::TRANSPARENT);
+        }
+
+        for (column, colour) in pixels.iter().enumerate().skip(clipped) {
+            let slot = &mut self.fifo.sprites[offset + column - clipped];
+
+            // Whatever got here first has priority, unless it's transparent
+            if slot.colour == 0 {
+                *slot = SpritePixel {
+                    colour: *colour,
+                    palette: sprite.attributes.contains(SpriteAttributes::PALETTE),
+                    bg_priority: sprite.attributes.contains(SpriteAttributes::BG_PRIORITY),
+                };
+            }
+        }
+    }
+
+    fn tick_fetcher(&mut self, vram: &[u8]) {
+        if self.fifo.step == FetcherStep::Push {
+            self.push_row();
+            return;
+        }
+
+        self.fifo.step_dots += 1;
+
+        if self.fifo.step_dots < FETCH_STEP_DOTS {
+            return;
+        }
+
+        self.fifo.step_dots = 0;
+
+        match self.fifo.step {
+            FetcherStep::Tile => {
+                self.fifo.tile = vram[self.fetcher_tile_map_address()];
+                self.fifo.step = FetcherStep::DataLow;
+            }
+            FetcherStep::DataLow => {
+                self.fifo.low = vram[self.fetcher_tile_row_address()];
+                self.fifo.step = FetcherStep::DataHigh;
+            }
+            FetcherStep::DataHigh => {
+                self.fifo.high = vram[self.fetcher_tile_row_address() + 1];
+                self.fifo.step = FetcherStep::Push;
+
+                // The row goes straight in if there's room, unless a sprite
+                // fetch is waiting to take over
+                if self.fifo.sprite_fetch.is_none() {
+                    self.push_row();
+                }
+            }
+            FetcherStep::Push => {}
+        }
+    }
+
+    /// Pushes the fetched row into the background FIFO, once it's empty.
+    fn push_row(&mut self) {
+        if !self.fifo.background.is_empty() {
+            return;
+        }
+
+        for bit in (0..8).rev() {
+            let colour = ((self.fifo.high >> bit) & 1) << 1 | ((self.fifo.low >> bit) & 1);
+            self.fifo.background.push_back(colour);
+        }
+
+        self.fifo.fetcher_x = self.fifo.fetcher_x.wrapping_add(1);
+        self.fifo.step = FetcherStep::Tile;
+    }
+
+    /// The line of the background or window the fetcher is reading from.
+    fn fetcher_y(&self) -> u8 {
+        if self.fifo.in_window {
+            self.window_line
+        } else {
+            self.ly.wrapping_add(self.scy)
+        }
+    }
+
+    fn fetcher_tile_map_address(&self) -> usize {
+        let (map, column) = if self.fifo.in_window {
+            (
+                tile_map(self.lcdc.contains(Lcdc::WINDOW_TILE_MAP)),
+                self.fifo.fetcher_x,
+            )
+        } else {
+            (
+                tile_map(self.lcdc.contains(Lcdc::BG_TILE_MAP)),
+                (self.scx / 8).wrapping_add(self.fifo.fetcher_x),
+            )
+        };
+
+        let row = usize::from(self.fetcher_y() / 8);
+        let column = usize::from(column) % TILE_MAP_WIDTH;
+
+        map + row * TILE_MAP_WIDTH + column
+    }
+
+    fn fetcher_tile_row_address(&self) -> usize {
+        bg_tile_address(self.lcdc, self.fifo.tile) + usize::from(self.fetcher_y() % 8) * 2
+    }
+
+    /// Shifts a pixel out of the FIFOs onto the LCD.
+    fn shift_pixel(&mut self) {
+        let colour = match self.fifo.background.pop_front() {
+            Some(colour) => colour,
+            None => return,
+        };
+
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return;
+        }
+
+        let sprite = self.fifo.sprites.pop_front();
+
+        // On the DMG this blanks the window too
+        let background = if self.lcdc.contains(Lcdc::BG_ENABLE) {
+            colour
+        } else {
+            0
+        };
+
+        let shade = match sprite {
+            Some(sprite)
+                if sprite.colour != 0
+                    && self.lcdc.contains(Lcdc::OBJ_ENABLE)
+                    && !(sprite.bg_priority && background != 0) =>
+            {
+                let palette = if sprite.palette { self.obp1 } else { self.obp0 };
+                apply_palette(palette, sprite.colour)
+            }
+            _ if self.lcdc.contains(Lcdc::BG_ENABLE) => apply_palette(self.bgp, background),
+            _ => 0,
+        };
+
+        let x = usize::from(self.fifo.lcd_x);
+        self.back_buffer.row_mut(usize::from(self.ly))[x] = shade;
+        self.fifo.lcd_x += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        tiles::{encode_tile, TILE_SIZE},
+        LcdMode, Renderer, BGP, LCDC, OBP0, SCREEN_HEIGHT, SCX, WX, WY,
+    };
+    use super::*;
+
+    /// VRAM where tile 1 is a gradient, laid out as a checkerboard with
+    /// the blank tile 0 in both maps.
+    fn vram() -> Vec<u8> {
+        let mut vram = vec![0; 0x2000];
+
+        vram[TILE_SIZE..TILE_SIZE * 2].copy_from_slice(&encode_tile(|x, y| ((x + y) % 4) as u8));
+        vram[TILE_SIZE * 2..TILE_SIZE * 3].copy_from_slice(&encode_tile(|_, _| 3));
+
+        for y in 0..TILE_MAP_WIDTH {
+            for x in 0..TILE_MAP_WIDTH {
+                let tile = if (x + y) % 2 == 0 { 1 } else { 0 };
+                vram[0x1800 + y * TILE_MAP_WIDTH + x] = tile;
+                vram[0x1C00 + y * TILE_MAP_WIDTH + x] = tile;
+            }
+        }
+
+        vram
+    }
+
+    fn ppu(renderer: Renderer) -> Ppu {
+        let mut ppu = Ppu::default();
+        ppu.set_renderer(renderer);
+        ppu.write(BGP, 0b11_10_01_00);
+        ppu.write(OBP0, 0b11_10_01_00);
+        ppu
+    }
+
+    fn render_frame(ppu: &mut Ppu, vram: &[u8], oam: &[u8]) {
+        while !ppu.take_frame_ready() {
+            ppu.step(1, vram, oam);
+        }
+    }
+
+    /// How many dots the first line spends in mode 3.
+    fn drawing_dots(ppu: &mut Ppu, vram: &[u8], oam: &[u8]) -> usize {
+        ppu.step(80, vram, oam);
+
+        let mut dots = 0;
+
+        while ppu.mode() == LcdMode::Drawing {
+            ppu.step(1, vram, oam);
+            dots += 1;
+        }
+
+        dots
+    }
+
+    /// Renders a frame with both renderers, checking they agree.
+    fn assert_renderers_agree(setup: impl Fn(&mut Ppu), oam: &[u8]) {
+        let vram = vram();
+
+        let mut scanline = ppu(Renderer::Scanline);
+        setup(&mut scanline);
+        render_frame(&mut scanline, &vram, oam);
+
+        let mut fifo = ppu(Renderer::Fifo);
+        setup(&mut fifo);
+        render_frame(&mut fifo, &vram, oam);
+
+        for y in 0..SCREEN_HEIGHT {
+            assert_eq!(
+                scanline.frame().row(y),
+                fifo.frame().row(y),
+                "line {} differs",
+                y
+            );
+        }
+    }
+
+    fn sprite_oam(entries: &[(u8, u8, u8, u8)]) -> Vec<u8> {
+        let mut oam = vec![0; 0xA0];
+
+        for (index, (y, x, tile, attributes)) in entries.iter().enumerate() {
+            oam[index * 4..index * 4 + 4].copy_from_slice(&[*y, *x, *tile, *attributes]);
+        }
+
+        oam
+    }
+
+    #[test]
+    fn it_matches_the_scanline_renderer() {
+        assert_renderers_agree(|_| {}, &[0; 0xA0]);
+    }
+
+    #[test]
+    fn it_matches_the_scanline_renderer_when_scrolled() {
+        assert_renderers_agree(
+            |ppu| {
+                ppu.write(SCX, 13);
+            },
+            &[0; 0xA0],
+        );
+    }
+
+    #[test]
+    fn it_matches_the_scanline_renderer_with_the_window() {
+        assert_renderers_agree(
+            |ppu| {
+                ppu.write(LCDC, ppu.lcdc.bits | Lcdc::WINDOW_ENABLE.bits);
+                ppu.write(WX, 50);
+                ppu.write(WY, 30);
+                ppu.write(SCX, 3);
+            },
+            &[0; 0xA0],
+        );
+    }
+
+    #[test]
+    fn it_matches_the_scanline_renderer_with_sprites() {
+        let oam = sprite_oam(&[
+            (16, 0, 2, 0),
+            (20, 4, 2, 0),
+            (24, 40, 1, SpriteAttributes::X_FLIP.bits()),
+            (24, 44, 2, SpriteAttributes::BG_PRIORITY.bits()),
+            (30, 44, 1, 0),
+            (40, 164, 2, 0),
+        ]);
+
+        assert_renderers_agree(
+            |ppu| {
+                ppu.write(LCDC, ppu.lcdc.bits | Lcdc::OBJ_ENABLE.bits);
+                ppu.write(SCX, 5);
+            },
+            &oam,
+        );
+    }
+
+    #[test]
+    fn it_takes_172_dots_to_draw_a_plain_line() {
+        let mut ppu = ppu(Renderer::Fifo);
+
+        assert_eq!(172, drawing_dots(&mut ppu, &vram(), &[0; 0xA0]));
+    }
+
+    #[test]
+    fn it_takes_longer_to_draw_fine_scrolled_lines() {
+        let mut ppu = ppu(Renderer::Fifo);
+        ppu.write(SCX, 5);
+
+        assert_eq!(177, drawing_dots(&mut ppu, &vram(), &[0; 0xA0]));
+    }
+
+    #[test]
+    fn it_takes_longer_to_draw_lines_with_the_window() {
+        let mut ppu = ppu(Renderer::Fifo);
+        ppu.write(LCDC, ppu.lcdc.bits | Lcdc::WINDOW_ENABLE.bits);
+        ppu.write(WX, 87);
+
+        assert!(drawing_dots(&mut ppu, &vram(), &[0; 0xA0]) > 172);
+    }
+
+    #[test]
+    fn it_takes_longer_to_draw_lines_with_sprites() {
+        let mut one = ppu(Renderer::Fifo);
+        one.write(LCDC, one.lcdc.bits | Lcdc::OBJ_ENABLE.bits);
+        let one = drawing_dots(&mut one, &vram(), &sprite_oam(&[(16, 40, 1, 0)]));
+
+        let mut two = ppu(Renderer::Fifo);
+        two.write(LCDC, two.lcdc.bits | Lcdc::OBJ_ENABLE.bits);
+        let two = drawing_dots(
+            &mut two,
+            &vram(),
+            &sprite_oam(&[(16, 40, 1, 0), (16, 80, 1, 0)]),
+        );
+
+        // A sprite costs at most 11 dots, when it's aligned with a tile
+        assert_eq!(172 + 11, one);
+        assert_eq!(172 + 2 * 11, two);
+    }
+
+    #[test]
+    fn it_applies_mid_line_palette_changes() {
+        let vram = vram();
+        let mut ppu = ppu(Renderer::Fifo);
+        ppu.write(BGP, 0b11_11_11_11);
+
+        // Run into the middle of the first line then change the palette
+        ppu.step(80 + 5 + 8 + 80, &vram, &[0; 0xA0]);
+        ppu.write(BGP, 0b00_00_00_00);
+
+        render_frame(&mut ppu, &vram, &[0; 0xA0]);
+
+        let row = ppu.frame().row(0);
+        assert_eq!(3, row[0]);
+        assert_eq!(0, row[SCREEN_WIDTH - 1]);
+
+        let changed = row.iter().position(|shade| *shade == 0).unwrap();
+        assert!((70..90).contains(&changed), "changed at {}", changed);
+    }
+    /// VRAM with solid tiles 0-3 in shades 0-3, written out by hand, with
+    /// the first map counting 0, 1, 2, 3 across and the second all 3s.
+    fn solid_vram() -> Vec<u8> {
+        let mut vram = vec![0; 0x2000];
+
+        for (tile, row) in [[0x00, 0x00], [0xFF, 0x00], [0x00, 0xFF], [0xFF, 0xFF]]
+            .iter()
+            .enumerate()
+        {
+            for y in 0..8 {
+                vram[tile * TILE_SIZE + y * 2..tile * TILE_SIZE + y * 2 + 2].copy_from_slice(row);
+            }
+        }
+
+        for x in 0..TILE_MAP_WIDTH {
+            vram[0x1800 + x] = (x % 4) as u8;
+            vram[0x1C00 + x] = 3;
+        }
+
+        vram
+    }
+
+    /// Expands a line written a tile at a time into its pixels.
+    fn tiles(line: &str) -> Vec<u8> {
+        line.bytes().flat_map(|shade| [shade - b'0'; 8]).collect()
+    }
+
+    #[test]
+    fn it_only_latches_the_fine_scroll_at_the_start_of_the_line() {
+        let vram = solid_vram();
+        let mut ppu = ppu(Renderer::Fifo);
+
+        // 42 dots into mode 3 the LCD is on pixel 30, in the fourth tile
+        ppu.step(80 + 42, &vram, &[0; 0xA0]);
+        ppu.write(SCX, 13);
+
+        let mut dots = 42;
+
+        while ppu.mode() == LcdMode::Drawing {
+            ppu.step(1, &vram, &[0; 0xA0]);
+            dots += 1;
+        }
+
+        // The fetcher reads the upper bits of SCX for every tile, but the
+        // lower bits only once the line starts. It's a tile ahead of the
+        // LCD, so the sixth tile is the first to jump, and by a whole tile.
+        assert_eq!(tiles("01230230123012301230"), ppu.back_buffer.row(0));
+        assert_eq!(172, dots);
+    }
+
+    #[test]
+    fn it_stalls_for_six_dots_to_start_the_window() {
+        let vram = solid_vram();
+        let mut ppu = ppu(Renderer::Fifo);
+        ppu.write(
+            LCDC,
+            ppu.lcdc.bits | Lcdc::WINDOW_ENABLE.bits | Lcdc::WINDOW_TILE_MAP.bits,
+        );
+        ppu.write(WX, 87);
+
+        assert_eq!(172 + 6, drawing_dots(&mut ppu, &vram, &[0; 0xA0]));
+        assert_eq!(tiles("01230123013333333333"), ppu.back_buffer.row(0));
+    }
+}
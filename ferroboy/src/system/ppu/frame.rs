@@ -0,0 +1,108 @@
+use super::{Palette, PixelFormat};
+
+/// The width of the DMG-01's LCD, in pixels.
+pub const SCREEN_WIDTH: usize = 160;
+/// The height of the DMG-01's LCD, in pixels.
+pub const SCREEN_HEIGHT: usize = 144;
+
+/// A single frame of LCD output.
+///
+/// Each pixel is a 2-bit shade, from `0` (lightest) to `3` (darkest), with
+/// the palettes already applied. Turning shades into colours is left to
+/// the frontend, since the DMG-01 itself had no say in what colour the
+/// LCD was.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Frame {
+    pixels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Self {
+            pixels: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * SCREEN_WIDTH + x]
+    }
+
+    /// The shades of every pixel, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn row(&self, y: usize) -> &[u8] {
+        &self.pixels[y * SCREEN_WIDTH..(y + 1) * SCREEN_WIDTH]
+    }
+
+    pub(crate) fn row_mut(&mut self, y: usize) -> &mut [u8] {
+        &mut self.pixels[y * SCREEN_WIDTH..(y + 1) * SCREEN_WIDTH]
+    }
+
+    /// Colours the frame with `palette`, returning a buffer ready to be
+    /// displayed in the given format.
+    pub fn convert(&self, palette: &Palette, format: PixelFormat) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.convert_into(palette, format, &mut buffer);
+        buffer
+    }
+
+    /// As [`Frame::convert`], but reuses an existing buffer.
+    pub fn convert_into(&self, palette: &Palette, format: PixelFormat, buffer: &mut Vec<u8>) {
+        self.colour_into(|_, _| *palette, format, buffer);
+    }
+
+    /// As [`Frame::convert_into`], but with a palette for each pixel.
+    pub(crate) fn colour_into<F>(&self, palette_at: F, format: PixelFormat, buffer: &mut Vec<u8>)
+    where
+        F: Fn(usize, usize) -> Palette,
+    {
+        buffer.clear();
+        buffer.reserve(self.pixels.len() * format.bytes_per_pixel());
+
+        for (index, shade) in self.pixels.iter().enumerate() {
+            let palette = palette_at(index % SCREEN_WIDTH, index / SCREEN_WIDTH);
+            format.encode(palette.colour(*shade), buffer);
+        }
+    }
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Frame {{ {}x{} }}", SCREEN_WIDTH, SCREEN_HEIGHT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_converts_frames() {
+        let mut frame = Frame::new();
+        frame.row_mut(0)[1] = 3;
+
+        let buffer = frame.convert(&Palette::HIGH_CONTRAST, PixelFormat::Rgba8);
+
+        assert_eq!(SCREEN_WIDTH * SCREEN_HEIGHT * 4, buffer.len());
+        assert_eq!([0xFF, 0xFF, 0xFF, 0xFF], buffer[0..4]);
+        assert_eq!([0x00, 0x00, 0x00, 0xFF], buffer[4..8]);
+    }
+
+    #[test]
+    fn it_reuses_buffers() {
+        let frame = Frame::new();
+        let mut buffer = vec![0; 3];
+
+        frame.convert_into(&Palette::DMG_GREEN, PixelFormat::Rgb565, &mut buffer);
+
+        assert_eq!(SCREEN_WIDTH * SCREEN_HEIGHT * 2, buffer.len());
+    }
+}
@@ -0,0 +1,506 @@
+//! Decoding VRAM and OAM into images and plain data, for debuggers and
+//! test assertions.
+
+use super::{
+    background::WINDOW_X_OFFSET,
+    sprites::{Sprite, SpriteAttributes, OAM_ENTRIES, SPRITE_X_OFFSET, SPRITE_Y_OFFSET},
+    tiles::{apply_palette, decode_row, tile_map, TILE_MAP_WIDTH, TILE_SIZE},
+    Colour, Lcdc, Palette, Ppu, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+/// The number of tiles VRAM has room for, across all three blocks.
+pub const TILE_COUNT: usize = 384;
+/// How many tiles wide `VideoInspector::tile_sheet` lays them out.
+const TILE_SHEET_COLUMNS: usize = 16;
+/// The width and height of a tile map, in pixels.
+const TILE_MAP_SIZE: usize = TILE_MAP_WIDTH * 8;
+/// The colour the viewport is outlined in on the background.
+const VIEWPORT_COLOUR: Colour = Colour::from_hex(0xFF0000);
+
+/// An image with an alpha channel, four bytes per pixel in R, G, B, A order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbaImage {
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// A fully transparent image.
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width * height * 4],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The raw pixel data, ready to hand to an image library.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let offset = (y * self.width + x) * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.data[offset..offset + 4]);
+        pixel
+    }
+
+    fn set(&mut self, x: usize, y: usize, colour: Colour) {
+        let offset = (y * self.width + x) * 4;
+        self.data[offset..offset + 4].copy_from_slice(&[colour.r, colour.g, colour.b, 0xFF]);
+    }
+}
+
+/// One of the two tile maps in VRAM.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TileMapArea {
+    /// The map at 0x9800.
+    Low,
+    /// The map at 0x9C00.
+    High,
+}
+
+impl TileMapArea {
+    pub fn address(&self) -> u16 {
+        0x8000 + self.offset() as u16
+    }
+
+    fn offset(&self) -> usize {
+        tile_map(*self == Self::High)
+    }
+
+    fn from_flag(high: bool) -> Self {
+        if high {
+            Self::High
+        } else {
+            Self::Low
+        }
+    }
+}
+
+/// A decoded tile.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tile {
+    /// The tile's position in VRAM, from 0 at 0x8000 to 383 at 0x97F0.
+    pub index: usize,
+    pub address: u16,
+    /// The colour indices of each row, before any palette is applied.
+    pub pixels: [[u8; 8]; 8],
+}
+
+/// Where the window is and what it's drawn from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WindowInfo {
+    pub enabled: bool,
+    /// WX, which is offset by 7 from the window's left edge on screen.
+    pub x: u8,
+    /// WY.
+    pub y: u8,
+    pub map: TileMapArea,
+}
+
+impl Sprite {
+    /// Where the sprite's top left corner is on screen, which can be
+    /// partially or fully off it.
+    pub fn screen_position(&self) -> (i16, i16) {
+        (
+            i16::from(self.x) - SPRITE_X_OFFSET,
+            i16::from(self.y) - SPRITE_Y_OFFSET,
+        )
+    }
+
+    /// Whether the sprite's X and Y would put it anywhere on screen.
+    pub fn is_on_screen(&self, height: u8) -> bool {
+        let (x, y) = self.screen_position();
+
+        x > -8 && x < SCREEN_WIDTH as i16 && y > -i16::from(height) && y < SCREEN_HEIGHT as i16
+    }
+}
+
+/// A read-only view of the PPU's memory and registers, decoded the way the
+/// PPU would see them.
+///
+/// Images are coloured with the given palette, applied through BGP, OBP0
+/// and OBP1 where the PPU would use them. The view is a snapshot of the
+/// current registers, so it won't reflect mid-frame changes.
+pub struct VideoInspector<'a> {
+    ppu: &'a Ppu,
+    vram: &'a [u8],
+    oam: &'a [u8],
+    palette: Palette,
+}
+
+impl<'a> VideoInspector<'a> {
+    pub(crate) fn new(ppu: &'a Ppu, vram: &'a [u8], oam: &'a [u8], palette: Palette) -> Self {
+        Self {
+            ppu,
+            vram,
+            oam,
+            palette,
+        }
+    }
+
+    pub fn tile(&self, index: usize) -> Tile {
+        let address = index * TILE_SIZE;
+        let mut pixels = [[0; 8]; 8];
+
+        for (row, pixels) in pixels.iter_mut().enumerate() {
+            *pixels = decode_row(self.vram, address, row);
+        }
+
+        Tile {
+            index,
+            address: 0x8000 + address as u16,
+            pixels,
+        }
+    }
+
+    /// Every tile in VRAM, in address order.
+    pub fn tiles(&self) -> Vec<Tile> {
+        (0..TILE_COUNT).map(|index| self.tile(index)).collect()
+    }
+
+    /// Every tile laid out 16 to a row, coloured by their raw colour indices
+    /// since tiles don't have a palette of their own.
+    pub fn tile_sheet(&self) -> RgbaImage {
+        let rows = TILE_COUNT / TILE_SHEET_COLUMNS;
+        let mut image = RgbaImage::new(TILE_SHEET_COLUMNS * 8, rows * 8);
+
+        for tile in self.tiles() {
+            let left = (tile.index % TILE_SHEET_COLUMNS) * 8;
+            let top = (tile.index / TILE_SHEET_COLUMNS) * 8;
+
+            for (y, row) in tile.pixels.iter().enumerate() {
+                for (x, index) in row.iter().enumerate() {
+                    image.set(left + x, top + y, self.palette.colour(*index));
+                }
+            }
+        }
+
+        image
+    }
+
+    /// The tile indices of a map, row by row.
+    pub fn tile_map(&self, area: TileMapArea) -> Vec<u8> {
+        let offset = area.offset();
+        self.vram[offset..offset + TILE_MAP_WIDTH * TILE_MAP_WIDTH].to_vec()
+    }
+
+    /// A whole 256x256 tile map, drawn with BGP and the tile data LCDC
+    /// currently selects.
+    pub fn tile_map_image(&self, area: TileMapArea) -> RgbaImage {
+        let mut image = RgbaImage::new(TILE_MAP_SIZE, TILE_MAP_SIZE);
+
+        for y in 0..TILE_MAP_SIZE {
+            for x in 0..TILE_MAP_SIZE {
+                let index = self
+                    .ppu
+                    .fetch_pixel(self.vram, area.offset(), x as u8, y as u8);
+                image.set(
+                    x,
+                    y,
+                    self.palette.colour(apply_palette(self.ppu.bgp, index)),
+                );
+            }
+        }
+
+        image
+    }
+
+    /// Which map the background is currently drawn from.
+    pub fn background_map(&self) -> TileMapArea {
+        TileMapArea::from_flag(self.ppu.lcdc.contains(Lcdc::BG_TILE_MAP))
+    }
+
+    /// The top left corner of the visible part of the background, SCX and SCY.
+    pub fn viewport(&self) -> (u8, u8) {
+        (self.ppu.scx, self.ppu.scy)
+    }
+
+    /// The background's tile map, with the visible area outlined. The
+    /// outline wraps around the edges just as the viewport does.
+    pub fn background(&self) -> RgbaImage {
+        let mut image = self.tile_map_image(self.background_map());
+        let (left, top) = self.viewport();
+        let (left, top) = (usize::from(left), usize::from(top));
+
+        let mut outline = |x: usize, y: usize| {
+            image.set(
+                (left + x) % TILE_MAP_SIZE,
+                (top + y) % TILE_MAP_SIZE,
+                VIEWPORT_COLOUR,
+            );
+        };
+
+        for x in 0..SCREEN_WIDTH {
+            outline(x, 0);
+            outline(x, SCREEN_HEIGHT - 1);
+        }
+
+        for y in 0..SCREEN_HEIGHT {
+            outline(0, y);
+            outline(SCREEN_WIDTH - 1, y);
+        }
+
+        image
+    }
+
+    pub fn window(&self) -> WindowInfo {
+        WindowInfo {
+            enabled: self.ppu.lcdc.contains(Lcdc::WINDOW_ENABLE),
+            x: self.ppu.wx,
+            y: self.ppu.wy,
+            map: TileMapArea::from_flag(self.ppu.lcdc.contains(Lcdc::WINDOW_TILE_MAP)),
+        }
+    }
+
+    /// The window as it's placed on screen, with everything it doesn't
+    /// cover left transparent. This is drawn regardless of whether the
+    /// window is enabled.
+    pub fn window_image(&self) -> RgbaImage {
+        let window = self.window();
+        let mut image = RgbaImage::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+
+        let left = i16::from(window.x) - i16::from(WINDOW_X_OFFSET);
+
+        for y in usize::from(window.y)..SCREEN_HEIGHT {
+            for x in left.max(0) as usize..SCREEN_WIDTH {
+                let window_x = (x as i16 - left) as u8;
+                let window_y = (y - usize::from(window.y)) as u8;
+                let index =
+                    self.ppu
+                        .fetch_pixel(self.vram, window.map.offset(), window_x, window_y);
+
+                image.set(
+                    x,
+                    y,
+                    self.palette.colour(apply_palette(self.ppu.bgp, index)),
+                );
+            }
+        }
+
+        image
+    }
+
+    /// Every entry in OAM, in order.
+    pub fn sprites(&self) -> Vec<Sprite> {
+        (0..OAM_ENTRIES)
+            .map(|index| Sprite::from_oam(self.oam, index))
+            .collect()
+    }
+
+    /// The height of sprites, which LCDC sets for all of them at once.
+    pub fn sprite_height(&self) -> u8 {
+        self.ppu.sprite_height()
+    }
+
+    /// A single sprite, flipped and coloured through its palette, with
+    /// colour 0 left transparent.
+    pub fn sprite_image(&self, index: usize) -> RgbaImage {
+        let sprite = Sprite::from_oam(self.oam, index);
+        let height = self.sprite_height();
+        let palette = if sprite.attributes.contains(SpriteAttributes::PALETTE) {
+            self.ppu.obp1
+        } else {
+            self.ppu.obp0
+        };
+
+        let mut image = RgbaImage::new(8, usize::from(height));
+        let (_, top) = sprite.screen_position();
+
+        for y in 0..height {
+            // `row` works in screen lines, so ask for the line this row is on
+            let line = (top + i16::from(y)) as u8;
+
+            for (x, index) in sprite.row(self.vram, line, height).iter().enumerate() {
+                if *index != 0 {
+                    let shade = apply_palette(palette, *index);
+                    image.set(x, usize::from(y), self.palette.colour(shade));
+                }
+            }
+        }
+
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tiles::encode_tile;
+    use super::*;
+
+    const WHITE: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+    const BLACK: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+    const TRANSPARENT: [u8; 4] = [0; 4];
+    const RED: [u8; 4] = [0xFF, 0x00, 0x00, 0xFF];
+
+    /// VRAM with a solid black tile 1, and a tile 2 with only its top left
+    /// pixel black, in both the unsigned and signed blocks.
+    fn vram() -> Vec<u8> {
+        let mut vram = vec![0; 0x2000];
+        let solid = encode_tile(|_, _| 3);
+        let dot = encode_tile(|x, y| if x == 0 && y == 0 { 3 } else { 0 });
+
+        for base in [0x0000, 0x1000] {
+            vram[base + TILE_SIZE..base + TILE_SIZE * 2].copy_from_slice(&solid);
+            vram[base + TILE_SIZE * 2..base + TILE_SIZE * 3].copy_from_slice(&dot);
+        }
+
+        vram
+    }
+
+    fn ppu() -> Ppu {
+        Ppu {
+            lcdc: Lcdc::LCD_ENABLE | Lcdc::BG_ENABLE | Lcdc::TILE_DATA,
+            bgp: 0b11_10_01_00,
+            obp0: 0b11_10_01_00,
+            obp1: 0b00_01_10_11,
+            ..Ppu::default()
+        }
+    }
+
+    fn inspect<'a>(ppu: &'a Ppu, vram: &'a [u8], oam: &'a [u8]) -> VideoInspector<'a> {
+        VideoInspector::new(ppu, vram, oam, Palette::HIGH_CONTRAST)
+    }
+
+    #[test]
+    fn it_decodes_every_tile() {
+        let (ppu, vram) = (ppu(), vram());
+        let tiles = inspect(&ppu, &vram, &[0; 0xA0]).tiles();
+
+        assert_eq!(TILE_COUNT, tiles.len());
+        assert_eq!(0x8010, tiles[1].address);
+        assert_eq!([[3; 8]; 8], tiles[1].pixels);
+        assert_eq!(3, tiles[2].pixels[0][0]);
+        assert_eq!(0, tiles[2].pixels[0][1]);
+        assert_eq!(0x9010, tiles[257].address);
+        assert_eq!(tiles[1].pixels, tiles[257].pixels);
+    }
+
+    #[test]
+    fn it_lays_out_the_tile_sheet() {
+        let (ppu, vram) = (ppu(), vram());
+        let sheet = inspect(&ppu, &vram, &[0; 0xA0]).tile_sheet();
+
+        assert_eq!((128, 192), (sheet.width(), sheet.height()));
+        assert_eq!(WHITE, sheet.pixel(0, 0));
+        assert_eq!(BLACK, sheet.pixel(8, 0));
+        assert_eq!(BLACK, sheet.pixel(15, 7));
+        // Tile 257 is on the 17th row
+        assert_eq!(BLACK, sheet.pixel(8, 16 * 8));
+    }
+
+    #[test]
+    fn it_draws_tile_maps_with_the_selected_tile_data() {
+        let mut ppu = ppu();
+        let mut vram = vram();
+        vram[0x1C00 + 1] = 1;
+
+        let image = inspect(&ppu, &vram, &[0; 0xA0]).tile_map_image(TileMapArea::High);
+        assert_eq!((256, 256), (image.width(), image.height()));
+        assert_eq!(WHITE, image.pixel(0, 0));
+        assert_eq!(BLACK, image.pixel(8, 0));
+
+        // Signed addressing reads tile 1 from 0x9010 instead
+        vram[0x1010..0x1020].fill(0);
+        ppu.lcdc.remove(Lcdc::TILE_DATA);
+
+        let image = inspect(&ppu, &vram, &[0; 0xA0]).tile_map_image(TileMapArea::High);
+        assert_eq!(WHITE, image.pixel(8, 0));
+        assert_eq!(
+            1,
+            inspect(&ppu, &vram, &[0; 0xA0]).tile_map(TileMapArea::High)[1]
+        );
+    }
+
+    #[test]
+    fn it_outlines_the_viewport() {
+        let ppu = Ppu {
+            scx: 200,
+            scy: 10,
+            ..ppu()
+        };
+        let vram = vram();
+        let background = inspect(&ppu, &vram, &[0; 0xA0]).background();
+
+        assert_eq!(RED, background.pixel(200, 10));
+        assert_eq!(RED, background.pixel(255, 10));
+        // The right edge wraps around to 200 + 159 - 256
+        assert_eq!(RED, background.pixel(103, 10));
+        assert_eq!(RED, background.pixel(103, 153));
+        assert_eq!(WHITE, background.pixel(104, 10));
+        assert_eq!(WHITE, background.pixel(201, 11));
+    }
+
+    #[test]
+    fn it_places_the_window() {
+        let mut ppu = ppu();
+        ppu.lcdc.insert(Lcdc::WINDOW_ENABLE | Lcdc::WINDOW_TILE_MAP);
+        ppu.wx = 17;
+        ppu.wy = 20;
+
+        let mut vram = vram();
+        vram[0x1C00] = 2;
+
+        let inspector = inspect(&ppu, &vram, &[0; 0xA0]);
+        assert_eq!(
+            WindowInfo {
+                enabled: true,
+                x: 17,
+                y: 20,
+                map: TileMapArea::High,
+            },
+            inspector.window()
+        );
+
+        let image = inspector.window_image();
+        assert_eq!(TRANSPARENT, image.pixel(9, 20));
+        assert_eq!(TRANSPARENT, image.pixel(10, 19));
+        assert_eq!(BLACK, image.pixel(10, 20));
+        assert_eq!(WHITE, image.pixel(11, 20));
+    }
+
+    #[test]
+    fn it_lists_oam_entries() {
+        let (ppu, vram) = (ppu(), vram());
+        let mut oam = [0; 0xA0];
+        oam[4..8].copy_from_slice(&[16, 8, 2, 0b0011_0000]);
+
+        let sprites = inspect(&ppu, &vram, &oam).sprites();
+
+        assert_eq!(OAM_ENTRIES, sprites.len());
+        assert_eq!(1, sprites[1].index);
+        assert_eq!(2, sprites[1].tile);
+        assert_eq!(
+            SpriteAttributes::PALETTE | SpriteAttributes::X_FLIP,
+            sprites[1].attributes
+        );
+        assert_eq!((0, 0), sprites[1].screen_position());
+        assert!(sprites[1].is_on_screen(8));
+        assert!(!sprites[0].is_on_screen(8));
+    }
+
+    #[test]
+    fn it_draws_sprites_through_their_palette() {
+        let (ppu, vram) = (ppu(), vram());
+        let mut oam = [0; 0xA0];
+        // Flipped, and through OBP1 which inverts the shades
+        oam[0..4].copy_from_slice(&[0, 0, 2, 0b0011_0000]);
+
+        let image = inspect(&ppu, &vram, &oam).sprite_image(0);
+
+        assert_eq!((8, 8), (image.width(), image.height()));
+        assert_eq!(WHITE, image.pixel(7, 0));
+        assert_eq!(TRANSPARENT, image.pixel(0, 0));
+    }
+}
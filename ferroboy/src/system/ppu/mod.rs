@@ -0,0 +1,668 @@
+mod background;
+mod blend;
+mod fifo;
+mod frame;
+#[cfg(feature = "introspection")]
+mod inspect;
+mod palette;
+mod sprites;
+mod tiles;
+
+use bitflags::bitflags;
+
+pub use blend::{FrameBlending, MAX_BLENDED_FRAMES};
+pub use frame::{Frame, SCREEN_HEIGHT, SCREEN_WIDTH};
+#[cfg(feature = "introspection")]
+pub use inspect::{RgbaImage, Tile, TileMapArea, VideoInspector, WindowInfo, TILE_COUNT};
+pub use palette::{Colour, Palette, PixelFormat};
+#[cfg(feature = "introspection")]
+pub use sprites::{Sprite, SpriteAttributes};
+
+use crate::system::Interrupts;
+
+use blend::FrameBlender;
+use fifo::PixelFifo;
+use tiles::{apply_palette, bg_tile_address, tile_map, TILE_MAP_WIDTH, TILE_SIZE};
+
+pub(crate) const LCDC: u16 = 0xFF40;
+pub(crate) const STAT: u16 = 0xFF41;
+pub(crate) const SCY: u16 = 0xFF42;
+pub(crate) const SCX: u16 = 0xFF43;
+pub(crate) const LY: u16 = 0xFF44;
+pub(crate) const LYC: u16 = 0xFF45;
+pub(crate) const BGP: u16 = 0xFF47;
+pub(crate) const OBP0: u16 = 0xFF48;
+pub(crate) const OBP1: u16 = 0xFF49;
+pub(crate) const WY: u16 = 0xFF4A;
+pub(crate) const WX: u16 = 0xFF4B;
+
+/// How many dots (T-cycles) it takes to process a single line.
+const DOTS_PER_LINE: u16 = 456;
+/// How many dots the PPU spends searching OAM for sprites on a line.
+const OAM_SCAN_DOTS: u16 = 80;
+/// How many dots the PPU spends drawing a line when nothing stalls it.
+const DRAWING_DOTS: u16 = 172;
+/// The first line of VBlank, i.e. the number of visible lines.
+const VBLANK_LINE: u8 = SCREEN_HEIGHT as u8;
+/// The number of lines in a frame, including VBlank.
+const LINES_PER_FRAME: u8 = 154;
+
+const STAT_LYC_EQUAL: u8 = 0b0000_0100;
+const STAT_HBLANK_SELECT: u8 = 0b0000_1000;
+const STAT_VBLANK_SELECT: u8 = 0b0001_0000;
+const STAT_OAM_SELECT: u8 = 0b0010_0000;
+const STAT_LYC_SELECT: u8 = 0b0100_0000;
+const STAT_SELECT_MASK: u8 = 0b0111_1000;
+
+bitflags! {
+    /// Bitflags for the LCD control register (LCDC, 0xFF40).
+    pub struct Lcdc: u8 {
+        const BG_ENABLE = 0b0000_0001;
+        const OBJ_ENABLE = 0b0000_0010;
+        /// Sprites are 8x16 rather than 8x8.
+        const OBJ_SIZE = 0b0000_0100;
+        /// The background uses the tile map at 0x9C00 rather than 0x9800.
+        const BG_TILE_MAP = 0b0000_1000;
+        /// Tiles are addressed unsigned from 0x8000 rather than signed from 0x9000.
+        const TILE_DATA = 0b0001_0000;
+        const WINDOW_ENABLE = 0b0010_0000;
+        /// The window uses the tile map at 0x9C00 rather than 0x9800.
+        const WINDOW_TILE_MAP = 0b0100_0000;
+        const LCD_ENABLE = 0b1000_0000;
+    }
+}
+
+/// The mode the PPU is in, as reported in the low bits of STAT.
+///
+/// Each visible line goes through an OAM scan, drawing, then HBlank,
+/// and the frame ends with ten lines of VBlank.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LcdMode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+/// How the PPU turns VRAM into pixels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Renderer {
+    /// Draws each line all at once at the end of mode 3. It's fast, but
+    /// any changes made to the registers partway through a line are missed.
+    Scanline,
+    /// Models the hardware's pixel fetcher and FIFOs a dot at a time, so
+    /// mid-line register changes land where they would on hardware, and
+    /// mode 3 stretches for sprites, the window and fine scrolling.
+    Fifo,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::Scanline
+    }
+}
+
+/// An implementation of the DMG-01's picture processing unit.
+///
+/// The PPU runs alongside the CPU, one dot per T-cycle, and draws into a
+/// frame a line at a time. Once the last visible line has been drawn the
+/// frame is handed off to be displayed, and the PPU enters VBlank.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ppu {
+    renderer: Renderer,
+    fifo: PixelFifo,
+
+    lcdc: Lcdc,
+    /// The interrupt selection bits of STAT, the rest is derived.
+    stat_select: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+
+    mode: LcdMode,
+    /// How many dots into the current line the PPU is.
+    dot: u16,
+    /// The STAT interrupt is only requested when this line goes from low to high.
+    stat_line: bool,
+    /// The window keeps its own line counter, which only advances on lines
+    /// where the window was actually drawn.
+    window_line: u8,
+    /// Whether LY has matched WY yet this frame.
+    window_triggered: bool,
+    /// Whether the LCD was just turned on, and is still on its first line.
+    lcd_starting: bool,
+    /// Whether the frame being drawn should be thrown away.
+    blank_frame: bool,
+
+    /// The frame currently being drawn.
+    back_buffer: Frame,
+    /// The most recently completed frame.
+    frame: Frame,
+    frame_ready: bool,
+    blender: FrameBlender,
+}
+
+impl Ppu {
+    pub fn renderer(&self) -> Renderer {
+        self.renderer
+    }
+
+    pub(crate) fn set_renderer(&mut self, renderer: Renderer) {
+        self.renderer = renderer;
+    }
+
+    pub(crate) fn set_frame_blending(&mut self, blending: FrameBlending) {
+        self.blender = FrameBlender::new(blending);
+    }
+
+    pub fn mode(&self) -> LcdMode {
+        self.mode
+    }
+
+    pub fn lcdc(&self) -> Lcdc {
+        self.lcdc
+    }
+
+    pub fn ly(&self) -> u8 {
+        self.ly
+    }
+
+    /// The most recently completed frame.
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// Colours the most recent frame for display, blending it with the
+    /// frames before it if frame blending is on.
+    pub fn convert_frame_into(&self, palette: &Palette, format: PixelFormat, buffer: &mut Vec<u8>) {
+        self.colour_frame_into(|_, _| *palette, format, buffer);
+    }
+
+    /// As `convert_frame_into`, but with a palette for each pixel.
+    pub(crate) fn colour_frame_into<F>(
+        &self,
+        palette_at: F,
+        format: PixelFormat,
+        buffer: &mut Vec<u8>,
+    ) where
+        F: Fn(usize, usize) -> Palette,
+    {
+        if self.blender.is_enabled() {
+            self.blender.colour_into(palette_at, format, buffer);
+        } else {
+            self.frame.colour_into(palette_at, format, buffer);
+        }
+    }
+
+    /// The data of the first `count` tiles on screen, in the order they're
+    /// displayed from the top left of the background map, 20 to a row.
+    /// This is how the SGB receives data through VRAM.
+    pub(crate) fn screen_tiles(&self, vram: &[u8], count: usize) -> Vec<u8> {
+        const TILES_PER_ROW: usize = SCREEN_WIDTH / 8;
+
+        let map = tile_map(self.lcdc.contains(Lcdc::BG_TILE_MAP));
+
+        (0..count)
+            .flat_map(|index| {
+                let row = index / TILES_PER_ROW;
+                let column = index % TILES_PER_ROW;
+                let tile = vram[map + row * TILE_MAP_WIDTH + column];
+                let address = bg_tile_address(self.lcdc, tile);
+
+                vram[address..address + TILE_SIZE].iter().copied()
+            })
+            .collect()
+    }
+
+    /// Whether the CPU can get at VRAM, which it can't while it's being drawn from.
+    pub(crate) fn vram_accessible(&self) -> bool {
+        !self.lcdc.contains(Lcdc::LCD_ENABLE) || self.mode != LcdMode::Drawing
+    }
+
+    /// Whether the CPU can get at OAM, which it can't while it's being
+    /// scanned or drawn from.
+    pub(crate) fn oam_accessible(&self) -> bool {
+        !self.lcdc.contains(Lcdc::LCD_ENABLE)
+            || !matches!(self.mode, LcdMode::OamScan | LcdMode::Drawing)
+    }
+
+    /// Whether a frame has completed since the last call.
+    pub(crate) fn take_frame_ready(&mut self) -> bool {
+        std::mem::replace(&mut self.frame_ready, false)
+    }
+
+    pub(crate) fn read(&self, address: u16) -> u8 {
+        match address {
+            LCDC => self.lcdc.bits,
+            STAT => self.stat(),
+            SCY => self.scy,
+            SCX => self.scx,
+            LY => self.ly,
+            LYC => self.lyc,
+            BGP => self.bgp,
+            OBP0 => self.obp0,
+            OBP1 => self.obp1,
+            WY => self.wy,
+            WX => self.wx,
+            _ => 0xFF,
+        }
+    }
+
+    pub(crate) fn write(&mut self, address: u16, value: u8) -> Interrupts {
+        match address {
+            LCDC => self.write_lcdc(value),
+            STAT => self.stat_select = value & STAT_SELECT_MASK,
+            SCY => self.scy = value,
+            SCX => self.scx = value,
+            // LY is read-only
+            LY => {}
+            LYC => self.lyc = value,
+            BGP => self.bgp = value,
+            OBP0 => self.obp0 = value,
+            OBP1 => self.obp1 = value,
+            WY => self.wy = value,
+            WX => self.wx = value,
+            _ => {}
+        }
+
+        self.update_stat_line()
+    }
+
+    /// Advances the PPU by a number of dots, returning any interrupts it
+    /// requested along the way.
+    pub(crate) fn step(&mut self, cycles: u64, vram: &[u8], oam: &[u8]) -> Interrupts {
+        let mut interrupts = Interrupts::empty();
+
+        if !self.lcdc.contains(Lcdc::LCD_ENABLE) {
+            return interrupts;
+        }
+
+        for _ in 0..cycles {
+            interrupts |= self.tick(vram, oam);
+        }
+
+        interrupts
+    }
+
+    fn tick(&mut self, vram: &[u8], oam: &[u8]) -> Interrupts {
+        let mut interrupts = Interrupts::empty();
+
+        self.dot += 1;
+
+        match self.mode {
+            LcdMode::OamScan if self.dot == OAM_SCAN_DOTS => {
+                self.start_drawing(oam);
+            }
+            // The first line after the LCD is turned on skips its OAM scan,
+            // and sits in mode 0 instead
+            LcdMode::HBlank if self.lcd_starting && self.dot == OAM_SCAN_DOTS => {
+                self.lcd_starting = false;
+                self.start_drawing(oam);
+            }
+            LcdMode::Drawing => {
+                let finished = match self.renderer {
+                    Renderer::Scanline if self.dot == OAM_SCAN_DOTS + DRAWING_DOTS => {
+                        self.render_line(vram, oam);
+                        true
+                    }
+                    Renderer::Scanline => false,
+                    Renderer::Fifo => self.tick_fifo(vram),
+                };
+
+                if finished {
+                    self.mode = LcdMode::HBlank;
+                }
+            }
+            LcdMode::HBlank if self.dot == DOTS_PER_LINE => {
+                self.dot = 0;
+                self.ly += 1;
+
+                if self.ly == VBLANK_LINE {
+                    self.mode = LcdMode::VBlank;
+                    self.complete_frame();
+                    interrupts |= Interrupts::VBLANK;
+                } else {
+                    self.mode = LcdMode::OamScan;
+                }
+            }
+            LcdMode::VBlank if self.dot == DOTS_PER_LINE => {
+                self.dot = 0;
+                self.ly += 1;
+
+                if self.ly == LINES_PER_FRAME {
+                    self.ly = 0;
+                    self.mode = LcdMode::OamScan;
+                    self.reset_window();
+                }
+            }
+            _ => {}
+        }
+
+        interrupts | self.update_stat_line()
+    }
+
+    fn start_drawing(&mut self, oam: &[u8]) {
+        self.mode = LcdMode::Drawing;
+
+        // LY is compared with WY on every line, whatever's being drawn
+        if self.ly == self.wy {
+            self.window_triggered = true;
+        }
+
+        if self.renderer == Renderer::Fifo {
+            self.start_fifo(oam);
+        }
+    }
+
+    fn render_line(&mut self, vram: &[u8], oam: &[u8]) {
+        let background = self.render_background(vram);
+        let sprites = self.render_sprites(vram, oam, &background);
+        let blank = !self.lcdc.contains(Lcdc::BG_ENABLE);
+        let bgp = self.bgp;
+
+        let row = self.back_buffer.row_mut(usize::from(self.ly));
+
+        for ((shade, index), sprite) in row.iter_mut().zip(background.iter()).zip(sprites) {
+            *shade = match sprite {
+                Some(sprite) => sprite,
+                None if blank => 0,
+                None => apply_palette(bgp, *index),
+            };
+        }
+    }
+
+    fn reset_window(&mut self) {
+        self.window_line = 0;
+        self.window_triggered = false;
+    }
+
+    fn complete_frame(&mut self) {
+        std::mem::swap(&mut self.back_buffer, &mut self.frame);
+
+        // The LCD doesn't show the first frame after it's turned on
+        if self.blank_frame {
+            self.blank_frame = false;
+            self.frame = Frame::new();
+        }
+
+        self.blender.push(&self.frame);
+        self.frame_ready = true;
+    }
+
+    fn write_lcdc(&mut self, value: u8) {
+        let was_enabled = self.lcdc.contains(Lcdc::LCD_ENABLE);
+        self.lcdc = Lcdc::from_bits_truncate(value);
+
+        match (was_enabled, self.lcdc.contains(Lcdc::LCD_ENABLE)) {
+            (true, false) => {
+                self.ly = 0;
+                self.dot = 0;
+                self.mode = LcdMode::HBlank;
+                self.reset_window();
+
+                // Nothing is shown while the LCD is off
+                self.frame = Frame::new();
+                self.blender.reset();
+            }
+            (false, true) => {
+                self.mode = LcdMode::HBlank;
+                self.lcd_starting = true;
+                self.blank_frame = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn stat(&self) -> u8 {
+        let lyc_equal = if self.ly == self.lyc {
+            STAT_LYC_EQUAL
+        } else {
+            0
+        };
+
+        0x80 | self.stat_select | lyc_equal | self.mode as u8
+    }
+
+    /// Recomputes the STAT interrupt line, requesting the interrupt if any
+    /// of the selected conditions just became true.
+    fn update_stat_line(&mut self) -> Interrupts {
+        let select = self.stat_select;
+        let line = self.lcdc.contains(Lcdc::LCD_ENABLE)
+            && ((select & STAT_LYC_SELECT != 0 && self.ly == self.lyc)
+                || match self.mode {
+                    LcdMode::HBlank => select & STAT_HBLANK_SELECT != 0,
+                    LcdMode::VBlank => select & STAT_VBLANK_SELECT != 0,
+                    LcdMode::OamScan => select & STAT_OAM_SELECT != 0,
+                    LcdMode::Drawing => false,
+                });
+
+        let rising = line && !self.stat_line;
+        self.stat_line = line;
+
+        if rising {
+            Interrupts::LCD_STAT
+        } else {
+            Interrupts::empty()
+        }
+    }
+}
+
+impl Default for Ppu {
+    /// The PPU as the boot ROM leaves it, with the LCD and background on.
+    fn default() -> Self {
+        Self {
+            renderer: Renderer::default(),
+            fifo: PixelFifo::default(),
+
+            lcdc: Lcdc::LCD_ENABLE | Lcdc::TILE_DATA | Lcdc::BG_ENABLE,
+            stat_select: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0xFC,
+            obp0: 0xFF,
+            obp1: 0xFF,
+            wy: 0,
+            wx: 0,
+
+            mode: LcdMode::OamScan,
+            dot: 0,
+            stat_line: false,
+            window_line: 0,
+            window_triggered: false,
+            lcd_starting: false,
+            blank_frame: false,
+
+            back_buffer: Frame::new(),
+            frame: Frame::new(),
+            frame_ready: false,
+            blender: FrameBlender::new(FrameBlending::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOTS_PER_FRAME: u64 = DOTS_PER_LINE as u64 * LINES_PER_FRAME as u64;
+
+    fn step(ppu: &mut Ppu, cycles: u64) -> Interrupts {
+        ppu.step(cycles, &[0; 0x2000], &[0; 0xA0])
+    }
+
+    #[test]
+    fn it_cycles_through_the_modes() {
+        let mut ppu = Ppu::default();
+        assert_eq!(LcdMode::OamScan, ppu.mode());
+
+        step(&mut ppu, 80);
+        assert_eq!(LcdMode::Drawing, ppu.mode());
+
+        step(&mut ppu, 172);
+        assert_eq!(LcdMode::HBlank, ppu.mode());
+
+        step(&mut ppu, 204);
+        assert_eq!(LcdMode::OamScan, ppu.mode());
+        assert_eq!(1, ppu.ly());
+    }
+
+    #[test]
+    fn it_enters_vblank_after_the_last_line() {
+        let mut ppu = Ppu::default();
+
+        let interrupts = step(&mut ppu, 456 * 144 - 1);
+        assert_eq!(Interrupts::empty(), interrupts);
+        assert_eq!(143, ppu.ly());
+
+        let interrupts = step(&mut ppu, 1);
+        assert_eq!(Interrupts::VBLANK, interrupts);
+        assert_eq!(LcdMode::VBlank, ppu.mode());
+        assert_eq!(144, ppu.ly());
+        assert!(ppu.take_frame_ready());
+        assert!(!ppu.take_frame_ready());
+    }
+
+    #[test]
+    fn it_wraps_around_after_a_frame() {
+        let mut ppu = Ppu::default();
+
+        step(&mut ppu, DOTS_PER_FRAME - 1);
+        assert_eq!(153, ppu.ly());
+
+        step(&mut ppu, 1);
+        assert_eq!(0, ppu.ly());
+        assert_eq!(LcdMode::OamScan, ppu.mode());
+    }
+
+    #[test]
+    fn it_reports_the_mode_and_coincidence_in_stat() {
+        let mut ppu = Ppu::default();
+        ppu.write(LYC, 1);
+
+        assert_eq!(0x80 | LcdMode::OamScan as u8, ppu.read(STAT));
+
+        step(&mut ppu, 456);
+        assert_eq!(
+            0x80 | STAT_LYC_EQUAL | LcdMode::OamScan as u8,
+            ppu.read(STAT)
+        );
+    }
+
+    #[test]
+    fn it_requests_stat_interrupts_for_lyc() {
+        let mut ppu = Ppu::default();
+        ppu.write(LYC, 2);
+        ppu.write(STAT, STAT_LYC_SELECT);
+
+        assert_eq!(Interrupts::empty(), step(&mut ppu, 456 * 2 - 1));
+        assert_eq!(Interrupts::LCD_STAT, step(&mut ppu, 1));
+        // The line stays high for the rest of the line, so no new interrupt
+        assert_eq!(Interrupts::empty(), step(&mut ppu, 455));
+    }
+
+    #[test]
+    fn it_requests_stat_interrupts_for_modes() {
+        let mut ppu = Ppu::default();
+        ppu.write(STAT, STAT_HBLANK_SELECT);
+
+        assert_eq!(Interrupts::empty(), step(&mut ppu, 251));
+        assert_eq!(Interrupts::LCD_STAT, step(&mut ppu, 1));
+    }
+
+    #[test]
+    fn it_ignores_writes_to_ly() {
+        let mut ppu = Ppu::default();
+        ppu.write(LY, 0x42);
+
+        assert_eq!(0, ppu.read(LY));
+    }
+
+    #[test]
+    fn it_starts_the_first_line_in_hblank_when_the_lcd_is_turned_on() {
+        let mut ppu = Ppu::default();
+        ppu.write(LCDC, 0x00);
+        ppu.write(STAT, STAT_OAM_SELECT);
+        ppu.write(LCDC, 0x91);
+
+        assert_eq!(LcdMode::HBlank, ppu.mode());
+        assert_eq!(0x80 | STAT_OAM_SELECT | STAT_LYC_EQUAL, ppu.read(STAT));
+
+        // There's no OAM scan, so no OAM interrupt either
+        assert_eq!(Interrupts::empty(), step(&mut ppu, 80));
+        assert_eq!(LcdMode::Drawing, ppu.mode());
+
+        step(&mut ppu, 376);
+        assert_eq!(1, ppu.ly());
+        assert_eq!(LcdMode::OamScan, ppu.mode());
+    }
+
+    #[test]
+    fn it_blanks_the_first_frame_after_the_lcd_is_turned_on() {
+        let vram = [0xFF; 0x2000];
+
+        let mut ppu = Ppu::default();
+        ppu.write(LCDC, 0x00);
+        ppu.write(LCDC, 0x91);
+
+        ppu.step(DOTS_PER_LINE as u64 * VBLANK_LINE as u64, &vram, &[0; 0xA0]);
+        assert!(ppu.take_frame_ready());
+        assert!(ppu.frame().pixels().iter().all(|shade| *shade == 0));
+
+        ppu.step(DOTS_PER_FRAME, &vram, &[0; 0xA0]);
+        assert!(ppu.take_frame_ready());
+        assert!(ppu.frame().pixels().iter().all(|shade| *shade == 3));
+    }
+
+    #[test]
+    fn it_blanks_the_screen_when_the_lcd_is_turned_off() {
+        let vram = [0xFF; 0x2000];
+
+        let mut ppu = Ppu::default();
+        ppu.step(DOTS_PER_FRAME, &vram, &[0; 0xA0]);
+        assert!(ppu.frame().pixels().iter().all(|shade| *shade == 3));
+
+        ppu.write(LCDC, 0x00);
+        assert!(ppu.frame().pixels().iter().all(|shade| *shade == 0));
+        assert_eq!(
+            0x80 | STAT_LYC_EQUAL | LcdMode::HBlank as u8,
+            ppu.read(STAT)
+        );
+    }
+
+    #[test]
+    fn it_blanks_the_blended_screen_when_the_lcd_is_turned_off() {
+        let vram = [0xFF; 0x2000];
+
+        let mut ppu = Ppu::default();
+        ppu.set_frame_blending(FrameBlending::Ghosting { persistence: 50 });
+        ppu.step(DOTS_PER_FRAME * 4, &vram, &[0; 0xA0]);
+
+        ppu.write(LCDC, 0x00);
+
+        let mut buffer = Vec::new();
+        ppu.convert_frame_into(&Palette::HIGH_CONTRAST, PixelFormat::Rgba8, &mut buffer);
+        assert!(buffer.iter().all(|byte| *byte == 0xFF));
+    }
+
+    #[test]
+    fn it_stops_when_the_lcd_is_off() {
+        let mut ppu = Ppu::default();
+        step(&mut ppu, 456 * 3);
+
+        ppu.write(LCDC, 0x00);
+        assert_eq!(0, ppu.ly());
+
+        assert_eq!(Interrupts::empty(), step(&mut ppu, DOTS_PER_FRAME));
+        assert_eq!(0, ppu.ly());
+        assert!(!ppu.take_frame_ready());
+    }
+}
@@ -0,0 +1,290 @@
+use std::str::FromStr;
+
+use crate::error::Error;
+
+/// A 24-bit RGB colour.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Colour {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Colour {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Builds a colour from a `0xRRGGBB` value.
+    pub const fn from_hex(hex: u32) -> Self {
+        Self::new((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+    }
+}
+
+impl FromStr for Colour {
+    type Err = Error;
+
+    /// Parses `RRGGBB`, optionally prefixed with `#` or `0x`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix('#')
+            .or_else(|| s.strip_prefix("0x"))
+            .unwrap_or(s);
+
+        if hex.len() != 6 {
+            return Err(Error::InvalidPalette(s.into()));
+        }
+
+        u32::from_str_radix(hex, 16)
+            .map(Self::from_hex)
+            .map_err(|_| Error::InvalidPalette(s.into()))
+    }
+}
+
+/// The colours the LCD's four shades are displayed as, lightest first.
+///
+/// The DMG-01 only ever output shades, and what they looked like depended
+/// on the model, so this is left up to the frontend.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    colours: [Colour; 4],
+}
+
+impl Palette {
+    /// The yellow-green of the original DMG-01's LCD.
+    pub const DMG_GREEN: Self = Self::from_hex([0x9BBC0F, 0x8BAC0F, 0x306230, 0x0F380F]);
+    /// The greys of the Game Boy Pocket's LCD.
+    pub const POCKET_GREY: Self = Self::from_hex([0xE0DBCD, 0xA89F94, 0x706B66, 0x2B2B26]);
+    /// The blue-green backlight of the Game Boy Light.
+    pub const LIGHT: Self = Self::from_hex([0x00B581, 0x009A71, 0x00694A, 0x004F3B]);
+    /// Evenly spaced greys from white to black.
+    pub const HIGH_CONTRAST: Self = Self::from_hex([0xFFFFFF, 0xAAAAAA, 0x555555, 0x000000]);
+
+    /// A user-defined palette, from lightest to darkest.
+    pub const fn new(colours: [Colour; 4]) -> Self {
+        Self { colours }
+    }
+
+    /// A user-defined palette of `0xRRGGBB` values, from lightest to darkest.
+    pub const fn from_hex(colours: [u32; 4]) -> Self {
+        Self::new([
+            Colour::from_hex(colours[0]),
+            Colour::from_hex(colours[1]),
+            Colour::from_hex(colours[2]),
+            Colour::from_hex(colours[3]),
+        ])
+    }
+
+    /// The colour a shade is displayed as.
+    pub fn colour(&self, shade: u8) -> Colour {
+        self.colours[usize::from(shade & 0b11)]
+    }
+
+    pub fn colours(&self) -> &[Colour; 4] {
+        &self.colours
+    }
+
+    /// The colour of a shade partway between two of the palette's, where
+    /// `level` is the shade multiplied by `scale`.
+    pub(crate) fn blend(&self, level: u16, scale: u16) -> Colour {
+        let shade = usize::from(level / scale).min(3);
+        let fraction = u32::from(level % scale);
+        let scale = u32::from(scale);
+
+        let from = self.colours[shade];
+        let to = self.colours[(shade + 1).min(3)];
+        let mix = |from: u8, to: u8| {
+            ((u32::from(from) * (scale - fraction) + u32::from(to) * fraction) / scale) as u8
+        };
+
+        Colour::new(mix(from.r, to.r), mix(from.g, to.g), mix(from.b, to.b))
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::DMG_GREEN
+    }
+}
+
+impl FromStr for Palette {
+    type Err = Error;
+
+    /// Parses either the name of a preset (`dmg`, `pocket`, `light` or
+    /// `contrast`) or four comma-separated colours, lightest first.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dmg" | "green" => return Ok(Self::DMG_GREEN),
+            "pocket" | "grey" | "gray" => return Ok(Self::POCKET_GREY),
+            "light" => return Ok(Self::LIGHT),
+            "contrast" | "high-contrast" => return Ok(Self::HIGH_CONTRAST),
+            _ => {}
+        }
+
+        let colours = s
+            .split(',')
+            .map(|colour| colour.trim().parse())
+            .collect::<Result<Vec<Colour>, _>>()?;
+
+        match colours[..] {
+            [lightest, light, dark, darkest] => Ok(Self::new([lightest, light, dark, darkest])),
+            _ => Err(Error::InvalidPalette(s.into())),
+        }
+    }
+}
+
+/// The layouts frames can be converted into for display.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 32-bit native-endian `0xAARRGGBB`, with an opaque alpha channel.
+    Argb8888,
+    /// 32-bit native-endian `0x00RRGGBB`, as libretro's XRGB8888.
+    Xrgb8888,
+    /// 16-bit native-endian, 5 bits of red, 6 of green and 5 of blue.
+    Rgb565,
+    /// Four bytes per pixel in R, G, B, A order.
+    Rgba8,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            Self::Rgb565 => 2,
+            Self::Argb8888 | Self::Xrgb8888 | Self::Rgba8 => 4,
+        }
+    }
+
+    /// Appends a single pixel to `buffer`.
+    pub(crate) fn encode(&self, colour: Colour, buffer: &mut Vec<u8>) {
+        let Colour { r, g, b } = colour;
+
+        match self {
+            Self::Argb8888 => {
+                let pixel = 0xFF00_0000 | u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b);
+                buffer.extend_from_slice(&pixel.to_ne_bytes());
+            }
+            Self::Xrgb8888 => {
+                let pixel = u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b);
+                buffer.extend_from_slice(&pixel.to_ne_bytes());
+            }
+            Self::Rgb565 => {
+                let pixel = u16::from(r >> 3) << 11 | u16::from(g >> 2) << 5 | u16::from(b >> 3);
+                buffer.extend_from_slice(&pixel.to_ne_bytes());
+            }
+            Self::Rgba8 => buffer.extend_from_slice(&[r, g, b, 0xFF]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Colour = Colour::from_hex(0xFF0000);
+    const GREEN: Colour = Colour::from_hex(0x00FF00);
+    const BLUE: Colour = Colour::from_hex(0x0000FF);
+
+    fn encode(format: PixelFormat, colour: Colour) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        format.encode(colour, &mut buffer);
+        assert_eq!(format.bytes_per_pixel(), buffer.len());
+        buffer
+    }
+
+    #[test]
+    fn it_maps_shades_to_colours() {
+        let palette = Palette::HIGH_CONTRAST;
+
+        assert_eq!(Colour::new(0xFF, 0xFF, 0xFF), palette.colour(0));
+        assert_eq!(Colour::new(0xAA, 0xAA, 0xAA), palette.colour(1));
+        assert_eq!(Colour::new(0x55, 0x55, 0x55), palette.colour(2));
+        assert_eq!(Colour::new(0x00, 0x00, 0x00), palette.colour(3));
+    }
+
+    #[test]
+    fn it_blends_between_shades() {
+        let palette = Palette::HIGH_CONTRAST;
+
+        assert_eq!(palette.colour(1), palette.blend(256, 256));
+        assert_eq!(palette.colour(3), palette.blend(768, 256));
+        assert_eq!(Colour::new(0xD4, 0xD4, 0xD4), palette.blend(128, 256));
+    }
+
+    #[test]
+    fn it_parses_presets() {
+        assert_eq!(Palette::DMG_GREEN, "dmg".parse().unwrap());
+        assert_eq!(Palette::POCKET_GREY, "Pocket".parse().unwrap());
+        assert_eq!(Palette::LIGHT, "light".parse().unwrap());
+        assert_eq!(Palette::HIGH_CONTRAST, "contrast".parse().unwrap());
+    }
+
+    #[test]
+    fn it_parses_user_defined_palettes() {
+        let palette: Palette = "#E0F8D0, 0x88C070,346856,081820".parse().unwrap();
+
+        assert_eq!(
+            Palette::from_hex([0xE0F8D0, 0x88C070, 0x346856, 0x081820]),
+            palette
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_palettes() {
+        assert!("sepia".parse::<Palette>().is_err());
+        assert!("FFFFFF,000000".parse::<Palette>().is_err());
+        assert!("FFFFFF,AAAAAA,555555,00000G".parse::<Palette>().is_err());
+        assert!("FFFFFF,AAAAAA,555555,000".parse::<Palette>().is_err());
+    }
+
+    #[test]
+    fn it_encodes_argb8888() {
+        assert_eq!(
+            0xFFFF0000u32.to_ne_bytes().to_vec(),
+            encode(PixelFormat::Argb8888, RED)
+        );
+        assert_eq!(
+            0xFF0000FFu32.to_ne_bytes().to_vec(),
+            encode(PixelFormat::Argb8888, BLUE)
+        );
+    }
+
+    #[test]
+    fn it_encodes_xrgb8888() {
+        assert_eq!(
+            0x00FF0000u32.to_ne_bytes().to_vec(),
+            encode(PixelFormat::Xrgb8888, RED)
+        );
+        assert_eq!(
+            0x0000FF00u32.to_ne_bytes().to_vec(),
+            encode(PixelFormat::Xrgb8888, GREEN)
+        );
+    }
+
+    #[test]
+    fn it_encodes_rgb565() {
+        assert_eq!(
+            0xF800u16.to_ne_bytes().to_vec(),
+            encode(PixelFormat::Rgb565, RED)
+        );
+        assert_eq!(
+            0x07E0u16.to_ne_bytes().to_vec(),
+            encode(PixelFormat::Rgb565, GREEN)
+        );
+        assert_eq!(
+            0x001Fu16.to_ne_bytes().to_vec(),
+            encode(PixelFormat::Rgb565, BLUE)
+        );
+    }
+
+    #[test]
+    fn it_encodes_rgba8() {
+        assert_eq!(
+            vec![0xFF, 0x00, 0x00, 0xFF],
+            encode(PixelFormat::Rgba8, RED)
+        );
+        assert_eq!(
+            vec![0x00, 0x00, 0xFF, 0xFF],
+            encode(PixelFormat::Rgba8, BLUE)
+        );
+    }
+}
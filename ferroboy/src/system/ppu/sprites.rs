@@ -0,0 +1,413 @@
+use bitflags::bitflags;
+
+use super::{
+    tiles::{apply_palette, decode_row, TILE_SIZE},
+    Lcdc, Ppu, SCREEN_WIDTH,
+};
+
+/// The number of sprites OAM has room for.
+pub(super) const OAM_ENTRIES: usize = 40;
+/// The most sprites the PPU will pick up on a single line.
+const SPRITES_PER_LINE: usize = 10;
+/// Sprite positions are offset so they can be partially off-screen.
+pub(super) const SPRITE_X_OFFSET: i16 = 8;
+pub(super) const SPRITE_Y_OFFSET: i16 = 16;
+
+bitflags! {
+    /// Bitflags for the attribute byte of an OAM entry.
+    pub struct SpriteAttributes: u8 {
+        /// Use OBP1 rather than OBP0.
+        const PALETTE = 0b0001_0000;
+        const X_FLIP = 0b0010_0000;
+        const Y_FLIP = 0b0100_0000;
+        /// Background colours 1-3 are drawn over the sprite.
+        const BG_PRIORITY = 0b1000_0000;
+    }
+}
+
+/// A single four-byte entry in OAM.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sprite {
+    /// The sprite's position in OAM, which breaks ties between sprites at
+    /// the same X coordinate.
+    pub index: usize,
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: SpriteAttributes,
+}
+
+impl Sprite {
+    pub(super) fn from_oam(oam: &[u8], index: usize) -> Self {
+        let entry = &oam[index * 4..index * 4 + 4];
+
+        Self {
+            index,
+            y: entry[0],
+            x: entry[1],
+            tile: entry[2],
+            attributes: SpriteAttributes::from_bits_truncate(entry[3]),
+        }
+    }
+
+    /// The colour indices of the sprite's row on `line`, with flipping
+    /// applied, leftmost pixel first.
+    pub(super) fn row(&self, vram: &[u8], line: u8, height: u8) -> [u8; 8] {
+        let mut row = (i16::from(line) - (i16::from(self.y) - SPRITE_Y_OFFSET)) as u8;
+
+        if self.attributes.contains(SpriteAttributes::Y_FLIP) {
+            row = height - 1 - row;
+        }
+
+        // 8x16 sprites ignore the low bit of the tile index
+        let tile = if height == 16 {
+            self.tile & 0xFE
+        } else {
+            self.tile
+        };
+
+        // Sprites always use unsigned addressing from 0x8000
+        let mut pixels = decode_row(vram, usize::from(tile) * TILE_SIZE, usize::from(row));
+
+        if self.attributes.contains(SpriteAttributes::X_FLIP) {
+            pixels.reverse();
+        }
+
+        pixels
+    }
+}
+
+impl Ppu {
+    pub(super) fn sprite_height(&self) -> u8 {
+        if self.lcdc.contains(Lcdc::OBJ_SIZE) {
+            16
+        } else {
+            8
+        }
+    }
+
+    /// Finds the sprites that overlap the current line, in OAM order.
+    ///
+    /// Only the first ten are kept, even if some of them are off-screen
+    /// horizontally and won't be drawn.
+    pub(super) fn scan_oam(&self, oam: &[u8]) -> Vec<Sprite> {
+        let height = i16::from(self.sprite_height());
+        let line = i16::from(self.ly);
+
+        (0..OAM_ENTRIES)
+            .map(|index| Sprite::from_oam(oam, index))
+            .filter(|sprite| {
+                let top = i16::from(sprite.y) - SPRITE_Y_OFFSET;
+                (top..top + height).contains(&line)
+            })
+            .take(SPRITES_PER_LINE)
+            .collect()
+    }
+
+    /// Draws the sprites on the current line into shades, leaving `None`
+    /// wherever the background should show through.
+    ///
+    /// `background` is the line's background colour indices, since
+    /// sprites with the BG priority bit set are only drawn over colour 0.
+    pub(super) fn render_sprites(
+        &self,
+        vram: &[u8],
+        oam: &[u8],
+        background: &[u8; SCREEN_WIDTH],
+    ) -> [Option<u8>; SCREEN_WIDTH] {
+        let mut line = [None; SCREEN_WIDTH];
+
+        if !self.lcdc.contains(Lcdc::OBJ_ENABLE) {
+            return line;
+        }
+
+        let height = self.sprite_height();
+        let mut sprites = self.scan_oam(oam);
+
+        // On the DMG the sprite with the lowest X wins, then the lowest OAM
+        // index, so draw them in that order and let the first one claim each pixel.
+        sprites.sort_by_key(|sprite| (sprite.x, sprite.index));
+
+        // Whether each pixel has been claimed by a higher priority sprite,
+        // even one that ended up hidden behind the background.
+        let mut claimed = [false; SCREEN_WIDTH];
+
+        for sprite in &sprites {
+            let pixels = sprite.row(vram, self.ly, height);
+            let palette = if sprite.attributes.contains(SpriteAttributes::PALETTE) {
+                self.obp1
+            } else {
+                self.obp0
+            };
+
+            for (column, index) in pixels.iter().enumerate() {
+                let x = i16::from(sprite.x) - SPRITE_X_OFFSET + column as i16;
+
+                // Colour 0 is transparent
+                if !(0..SCREEN_WIDTH as i16).contains(&x) || *index == 0 {
+                    continue;
+                }
+
+                let x = x as usize;
+
+                if claimed[x] {
+                    continue;
+                }
+
+                claimed[x] = true;
+
+                let hidden =
+                    sprite.attributes.contains(SpriteAttributes::BG_PRIORITY) && background[x] != 0;
+
+                if !hidden {
+                    line[x] = Some(apply_palette(palette, *index));
+                }
+            }
+        }
+
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{tiles::encode_tile, LCDC, OBP0, OBP1};
+    use super::*;
+
+    const OBJ_LCDC: Lcdc = Lcdc::from_bits_truncate(
+        Lcdc::LCD_ENABLE.bits | Lcdc::TILE_DATA.bits | Lcdc::BG_ENABLE.bits | Lcdc::OBJ_ENABLE.bits,
+    );
+
+    /// VRAM with a few distinguishable sprite tiles:
+    /// - Tile 1 is solid colour 1
+    /// - Tile 2 is solid colour 2
+    /// - Tile 3 has colour 3 in its top-left pixel and is otherwise transparent
+    /// - Tile 4 is solid colour 3
+    /// - Tile 5 is transparent on the left half and colour 1 on the right
+    fn vram() -> Vec<u8> {
+        let mut vram = vec![0; 0x2000];
+        let tiles = [
+            encode_tile(|_, _| 1),
+            encode_tile(|_, _| 2),
+            encode_tile(|x, y| if x == 0 && y == 0 { 3 } else { 0 }),
+            encode_tile(|_, _| 3),
+            encode_tile(|x, _| if x < 4 { 0 } else { 1 }),
+        ];
+
+        for (index, tile) in tiles.iter().enumerate() {
+            let address = (index + 1) * TILE_SIZE;
+            vram[address..address + TILE_SIZE].copy_from_slice(tile);
+        }
+
+        vram
+    }
+
+    /// Builds OAM from `(y, x, tile, attributes)` entries, leaving the rest
+    /// off-screen.
+    fn oam(entries: &[(u8, u8, u8, u8)]) -> Vec<u8> {
+        let mut oam = vec![0; OAM_ENTRIES * 4];
+
+        for (index, (y, x, tile, attributes)) in entries.iter().enumerate() {
+            oam[index * 4..index * 4 + 4].copy_from_slice(&[*y, *x, *tile, *attributes]);
+        }
+
+        oam
+    }
+
+    fn ppu(lcdc: Lcdc, ly: u8) -> Ppu {
+        let mut ppu = Ppu::default();
+        ppu.write(LCDC, lcdc.bits);
+        ppu.write(OBP0, 0b11_10_01_00);
+        ppu.write(OBP1, 0b00_01_10_11);
+        ppu.ly = ly;
+        ppu
+    }
+
+    fn render(ppu: &Ppu, oam: &[u8]) -> [Option<u8>; SCREEN_WIDTH] {
+        ppu.render_sprites(&vram(), oam, &[0; SCREEN_WIDTH])
+    }
+
+    #[test]
+    fn it_offsets_sprite_positions() {
+        let ppu = ppu(OBJ_LCDC, 0);
+        let line = render(&ppu, &oam(&[(16, 8, 1, 0)]));
+
+        assert_eq!([Some(1); 8], line[0..8]);
+        assert_eq!(None, line[8]);
+    }
+
+    #[test]
+    fn it_clips_sprites_at_the_edges() {
+        let ppu = ppu(OBJ_LCDC, 0);
+        let line = render(&ppu, &oam(&[(16, 4, 1, 0), (16, 164, 2, 0)]));
+
+        assert_eq!([Some(1); 4], line[0..4]);
+        assert_eq!(None, line[4]);
+        assert_eq!(None, line[155]);
+        assert_eq!([Some(2); 4], line[156..160]);
+    }
+
+    #[test]
+    fn it_only_draws_sprites_when_enabled() {
+        let ppu = ppu(OBJ_LCDC - Lcdc::OBJ_ENABLE, 0);
+        let line = render(&ppu, &oam(&[(16, 8, 1, 0)]));
+
+        assert_eq!([None; SCREEN_WIDTH], line);
+    }
+
+    #[test]
+    fn it_limits_sprites_to_ten_per_line() {
+        let entries: Vec<_> = (0..12).map(|i| (16, 8 + i * 8, 1, 0)).collect();
+        let ppu = ppu(OBJ_LCDC, 0);
+
+        assert_eq!(10, ppu.scan_oam(&oam(&entries)).len());
+
+        let line = render(&ppu, &oam(&entries));
+        assert_eq!(Some(1), line[79]);
+        assert_eq!(None, line[80]);
+    }
+
+    #[test]
+    fn it_counts_off_screen_sprites_towards_the_limit() {
+        // Ten sprites hidden at X=0 still use up the line's slots
+        let mut entries: Vec<_> = (0..10).map(|_| (16, 0, 1, 0)).collect();
+        entries.push((16, 8, 1, 0));
+
+        let ppu = ppu(OBJ_LCDC, 0);
+        let line = render(&ppu, &oam(&entries));
+
+        assert_eq!([None; SCREEN_WIDTH], line);
+    }
+
+    #[test]
+    fn it_draws_tall_sprites() {
+        let entries = [(16, 8, 1, 0)];
+
+        // The low bit is ignored, so the top half is tile 0 and the bottom tile 1
+        let ppu_top = ppu(OBJ_LCDC | Lcdc::OBJ_SIZE, 0);
+        assert_eq!(None, render(&ppu_top, &oam(&entries))[0]);
+
+        let ppu_bottom = ppu(OBJ_LCDC | Lcdc::OBJ_SIZE, 15);
+        assert_eq!(Some(1), render(&ppu_bottom, &oam(&entries))[0]);
+
+        let ppu_below = ppu(OBJ_LCDC | Lcdc::OBJ_SIZE, 16);
+        assert_eq!(None, render(&ppu_below, &oam(&entries))[0]);
+
+        // 8x8 sprites stop after the first tile
+        let ppu_short = ppu(OBJ_LCDC, 8);
+        assert_eq!(None, render(&ppu_short, &oam(&entries))[0]);
+    }
+
+    #[test]
+    fn it_flips_sprites() {
+        let x_flip = SpriteAttributes::X_FLIP.bits;
+        let y_flip = SpriteAttributes::Y_FLIP.bits;
+        let entries = [
+            (16, 8, 3, 0),
+            (16, 16, 3, x_flip),
+            (16, 24, 3, y_flip),
+            (16, 32, 3, x_flip | y_flip),
+        ];
+
+        let top = render(&ppu(OBJ_LCDC, 0), &oam(&entries));
+        assert_eq!(Some(3), top[0]);
+        assert_eq!(Some(3), top[15]);
+        assert_eq!(None, top[16]);
+        assert_eq!(None, top[31]);
+
+        let bottom = render(&ppu(OBJ_LCDC, 7), &oam(&entries));
+        assert_eq!(None, bottom[0]);
+        assert_eq!(None, bottom[15]);
+        assert_eq!(Some(3), bottom[16]);
+        assert_eq!(Some(3), bottom[31]);
+    }
+
+    #[test]
+    fn it_flips_tall_sprites_across_both_tiles() {
+        let entries = [(16, 8, 4, SpriteAttributes::Y_FLIP.bits)];
+
+        // Flipped, the bottom row of the second tile is now on top
+        let top = render(&ppu(OBJ_LCDC | Lcdc::OBJ_SIZE, 0), &oam(&entries));
+        assert_eq!(None, top[0]);
+        assert_eq!(Some(1), top[4]);
+
+        let bottom = render(&ppu(OBJ_LCDC | Lcdc::OBJ_SIZE, 15), &oam(&entries));
+        assert_eq!(Some(3), bottom[0]);
+    }
+
+    #[test]
+    fn it_applies_the_sprite_palettes() {
+        let entries = [(16, 8, 1, 0), (16, 16, 1, SpriteAttributes::PALETTE.bits)];
+        let line = render(&ppu(OBJ_LCDC, 0), &oam(&entries));
+
+        assert_eq!(Some(1), line[0]);
+        assert_eq!(Some(2), line[8]);
+    }
+
+    #[test]
+    fn it_hides_sprites_behind_the_background() {
+        let mut background = [0; SCREEN_WIDTH];
+        background[4..8].fill(2);
+
+        let entries = [
+            (16, 8, 1, SpriteAttributes::BG_PRIORITY.bits),
+            (16, 16, 1, 0),
+        ];
+        let line = ppu(OBJ_LCDC, 0).render_sprites(&vram(), &oam(&entries), &background);
+
+        // Only colour 0 of the background lets the sprite through
+        assert_eq!([Some(1); 4], line[0..4]);
+        assert_eq!([None; 4], line[4..8]);
+
+        // Sprites without the bit are drawn over everything
+        let mut background = [0; SCREEN_WIDTH];
+        background[8..16].fill(3);
+        let line = ppu(OBJ_LCDC, 0).render_sprites(&vram(), &oam(&entries), &background);
+        assert_eq!([Some(1); 8], line[8..16]);
+    }
+
+    #[test]
+    fn it_prioritises_sprites_by_x_coordinate() {
+        // The later entry is further left, so it wins the overlap
+        let entries = [(16, 12, 1, 0), (16, 8, 2, 0)];
+        let line = render(&ppu(OBJ_LCDC, 0), &oam(&entries));
+
+        assert_eq!([Some(2); 8], line[0..8]);
+        assert_eq!([Some(1); 4], line[8..12]);
+    }
+
+    #[test]
+    fn it_prioritises_sprites_by_oam_index_at_the_same_x() {
+        let entries = [(16, 8, 2, 0), (16, 8, 1, 0)];
+        let line = render(&ppu(OBJ_LCDC, 0), &oam(&entries));
+
+        assert_eq!([Some(2); 8], line[0..8]);
+    }
+
+    #[test]
+    fn it_lets_lower_priority_sprites_show_through_transparency() {
+        // Tile 5 is transparent on its left half
+        let entries = [(16, 8, 5, 0), (16, 8, 4, 0)];
+        let line = render(&ppu(OBJ_LCDC, 0), &oam(&entries));
+
+        assert_eq!([Some(3); 4], line[0..4]);
+        assert_eq!([Some(1); 4], line[4..8]);
+    }
+
+    #[test]
+    fn it_hides_lower_priority_sprites_with_the_winner() {
+        // The winning sprite is behind the background, and takes the
+        // sprite underneath it along with it
+        let mut background = [0; SCREEN_WIDTH];
+        background[0..8].fill(1);
+
+        let entries = [
+            (16, 8, 1, SpriteAttributes::BG_PRIORITY.bits),
+            (16, 9, 2, 0),
+        ];
+        let line = ppu(OBJ_LCDC, 0).render_sprites(&vram(), &oam(&entries), &background);
+
+        assert_eq!([None; 8], line[0..8]);
+        assert_eq!(Some(2), line[8]);
+    }
+}
@@ -0,0 +1,125 @@
+use super::Lcdc;
+
+/// The size of a single tile in VRAM, two bytes for each of its eight rows.
+pub(super) const TILE_SIZE: usize = 16;
+/// The width and height of the tile maps, in tiles.
+pub(super) const TILE_MAP_WIDTH: usize = 32;
+
+/// The offset into VRAM of the tile map at 0x9800.
+const TILE_MAP_0: usize = 0x1800;
+/// The offset into VRAM of the tile map at 0x9C00.
+const TILE_MAP_1: usize = 0x1C00;
+/// The offset into VRAM that signed tile indices are relative to (0x9000).
+const SIGNED_TILE_BASE: usize = 0x1000;
+
+/// The offset into VRAM of the selected tile map.
+pub(super) fn tile_map(high: bool) -> usize {
+    if high {
+        TILE_MAP_1
+    } else {
+        TILE_MAP_0
+    }
+}
+
+/// The offset into VRAM of a background or window tile.
+///
+/// With `TILE_DATA` set tiles are indexed unsigned from 0x8000, otherwise
+/// they're indexed signed from 0x9000, so tiles 128-255 are shared between
+/// both modes.
+pub(super) fn bg_tile_address(lcdc: Lcdc, tile: u8) -> usize {
+    if lcdc.contains(Lcdc::TILE_DATA) {
+        usize::from(tile) * TILE_SIZE
+    } else {
+        (SIGNED_TILE_BASE as isize + isize::from(tile as i8) * TILE_SIZE as isize) as usize
+    }
+}
+
+/// Decodes one row of a tile into colour indices, leftmost pixel first.
+///
+/// Each row is two bytes, the first holding the low bit of every pixel
+/// and the second the high bit, with the leftmost pixel in bit 7.
+pub(super) fn decode_row(vram: &[u8], tile_address: usize, row: usize) -> [u8; 8] {
+    let low = vram[tile_address + row * 2];
+    let high = vram[tile_address + row * 2 + 1];
+
+    let mut pixels = [0; 8];
+
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        let bit = 7 - index;
+        *pixel = ((high >> bit) & 1) << 1 | ((low >> bit) & 1);
+    }
+
+    pixels
+}
+
+/// Maps a colour index to a shade through one of the palette registers.
+pub(super) fn apply_palette(palette: u8, index: u8) -> u8 {
+    (palette >> (index * 2)) & 0b11
+}
+
+/// Encodes a tile from a function of each pixel's colour index, the
+/// inverse of [`decode_row`].
+#[cfg(test)]
+pub(super) fn encode_tile(colour: impl Fn(usize, usize) -> u8) -> [u8; TILE_SIZE] {
+    let mut tile = [0; TILE_SIZE];
+
+    for y in 0..8 {
+        for x in 0..8 {
+            let index = colour(x, y);
+            tile[y * 2] |= (index & 1) << (7 - x);
+            tile[y * 2 + 1] |= ((index >> 1) & 1) << (7 - x);
+        }
+    }
+
+    tile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_decodes_rows() {
+        let vram = [0b1010_0101, 0b1100_0011];
+
+        assert_eq!([3, 2, 1, 0, 0, 1, 2, 3], decode_row(&vram, 0, 0));
+    }
+
+    #[test]
+    fn it_round_trips_tiles() {
+        let tile = encode_tile(|x, y| ((x + y) % 4) as u8);
+
+        for y in 0..8 {
+            let row = decode_row(&tile, 0, y);
+
+            for (x, index) in row.iter().enumerate() {
+                assert_eq!(((x + y) % 4) as u8, *index);
+            }
+        }
+    }
+
+    #[test]
+    fn it_addresses_tiles_unsigned() {
+        assert_eq!(0x0000, bg_tile_address(Lcdc::TILE_DATA, 0));
+        assert_eq!(0x0800, bg_tile_address(Lcdc::TILE_DATA, 0x80));
+        assert_eq!(0x0FF0, bg_tile_address(Lcdc::TILE_DATA, 0xFF));
+    }
+
+    #[test]
+    fn it_addresses_tiles_signed() {
+        assert_eq!(0x1000, bg_tile_address(Lcdc::empty(), 0));
+        assert_eq!(0x17F0, bg_tile_address(Lcdc::empty(), 0x7F));
+        assert_eq!(0x0800, bg_tile_address(Lcdc::empty(), 0x80));
+        assert_eq!(0x0FF0, bg_tile_address(Lcdc::empty(), 0xFF));
+    }
+
+    #[test]
+    fn it_applies_palettes() {
+        let palette = 0b00_01_10_11;
+
+        assert_eq!(3, apply_palette(palette, 0));
+        assert_eq!(2, apply_palette(palette, 1));
+        assert_eq!(1, apply_palette(palette, 2));
+        assert_eq!(0, apply_palette(palette, 3));
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+
+/// The other end of the link cable.
+///
+/// A transfer swaps the contents of both sides' shift registers, with one
+/// side driving the clock and the other following it. Rather than passing
+/// individual bits over the cable, whole bytes are exchanged at the start
+/// of a transfer and shifted in as the clock runs.
+pub trait LinkCable: Send {
+    /// Starts a transfer on this side's internal clock, sending `outgoing`
+    /// and returning the byte the other side sent back.
+    ///
+    /// Nothing answers when the other side isn't listening, and the line
+    /// floats high, so this should return 0xFF.
+    fn exchange(&mut self, outgoing: u8) -> u8;
+
+    /// Called every step of the emulation with how many T-cycles have
+    /// passed, so the cable can keep time with the other side.
+    ///
+    /// `listening` holds the byte in SB while this side is waiting on an
+    /// external clock. If the other side has clocked a transfer, that byte
+    /// is sent to it, and the byte it sent is returned.
+    ///
+    /// Peripherals that never drive the clock can leave this as it is.
+    fn poll(&mut self, cycles: u64, listening: Option<u8>) -> Option<u8> {
+        let _ = (cycles, listening);
+        None
+    }
+}
+
+/// Connects the serial port to itself, so every byte sent comes straight
+/// back.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Loopback;
+
+impl LinkCable for Loopback {
+    fn exchange(&mut self, outgoing: u8) -> u8 {
+        outgoing
+    }
+}
+
+/// Answers transfers with a prerecorded sequence of bytes, and records the
+/// bytes sent in return.
+///
+/// Once the recording runs out it answers with 0xFF, as if the other side
+/// had been unplugged.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecordedStream {
+    incoming: VecDeque<u8>,
+    sent: Vec<u8>,
+}
+
+impl RecordedStream {
+    pub fn new<I>(incoming: I) -> Self
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        Self {
+            incoming: incoming.into_iter().collect(),
+            sent: Vec::new(),
+        }
+    }
+
+    /// The bytes sent so far.
+    pub fn sent(&self) -> &[u8] {
+        &self.sent
+    }
+
+    /// Whether every recorded byte has been sent back.
+    pub fn is_finished(&self) -> bool {
+        self.incoming.is_empty()
+    }
+}
+
+impl LinkCable for RecordedStream {
+    fn exchange(&mut self, outgoing: u8) -> u8 {
+        self.sent.push(outgoing);
+        self.incoming.pop_front().unwrap_or(0xFF)
+    }
+}
+
+/// Collects every byte sent over the serial port, as test ROMs print their
+/// results that way.
+///
+/// Nothing answers, so every transfer reads back 0xFF.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SerialCapture {
+    bytes: Vec<u8>,
+}
+
+impl SerialCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// What's been sent, as text. Anything that isn't valid UTF-8 is
+    /// replaced.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).into_owned()
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+}
+
+impl LinkCable for SerialCapture {
+    fn exchange(&mut self, outgoing: u8) -> u8 {
+        self.bytes.push(outgoing);
+        0xFF
+    }
+}
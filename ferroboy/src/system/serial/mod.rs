@@ -0,0 +1,310 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::system::Interrupts;
+
+mod cable;
+
+pub use cable::{LinkCable, Loopback, RecordedStream, SerialCapture};
+
+pub(crate) const SB: u16 = 0xFF01;
+pub(crate) const SC: u16 = 0xFF02;
+
+/// Set in SC to start a transfer, and cleared once it's done.
+const TRANSFER_START: u8 = 0b1000_0000;
+/// Set in SC to drive the clock from this side.
+const INTERNAL_CLOCK: u8 = 0b0000_0001;
+/// How many T-cycles each bit takes on the internal 8192 Hz clock.
+const CYCLES_PER_BIT: u16 = 512;
+
+/// An implementation of the DMG-01's serial port.
+///
+/// SB is a shift register that's swapped with the other side's one bit at a
+/// time, most significant first, on every tick of the serial clock. Setting
+/// bit 7 of SC starts a transfer, with bit 0 choosing whether this side
+/// drives the clock at 8192 Hz, or waits for the other side to. Either way
+/// the serial interrupt fires once all eight bits have been shifted.
+#[derive(Clone, Default)]
+pub struct Serial {
+    sb: u8,
+    sc: u8,
+    /// The byte being shifted in from the other side.
+    incoming: u8,
+    /// How many bits are left to shift, or 0 if no byte is on the way.
+    bits_remaining: u8,
+    /// T-cycles since the last bit was shifted.
+    counter: u16,
+    cable: Option<Arc<Mutex<dyn LinkCable>>>,
+}
+
+impl Serial {
+    /// Whether a transfer has been started and hasn't finished yet.
+    pub fn is_transferring(&self) -> bool {
+        self.sc & TRANSFER_START != 0
+    }
+
+    /// Whether a link cable is plugged in.
+    pub fn is_connected(&self) -> bool {
+        self.cable.is_some()
+    }
+
+    pub(crate) fn connect(&mut self, cable: Arc<Mutex<dyn LinkCable>>) {
+        self.cable = Some(cable);
+    }
+
+    pub(crate) fn disconnect(&mut self) -> Option<Arc<Mutex<dyn LinkCable>>> {
+        self.cable.take()
+    }
+
+    pub(crate) fn read(&self, address: u16) -> u8 {
+        match address {
+            SB => self.sb,
+            // Only the start and clock bits of SC are used
+            SC => 0x7E | self.sc,
+            _ => 0xFF,
+        }
+    }
+
+    pub(crate) fn write(&mut self, address: u16, value: u8) {
+        match address {
+            SB => self.sb = value,
+            SC => {
+                self.sc = value & (TRANSFER_START | INTERNAL_CLOCK);
+                self.bits_remaining = 0;
+                self.counter = 0;
+
+                if self.sc == TRANSFER_START | INTERNAL_CLOCK {
+                    let outgoing = self.sb;
+                    let incoming = self.with_cable(|cable| cable.exchange(outgoing));
+                    self.receive(incoming.unwrap_or(0xFF));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Advances the serial clock by a number of T-cycles, returning the
+    /// interrupts it requested.
+    pub(crate) fn step(&mut self, cycles: u64) -> Interrupts {
+        let listening = self.is_listening().then(|| self.sb);
+        let incoming = self
+            .with_cable(|cable| cable.poll(cycles, listening))
+            .flatten();
+
+        if let Some(incoming) = incoming {
+            self.receive(incoming);
+        }
+
+        if self.bits_remaining == 0 {
+            return Interrupts::empty();
+        }
+
+        let mut interrupts = Interrupts::empty();
+
+        for _ in 0..cycles {
+            interrupts |= self.tick();
+        }
+
+        interrupts
+    }
+
+    /// Whether a transfer is waiting on the other side to drive the clock.
+    fn is_listening(&self) -> bool {
+        self.sc == TRANSFER_START && self.bits_remaining == 0
+    }
+
+    fn tick(&mut self) -> Interrupts {
+        if self.bits_remaining == 0 {
+            return Interrupts::empty();
+        }
+
+        self.counter += 1;
+
+        if self.counter < CYCLES_PER_BIT {
+            return Interrupts::empty();
+        }
+
+        self.counter = 0;
+        self.sb = (self.sb << 1) | (self.incoming >> 7);
+        self.incoming <<= 1;
+        self.bits_remaining -= 1;
+
+        if self.bits_remaining == 0 {
+            self.sc &= !TRANSFER_START;
+            Interrupts::SERIAL
+        } else {
+            Interrupts::empty()
+        }
+    }
+
+    /// Starts shifting in a byte from the other side.
+    fn receive(&mut self, incoming: u8) {
+        self.incoming = incoming;
+        self.bits_remaining = 8;
+        self.counter = 0;
+    }
+
+    fn with_cable<T, F>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut dyn LinkCable) -> T,
+    {
+        self.cable.as_ref().map(|cable| {
+            let mut cable = cable.lock().unwrap_or_else(PoisonError::into_inner);
+            f(&mut *cable)
+        })
+    }
+}
+
+impl std::fmt::Debug for Serial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Serial")
+            .field("sb", &self.sb)
+            .field("sc", &self.sc)
+            .field("incoming", &self.incoming)
+            .field("bits_remaining", &self.bits_remaining)
+            .field("counter", &self.counter)
+            .field("connected", &self.is_connected())
+            .finish()
+    }
+}
+
+impl PartialEq for Serial {
+    fn eq(&self, other: &Self) -> bool {
+        let same_cable = match (&self.cable, &other.cable) {
+            (Some(cable), Some(other)) => Arc::ptr_eq(cable, other),
+            (None, None) => true,
+            _ => false,
+        };
+
+        same_cable
+            && self.sb == other.sb
+            && self.sc == other.sc
+            && self.incoming == other.incoming
+            && self.bits_remaining == other.bits_remaining
+            && self.counter == other.counter
+    }
+}
+
+impl Eq for Serial {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cable whose other side clocks a transfer as soon as it's polled.
+    struct Master(u8);
+
+    impl LinkCable for Master {
+        fn exchange(&mut self, _outgoing: u8) -> u8 {
+            0xFF
+        }
+
+        fn poll(&mut self, _cycles: u64, listening: Option<u8>) -> Option<u8> {
+            listening.map(|_| self.0)
+        }
+    }
+
+    fn serial<C: LinkCable + 'static>(cable: C) -> Serial {
+        let mut serial = Serial::default();
+        serial.connect(Arc::new(Mutex::new(cable)));
+        serial
+    }
+
+    #[test]
+    fn it_reads_unused_bits_high() {
+        let mut serial = Serial::default();
+        assert_eq!(0x7E, serial.read(SC));
+
+        serial.write(SC, 0xFF);
+        assert_eq!(0xFF, serial.read(SC));
+    }
+
+    #[test]
+    fn it_shifts_in_a_bit_every_512_cycles() {
+        let mut serial = serial(RecordedStream::new([0b1010_0000]));
+        serial.write(SB, 0x0F);
+        serial.write(SC, 0x81);
+
+        serial.step(511);
+        assert_eq!(0x0F, serial.read(SB));
+
+        serial.step(1);
+        assert_eq!(0x1F, serial.read(SB));
+
+        serial.step(512);
+        assert_eq!(0x3E, serial.read(SB));
+        assert!(serial.is_transferring());
+    }
+
+    #[test]
+    fn it_interrupts_when_a_transfer_completes() {
+        let mut serial = serial(Loopback);
+        serial.write(SB, 0x42);
+        serial.write(SC, 0x81);
+
+        assert!(serial.step(4095).is_empty());
+        assert_eq!(Interrupts::SERIAL, serial.step(1));
+        assert_eq!(0x42, serial.read(SB));
+        assert_eq!(0x7F, serial.read(SC));
+    }
+
+    #[test]
+    fn it_reads_0xff_with_nothing_connected() {
+        let mut serial = Serial::default();
+        serial.write(SB, 0x00);
+        serial.write(SC, 0x81);
+
+        assert_eq!(Interrupts::SERIAL, serial.step(4096));
+        assert_eq!(0xFF, serial.read(SB));
+    }
+
+    #[test]
+    fn it_records_what_was_sent() {
+        let cable = Arc::new(Mutex::new(RecordedStream::new([0x12])));
+        let mut serial = Serial::default();
+        serial.connect(cable.clone());
+
+        for byte in [0xAB, 0xCD] {
+            serial.write(SB, byte);
+            serial.write(SC, 0x81);
+            serial.step(4096);
+        }
+
+        let cable = cable.lock().unwrap();
+        assert_eq!(&[0xAB, 0xCD], cable.sent());
+        assert!(cable.is_finished());
+        assert_eq!(0xFF, serial.read(SB));
+    }
+
+    #[test]
+    fn it_captures_what_was_sent() {
+        let cable = Arc::new(Mutex::new(SerialCapture::new()));
+        let mut serial = Serial::default();
+        serial.connect(cable.clone());
+
+        for byte in b"Passed" {
+            serial.write(SB, *byte);
+            serial.write(SC, 0x81);
+            serial.step(4096);
+        }
+
+        assert_eq!("Passed", cable.lock().unwrap().text());
+        assert_eq!(0xFF, serial.read(SB));
+    }
+
+    #[test]
+    fn it_waits_for_an_external_clock() {
+        let mut serial = Serial::default();
+        serial.write(SC, 0x80);
+
+        assert!(serial.step(10_000).is_empty());
+        assert!(serial.is_transferring());
+
+        let mut serial = self::serial(Master(0x99));
+        serial.write(SB, 0x33);
+        serial.write(SC, 0x80);
+
+        assert_eq!(Interrupts::SERIAL, serial.step(4096));
+        assert_eq!(0x99, serial.read(SB));
+        assert!(!serial.is_transferring());
+    }
+}
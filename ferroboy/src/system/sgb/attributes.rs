@@ -0,0 +1,280 @@
+use std::cmp::Ordering;
+
+/// The width of the screen, in tiles.
+pub(super) const WIDTH: usize = 20;
+/// The height of the screen, in tiles.
+pub(super) const HEIGHT: usize = 18;
+/// The size of an attribute file, with two bits for every tile.
+pub(super) const ATTRIBUTE_FILE_SIZE: usize = WIDTH * HEIGHT / 4;
+
+/// Which of the four SGB palettes each tile on screen is coloured with.
+///
+/// The ATTR commands each describe a different way of painting palettes
+/// onto the map, and only touch the tiles they cover.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct AttributeMap {
+    palettes: Vec<u8>,
+}
+
+impl Default for AttributeMap {
+    fn default() -> Self {
+        Self {
+            palettes: vec![0; WIDTH * HEIGHT],
+        }
+    }
+}
+
+impl AttributeMap {
+    /// The palette of the tile at the given tile coordinates.
+    pub(super) fn palette(&self, x: usize, y: usize) -> u8 {
+        self.palettes[y * WIDTH + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize, palette: u8) {
+        if x < WIDTH && y < HEIGHT {
+            self.palettes[y * WIDTH + x] = palette & 0b11;
+        }
+    }
+
+    /// ATTR_BLK, which colours the inside, border and outside of
+    /// rectangles.
+    ///
+    /// Each of the data sets is a control byte choosing which of the three
+    /// to change, their palettes, and the rectangle's corners.
+    pub(super) fn apply_blocks(&mut self, data: &[u8]) {
+        let count = usize::from(data[1]);
+
+        for block in data[2..].chunks_exact(6).take(count) {
+            self.apply_block(block);
+        }
+    }
+
+    fn apply_block(&mut self, block: &[u8]) {
+        let control = block[0] & 0b111;
+        let palette = |shift: u8| (block[1] >> shift) & 0b11;
+        let corner = |index: usize| usize::from(block[index] & 0x1F);
+        let (left, top, right, bottom) = (corner(2), corner(3), corner(4), corner(5));
+
+        let inside = if control & 0b001 != 0 {
+            Some(palette(0))
+        } else {
+            None
+        };
+        let outside = if control & 0b100 != 0 {
+            Some(palette(4))
+        } else {
+            None
+        };
+        // When only one of the inside and outside is changed, the border
+        // goes along with it
+        let border = match control {
+            0b001 => inside,
+            0b100 => outside,
+            _ if control & 0b010 != 0 => Some(palette(2)),
+            _ => None,
+        };
+
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let region = if left < x && x < right && top < y && y < bottom {
+                    inside
+                } else if (left..=right).contains(&x) && (top..=bottom).contains(&y) {
+                    border
+                } else {
+                    outside
+                };
+
+                if let Some(palette) = region {
+                    self.set(x, y, palette);
+                }
+            }
+        }
+    }
+
+    /// ATTR_LIN, which colours whole rows and columns.
+    ///
+    /// Each data set is a byte, with the row or column in the low five
+    /// bits, the palette in the next two, and bit 7 set for a row.
+    pub(super) fn apply_lines(&mut self, data: &[u8]) {
+        let count = usize::from(data[1]);
+
+        for line in data[2..].iter().take(count) {
+            let index = usize::from(line & 0x1F);
+            let palette = (line >> 5) & 0b11;
+
+            if line & 0x80 != 0 {
+                (0..WIDTH).for_each(|x| self.set(x, index, palette));
+            } else {
+                (0..HEIGHT).for_each(|y| self.set(index, y, palette));
+            }
+        }
+    }
+
+    /// ATTR_DIV, which splits the screen in two along a row or column,
+    /// with a third palette for the dividing line itself.
+    pub(super) fn divide(&mut self, data: &[u8]) {
+        let palette = |shift: u8| (data[1] >> shift) & 0b11;
+        let (after, before, on) = (palette(0), palette(2), palette(4));
+        let rows = data[1] & 0x40 != 0;
+        let line = usize::from(data[2] & 0x1F);
+
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let position = if rows { y } else { x };
+                let palette = match position.cmp(&line) {
+                    Ordering::Less => before,
+                    Ordering::Equal => on,
+                    Ordering::Greater => after,
+                };
+
+                self.set(x, y, palette);
+            }
+        }
+    }
+
+    /// ATTR_CHR, which colours tiles one at a time from a starting point,
+    /// either across rows or down columns.
+    pub(super) fn apply_characters(&mut self, data: &[u8]) {
+        let (mut x, mut y) = (usize::from(data[1] & 0x1F), usize::from(data[2] & 0x1F));
+        let count = usize::from(u16::from_le_bytes([data[3], data[4]])).min(WIDTH * HEIGHT);
+        let columns = data[5] & 0x01 != 0;
+        let palettes = &data[6..];
+
+        for index in 0..count.min(palettes.len() * 4) {
+            if x >= WIDTH || y >= HEIGHT {
+                break;
+            }
+
+            self.set(x, y, unpack(palettes, index));
+
+            if columns {
+                y += 1;
+
+                if y == HEIGHT {
+                    y = 0;
+                    x += 1;
+                }
+            } else {
+                x += 1;
+
+                if x == WIDTH {
+                    x = 0;
+                    y += 1;
+                }
+            }
+        }
+    }
+
+    /// Replaces the whole map with an attribute file, as ATTR_SET and
+    /// PAL_SET do.
+    pub(super) fn load_file(&mut self, file: &[u8]) {
+        for (index, palette) in self.palettes.iter_mut().enumerate() {
+            *palette = unpack(file, index);
+        }
+    }
+}
+
+/// Palettes packed four to a byte, the first in the top two bits.
+fn unpack(palettes: &[u8], index: usize) -> u8 {
+    (palettes[index / 4] >> (6 - 2 * (index % 4))) & 0b11
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(map: &AttributeMap) -> Vec<String> {
+        (0..HEIGHT)
+            .map(|y| (0..WIDTH).map(|x| map.palette(x, y).to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn it_applies_blocks() {
+        let mut map = AttributeMap::default();
+
+        #[rustfmt::skip]
+        map.apply_blocks(&[
+            0x21, 2,
+            0b111, 0b10_01_11, 1, 1, 4, 3,
+            // Only the inside, which takes the border with it
+            0b001, 0b00_00_01, 10, 10, 11, 11,
+        ]);
+
+        let rows = rows(&map);
+        assert_eq!("22222222222222222222", rows[0]);
+        assert_eq!("21111222222222222222", rows[1]);
+        assert_eq!("21331222222222222222", rows[2]);
+        assert_eq!("21111222222222222222", rows[3]);
+        assert_eq!("22222222221122222222", rows[10]);
+        assert_eq!("22222222221122222222", rows[11]);
+    }
+
+    #[test]
+    fn it_leaves_regions_that_arent_chosen() {
+        let mut map = AttributeMap::default();
+        map.apply_blocks(&[0x21, 1, 0b010, 0b00_11_00, 0, 0, 2, 2]);
+
+        let rows = rows(&map);
+        assert_eq!("33300000000000000000", rows[0]);
+        assert_eq!("30300000000000000000", rows[1]);
+        assert_eq!("33300000000000000000", rows[2]);
+    }
+
+    #[test]
+    fn it_applies_lines() {
+        let mut map = AttributeMap::default();
+        // Row 1 in palette 1, then column 3 in palette 2
+        map.apply_lines(&[0x29, 2, 0xA1, 0x43]);
+
+        let rows = rows(&map);
+        assert_eq!("00020000000000000000", rows[0]);
+        assert_eq!("11121111111111111111", rows[1]);
+        assert_eq!("00020000000000000000", rows[17]);
+    }
+
+    #[test]
+    fn it_divides_the_screen() {
+        let mut map = AttributeMap::default();
+        map.divide(&[0x31, 0b0_11_10_01, 5]);
+
+        assert_eq!("22222311111111111111", rows(&map)[0]);
+
+        map.divide(&[0x31, 0b1_00_01_10, 1]);
+
+        let rows = rows(&map);
+        assert_eq!("11111111111111111111", rows[0]);
+        assert_eq!("00000000000000000000", rows[1]);
+        assert_eq!("22222222222222222222", rows[2]);
+    }
+
+    #[test]
+    fn it_applies_characters() {
+        let mut map = AttributeMap::default();
+        map.apply_characters(&[0x39, 18, 0, 5, 0, 0, 0b11_10_01_11, 0b10_000000]);
+
+        assert_eq!("00000000000000000032", rows(&map)[0]);
+        assert_eq!("13200000000000000000", rows(&map)[1]);
+
+        map.apply_characters(&[0x39, 0, 16, 3, 0, 1, 0b01_10_11_00]);
+
+        let rows = rows(&map);
+        assert_eq!("10000000000000000000", rows[16]);
+        assert_eq!("20000000000000000000", rows[17]);
+        assert_eq!("03000000000000000032", rows[0]);
+    }
+
+    #[test]
+    fn it_loads_attribute_files() {
+        let mut file = [0u8; ATTRIBUTE_FILE_SIZE];
+        file[0] = 0b00_01_10_11;
+        file[ATTRIBUTE_FILE_SIZE - 1] = 0b11_11_11_11;
+
+        let mut map = AttributeMap::default();
+        map.load_file(&file);
+
+        let rows = rows(&map);
+        assert_eq!("01230000000000000000", rows[0]);
+        assert_eq!("00000000000000003333", rows[17]);
+    }
+}
@@ -0,0 +1,405 @@
+mod attributes;
+mod packet;
+
+use crate::system::{Colour, Palette};
+
+use attributes::{AttributeMap, ATTRIBUTE_FILE_SIZE};
+use packet::{PacketReceiver, PACKET_SIZE};
+
+#[cfg(test)]
+pub(crate) use packet::pulses;
+
+const PAL01: u8 = 0x00;
+const PAL23: u8 = 0x01;
+const PAL03: u8 = 0x02;
+const PAL12: u8 = 0x03;
+const ATTR_BLK: u8 = 0x04;
+const ATTR_LIN: u8 = 0x05;
+const ATTR_DIV: u8 = 0x06;
+const ATTR_CHR: u8 = 0x07;
+const PAL_SET: u8 = 0x0A;
+const PAL_TRN: u8 = 0x0B;
+const MLT_REQ: u8 = 0x11;
+const ATTR_TRN: u8 = 0x15;
+const ATTR_SET: u8 = 0x16;
+
+/// How many tiles a VRAM transfer copies from the screen.
+pub(crate) const TRANSFER_TILES: usize = 256;
+/// The palettes PAL_TRN uploads, four RGB555 colours each.
+const SYSTEM_PALETTE_COUNT: usize = 512;
+const SYSTEM_PALETTE_SIZE: usize = 8;
+/// The attribute files ATTR_TRN uploads.
+const ATTRIBUTE_FILE_COUNT: usize = 45;
+
+/// P15, which moves on to the next joypad when it goes high in
+/// multiplayer mode.
+const SELECT_BUTTONS: u8 = 0x20;
+/// Both of P1's select lines.
+const SELECT_MASK: u8 = 0x30;
+
+/// An implementation of the Super Game Boy's colouring.
+///
+/// Games talk to the SGB by sending packets over the joypad register, a
+/// bit at a time. Commands are one to seven packets long, the length being
+/// in the low three bits of the first byte and the command in the rest.
+///
+/// The screen is coloured with four palettes, chosen for each tile by an
+/// attribute map. Colour 0 is shared by every palette. Palettes and
+/// attribute maps can also be uploaded in bulk through VRAM, by putting
+/// the data on screen as tiles and sending PAL_TRN or ATTR_TRN, then
+/// picked from with PAL_SET and ATTR_SET.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sgb {
+    receiver: PacketReceiver,
+    /// The packets of a command that's still arriving.
+    command: Vec<u8>,
+    palettes: [[Colour; 4]; 4],
+    system_palettes: Vec<u8>,
+    attribute_files: Vec<u8>,
+    attributes: AttributeMap,
+    /// How many joypads MLT_REQ asked for.
+    players: u8,
+    /// The joypad P1 is reading.
+    player: u8,
+    /// The select bits last written to P1.
+    select: u8,
+}
+
+impl Sgb {
+    /// Starts with every palette set to `palette`, until the game sends
+    /// its own.
+    pub(crate) fn new(palette: Palette) -> Self {
+        Self {
+            receiver: PacketReceiver::default(),
+            command: Vec::new(),
+            palettes: [*palette.colours(); 4],
+            system_palettes: vec![0; SYSTEM_PALETTE_COUNT * SYSTEM_PALETTE_SIZE],
+            attribute_files: vec![0; ATTRIBUTE_FILE_COUNT * ATTRIBUTE_FILE_SIZE],
+            attributes: AttributeMap::default(),
+            players: 1,
+            player: 0,
+            select: SELECT_MASK,
+        }
+    }
+
+    /// One of the four palettes the screen is coloured with.
+    pub fn palette(&self, index: usize) -> Palette {
+        Palette::new(self.palettes[index])
+    }
+
+    /// The palette a pixel is coloured with, according to the attribute map.
+    pub fn palette_at(&self, x: usize, y: usize) -> Palette {
+        self.palette(usize::from(self.attributes.palette(x / 8, y / 8)))
+    }
+
+    /// The joypad P1 is reading, which only changes once a game has asked
+    /// for more than one with MLT_REQ.
+    pub fn player(&self) -> u8 {
+        self.player
+    }
+
+    /// Adjusts a read of P1. With neither half of the matrix selected the
+    /// low nibble holds the joypad's ID, which games use to detect the SGB,
+    /// and only the first joypad has anything held.
+    pub(crate) fn read(&self, value: u8) -> u8 {
+        if value & SELECT_MASK == SELECT_MASK {
+            (value & 0xF0) | (0x0F - self.player)
+        } else if self.player != 0 {
+            value | 0x0F
+        } else {
+            value
+        }
+    }
+
+    /// Handles a write to P1, running any command it completes.
+    ///
+    /// `screen` reads the tiles on screen, for the commands that transfer
+    /// data through VRAM.
+    pub(crate) fn write<F>(&mut self, value: u8, screen: F)
+    where
+        F: FnOnce() -> Vec<u8>,
+    {
+        let select = value & SELECT_MASK;
+
+        if self.select & SELECT_BUTTONS == 0 && select & SELECT_BUTTONS != 0 {
+            self.player = (self.player + 1) % self.players;
+        }
+
+        self.select = select;
+
+        if let Some(packet) = self.receiver.write(select) {
+            self.receive(packet, screen);
+        }
+    }
+
+    fn receive<F>(&mut self, packet: [u8; PACKET_SIZE], screen: F)
+    where
+        F: FnOnce() -> Vec<u8>,
+    {
+        // A command has to be at least a packet long
+        if self.command.is_empty() && packet[0] & 0b111 == 0 {
+            return;
+        }
+
+        self.command.extend_from_slice(&packet);
+
+        if self.command.len() >= usize::from(self.command[0] & 0b111) * PACKET_SIZE {
+            let command = std::mem::take(&mut self.command);
+            self.execute(&command, screen);
+        }
+    }
+
+    fn execute<F>(&mut self, data: &[u8], screen: F)
+    where
+        F: FnOnce() -> Vec<u8>,
+    {
+        match data[0] >> 3 {
+            PAL01 => self.set_palettes(0, 1, data),
+            PAL23 => self.set_palettes(2, 3, data),
+            PAL03 => self.set_palettes(0, 3, data),
+            PAL12 => self.set_palettes(1, 2, data),
+            ATTR_BLK => self.attributes.apply_blocks(data),
+            ATTR_LIN => self.attributes.apply_lines(data),
+            ATTR_DIV => self.attributes.divide(data),
+            ATTR_CHR => self.attributes.apply_characters(data),
+            PAL_SET => self.set_system_palettes(data),
+            PAL_TRN => transfer(&mut self.system_palettes, &screen()),
+            MLT_REQ => {
+                self.players = match data[1] & 0b11 {
+                    0b01 => 2,
+                    0b11 => 4,
+                    _ => 1,
+                };
+                self.player = 0;
+            }
+            ATTR_TRN => transfer(&mut self.attribute_files, &screen()),
+            ATTR_SET => self.load_attribute_file(data[1]),
+            // Sound, borders, masking and the rest aren't emulated
+            _ => {}
+        }
+    }
+
+    /// PAL01, PAL23, PAL03 and PAL12, which set colour 0 and the other
+    /// three colours of two palettes.
+    fn set_palettes(&mut self, first: usize, second: usize, data: &[u8]) {
+        for (palette, start) in [(first, 3), (second, 9)] {
+            for (shade, colour) in data[start..start + 6].chunks_exact(2).enumerate() {
+                self.palettes[palette][shade + 1] = rgb555(colour);
+            }
+        }
+
+        self.set_colour_0(rgb555(&data[1..3]));
+    }
+
+    /// PAL_SET, which copies four of the palettes uploaded with PAL_TRN,
+    /// and optionally loads an attribute file.
+    fn set_system_palettes(&mut self, data: &[u8]) {
+        for (palette, number) in data[1..9].chunks_exact(2).enumerate() {
+            let number =
+                usize::from(u16::from_le_bytes([number[0], number[1]])) % SYSTEM_PALETTE_COUNT;
+            let start = number * SYSTEM_PALETTE_SIZE;
+            let colours = &self.system_palettes[start..start + SYSTEM_PALETTE_SIZE];
+
+            for (shade, colour) in colours.chunks_exact(2).enumerate() {
+                self.palettes[palette][shade] = rgb555(colour);
+            }
+        }
+
+        self.set_colour_0(self.palettes[0][0]);
+
+        if data[9] & 0x80 != 0 {
+            self.load_attribute_file(data[9]);
+        }
+    }
+
+    fn set_colour_0(&mut self, colour: Colour) {
+        for palette in &mut self.palettes {
+            palette[0] = colour;
+        }
+    }
+
+    /// Replaces the attribute map with one uploaded with ATTR_TRN.
+    fn load_attribute_file(&mut self, number: u8) {
+        let number = usize::from(number & 0x3F);
+
+        if number < ATTRIBUTE_FILE_COUNT {
+            let start = number * ATTRIBUTE_FILE_SIZE;
+            self.attributes
+                .load_file(&self.attribute_files[start..start + ATTRIBUTE_FILE_SIZE]);
+        }
+    }
+}
+
+/// Copies the start of a VRAM transfer over `destination`.
+fn transfer(destination: &mut [u8], data: &[u8]) {
+    let length = destination.len().min(data.len());
+    destination[..length].copy_from_slice(&data[..length]);
+}
+
+/// Converts the SGB's little-endian 15-bit colours, five bits each of red,
+/// green and blue from the bottom up.
+fn rgb555(bytes: &[u8]) -> Colour {
+    let colour = u16::from_le_bytes([bytes[0], bytes[1]]);
+    let channel = |shift: u16| {
+        let value = ((colour >> shift) & 0x1F) as u8;
+        value << 3 | value >> 2
+    };
+
+    Colour::new(channel(0), channel(5), channel(10))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Colour = Colour::from_hex(0xFFFFFF);
+    const RED: Colour = Colour::from_hex(0xFF0000);
+    const GREEN: Colour = Colour::from_hex(0x00FF00);
+    const BLUE: Colour = Colour::from_hex(0x0000FF);
+    const BLACK: Colour = Colour::from_hex(0x000000);
+
+    /// Sends a command, split into packets, with an empty screen.
+    fn send(sgb: &mut Sgb, command: &[u8]) {
+        send_with_screen(sgb, command, &[]);
+    }
+
+    fn send_with_screen(sgb: &mut Sgb, command: &[u8], screen: &[u8]) {
+        for chunk in command.chunks(PACKET_SIZE) {
+            let mut packet = [0; PACKET_SIZE];
+            packet[..chunk.len()].copy_from_slice(chunk);
+
+            for select in pulses(&packet) {
+                sgb.write(select, || screen.to_vec());
+            }
+        }
+    }
+
+    fn sgb() -> Sgb {
+        Sgb::new(Palette::HIGH_CONTRAST)
+    }
+
+    #[test]
+    fn it_starts_with_the_configured_palette() {
+        let sgb = sgb();
+
+        assert_eq!(Palette::HIGH_CONTRAST, sgb.palette(3));
+        assert_eq!(Palette::HIGH_CONTRAST, sgb.palette_at(159, 143));
+    }
+
+    #[test]
+    fn it_sets_palette_pairs() {
+        let mut sgb = sgb();
+
+        #[rustfmt::skip]
+        send(&mut sgb, &[
+            PAL12 << 3 | 1,
+            0xFF, 0x7F,
+            0x1F, 0x00, 0xE0, 0x03, 0x00, 0x7C,
+            0x00, 0x00, 0x1F, 0x00, 0x00, 0x00,
+        ]);
+
+        assert_eq!(Palette::new([WHITE, RED, GREEN, BLUE]), sgb.palette(1));
+        assert_eq!(Palette::new([WHITE, BLACK, RED, BLACK]), sgb.palette(2));
+
+        // Colour 0 is shared with the palettes that weren't set
+        assert_eq!(WHITE, sgb.palette(0).colour(0));
+        assert_eq!(Palette::HIGH_CONTRAST.colour(1), sgb.palette(0).colour(1));
+    }
+
+    #[test]
+    fn it_colours_pixels_by_their_tile() {
+        let mut sgb = sgb();
+        send(&mut sgb, &[PAL23 << 3 | 1, 0, 0, 0x1F, 0x00]);
+        // Palette 2 for the column of tiles at x = 1
+        send(&mut sgb, &[ATTR_LIN << 3 | 1, 1, 0x41]);
+
+        assert_eq!(
+            Palette::HIGH_CONTRAST.colour(1),
+            sgb.palette_at(7, 0).colour(1)
+        );
+        assert_eq!(RED, sgb.palette_at(8, 0).colour(1));
+        assert_eq!(RED, sgb.palette_at(15, 143).colour(1));
+        assert_eq!(
+            Palette::HIGH_CONTRAST.colour(1),
+            sgb.palette_at(16, 0).colour(1)
+        );
+    }
+
+    #[test]
+    fn it_receives_multi_packet_commands() {
+        let mut sgb = sgb();
+
+        // Fifteen rows in palette 3, the last of which is in the second packet
+        let mut command = vec![ATTR_LIN << 3 | 2, 15];
+        command.extend((0..15).map(|row| 0xE0 | row));
+
+        send(&mut sgb, &command[..PACKET_SIZE]);
+        assert_eq!(0, sgb.attributes.palette(0, 0));
+
+        send(&mut sgb, &command[PACKET_SIZE..]);
+        assert_eq!(3, sgb.attributes.palette(0, 0));
+        assert_eq!(3, sgb.attributes.palette(0, 14));
+        assert_eq!(0, sgb.attributes.palette(0, 15));
+    }
+
+    #[test]
+    fn it_ignores_packets_without_a_length() {
+        let mut sgb = sgb();
+        send(&mut sgb, &[ATTR_LIN << 3, 1, 0xE0]);
+
+        assert_eq!(0, sgb.attributes.palette(0, 0));
+    }
+
+    #[test]
+    fn it_sets_palettes_transferred_through_vram() {
+        let mut sgb = sgb();
+
+        let mut screen = vec![0; TRANSFER_TILES * 16];
+        screen[8 * 5..8 * 6].copy_from_slice(&[0x1F, 0x00, 0xE0, 0x03, 0x00, 0x7C, 0, 0]);
+        screen[8 * 7..8 * 8].copy_from_slice(&[0xFF, 0x7F, 0, 0, 0, 0, 0, 0]);
+        send_with_screen(&mut sgb, &[PAL_TRN << 3 | 1], &screen);
+
+        send(&mut sgb, &[PAL_SET << 3 | 1, 5, 0, 7, 0, 5, 0, 5, 0, 0]);
+
+        assert_eq!(Palette::new([RED, GREEN, BLUE, BLACK]), sgb.palette(0));
+        // Colour 0 comes from the first palette
+        assert_eq!(Palette::new([RED, BLACK, BLACK, BLACK]), sgb.palette(1));
+    }
+
+    #[test]
+    fn it_sets_attribute_files_transferred_through_vram() {
+        let mut sgb = sgb();
+
+        let mut screen = vec![0; TRANSFER_TILES * 16];
+        screen[ATTRIBUTE_FILE_SIZE * 2] = 0b11_10_00_00;
+        send_with_screen(&mut sgb, &[ATTR_TRN << 3 | 1], &screen);
+
+        send(&mut sgb, &[ATTR_SET << 3 | 1, 2]);
+        assert_eq!(3, sgb.attributes.palette(0, 0));
+        assert_eq!(2, sgb.attributes.palette(1, 0));
+
+        // PAL_SET can load one too
+        send(&mut sgb, &[PAL_SET << 3 | 1, 0, 0, 0, 0, 0, 0, 0, 0, 0x80]);
+        assert_eq!(0, sgb.attributes.palette(0, 0));
+    }
+
+    #[test]
+    fn it_reports_joypad_ids_in_multiplayer_mode() {
+        let mut sgb = sgb();
+        assert_eq!(0xFF, sgb.read(0xFF));
+
+        send(&mut sgb, &[MLT_REQ << 3 | 1, 0x01]);
+        assert_eq!(0xFF, sgb.read(0xFF));
+
+        sgb.write(0x10, Vec::new);
+        sgb.write(0x30, Vec::new);
+        assert_eq!(0xFE, sgb.read(0xFF));
+        // Nothing's held on the second joypad
+        assert_eq!(0xEF, sgb.read(0xEE));
+
+        sgb.write(0x10, Vec::new);
+        sgb.write(0x30, Vec::new);
+        assert_eq!(0xFF, sgb.read(0xFF));
+        assert_eq!(0xEE, sgb.read(0xEE));
+    }
+}
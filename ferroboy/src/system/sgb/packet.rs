@@ -0,0 +1,144 @@
+/// The size of a single packet, in bytes.
+pub(super) const PACKET_SIZE: usize = 16;
+
+/// Both select lines low, which starts a packet.
+const RESET: u8 = 0x00;
+/// P14 low and P15 high, a 0 bit.
+const ZERO: u8 = 0x20;
+/// P14 high and P15 low, a 1 bit.
+const ONE: u8 = 0x10;
+/// Both select lines high, which has to come between pulses.
+const IDLE: u8 = 0x30;
+
+/// Decodes the packets a game sends the SGB by pulsing P1's select lines.
+///
+/// Each packet starts with a reset pulse, followed by 128 bits sent LSB
+/// first and a 0 as a stop bit. The lines go back high between pulses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct PacketReceiver {
+    packet: [u8; PACKET_SIZE],
+    /// How many bits of the packet have arrived, or `None` if there isn't
+    /// one being sent.
+    bits: Option<usize>,
+    /// Whether the lines have gone back high since the last pulse.
+    ready: bool,
+}
+
+impl PacketReceiver {
+    /// Handles a write to P1's select lines, returning the packet once its
+    /// stop bit arrives.
+    pub(super) fn write(&mut self, select: u8) -> Option<[u8; PACKET_SIZE]> {
+        match select {
+            IDLE => {
+                self.ready = true;
+                None
+            }
+            RESET => {
+                self.ready = false;
+                self.packet = [0; PACKET_SIZE];
+                self.bits = Some(0);
+                None
+            }
+            // Holding a line low only counts as a single pulse
+            _ if !self.ready => None,
+            ZERO | ONE => {
+                self.ready = false;
+                let bits = self.bits?;
+
+                if bits == PACKET_SIZE * 8 {
+                    self.bits = None;
+
+                    // Anything but a 0 for the stop bit throws the packet away
+                    return if select == ZERO {
+                        Some(self.packet)
+                    } else {
+                        None
+                    };
+                }
+
+                if select == ONE {
+                    self.packet[bits / 8] |= 1 << (bits % 8);
+                }
+
+                self.bits = Some(bits + 1);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The P1 writes that send a packet, for tests.
+#[cfg(test)]
+pub(crate) fn pulses(packet: &[u8; PACKET_SIZE]) -> Vec<u8> {
+    let mut pulses = vec![RESET, IDLE];
+
+    for byte in packet {
+        for bit in 0..8 {
+            pulses.push(if byte & (1 << bit) != 0 { ONE } else { ZERO });
+            pulses.push(IDLE);
+        }
+    }
+
+    pulses.extend_from_slice(&[ZERO, IDLE]);
+    pulses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKET: [u8; PACKET_SIZE] = [
+        0x01, 0xFF, 0x7F, 0x1F, 0x00, 0x00, 0x7C, 0xE0, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x80,
+    ];
+
+    fn receive(pulses: &[u8]) -> Vec<[u8; PACKET_SIZE]> {
+        let mut receiver = PacketReceiver::default();
+
+        pulses
+            .iter()
+            .filter_map(|select| receiver.write(*select))
+            .collect()
+    }
+
+    #[test]
+    fn it_receives_packets() {
+        assert_eq!(vec![PACKET], receive(&pulses(&PACKET)));
+    }
+
+    #[test]
+    fn it_receives_packets_back_to_back() {
+        let mut pulses = pulses(&PACKET);
+        pulses.extend(super::pulses(&[0xAA; PACKET_SIZE]));
+
+        assert_eq!(vec![PACKET, [0xAA; PACKET_SIZE]], receive(&pulses));
+    }
+
+    #[test]
+    fn it_ignores_pulses_without_the_lines_going_high() {
+        let pulses: Vec<u8> = pulses(&PACKET)
+            .into_iter()
+            .flat_map(|select| match select {
+                ONE => vec![ONE, ONE],
+                select => vec![select],
+            })
+            .collect();
+
+        assert_eq!(vec![PACKET], receive(&pulses));
+    }
+
+    #[test]
+    fn it_drops_packets_without_a_stop_bit() {
+        let mut pulses = pulses(&PACKET);
+        let stop = pulses.len() - 2;
+        pulses[stop] = ONE;
+
+        assert!(receive(&pulses).is_empty());
+    }
+
+    #[test]
+    fn it_ignores_pulses_outside_a_packet() {
+        assert!(receive(&[ONE, IDLE, ZERO, IDLE, ZERO, IDLE]).is_empty());
+    }
+}
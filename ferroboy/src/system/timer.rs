@@ -0,0 +1,280 @@
+use crate::system::Interrupts;
+
+pub(crate) const DIV: u16 = 0xFF04;
+pub(crate) const TIMA: u16 = 0xFF05;
+pub(crate) const TMA: u16 = 0xFF06;
+pub(crate) const TAC: u16 = 0xFF07;
+
+/// The bit of the internal divider that clocks TIMA, for each frequency
+/// TAC can select: 4096 Hz, 262144 Hz, 65536 Hz and 16384 Hz.
+const TAC_BITS: [u16; 4] = [9, 3, 5, 7];
+/// How many T-cycles TIMA reads as 0 after overflowing, and then how many
+/// it spends being reloaded from TMA.
+const RELOAD_DELAY: u8 = 4;
+
+/// An implementation of the DMG-01's timer.
+///
+/// DIV is the top half of a 16-bit divider that counts every T-cycle.
+/// Rather than counting on its own, TIMA is incremented whenever the bit of
+/// the divider that TAC selects falls from 1 to 0, while the timer is
+/// enabled. That means anything which drops that bit counts, including
+/// resetting DIV or changing TAC.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Timer {
+    divider: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    /// Counts down the T-cycles from TIMA overflowing until it's reloaded,
+    /// or 0 if it hasn't.
+    overflow_delay: u8,
+    /// Counts down the T-cycles TIMA spends being reloaded, during which
+    /// writes to TIMA are ignored and writes to TMA go straight through.
+    reloading: u8,
+}
+
+/// How many T-cycles TIMA takes to count once at the frequency `tac`
+/// selects, whether or not it enables the timer.
+pub(crate) fn tima_period(tac: u8) -> u64 {
+    1 << (TAC_BITS[usize::from(tac & 0b11)] + 1)
+}
+
+impl Timer {
+    /// The full 16-bit divider, of which DIV is the top half.
+    pub fn divider(&self) -> u16 {
+        self.divider
+    }
+
+    pub(crate) fn read(&self, address: u16) -> u8 {
+        match address {
+            DIV => (self.divider >> 8) as u8,
+            TIMA => self.tima,
+            TMA => self.tma,
+            // Only the bottom three bits of TAC are used
+            TAC => 0xF8 | self.tac,
+            _ => 0xFF,
+        }
+    }
+
+    pub(crate) fn write(&mut self, address: u16, value: u8) {
+        match address {
+            DIV => self.update_signal(|timer| timer.divider = 0),
+            // Writes are ignored while TIMA's being reloaded
+            TIMA if self.reloading > 0 => {}
+            TIMA => {
+                self.tima = value;
+                // Writing during the delay cancels the reload
+                self.overflow_delay = 0;
+            }
+            TMA => {
+                self.tma = value;
+
+                if self.reloading > 0 {
+                    self.tima = value;
+                }
+            }
+            TAC => self.update_signal(|timer| timer.tac = value & 0b111),
+            _ => {}
+        }
+    }
+
+    /// Advances the timer by a number of T-cycles, returning the
+    /// interrupts it requested.
+    pub(crate) fn step(&mut self, cycles: u64) -> Interrupts {
+        let mut interrupts = Interrupts::empty();
+
+        for _ in 0..cycles {
+            interrupts |= self.tick();
+        }
+
+        interrupts
+    }
+
+    fn tick(&mut self) -> Interrupts {
+        let mut interrupts = Interrupts::empty();
+
+        self.reloading = self.reloading.saturating_sub(1);
+
+        if self.overflow_delay > 0 {
+            self.overflow_delay -= 1;
+
+            if self.overflow_delay == 0 {
+                self.tima = self.tma;
+                self.reloading = RELOAD_DELAY;
+                interrupts |= Interrupts::TIMER;
+            }
+        }
+
+        self.update_signal(|timer| timer.divider = timer.divider.wrapping_add(1));
+
+        interrupts
+    }
+
+    /// Makes a change, incrementing TIMA if it makes the selected divider
+    /// bit fall.
+    fn update_signal<F>(&mut self, change: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let before = self.signal();
+        change(self);
+
+        if before && !self.signal() {
+            self.increment();
+        }
+    }
+
+    /// The selected divider bit, masked by whether the timer is enabled.
+    fn signal(&self) -> bool {
+        let bit = TAC_BITS[usize::from(self.tac & 0b11)];
+
+        self.tac & 0b100 != 0 && self.divider & (1 << bit) != 0
+    }
+
+    fn increment(&mut self) {
+        let (tima, overflowed) = self.tima.overflowing_add(1);
+        self.tima = tima;
+
+        if overflowed {
+            self.overflow_delay = RELOAD_DELAY;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer(tac: u8) -> Timer {
+        let mut timer = Timer::default();
+        timer.write(TAC, tac);
+        timer
+    }
+
+    #[test]
+    fn it_counts_div_every_256_cycles() {
+        let mut timer = Timer::default();
+
+        timer.step(255);
+        assert_eq!(0x00, timer.read(DIV));
+
+        timer.step(1);
+        assert_eq!(0x01, timer.read(DIV));
+
+        timer.step(256 * 0xFF);
+        assert_eq!(0x00, timer.read(DIV));
+    }
+
+    #[test]
+    fn it_resets_div_on_write() {
+        let mut timer = Timer::default();
+        timer.step(1000);
+
+        timer.write(DIV, 0x42);
+        assert_eq!(0, timer.divider());
+    }
+
+    #[test]
+    fn it_counts_tima_at_each_frequency() {
+        for (tac, period) in [(0b100, 1024), (0b101, 16), (0b110, 64), (0b111, 256)] {
+            assert_eq!(period, tima_period(tac), "TAC {:03b}", tac);
+
+            let mut timer = timer(tac);
+
+            timer.step(period - 1);
+            assert_eq!(0, timer.read(TIMA), "TAC {:03b}", tac);
+
+            timer.step(1);
+            assert_eq!(1, timer.read(TIMA), "TAC {:03b}", tac);
+
+            timer.step(period * 9);
+            assert_eq!(10, timer.read(TIMA), "TAC {:03b}", tac);
+        }
+    }
+
+    #[test]
+    fn it_stops_counting_when_disabled() {
+        let mut timer = timer(0b001);
+        timer.step(1024);
+
+        assert_eq!(0, timer.read(TIMA));
+        assert_eq!(0xF9, timer.read(TAC));
+    }
+
+    #[test]
+    fn it_reloads_a_cycle_after_overflowing() {
+        let mut timer = timer(0b101);
+        timer.write(TMA, 0xAB);
+        timer.write(TIMA, 0xFF);
+
+        assert!(timer.step(16).is_empty());
+        assert_eq!(0x00, timer.read(TIMA));
+
+        assert!(timer.step(3).is_empty());
+        assert_eq!(0x00, timer.read(TIMA));
+
+        assert_eq!(Interrupts::TIMER, timer.step(1));
+        assert_eq!(0xAB, timer.read(TIMA));
+    }
+
+    #[test]
+    fn it_cancels_the_reload_when_tima_is_written() {
+        let mut timer = timer(0b101);
+        timer.write(TIMA, 0xFF);
+        timer.step(16);
+
+        timer.write(TIMA, 0x12);
+
+        assert!(timer.step(4).is_empty());
+        assert_eq!(0x12, timer.read(TIMA));
+    }
+
+    #[test]
+    fn it_ignores_tima_writes_while_reloading() {
+        let mut timer = timer(0b101);
+        timer.write(TMA, 0xAB);
+        timer.write(TIMA, 0xFF);
+        timer.step(20);
+
+        timer.write(TIMA, 0x12);
+        assert_eq!(0xAB, timer.read(TIMA));
+
+        // TMA writes go straight through though
+        timer.write(TMA, 0xCD);
+        assert_eq!(0xCD, timer.read(TIMA));
+
+        timer.step(4);
+        timer.write(TIMA, 0x12);
+        assert_eq!(0x12, timer.read(TIMA));
+    }
+
+    #[test]
+    fn it_increments_when_div_is_reset_with_the_bit_high() {
+        let mut timer = timer(0b101);
+
+        // Bit 3 is high from 8 to 15
+        timer.step(8);
+        timer.write(DIV, 0x00);
+        assert_eq!(1, timer.read(TIMA));
+
+        timer.step(4);
+        timer.write(DIV, 0x00);
+        assert_eq!(1, timer.read(TIMA));
+    }
+
+    #[test]
+    fn it_increments_when_tac_drops_the_bit() {
+        let mut disabled = timer(0b101);
+        disabled.step(8);
+
+        // Disabling the timer with the bit high
+        disabled.write(TAC, 0b001);
+        assert_eq!(1, disabled.read(TIMA));
+
+        // Switching to a frequency whose bit is low
+        let mut switched = timer(0b101);
+        switched.step(8);
+        switched.write(TAC, 0b100);
+        assert_eq!(1, switched.read(TIMA));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::Error, start, Cartridge, CartridgeBuilder, Config, HeaderWriter, State, StateBuilder,
+};
+
+/// Where a built ROM's program starts, just past the header.
+const PROGRAM_START: u16 = 0x0150;
+/// The size of a built ROM, a single pair of banks with no mapper.
+const ROM_SIZE: usize = 0x8000;
+
+/// Builds a 32KiB ROM with a valid header around a hand-assembled program,
+/// for tests that need the CPU to run something.
+///
+/// This is only meant for tests, so it's only available with the
+/// `test-util` feature.
+///
+/// The program starts at 0x0150, where the entry point jumps to. Rather
+/// than work out addresses by hand, positions in the program can be
+/// labelled and jumped to by name.
+#[derive(Clone, Debug, Default)]
+pub struct RomBuilder {
+    title: String,
+    sgb: bool,
+    program: Vec<u8>,
+    labels: HashMap<String, u16>,
+    /// Where in the program each label's address needs to be written.
+    references: Vec<(usize, String)>,
+    data: Vec<(u16, Vec<u8>)>,
+}
+
+impl RomBuilder {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Marks the ROM as supporting the Super Game Boy.
+    pub fn with_sgb_support(mut self) -> Self {
+        self.sgb = true;
+        self
+    }
+
+    /// Appends instructions to the program.
+    pub fn code(mut self, bytes: &[u8]) -> Self {
+        self.program.extend_from_slice(bytes);
+        self
+    }
+
+    /// Names the address of the next instruction.
+    pub fn label(mut self, name: &str) -> Self {
+        let address = usize::from(PROGRAM_START) + self.program.len();
+        // Past the end of the ROM, which `build` reports
+        let address = u16::try_from(address).unwrap_or(u16::MAX);
+
+        self.labels.insert(name.into(), address);
+        self
+    }
+
+    /// Appends an instruction whose operand is a label's address, such as
+    /// a `JP` or `CALL`. The label can come before or after it.
+    pub fn jump(mut self, opcode: u8, label: &str) -> Self {
+        self.program.push(opcode);
+        self.references.push((self.program.len(), label.into()));
+        self.program.extend_from_slice(&[0, 0]);
+        self
+    }
+
+    /// Places bytes at a fixed address, for the program to read.
+    pub fn data(mut self, address: u16, bytes: &[u8]) -> Self {
+        self.data.push((address, bytes.into()));
+        self
+    }
+
+    /// Assembles the ROM.
+    ///
+    /// # Errors
+    /// - `UndefinedLabel` if the program jumps to a label that was never
+    ///   placed
+    /// - `RomOverflow` if the program or data runs past the end of the ROM
+    pub fn build(&self) -> crate::Result<Vec<u8>> {
+        let mut program = self.program.clone();
+
+        for (offset, label) in &self.references {
+            let address = self
+                .labels
+                .get(label)
+                .ok_or_else(|| Error::UndefinedLabel(label.clone()))?;
+
+            program[*offset..*offset + 2].copy_from_slice(&address.to_le_bytes());
+        }
+
+        let mut rom = vec![0; ROM_SIZE];
+
+        // JP PROGRAM_START
+        rom[0x0100] = 0xC3;
+        rom[0x0101..0x0103].copy_from_slice(&PROGRAM_START.to_le_bytes());
+        Self::place(&mut rom, usize::from(PROGRAM_START), &program)?;
+
+        for (address, bytes) in &self.data {
+            Self::place(&mut rom, usize::from(*address), bytes)?;
+        }
+
+        let header = HeaderWriter::new(&mut rom)
+            .write_logo()
+            .write_title(&self.title)?;
+
+        if self.sgb {
+            header.write_sgb_support().fix_checksums();
+        } else {
+            header.fix_checksums();
+        }
+
+        Ok(rom)
+    }
+
+    pub fn cartridge(&self) -> crate::Result<Cartridge> {
+        CartridgeBuilder::new().with_buffer(&self.build()?).build()
+    }
+
+    /// Builds the ROM into a state and starts it.
+    pub fn start(&self, config: Config) -> crate::Result<State> {
+        let mut state = StateBuilder::new()
+            .with_config(config)
+            .with_cartridge(self.cartridge()?)
+            .build();
+
+        start(&mut state)?;
+
+        Ok(state)
+    }
+
+    fn place(rom: &mut [u8], address: usize, bytes: &[u8]) -> crate::Result<()> {
+        let end = address + bytes.len();
+
+        match rom.get_mut(address..end) {
+            Some(destination) => {
+                destination.copy_from_slice(bytes);
+                Ok(())
+            }
+            None => Err(Error::RomOverflow(end)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_builds_roms_with_labels() {
+        let rom = RomBuilder::new("LABELS")
+            .code(&[0x00])
+            .jump(0xC3, "end")
+            .label("end")
+            .jump(0xC3, "end")
+            .build()
+            .unwrap();
+
+        assert_eq!([0xC3, 0x50, 0x01], rom[0x0100..0x0103]);
+        assert_eq!(
+            [0x00, 0xC3, 0x54, 0x01, 0xC3, 0x54, 0x01],
+            rom[0x0150..0x0157]
+        );
+        assert!(crate::validate_header(&rom).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_rejects_missing_labels() {
+        let error = RomBuilder::new("LABELS")
+            .jump(0xC3, "nowhere")
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(error, Error::UndefinedLabel(label) if label == "nowhere"));
+    }
+
+    #[test]
+    fn it_rejects_programs_past_the_end_of_the_rom() {
+        let error = RomBuilder::new("LONG")
+            .code(&[0x00; ROM_SIZE])
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(error, Error::RomOverflow(end) if end == 0x150 + ROM_SIZE));
+    }
+
+    #[test]
+    fn it_rejects_data_past_the_end_of_the_rom() {
+        let error = RomBuilder::new("DATA")
+            .data(0x7FFF, &[0xAA, 0xBB])
+            .build()
+            .unwrap_err();
+
+        assert!(matches!(error, Error::RomOverflow(0x8001)));
+    }
+}
@@ -0,0 +1,167 @@
+//! Running test ROMs that report their results over the serial port, like
+//! Blargg's, so they can be checked from ordinary tests.
+//!
+//! With the `test-util` feature, this also has a `RomBuilder` for tests
+//! that need a small ROM of their own.
+
+#[cfg(any(test, feature = "test-util"))]
+mod builder;
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+#[cfg(any(test, feature = "test-util"))]
+pub use builder::RomBuilder;
+
+use crate::{system::SerialCapture, State, CYCLES_PER_FRAME};
+
+/// How a test ROM finished, along with everything it printed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Passed(String),
+    Failed(String),
+    /// The cycle budget ran out before the ROM said either way.
+    TimedOut(String),
+}
+
+impl Verdict {
+    pub fn is_passed(&self) -> bool {
+        matches!(self, Verdict::Passed(_))
+    }
+
+    /// Everything the ROM printed over the serial port.
+    pub fn output(&self) -> &str {
+        match self {
+            Verdict::Passed(output) | Verdict::Failed(output) | Verdict::TimedOut(output) => output,
+        }
+    }
+
+    fn from_output(output: String) -> Option<Self> {
+        if output.contains("Failed") {
+            Some(Verdict::Failed(output))
+        } else if output.contains("Passed") {
+            Some(Verdict::Passed(output))
+        } else {
+            None
+        }
+    }
+}
+
+/// Runs a started ROM until it prints "Passed" or "Failed" over the serial
+/// port, or `cycle_budget` T-cycles elapse.
+///
+/// Whatever was plugged into the serial port is replaced. Once the ROM has
+/// given its verdict it's run until it's been quiet for a frame, so the
+/// rest of what it had to say (e.g. which test failed) is captured too.
+pub fn run_test_rom(state: &mut State, cycle_budget: u64) -> crate::Result<Verdict> {
+    let capture = state.connect_link_cable(SerialCapture::new());
+    let end = state.cpu.clock() + cycle_budget;
+
+    let mut sent = 0;
+    let mut quiet_since = None;
+
+    while state.cpu.clock() < end {
+        crate::tick(state)?;
+
+        let bytes = lock(&capture).bytes().len();
+
+        if bytes != sent {
+            sent = bytes;
+
+            if quiet_since.is_some() || Verdict::from_output(lock(&capture).text()).is_some() {
+                quiet_since = Some(state.cpu.clock());
+            }
+        }
+
+        if let Some(since) = quiet_since {
+            if state.cpu.clock() - since >= CYCLES_PER_FRAME {
+                break;
+            }
+        }
+    }
+
+    let output = lock(&capture).text();
+
+    Ok(Verdict::from_output(output.clone()).unwrap_or(Verdict::TimedOut(output)))
+}
+
+fn lock(capture: &Arc<Mutex<SerialCapture>>) -> std::sync::MutexGuard<'_, SerialCapture> {
+    capture.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    /// Boots a ROM that prints `message` over the serial port and then
+    /// spins forever.
+    fn serial_printer_rom(message: &str) -> State {
+        const MESSAGE: u16 = 0x0200;
+
+        #[rustfmt::skip]
+        let rom = RomBuilder::new("PRINTER")
+            .code(&[
+                0x11, 0x00, 0x02, // LD DE, MESSAGE
+                0x01, 0x01, 0xFF, // LD BC, SB
+            ])
+            // Stop at the terminator
+            .label("next")
+            .code(&[
+                0x1A,             // LD A, (DE)
+                0xB7,             // OR A
+            ])
+            .jump(0xCA, "done")   // JP Z, done
+            // Send it
+            .code(&[
+                0x02,             // LD (BC), A
+                0x0C,             // INC C
+                0x3E, 0x81,       // LD A, 0x81
+                0x02,             // LD (BC), A
+            ])
+            // Wait for the transfer to finish
+            .label("wait")
+            .code(&[
+                0x0A,             // LD A, (BC)
+                0xE6, 0x80,       // AND 0x80
+            ])
+            .jump(0xC2, "wait")   // JP NZ, wait
+            .code(&[
+                0x0D,             // DEC C
+                0x13,             // INC DE
+            ])
+            .jump(0xC3, "next")   // JP next
+            .label("done")
+            .jump(0xC3, "done")   // JP done
+            .data(MESSAGE, message.as_bytes());
+
+        rom.start(Config::default()).unwrap()
+    }
+
+    #[test]
+    fn it_passes() {
+        let mut state = serial_printer_rom("01-special\n\n\nPassed\n");
+        let verdict = run_test_rom(&mut state, 10 * CYCLES_PER_FRAME).unwrap();
+
+        assert_eq!(
+            Verdict::Passed("01-special\n\n\nPassed\n".to_string()),
+            verdict
+        );
+    }
+
+    #[test]
+    fn it_fails_with_the_rest_of_the_message() {
+        let mut state = serial_printer_rom("02-interrupts\n\n\nFailed #4\n");
+        let verdict = run_test_rom(&mut state, 10 * CYCLES_PER_FRAME).unwrap();
+
+        assert!(!verdict.is_passed());
+        assert_eq!("02-interrupts\n\n\nFailed #4\n", verdict.output());
+    }
+
+    #[test]
+    fn it_times_out() {
+        let mut state = serial_printer_rom("03-op sp,hl\n\n\n");
+        let verdict = run_test_rom(&mut state, 2 * CYCLES_PER_FRAME).unwrap();
+
+        assert_eq!(Verdict::TimedOut("03-op sp,hl\n\n\n".to_string()), verdict);
+    }
+}
@@ -0,0 +1,126 @@
+//! An edge-blending 2x filter, inspired by hq2x.
+//!
+//! hq2x picks one of 256 hand-tuned blends for each pixel, based on which
+//! of its eight neighbours look different. This borrows its YUV thresholds
+//! and interpolation weights, but chooses each corner of the output from
+//! only the three neighbours touching it. It isn't hq2x: it skips most of
+//! hq2x's special cases, and its output doesn't match hq2x's.
+
+use super::{mix, Image, Yuv};
+use crate::system::Colour;
+
+/// How far apart colours' YUV components can be before they're treated as
+/// different colours, the same thresholds as hq2x.
+const Y_THRESHOLD: f32 = 48.0;
+const U_THRESHOLD: f32 = 7.0;
+const V_THRESHOLD: f32 = 6.0;
+
+fn is_different(a: Colour, b: Colour) -> bool {
+    let (a, b) = (Yuv::from(a), Yuv::from(b));
+
+    (a.y - b.y).abs() > Y_THRESHOLD
+        || (a.u - b.u).abs() > U_THRESHOLD
+        || (a.v - b.v).abs() > V_THRESHOLD
+}
+
+/// Expands a single pixel into a 2x2 block, top row first.
+pub(super) fn edge_blend2x(image: &Image, x: usize, y: usize) -> [Colour; 4] {
+    let corner = |dx, dy| {
+        let centre = image.pixel(x, y);
+        let horizontal = image.neighbour(x, y, dx, 0);
+        let vertical = image.neighbour(x, y, 0, dy);
+        let diagonal = image.neighbour(x, y, dx, dy);
+
+        if !is_different(horizontal, vertical) && is_different(centre, horizontal) {
+            if is_different(centre, diagonal) {
+                // The corner is cut off by an edge running across it
+                mix(&[(centre, 2), (horizontal, 1), (vertical, 1)])
+            } else {
+                // The edge only grazes the corner
+                mix(&[(centre, 6), (horizontal, 1), (vertical, 1)])
+            }
+        } else if is_different(centre, diagonal)
+            && !is_different(centre, horizontal)
+            && !is_different(centre, vertical)
+        {
+            mix(&[(centre, 3), (diagonal, 1)])
+        } else {
+            centre
+        }
+    };
+
+    [corner(-1, -1), corner(1, -1), corner(-1, 1), corner(1, 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upscale::{
+        tests::{image, render},
+        Filter,
+    };
+
+    #[test]
+    fn it_compares_colours_in_yuv() {
+        assert!(!is_different(
+            Colour::new(0x80, 0x80, 0x80),
+            Colour::new(0x90, 0x90, 0x90)
+        ));
+        assert!(is_different(
+            Colour::new(0x80, 0x80, 0x80),
+            Colour::new(0xC0, 0xC0, 0xC0)
+        ));
+        assert!(is_different(
+            Colour::new(0x80, 0x80, 0x80),
+            Colour::new(0x80, 0x80, 0xA0)
+        ));
+    }
+
+    #[test]
+    fn it_smooths_diagonals() {
+        let input = image(&[
+            "#...", //
+            ".#..", //
+            "..#.", //
+            "...#",
+        ]);
+
+        assert_eq!(
+            vec![
+                "99000000", //
+                "97202000", //
+                "02740000", //
+                "00472020", //
+                "02027400", //
+                "00004720", //
+                "00020279", //
+                "00000099",
+            ],
+            render(&Filter::EdgeBlend2x.apply(&input))
+        );
+    }
+
+    #[test]
+    fn it_smooths_the_corners_of_blocks() {
+        let input = image(&[
+            "....", //
+            ".##.", //
+            ".##.", //
+            "....",
+        ]);
+
+        assert_eq!(
+            vec![
+                "00000000", //
+                "02000020", //
+                "00499400", //
+                "00999900", //
+                "00999900", //
+                "00499400", //
+                "02000020", //
+                "00000000",
+            ],
+            render(&Filter::EdgeBlend2x.apply(&input))
+        );
+    }
+}
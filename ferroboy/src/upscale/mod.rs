@@ -0,0 +1,342 @@
+//! CPU-side upscaling filters for frame output.
+//!
+//! These are for frontends that can't lean on GPU shaders, and for
+//! screenshots. They work on coloured images rather than shades, so the
+//! palette (and any frame blending) is applied first.
+
+mod edge_blend;
+mod scale;
+mod xbr;
+
+use std::str::FromStr;
+
+use crate::{
+    error::Error,
+    system::{Colour, Frame, Palette, PixelFormat, SCREEN_HEIGHT, SCREEN_WIDTH},
+};
+
+/// An RGB image, stored row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+}
+
+impl Image {
+    /// # Panics
+    /// If `pixels` doesn't hold exactly `width * height` colours.
+    pub fn new(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        assert_eq!(width * height, pixels.len(), "Image dimensions don't match");
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Colours a frame with `palette`.
+    pub fn from_frame(frame: &Frame, palette: &Palette) -> Self {
+        let pixels = frame
+            .pixels()
+            .iter()
+            .map(|shade| palette.colour(*shade))
+            .collect();
+
+        Self::new(SCREEN_WIDTH, SCREEN_HEIGHT, pixels)
+    }
+
+    /// Reads an image from a buffer in `PixelFormat::Rgba8`, ignoring alpha,
+    /// e.g. the output of `State::frame_pixels`.
+    pub fn from_rgba8(width: usize, height: usize, buffer: &[u8]) -> Self {
+        let pixels = buffer
+            .chunks_exact(4)
+            .map(|pixel| Colour::new(pixel[0], pixel[1], pixel[2]))
+            .collect();
+
+        Self::new(width, height, pixels)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Colour] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Colour {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Converts the image into a buffer ready to be displayed.
+    pub fn convert(&self, format: PixelFormat) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(self.pixels.len() * format.bytes_per_pixel());
+
+        for colour in &self.pixels {
+            format.encode(*colour, &mut buffer);
+        }
+
+        buffer
+    }
+
+    /// Looks up a pixel relative to another, repeating the edges of the
+    /// image for anything out of bounds.
+    fn neighbour(&self, x: usize, y: usize, dx: isize, dy: isize) -> Colour {
+        let x = (x as isize + dx).clamp(0, self.width as isize - 1) as usize;
+        let y = (y as isize + dy).clamp(0, self.height as isize - 1) as usize;
+
+        self.pixel(x, y)
+    }
+}
+
+/// The available upscaling filters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// Repeats every pixel to scale by a whole number.
+    Nearest(usize),
+    /// Andrea Mazzoleni's Scale2x, which rounds off diagonals without
+    /// introducing any new colours.
+    Scale2x,
+    /// Scale3x, the 3x variant of Scale2x.
+    Scale3x,
+    /// Blends along edges, with the thresholds and weights of Maxim
+    /// Stepin's hq2x but a much simpler choice of blend. It's inspired by
+    /// hq2x rather than a port of it, so its output is different.
+    EdgeBlend2x,
+    /// Hyllian's 2xBR, which detects edges from a wider neighbourhood and
+    /// blends them more smoothly than `EdgeBlend2x`.
+    Xbr2x,
+}
+
+impl Filter {
+    /// How many times larger the filter makes an image on each side.
+    pub fn factor(&self) -> usize {
+        match self {
+            Self::Nearest(factor) => *factor,
+            Self::Scale2x | Self::EdgeBlend2x | Self::Xbr2x => 2,
+            Self::Scale3x => 3,
+        }
+    }
+
+    pub fn apply(&self, image: &Image) -> Image {
+        let factor = self.factor();
+        let mut output = Image {
+            width: image.width * factor,
+            height: image.height * factor,
+            pixels: vec![Colour::new(0, 0, 0); image.pixels.len() * factor * factor],
+        };
+
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let block = match self {
+                    Self::Nearest(_) => vec![image.pixel(x, y); factor * factor],
+                    Self::Scale2x => scale::scale2x(image, x, y).to_vec(),
+                    Self::Scale3x => scale::scale3x(image, x, y).to_vec(),
+                    Self::EdgeBlend2x => edge_blend::edge_blend2x(image, x, y).to_vec(),
+                    Self::Xbr2x => xbr::xbr2x(image, x, y).to_vec(),
+                };
+
+                for (index, colour) in block.into_iter().enumerate() {
+                    let output_x = x * factor + index % factor;
+                    let output_y = y * factor + index / factor;
+
+                    output.pixels[output_y * output.width + output_x] = colour;
+                }
+            }
+        }
+
+        output
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    /// Parses a filter's name, e.g. `scale2x` or `edgeblend2x`, or `nearest` and
+    /// a factor, e.g. `nearest4` or `4x`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase();
+
+        match name.as_str() {
+            "scale2x" => return Ok(Self::Scale2x),
+            "scale3x" => return Ok(Self::Scale3x),
+            "edgeblend" | "edgeblend2x" => return Ok(Self::EdgeBlend2x),
+            "xbr" | "2xbr" | "xbr2x" => return Ok(Self::Xbr2x),
+            _ => {}
+        }
+
+        name.strip_prefix("nearest")
+            .or_else(|| name.strip_suffix('x'))
+            .and_then(|factor| factor.parse().ok())
+            .filter(|factor| *factor > 0)
+            .map(Self::Nearest)
+            .ok_or_else(|| Error::InvalidFilter(s.into()))
+    }
+}
+
+/// Mixes colours by weight, rounding to the nearest value.
+fn mix(colours: &[(Colour, u32)]) -> Colour {
+    let total: u32 = colours.iter().map(|(_, weight)| weight).sum();
+    let channel = |get: fn(&Colour) -> u8| {
+        let sum: u32 = colours
+            .iter()
+            .map(|(colour, weight)| u32::from(get(colour)) * weight)
+            .sum();
+
+        ((sum + total / 2) / total) as u8
+    };
+
+    Colour::new(channel(|c| c.r), channel(|c| c.g), channel(|c| c.b))
+}
+
+/// A colour in YUV, which the edge-blending filters compare colours in,
+/// since it's closer to how different they look.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Yuv {
+    y: f32,
+    u: f32,
+    v: f32,
+}
+
+impl From<Colour> for Yuv {
+    fn from(colour: Colour) -> Self {
+        let (r, g, b) = (
+            f32::from(colour.r),
+            f32::from(colour.g),
+            f32::from(colour.b),
+        );
+
+        Self {
+            y: 0.299 * r + 0.587 * g + 0.114 * b,
+            u: -0.169 * r - 0.331 * g + 0.5 * b,
+            v: 0.5 * r - 0.419 * g - 0.081 * b,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const WHITE: Colour = Colour::new(0xFF, 0xFF, 0xFF);
+    const BLACK: Colour = Colour::new(0x00, 0x00, 0x00);
+
+    /// Builds an image from rows of `.` (white) and `#` (black).
+    pub(crate) fn image(rows: &[&str]) -> Image {
+        let pixels = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|pixel| if pixel == '#' { BLACK } else { WHITE })
+            .collect();
+
+        Image::new(rows[0].len(), rows.len(), pixels)
+    }
+
+    /// Draws an image as rows of digits, from `0` for white to `9` for
+    /// black, so golden images can be written inline.
+    pub(crate) fn render(image: &Image) -> Vec<String> {
+        (0..image.height())
+            .map(|y| {
+                (0..image.width())
+                    .map(|x| {
+                        let darkness = 255 - u32::from(image.pixel(x, y).r);
+                        char::from_digit((darkness * 9 + 127) / 255, 10).unwrap()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_scales_to_the_filters_factor() {
+        let input = image(&["#.", ".#", "##"]);
+
+        for filter in [
+            Filter::Nearest(4),
+            Filter::Scale2x,
+            Filter::Scale3x,
+            Filter::EdgeBlend2x,
+            Filter::Xbr2x,
+        ] {
+            let output = filter.apply(&input);
+
+            assert_eq!(2 * filter.factor(), output.width());
+            assert_eq!(3 * filter.factor(), output.height());
+        }
+    }
+
+    #[test]
+    fn it_leaves_flat_images_alone() {
+        let input = Image::new(4, 4, vec![Colour::new(0x12, 0x34, 0x56); 16]);
+
+        for filter in [
+            Filter::Scale2x,
+            Filter::Scale3x,
+            Filter::EdgeBlend2x,
+            Filter::Xbr2x,
+        ] {
+            let output = filter.apply(&input);
+
+            assert!(output
+                .pixels()
+                .iter()
+                .all(|pixel| *pixel == input.pixel(0, 0)));
+        }
+    }
+
+    #[test]
+    fn it_scales_by_nearest_neighbour() {
+        let output = Filter::Nearest(2).apply(&image(&["#.", ".#"]));
+
+        assert_eq!(vec!["9900", "9900", "0099", "0099"], render(&output));
+    }
+
+    #[test]
+    fn it_converts_frames_to_images() {
+        let image = Image::from_frame(&Frame::new(), &Palette::HIGH_CONTRAST);
+
+        assert_eq!(SCREEN_WIDTH, image.width());
+        assert_eq!(SCREEN_HEIGHT, image.height());
+        assert_eq!(WHITE, image.pixel(0, 0));
+    }
+
+    #[test]
+    fn it_round_trips_rgba8() {
+        let input = image(&["#.", ".#"]);
+        let output = Image::from_rgba8(2, 2, &input.convert(PixelFormat::Rgba8));
+
+        assert_eq!(input, output);
+    }
+
+    #[test]
+    fn it_parses_filters() {
+        assert_eq!(Filter::Scale2x, "scale2x".parse().unwrap());
+        assert_eq!(Filter::Scale3x, "Scale3x".parse().unwrap());
+        assert_eq!(Filter::EdgeBlend2x, "edgeblend2x".parse().unwrap());
+        assert_eq!(Filter::Xbr2x, "xbr".parse().unwrap());
+        assert_eq!(Filter::Nearest(4), "nearest4".parse().unwrap());
+        assert_eq!(Filter::Nearest(3), "3x".parse().unwrap());
+
+        assert!("nearest0".parse::<Filter>().is_err());
+        assert!("hq2x".parse::<Filter>().is_err());
+        assert!("bilinear".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn it_mixes_colours() {
+        assert_eq!(
+            Colour::new(0x80, 0x80, 0x80),
+            mix(&[(WHITE, 1), (BLACK, 1)])
+        );
+        assert_eq!(
+            Colour::new(0xBF, 0xBF, 0xBF),
+            mix(&[(WHITE, 3), (BLACK, 1)])
+        );
+    }
+}
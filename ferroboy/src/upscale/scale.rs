@@ -0,0 +1,154 @@
+//! Scale2x and Scale3x, as described at <https://www.scale2x.it/algorithm>.
+//!
+//! Neighbours are named as in the reference:
+//!
+//! ```text
+//! A B C
+//! D E F
+//! G H I
+//! ```
+
+use super::Image;
+use crate::system::Colour;
+
+/// Expands a single pixel into a 2x2 block, top row first.
+pub(super) fn scale2x(image: &Image, x: usize, y: usize) -> [Colour; 4] {
+    let e = image.pixel(x, y);
+    let b = image.neighbour(x, y, 0, -1);
+    let d = image.neighbour(x, y, -1, 0);
+    let f = image.neighbour(x, y, 1, 0);
+    let h = image.neighbour(x, y, 0, 1);
+
+    if b == h || d == f {
+        return [e; 4];
+    }
+
+    [
+        if d == b { d } else { e },
+        if b == f { f } else { e },
+        if d == h { d } else { e },
+        if h == f { f } else { e },
+    ]
+}
+
+/// Expands a single pixel into a 3x3 block, top row first.
+pub(super) fn scale3x(image: &Image, x: usize, y: usize) -> [Colour; 9] {
+    let a = image.neighbour(x, y, -1, -1);
+    let b = image.neighbour(x, y, 0, -1);
+    let c = image.neighbour(x, y, 1, -1);
+    let d = image.neighbour(x, y, -1, 0);
+    let e = image.pixel(x, y);
+    let f = image.neighbour(x, y, 1, 0);
+    let g = image.neighbour(x, y, -1, 1);
+    let h = image.neighbour(x, y, 0, 1);
+    let i = image.neighbour(x, y, 1, 1);
+
+    if b == h || d == f {
+        return [e; 9];
+    }
+
+    [
+        if d == b { d } else { e },
+        if (d == b && e != c) || (b == f && e != a) {
+            b
+        } else {
+            e
+        },
+        if b == f { f } else { e },
+        if (d == b && e != g) || (d == h && e != a) {
+            d
+        } else {
+            e
+        },
+        e,
+        if (b == f && e != i) || (h == f && e != c) {
+            f
+        } else {
+            e
+        },
+        if d == h { d } else { e },
+        if (d == h && e != i) || (h == f && e != g) {
+            h
+        } else {
+            e
+        },
+        if h == f { f } else { e },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::upscale::{
+        tests::{image, render},
+        Filter,
+    };
+
+    #[test]
+    fn it_rounds_off_diagonals_at_2x() {
+        let input = image(&[
+            "#...", //
+            ".#..", //
+            "..#.", //
+            "...#",
+        ]);
+
+        assert_eq!(
+            vec![
+                "99000000", //
+                "90900000", //
+                "09990000", //
+                "00999000", //
+                "00099900", //
+                "00009990", //
+                "00000909", //
+                "00000099",
+            ],
+            render(&Filter::Scale2x.apply(&input))
+        );
+    }
+
+    #[test]
+    fn it_keeps_straight_edges_at_2x() {
+        let input = image(&[
+            "##..", //
+            "##..", //
+            "....",
+        ]);
+
+        assert_eq!(
+            vec![
+                "99990000", //
+                "99990000", //
+                "99990000", //
+                "99900000", //
+                "00000000", //
+                "00000000",
+            ],
+            render(&Filter::Scale2x.apply(&input))
+        );
+    }
+
+    #[test]
+    fn it_rounds_off_diagonals_at_3x() {
+        let input = image(&[
+            "#..", //
+            ".#.", //
+            "..#",
+        ]);
+
+        assert_eq!(
+            vec![
+                "999000000", //
+                "990900000", //
+                "900900000", //
+                "099999000", //
+                "000999000", //
+                "000999990", //
+                "000009009", //
+                "000009099", //
+                "000000999",
+            ],
+            render(&Filter::Scale3x.apply(&input))
+        );
+    }
+}
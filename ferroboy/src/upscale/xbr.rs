@@ -0,0 +1,123 @@
+//! Hyllian's 2xBR, at level 1.
+//!
+//! Each corner of a pixel is looked at on its own, as the bottom-right
+//! corner of this neighbourhood, mirrored for the other three:
+//!
+//! ```text
+//!      A1 B1 C1
+//!   A0 A  B  C  C4
+//!   D0 D  E  F  F4
+//!   G0 G  H  I  I4
+//!      G5 H5 I5
+//! ```
+//!
+//! An edge runs between F and H when the colours along that direction
+//! differ less than the colours across it, in which case the corner is
+//! blended with whichever of F and H is closer to E.
+
+use super::{mix, Image, Yuv};
+use crate::system::Colour;
+
+/// How different two colours look, weighing brightness over colour.
+fn distance(a: Colour, b: Colour) -> f32 {
+    let (a, b) = (Yuv::from(a), Yuv::from(b));
+
+    48.0 * (a.y - b.y).abs() + 7.0 * (a.u - b.u).abs() + 6.0 * (a.v - b.v).abs()
+}
+
+/// Expands a single pixel into a 2x2 block, top row first.
+pub(super) fn xbr2x(image: &Image, x: usize, y: usize) -> [Colour; 4] {
+    let corner = |sx: isize, sy: isize| {
+        let at = |dx: isize, dy: isize| image.neighbour(x, y, dx * sx, dy * sy);
+
+        let (b, c) = (at(0, -1), at(1, -1));
+        let (d, e, f, f4) = (at(-1, 0), at(0, 0), at(1, 0), at(2, 0));
+        let (g, h, i, i4) = (at(-1, 1), at(0, 1), at(1, 1), at(2, 1));
+        let (h5, i5) = (at(0, 2), at(1, 2));
+
+        if e == f || e == h {
+            return e;
+        }
+
+        let along = distance(e, c)
+            + distance(e, g)
+            + distance(i, h5)
+            + distance(i, f4)
+            + 4.0 * distance(h, f);
+        let across = distance(h, d)
+            + distance(h, i5)
+            + distance(f, i4)
+            + distance(f, b)
+            + 4.0 * distance(e, i);
+
+        if along < across {
+            let closer = if distance(e, f) <= distance(e, h) {
+                f
+            } else {
+                h
+            };
+
+            mix(&[(e, 1), (closer, 1)])
+        } else {
+            e
+        }
+    };
+
+    [corner(-1, -1), corner(1, -1), corner(-1, 1), corner(1, 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::upscale::{
+        tests::{image, render},
+        Filter,
+    };
+
+    #[test]
+    fn it_smooths_diagonals() {
+        let input = image(&[
+            "#...", //
+            ".#..", //
+            "..#.", //
+            "...#",
+        ]);
+
+        assert_eq!(
+            vec![
+                "99000000", //
+                "99400000", //
+                "04940000", //
+                "00494000", //
+                "00049400", //
+                "00004940", //
+                "00000499", //
+                "00000099",
+            ],
+            render(&Filter::Xbr2x.apply(&input))
+        );
+    }
+
+    #[test]
+    fn it_rounds_the_corners_of_blocks() {
+        let input = image(&[
+            "....", //
+            ".##.", //
+            ".##.", //
+            "....",
+        ]);
+
+        assert_eq!(
+            vec![
+                "00000000", //
+                "00000000", //
+                "00499400", //
+                "00999900", //
+                "00999900", //
+                "00499400", //
+                "00000000", //
+                "00000000",
+            ],
+            render(&Filter::Xbr2x.apply(&input))
+        );
+    }
+}
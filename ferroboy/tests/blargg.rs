@@ -0,0 +1,44 @@
+//! Runs the CPU instruction tests from Blargg's test ROMs, from
+//! https://github.com/retrio/gb-test-roms, if they've been placed under
+//! `assets/gb-test-roms`.
+//!
+//! LDH and LD (a16) are in, but the CPU is still missing instructions (ADC,
+//! SBC, DAA and others) and JR can't jump backwards, so the test is ignored
+//! until it's complete.
+
+use ferroboy::{test_rom::run_test_rom, CYCLES_PER_SECOND};
+
+mod common;
+
+const TESTS: &[&str] = &[
+    "01-special",
+    "02-interrupts",
+    "03-op sp,hl",
+    "04-op r,imm",
+    "05-op rp",
+    "06-ld r,r",
+    "07-jr,jp,call,ret,rst",
+    "08-misc instrs",
+    "09-op r,r",
+    "10-bit ops",
+    "11-op a,(hl)",
+];
+
+#[test]
+#[ignore = "needs the rest of the CPU's instructions"]
+fn it_passes_the_blargg_cpu_instrs_tests() {
+    let mut failures = Vec::new();
+
+    for test in TESTS {
+        let mut state =
+            common::load_rom(&format!("gb-test-roms/cpu_instrs/individual/{}.gb", test));
+
+        match run_test_rom(&mut state, 30 * CYCLES_PER_SECOND) {
+            Ok(verdict) if verdict.is_passed() => {}
+            Ok(verdict) => failures.push(format!("{} ({:?})", test, verdict.output())),
+            Err(error) => failures.push(format!("{} ({})", test, error)),
+        }
+    }
+
+    assert!(failures.is_empty(), "Failed: {}", failures.join(", "));
+}
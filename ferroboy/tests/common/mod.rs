@@ -0,0 +1,219 @@
+//! Helpers for running test ROMs that aren't checked in.
+//!
+//! ROMs and reference images live under `ferroboy/assets`. The tests that
+//! need them are ignored, and fail if they're run without them rather than
+//! passing without having checked anything.
+
+// Not every test uses every helper
+#![allow(dead_code)]
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use ferroboy::{
+    run_frame, start, test_rom::RomBuilder, CartridgeBuilder, Config, ConfigBuilder, State,
+    StateBuilder, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+pub fn asset(path: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("assets")
+        .join(path);
+
+    assert!(
+        path.exists(),
+        "{} is missing, see the readme for where to get it",
+        path.display()
+    );
+
+    path
+}
+
+/// Boots a ROM from the assets directory.
+pub fn load_rom(path: &str) -> State {
+    load_rom_with_config(path, ConfigBuilder::new().without_boot_check())
+}
+
+pub fn load_rom_with_config(path: &str, config: ConfigBuilder) -> State {
+    let path = asset(path);
+    let config = config.build();
+    let cartridge = CartridgeBuilder::new()
+        .with_config(&config)
+        .with_file(File::open(path).unwrap())
+        .build()
+        .unwrap();
+
+    let mut state = StateBuilder::new()
+        .with_config(config)
+        .with_cartridge(cartridge)
+        .build();
+
+    start(&mut state).unwrap();
+
+    state
+}
+
+/// Where a trader stores what it received, followed by `TRADE_DONE`.
+pub const TRADE_BUFFER: u16 = 0xC000;
+pub const TRADE_DONE: u8 = 0xAA;
+
+/// Boots a ROM that swaps `party` over the serial port a byte at a time,
+/// either driving the clock or following the other side's, and stores
+/// what comes back at `TRADE_BUFFER`.
+pub fn trader(internal_clock: bool, party: &[u8]) -> State {
+    const PARTY: u16 = 0x0200;
+
+    assert!(party.len() < 0x100, "party must fit in one page");
+
+    // The side driving the clock gives the other a moment to start
+    // listening before each byte, like real trade code does
+    let (sc, delay) = if internal_clock {
+        (0x81, 0x20)
+    } else {
+        (0x80, 0x01)
+    };
+    let end = party.len() as u8;
+
+    #[rustfmt::skip]
+    let rom = RomBuilder::new("TRADER")
+        .code(&[
+            0x11, 0x00, 0x02, // LD DE, PARTY
+            0x21, 0x00, 0xC0, // LD HL, TRADE_BUFFER
+            0x01, 0x01, 0xFF, // LD BC, SB
+        ])
+        // Wait a moment
+        .label("next")
+        .code(&[0x3E, delay])  // LD A, delay
+        .label("delay")
+        .code(&[0x3D])         // DEC A
+        .jump(0xC2, "delay")   // JP NZ, delay
+        // Send the next byte
+        .code(&[
+            0x1A,             // LD A, (DE)
+            0x02,             // LD (BC), A
+            0x0C,             // INC C
+            0x3E, sc,         // LD A, sc
+            0x02,             // LD (BC), A
+        ])
+        // Wait for the transfer to finish
+        .label("wait")
+        .code(&[
+            0x0A,             // LD A, (BC)
+            0xE6, 0x80,       // AND 0x80
+        ])
+        .jump(0xC2, "wait")   // JP NZ, wait
+        // Store what came back
+        .code(&[
+            0x0D,             // DEC C
+            0x0A,             // LD A, (BC)
+            0x22,             // LD (HL+), A
+            0x13,             // INC DE
+            0x7B,             // LD A, E
+            0xFE, end,        // CP end
+        ])
+        .jump(0xC2, "next")   // JP NZ, next
+        .code(&[
+            0x3E, TRADE_DONE, // LD A, TRADE_DONE
+            0x77,             // LD (HL), A
+        ])
+        .label("done")
+        .jump(0xC3, "done")   // JP done
+        .data(PARTY, party);
+
+    rom.start(Config::default()).unwrap()
+}
+
+/// What a trader has received, once it's done.
+pub fn traded(state: &State, length: usize) -> Option<Vec<u8>> {
+    let start = usize::from(TRADE_BUFFER);
+
+    if state.mmu[TRADE_BUFFER + length as u16] == TRADE_DONE {
+        Some(
+            (start..start + length)
+                .map(|a| state.mmu[a as u16])
+                .collect(),
+        )
+    } else {
+        None
+    }
+}
+
+pub fn run_frames(state: &mut State, frames: usize) {
+    for _ in 0..frames {
+        run_frame(state).unwrap();
+    }
+}
+
+/// Runs a Mooneye test ROM until it hits the `LD B, B` breakpoint it ends
+/// on, then checks for the Fibonacci numbers it leaves in the registers
+/// when it passes.
+///
+/// Gives up after `frames` frames' worth of cycles.
+#[cfg(feature = "introspection")]
+pub fn passes_mooneye_test(state: &mut State, frames: u64) -> bool {
+    use ferroboy::{Register, WideRegister, CYCLES_PER_FRAME};
+
+    const LD_B_B: u8 = 0x40;
+
+    for _ in 0..frames * CYCLES_PER_FRAME / 4 {
+        if state.mmu.read(state.cpu.get16(WideRegister::Pc)) == LD_B_B {
+            let registers = [
+                Register::B,
+                Register::C,
+                Register::D,
+                Register::E,
+                Register::H,
+                Register::L,
+            ];
+
+            return registers
+                .iter()
+                .map(|register| state.cpu.get(*register))
+                .eq([3, 5, 8, 13, 21, 34]);
+        }
+
+        if ferroboy::tick(state).is_err() {
+            return false;
+        }
+    }
+
+    false
+}
+
+/// Loads a greyscale reference screenshot as shades, from `0` (white) to
+/// `3` (black).
+pub fn load_reference(path: &str) -> Vec<u8> {
+    let path = asset(path);
+
+    let mut decoder = png::Decoder::new(File::open(path).unwrap());
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info().unwrap();
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).unwrap();
+
+    assert_eq!(SCREEN_WIDTH, info.width as usize);
+    assert_eq!(SCREEN_HEIGHT, info.height as usize);
+
+    let channels = info.color_type.samples();
+
+    buffer[..info.buffer_size()]
+        .chunks(channels)
+        // Every channel is the same in a grey image, so any will do
+        .map(|pixel| 3 - ((u16::from(pixel[0]) + 0x2A) / 0x55) as u8)
+        .collect()
+}
+
+/// Compares a frame against a reference, reporting the first pixel that
+/// differs.
+pub fn assert_matches_reference(frame: &[u8], reference: &[u8]) {
+    if let Some(index) = frame.iter().zip(reference).position(|(a, b)| a != b) {
+        panic!(
+            "Frame differs from the reference at ({}, {}): expected {}, found {}",
+            index % SCREEN_WIDTH,
+            index / SCREEN_WIDTH,
+            reference[index],
+            frame[index]
+        );
+    }
+}
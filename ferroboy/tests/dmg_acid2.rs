@@ -0,0 +1,22 @@
+//! Runs Matt Currie's dmg-acid2 PPU test, from
+//! https://github.com/mattcurrie/dmg-acid2, if it's been placed under
+//! `assets/dmg-acid2`.
+//!
+//! OAM DMA and LDH are in, but the CPU is still missing instructions (ADC,
+//! SBC, DAA and others) and JR can't jump backwards, so the test is ignored
+//! until it's complete. Checking the sprites against the reference is
+//! deferred until then.
+
+mod common;
+
+#[test]
+#[ignore = "needs the rest of the CPU's instructions"]
+fn it_passes_dmg_acid2() {
+    let mut state = common::load_rom("dmg-acid2/dmg-acid2.gb");
+    let reference = common::load_reference("dmg-acid2/reference-dmg.png");
+
+    // The test draws a single static frame, a few frames in is plenty
+    common::run_frames(&mut state, 10);
+
+    common::assert_matches_reference(state.frame().pixels(), &reference);
+}
@@ -0,0 +1,43 @@
+//! Trades data between two headless instances linked over localhost.
+
+mod common;
+
+use std::{net::TcpListener, thread};
+
+use ferroboy::{link::TcpLinkCable, run_frame, State};
+
+/// Pokémon's trade protocol opens with a run of 0xFD to sync up the two
+/// sides before sending each party.
+const RED: &[u8] = b"\xFD\xFD\xFD\xFDPIKACHU\x19CHARMANDER\x05\x00";
+const BLUE: &[u8] = b"\xFD\xFD\xFD\xFDBULBASAUR\x07SQUIRTLE\x0C\x00";
+
+fn trade(mut state: State, cable: TcpLinkCable, length: usize) -> Vec<u8> {
+    state.connect_link_cable(cable);
+
+    for _ in 0..600 {
+        if let Some(received) = common::traded(&state, length) {
+            return received;
+        }
+
+        run_frame(&mut state).unwrap();
+    }
+
+    panic!("Trade didn't finish");
+}
+
+#[test]
+fn it_trades_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let blue = thread::spawn(move || {
+        let cable = TcpLinkCable::connect(address).unwrap();
+        trade(common::trader(false, BLUE), cable, BLUE.len())
+    });
+
+    let cable = TcpLinkCable::accept(&listener).unwrap();
+    let red = trade(common::trader(true, RED), cable, RED.len());
+
+    assert_eq!(BLUE, &red[..]);
+    assert_eq!(RED, &blue.join().unwrap()[..]);
+}
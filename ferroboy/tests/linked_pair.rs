@@ -0,0 +1,72 @@
+//! Trades data between two instances linked in the same process.
+
+mod common;
+
+use ferroboy::{
+    link::{LinkedPair, Side},
+    Buttons,
+};
+
+const RED: &[u8] = b"\xFD\xFD\xFD\xFDPIKACHU\x19CHARMANDER\x05\x00";
+const BLUE: &[u8] = b"\xFD\xFD\xFD\xFDBULBASAUR\x07SQUIRTLE\x0C\x00";
+
+fn trade() -> LinkedPair {
+    let mut pair = LinkedPair::new(common::trader(true, RED), common::trader(false, BLUE));
+
+    for _ in 0..600 {
+        let done = [Side::Left, Side::Right]
+            .iter()
+            .all(|side| common::traded(pair.state(*side), RED.len()).is_some());
+
+        if done {
+            return pair;
+        }
+
+        pair.run_frame().unwrap();
+    }
+
+    panic!("Trade didn't finish");
+}
+
+#[test]
+fn it_trades_in_process() {
+    let pair = trade();
+
+    assert_eq!(
+        Some(BLUE.to_vec()),
+        common::traded(pair.state(Side::Left), BLUE.len())
+    );
+    assert_eq!(
+        Some(RED.to_vec()),
+        common::traded(pair.state(Side::Right), RED.len())
+    );
+}
+
+#[test]
+fn it_trades_the_same_way_every_time() {
+    let first = trade();
+    let second = trade();
+
+    assert_eq!(first.frames(), second.frames());
+
+    for side in [Side::Left, Side::Right] {
+        assert_eq!(
+            first.state(side).mmu.timer().divider(),
+            second.state(side).mmu.timer().divider()
+        );
+    }
+}
+
+#[test]
+fn it_scripts_inputs_to_each_side() {
+    let mut pair = trade();
+
+    pair.set_buttons(Side::Left, Buttons::A);
+    pair.set_buttons(Side::Right, Buttons::START | Buttons::UP);
+
+    assert_eq!(Buttons::A, pair.state(Side::Left).mmu.joypad().buttons());
+    assert_eq!(
+        Buttons::START | Buttons::UP,
+        pair.state(Side::Right).mmu.joypad().buttons()
+    );
+}
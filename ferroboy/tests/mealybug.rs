@@ -0,0 +1,70 @@
+//! Runs the DMG PPU tests from Matt Currie's mealybug-tearoom-tests, from
+//! https://github.com/mattcurrie/mealybug-tearoom-tests, if they've been
+//! placed under `assets/mealybug-tearoom-tests`.
+//!
+//! Every test changes registers partway through mode 3, so they're run
+//! with the FIFO renderer.
+//!
+//! OAM DMA and LDH are in, but the CPU is still missing instructions (ADC,
+//! SBC, DAA and others) and JR can't jump backwards, so the test is ignored
+//! until it's complete.
+
+mod common;
+
+use ferroboy::{ConfigBuilder, Renderer};
+
+const TESTS: &[&str] = &[
+    "m2_win_en_toggle",
+    "m3_bgp_change",
+    "m3_bgp_change_sprites",
+    "m3_lcdc_bg_en_change",
+    "m3_lcdc_bg_map_change",
+    "m3_lcdc_obj_en_change",
+    "m3_lcdc_obj_en_change_variant",
+    "m3_lcdc_obj_size_change",
+    "m3_lcdc_obj_size_change_scx",
+    "m3_lcdc_tile_sel_change",
+    "m3_lcdc_tile_sel_win_change",
+    "m3_lcdc_win_en_change_multiple",
+    "m3_lcdc_win_en_change_multiple_wx",
+    "m3_lcdc_win_map_change",
+    "m3_obp0_change",
+    "m3_scx_high_5_bits",
+    "m3_scx_low_3_bits",
+    "m3_scy_change",
+    "m3_window_timing",
+    "m3_window_timing_wx_0",
+    "m3_wx_4_change",
+    "m3_wx_4_change_sprites",
+    "m3_wx_5_change",
+    "m3_wx_6_change",
+];
+
+#[test]
+#[ignore = "needs the rest of the CPU's instructions"]
+fn it_passes_the_mealybug_tearoom_tests() {
+    let mut failures = Vec::new();
+
+    for test in TESTS {
+        let config = ConfigBuilder::new()
+            .without_boot_check()
+            .with_renderer(Renderer::Fifo);
+
+        let mut state = common::load_rom_with_config(
+            &format!("mealybug-tearoom-tests/build/ppu/{}.gb", test),
+            config,
+        );
+        let reference = common::load_reference(&format!(
+            "mealybug-tearoom-tests/expected/DMG-blob/{}.png",
+            test
+        ));
+
+        common::run_frames(&mut state, 10);
+
+        if state.frame().pixels() != reference.as_slice() {
+            failures.push(*test);
+        }
+    }
+
+    assert!(failures.is_empty(), "Failed: {}", failures.join(", "));
+}
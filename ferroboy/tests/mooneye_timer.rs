@@ -0,0 +1,42 @@
+//! Runs the timer tests from Joonas Javanainen's Mooneye Test Suite, from
+//! https://github.com/Gekkio/mooneye-test-suite, if they've been built and
+//! placed under `assets/mooneye-test-suite`.
+//!
+//! LDH and LD (a16) are in, but the CPU is still missing instructions (ADC,
+//! SBC, DAA and others) and JR can't jump backwards, so the test is ignored
+//! until it's complete.
+
+mod common;
+
+const TESTS: &[&str] = &[
+    "div_write",
+    "rapid_toggle",
+    "tim00",
+    "tim00_div_trigger",
+    "tim01",
+    "tim01_div_trigger",
+    "tim10",
+    "tim10_div_trigger",
+    "tim11",
+    "tim11_div_trigger",
+    "tima_reload",
+    "tima_write_reloading",
+    "tma_write_reloading",
+];
+
+#[test]
+#[ignore = "needs the rest of the CPU's instructions"]
+fn it_passes_the_mooneye_timer_tests() {
+    let mut failures = Vec::new();
+
+    for test in TESTS {
+        let mut state =
+            common::load_rom(&format!("mooneye-test-suite/acceptance/timer/{}.gb", test));
+
+        if !common::passes_mooneye_test(&mut state, 600) {
+            failures.push(*test);
+        }
+    }
+
+    assert!(failures.is_empty(), "Failed: {}", failures.join(", "));
+}